@@ -278,4 +278,6 @@ struct ResourceInfo<'a> {
     resource_name: &'a str,
     create_requests: &'a [CreateInfo<'a>],
     free_request: &'a str,
+    /// Should the wrapper remember the depth and size that the resource was created with?
+    tracks_geometry: bool,
 }
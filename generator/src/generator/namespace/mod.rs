@@ -370,6 +370,7 @@ impl<'ns, 'c> NamespaceGenerator<'ns, 'c> {
             None,
             out,
         );
+        special_cases::handle_struct(struct_def, &rust_name, out);
 
         outln!(out, "");
     }
@@ -874,6 +875,8 @@ impl<'ns, 'c> NamespaceGenerator<'ns, 'c> {
             outln!(out, "bitmask_binop!({}, {});", rust_name, raw_type);
         }
 
+        special_cases::handle_enum(enum_def, &rust_name, out);
+
         outln!(out, "");
     }
 
@@ -17,6 +17,12 @@ pub(super) fn generate(
     let lower_name = info.resource_name.to_ascii_lowercase();
     let free_function = camel_case_to_lower_snake(info.free_request);
     let wrapper = format!("{}Wrapper", info.resource_name);
+    // Wrappers that track the geometry have named fields, the others are tuple structs
+    let (conn_field, id_field) = if info.tracks_geometry {
+        ("conn", "id")
+    } else {
+        ("0", "1")
+    };
     outln!(out, "");
     outln!(
         out,
@@ -36,12 +42,32 @@ pub(super) fn generate(
         "/// X11 connection is broken and later requests will also fail.",
     );
     outln!(out, "#[derive(Debug)]");
-    outln!(
-        out,
-        "pub struct {wrapper}<'c, C: RequestConnection>(&'c C, {name});",
-        name = info.resource_name,
-        wrapper = wrapper,
-    );
+    if info.tracks_geometry {
+        outln!(out, "pub struct {}<'c, C: RequestConnection> {{", wrapper,);
+        out.indented(|out| {
+            outln!(out, "conn: &'c C,");
+            outln!(out, "id: {},", info.resource_name);
+            outln!(
+                out,
+                "/// The depth that the {} was created with, if known.",
+                info.resource_name,
+            );
+            outln!(out, "depth: Option<u8>,");
+            outln!(
+                out,
+                "/// The width and height at creation time or from the last query, if known.",
+            );
+            outln!(out, "size: Option<(u16, u16)>,");
+        });
+        outln!(out, "}}");
+    } else {
+        outln!(
+            out,
+            "pub struct {wrapper}<'c, C: RequestConnection>(&'c C, {name});",
+            name = info.resource_name,
+            wrapper = wrapper,
+        );
+    }
     outln!(out, "");
     outln!(out, "impl<'c, C: RequestConnection> {}<'c, C>", wrapper);
     outln!(out, "{{");
@@ -56,7 +82,15 @@ pub(super) fn generate(
             lower_name,
             info.resource_name,
         );
-        outln!(out.indent(), "{}(conn, id)", wrapper);
+        if info.tracks_geometry {
+            outln!(
+                out.indent(),
+                "{} {{ conn, id, depth: None, size: None }}",
+                wrapper,
+            );
+        } else {
+            outln!(out.indent(), "{}(conn, id)", wrapper);
+        }
         outln!(out, "}}");
         outln!(out, "");
 
@@ -67,7 +101,7 @@ pub(super) fn generate(
             lower_name,
             info.resource_name,
         );
-        outln!(out.indent(), "self.1");
+        outln!(out.indent(), "self.{}", id_field);
         outln!(out, "}}");
         outln!(out, "");
 
@@ -86,10 +120,14 @@ pub(super) fn generate(
             lower_name,
             info.resource_name,
         );
-        outln!(out.indent(), "let id = self.1;");
+        outln!(out.indent(), "let id = self.{};", id_field);
         outln!(out.indent(), "std::mem::forget(self);");
         outln!(out.indent(), "id");
         outln!(out, "}}");
+
+        if info.tracks_geometry {
+            generate_geometry_accessors(out, info.resource_name);
+        }
     });
     outln!(out, "}}");
     outln!(out, "");
@@ -104,7 +142,7 @@ pub(super) fn generate(
                 generator,
                 out,
                 create_request,
-                info.resource_name,
+                info,
                 &wrapper,
                 &lower_name,
                 &mut uses,
@@ -127,7 +165,7 @@ pub(super) fn generate(
     );
     out.indented(|out| {
         outln!(out, "fn from(from: &{}<'_, C>) -> Self {{", wrapper);
-        outln!(out.indent(), "from.1");
+        outln!(out.indent(), "from.{}", id_field);
         outln!(out, "}}");
     });
     outln!(out, "}}");
@@ -139,7 +177,13 @@ pub(super) fn generate(
     );
     out.indented(|out| {
         outln!(out, "fn drop(&mut self) {{");
-        outln!(out.indent(), "let _ = {}(self.0, self.1);", free_function);
+        outln!(
+            out.indent(),
+            "let _ = {}(self.{}, self.{});",
+            free_function,
+            conn_field,
+            id_field,
+        );
         outln!(out, "}}");
     });
     outln!(out, "}}");
@@ -149,7 +193,7 @@ fn generate_creator(
     generator: &NamespaceGenerator<'_, '_>,
     out: &mut Output,
     request_info: &CreateInfo<'_>,
-    resource_name: &str,
+    resource_info: &ResourceInfo<'_>,
     wrapper_name: &str,
     lower_name: &str,
     uses: &mut BTreeSet<String>,
@@ -183,8 +227,15 @@ fn generate_creator(
                 );
             }),
     );
+    let resource_name = resource_info.resource_name;
     let request_fields = request_def.fields.borrow();
     let deducible_fields = gather_deducible_fields(&*request_fields);
+    let has_geometry = resource_info.tracks_geometry
+        && ["depth", "width", "height"].iter().all(|name| {
+            request_fields
+                .iter()
+                .any(|field| field.name() == Some(name))
+        });
 
     if request_ext.is_some() {
         uses.insert(request_ns.header.clone());
@@ -337,12 +388,21 @@ fn generate_creator(
         function_name,
         forward_args_with_resource.join(", "),
     );
-    outln!(
-        out.indent(),
-        "Ok((Self::for_{}(conn, {}), cookie))",
-        lower_name,
-        request_info.created_argument,
-    );
+    if has_geometry {
+        outln!(
+            out.indent(),
+            "Ok((Self::for_{}(conn, {}).with_geometry(depth, width, height), cookie))",
+            lower_name,
+            request_info.created_argument,
+        );
+    } else {
+        outln!(
+            out.indent(),
+            "Ok((Self::for_{}(conn, {}), cookie))",
+            lower_name,
+            request_info.created_argument,
+        );
+    }
     outln!(out, "}}");
     outln!(out, "");
 
@@ -392,6 +452,107 @@ fn generate_creator(
     outln!(out, "}}");
 }
 
+fn generate_geometry_accessors(out: &mut Output, resource_name: &str) {
+    outln!(out, "");
+    outln!(
+        out,
+        "/// Get the depth that the {} was created with.",
+        resource_name,
+    );
+    outln!(out, "///");
+    outln!(
+        out,
+        "/// This is `None` if the depth is not known, for example because this wrapper was",
+    );
+    outln!(
+        out,
+        "/// created for an existing {}. Use [Self::query_geometry] to ask the X11 server.",
+        resource_name,
+    );
+    outln!(out, "pub fn depth(&self) -> Option<u8> {{");
+    outln!(out.indent(), "self.depth");
+    outln!(out, "}}");
+    outln!(out, "");
+    outln!(
+        out,
+        "/// Get the width and height that the {} was created with.",
+        resource_name,
+    );
+    outln!(out, "///");
+    outln!(
+        out,
+        "/// This is `None` if the size is not known, for example because this wrapper was",
+    );
+    outln!(
+        out,
+        "/// created for an existing {}. Use [Self::query_geometry] to ask the X11 server.",
+        resource_name,
+    );
+    outln!(out, "///");
+    outln!(
+        out,
+        "/// The value is not updated when the size changes later, e.g. when a window is resized.",
+    );
+    outln!(
+        out,
+        "/// Use [Self::query_geometry] to get the current size.",
+    );
+    outln!(out, "pub fn size(&self) -> Option<(u16, u16)> {{");
+    outln!(out.indent(), "self.size");
+    outln!(out, "}}");
+    outln!(out, "");
+    outln!(
+        out,
+        "/// Query the depth and size of the {} from the X11 server and remember them.",
+        resource_name,
+    );
+    outln!(out, "///");
+    outln!(
+        out,
+        "/// This sends a [get_geometry] request and waits for its reply. Afterwards,",
+    );
+    outln!(
+        out,
+        "/// [Self::depth] and [Self::size] return the values from the reply.",
+    );
+    outln!(
+        out,
+        "pub fn query_geometry(&mut self) -> Result<(u8, (u16, u16)), crate::errors::ReplyError> {{",
+    );
+    out.indented(|out| {
+        outln!(
+            out,
+            "let reply = get_geometry(self.conn, self.id)?.reply()?;"
+        );
+        outln!(out, "self.depth = Some(reply.depth);");
+        outln!(out, "self.size = Some((reply.width, reply.height));");
+        outln!(out, "Ok((reply.depth, (reply.width, reply.height)))");
+    });
+    outln!(out, "}}");
+    outln!(out, "");
+    outln!(
+        out,
+        "/// Remember the depth and size that the resource was created with."
+    );
+    outln!(out, "///");
+    outln!(
+        out,
+        "/// A depth of zero is not remembered, because it means \"copy from parent\" for windows.",
+    );
+    outln!(
+        out,
+        "fn with_geometry(mut self, depth: u8, width: u16, height: u16) -> Self {{",
+    );
+    out.indented(|out| {
+        outln!(out, "if depth != 0 {{");
+        outln!(out.indent(), "self.depth = Some(depth);");
+        outln!(out, "}}");
+        outln!(out, "self.size = Some((width, height));");
+        outln!(out, "self");
+    });
+    outln!(out, "}}");
+}
+
 fn emit_where(out: &mut Output, wheres: &[String]) {
     if !wheres.is_empty() {
         outln!(out, "where");
@@ -19,6 +19,7 @@ const EXTENSION_RESOURCES: [(&str, &[ResourceInfo<'_>]); 7] = [
                     created_argument: "pid",
                 }],
                 free_request: "FreePixmap",
+                tracks_geometry: true,
             },
             ResourceInfo {
                 resource_name: "Window",
@@ -27,6 +28,7 @@ const EXTENSION_RESOURCES: [(&str, &[ResourceInfo<'_>]); 7] = [
                     created_argument: "wid",
                 }],
                 free_request: "DestroyWindow",
+                tracks_geometry: true,
             },
             ResourceInfo {
                 resource_name: "Font",
@@ -35,6 +37,7 @@ const EXTENSION_RESOURCES: [(&str, &[ResourceInfo<'_>]); 7] = [
                     created_argument: "fid",
                 }],
                 free_request: "CloseFont",
+                tracks_geometry: false,
             },
             ResourceInfo {
                 resource_name: "Gcontext",
@@ -43,6 +46,7 @@ const EXTENSION_RESOURCES: [(&str, &[ResourceInfo<'_>]); 7] = [
                     created_argument: "cid",
                 }],
                 free_request: "FreeGC",
+                tracks_geometry: false,
             },
             ResourceInfo {
                 resource_name: "Colormap",
@@ -51,6 +55,7 @@ const EXTENSION_RESOURCES: [(&str, &[ResourceInfo<'_>]); 7] = [
                     created_argument: "mid",
                 }],
                 free_request: "FreeColormap",
+                tracks_geometry: false,
             },
             ResourceInfo {
                 resource_name: "Cursor",
@@ -65,6 +70,7 @@ const EXTENSION_RESOURCES: [(&str, &[ResourceInfo<'_>]); 7] = [
                     },
                 ],
                 free_request: "FreeCursor",
+                tracks_geometry: false,
             },
         ],
     ),
@@ -77,6 +83,7 @@ const EXTENSION_RESOURCES: [(&str, &[ResourceInfo<'_>]); 7] = [
                 created_argument: "damage",
             }],
             free_request: "Destroy",
+            tracks_geometry: false,
         }],
     ),
     (
@@ -88,6 +95,7 @@ const EXTENSION_RESOURCES: [(&str, &[ResourceInfo<'_>]); 7] = [
                 created_argument: "context",
             }],
             free_request: "FreeContext",
+            tracks_geometry: false,
         }],
     ),
     (
@@ -118,6 +126,7 @@ const EXTENSION_RESOURCES: [(&str, &[ResourceInfo<'_>]); 7] = [
                     },
                 ],
                 free_request: "FreePicture",
+                tracks_geometry: false,
             },
             ResourceInfo {
                 resource_name: "Glyphset",
@@ -126,6 +135,7 @@ const EXTENSION_RESOURCES: [(&str, &[ResourceInfo<'_>]); 7] = [
                     created_argument: "gsid",
                 }],
                 free_request: "FreeGlyphSet",
+                tracks_geometry: false,
             },
         ],
     ),
@@ -144,6 +154,7 @@ const EXTENSION_RESOURCES: [(&str, &[ResourceInfo<'_>]); 7] = [
                 },
             ],
             free_request: "Detach",
+            tracks_geometry: false,
         }],
     ),
     (
@@ -156,6 +167,7 @@ const EXTENSION_RESOURCES: [(&str, &[ResourceInfo<'_>]); 7] = [
                     created_argument: "id",
                 }],
                 free_request: "DestroyCounter",
+                tracks_geometry: false,
             },
             ResourceInfo {
                 resource_name: "Alarm",
@@ -164,6 +176,7 @@ const EXTENSION_RESOURCES: [(&str, &[ResourceInfo<'_>]); 7] = [
                     created_argument: "id",
                 }],
                 free_request: "DestroyAlarm",
+                tracks_geometry: false,
             },
             ResourceInfo {
                 resource_name: "Fence",
@@ -178,6 +191,7 @@ const EXTENSION_RESOURCES: [(&str, &[ResourceInfo<'_>]); 7] = [
                     },
                 ],
                 free_request: "DestroyFence",
+                tracks_geometry: false,
             },
         ],
     ),
@@ -212,6 +226,7 @@ const EXTENSION_RESOURCES: [(&str, &[ResourceInfo<'_>]); 7] = [
                 },
             ],
             free_request: "DestroyRegion",
+            tracks_geometry: false,
         }],
     ),
 ];
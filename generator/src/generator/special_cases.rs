@@ -83,6 +83,50 @@ pub fn value{width}(&self) -> Option<impl Iterator<Item=u{width}> + '_> {{
     }
 }
 
+pub(super) fn handle_enum(enum_def: &xcbdefs::EnumDef, rust_name: &str, out: &mut Output) {
+    let ns = enum_def.namespace.upgrade().unwrap();
+    if enum_def.name == "KeyButMask" && ns.header == "xproto" {
+        outln!(out, "impl {} {{", rust_name);
+        out.indented(|out| {
+            outln!(
+                out,
+                r"/// Get the modifier part of this mask.
+///
+/// The `state` field of e.g. a `KeyPressEvent` contains both the state of the modifiers and the
+/// state of the pointer buttons. This function removes the pointer buttons so that the result
+/// can be compared with a `ModMask`.
+///
+/// # Example
+///
+/// ```
+/// use x11rb_protocol::protocol::xproto::{{KeyButMask, ModMask}};
+/// let state = KeyButMask::CONTROL | KeyButMask::BUTTON1;
+/// assert_eq!(state.modifiers(), ModMask::CONTROL);
+/// ```
+pub fn modifiers(self) -> ModMask {{
+    ModMask::from(self.0 & 0x00ff)
+}}
+
+/// Get the pointer button part of this mask.
+///
+/// This function removes the modifiers so that the result can be compared with a `ButtonMask`.
+///
+/// # Example
+///
+/// ```
+/// use x11rb_protocol::protocol::xproto::{{ButtonMask, KeyButMask}};
+/// let state = KeyButMask::CONTROL | KeyButMask::BUTTON1;
+/// assert_eq!(state.buttons(), ButtonMask::M1);
+/// ```
+pub fn buttons(self) -> ButtonMask {{
+    ButtonMask::from(self.0 & 0x1f00)
+}}"
+            );
+        });
+        outln!(out, "}}");
+    }
+}
+
 pub(super) fn handle_request_switch(
     request_def: &xcbdefs::RequestDef,
     switch_field: &xcbdefs::SwitchField,
@@ -180,3 +224,53 @@ pub(super) fn handle_event(
         outln!(out, "}}");
     }
 }
+
+pub(super) fn handle_struct(struct_def: &xcbdefs::StructDef, rust_name: &str, out: &mut Output) {
+    let ns = struct_def.namespace.upgrade().unwrap();
+    if ns.header != "xproto" {
+        return;
+    }
+    if struct_def.name == "Setup" {
+        outln!(out, "impl {} {{", rust_name);
+        out.indented(|out| {
+            outln!(
+                out,
+                r"/// Get the screen with the given index.
+///
+/// This is the same as `self.roots.get(index)`. The index of the default screen is returned
+/// when connecting to the X11 server.
+pub fn default_screen(&self, index: usize) -> Option<&Screen> {{
+    self.roots.get(index)
+}}"
+            );
+        });
+        outln!(out, "}}");
+    } else if struct_def.name == "SCREEN" {
+        outln!(out, "impl {} {{", rust_name);
+        out.indented(|out| {
+            outln!(
+                out,
+                r"/// Get the depth of the root window, i.e. the entry of `allowed_depths` for `root_depth`.
+pub fn default_depth(&self) -> Option<&Depth> {{
+    self.allowed_depths
+        .iter()
+        .find(|depth| depth.depth == self.root_depth)
+}}
+
+/// Find the visual with the given ID and the depth that it belongs to.
+///
+/// The root visual can be found via `screen.visual_by_id(screen.root_visual)`.
+pub fn visual_by_id(&self, id: Visualid) -> Option<(&Depth, &Visualtype)> {{
+    self.allowed_depths.iter().find_map(|depth| {{
+        depth
+            .visuals
+            .iter()
+            .find(|visual| visual.visual_id == id)
+            .map(|visual| (depth, visual))
+    }})
+}}"
+            );
+        });
+        outln!(out, "}}");
+    }
+}
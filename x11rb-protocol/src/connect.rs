@@ -218,21 +218,24 @@ impl Connect {
             });
         }
 
+        // only parse the bytes that the server claims to have sent
+        let buffer = bounded_setup_bytes(&self.buffer)?;
+
         // parse the setup response
-        match self.buffer[0] {
+        match buffer[0] {
             0 => {
                 // an error has occurred
-                let (failed, _) = SetupFailed::try_parse(&self.buffer)?;
+                let (failed, _) = SetupFailed::try_parse(buffer)?;
                 Err(ConnectError::SetupFailed(failed))
             }
             1 => {
                 // the setup is valid!
-                let (success, _) = Setup::try_parse(&self.buffer)?;
+                let (success, _) = Setup::try_parse(buffer)?;
                 Ok(success)
             }
             2 => {
                 // we need further authentication
-                let (more_auth, _) = SetupAuthenticate::try_parse(&self.buffer)?;
+                let (more_auth, _) = SetupAuthenticate::try_parse(buffer)?;
                 Err(ConnectError::SetupAuthenticate(more_auth))
             }
             _ => {
@@ -243,6 +246,20 @@ impl Connect {
     }
 }
 
+/// Limit the given setup response to the length claimed in its header.
+///
+/// The header of every setup response is eight bytes long and contains the length of the
+/// remaining data in four-byte units at bytes 6-7. This returns
+/// `ParseError::InsufficientData` if fewer bytes than claimed were received.
+pub fn bounded_setup_bytes(bytes: &[u8]) -> Result<&[u8], ParseError> {
+    let header = bytes
+        .get(..INITIAL_CAPACITY)
+        .ok_or(ParseError::InsufficientData)?;
+    let length = u16::from_ne_bytes([header[6], header[7]]);
+    let length = usize::from(length) * 4 + INITIAL_CAPACITY;
+    bytes.get(..length).ok_or(ParseError::InsufficientData)
+}
+
 impl fmt::Debug for Connect {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Connect")
@@ -264,8 +281,8 @@ impl TryFrom<Connect> for Setup {
 
 #[cfg(test)]
 mod tests {
-    use super::Connect;
-    use crate::errors::ConnectError;
+    use super::{bounded_setup_bytes, Connect};
+    use crate::errors::{ConnectError, ParseError};
     use crate::protocol::xproto::{ImageOrder, Setup, SetupAuthenticate, SetupFailed};
     use crate::x11_utils::Serialize;
     use alloc::vec;
@@ -339,6 +356,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_connect_receive_truncated_setup() {
+        // The length field claims less data than what is needed for the setup
+        let mut setup = test_setup();
+        setup.length -= 2;
+        let b = try_receive_bytes(&setup);
+
+        match b {
+            Err(ConnectError::ParseError(ParseError::InsufficientData)) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_bounded_setup_bytes_claims_too_much() {
+        // The length field claims more data than what was received
+        let mut bytes = test_setup().serialize();
+        bytes[6..8].copy_from_slice(&u16::MAX.to_ne_bytes());
+
+        assert_eq!(
+            bounded_setup_bytes(&bytes),
+            Err(ParseError::InsufficientData)
+        );
+        assert_eq!(
+            bounded_setup_bytes(&bytes[..4]),
+            Err(ParseError::InsufficientData)
+        );
+    }
+
     #[test]
     fn test_connect_receive_setup_authenticate() {
         let setup = SetupAuthenticate {
@@ -376,4 +376,44 @@ mod test {
         let seqno = connection.send_request(ReplyFdKind::ReplyWithoutFDs);
         assert_eq!(Some(0x10000), seqno);
     }
+
+    #[cfg(all(feature = "std", unix))]
+    #[test]
+    fn discarded_reply_consumes_fds() {
+        use crate::utils::RawFdContainer;
+        use crate::{DiscardMode, SequenceNumber};
+        use alloc::vec;
+        use alloc::vec::Vec;
+        use std::fs::File;
+        use std::os::unix::io::{AsRawFd, IntoRawFd};
+
+        fn reply(seqno: SequenceNumber, num_fds: u8) -> Vec<u8> {
+            let mut packet = vec![0; 32];
+            packet[0] = 1;
+            packet[1] = num_fds;
+            packet[2..4].copy_from_slice(&(seqno as u16).to_ne_bytes());
+            packet
+        }
+
+        let mut connection = Connection::new();
+        let first = connection.send_request(ReplyFdKind::ReplyWithFDs).unwrap();
+        let second = connection.send_request(ReplyFdKind::ReplyWithFDs).unwrap();
+        connection.discard_reply(first, DiscardMode::DiscardReply);
+
+        let fds = (0..3)
+            .map(|_| RawFdContainer::new(File::open("/dev/null").unwrap().into_raw_fd()))
+            .collect::<Vec<_>>();
+        let last_fd = fds[2].as_raw_fd();
+        connection.enqueue_fds(fds);
+        connection.enqueue_packet(reply(first, 2));
+        connection.enqueue_packet(reply(second, 1));
+
+        // The FDs of the discarded reply are not handed out for the next reply
+        assert!(connection.poll_for_reply_or_error(first).is_none());
+        let (_, fds) = connection.poll_for_reply_or_error(second).unwrap();
+        assert_eq!(
+            fds.iter().map(|fd| fd.as_raw_fd()).collect::<Vec<_>>(),
+            [last_fd]
+        );
+    }
 }
@@ -76,6 +76,37 @@ impl fmt::Display for ParseError {
     }
 }
 
+/// An event could not be parsed.
+///
+/// This error is produced when an event that was received from the X11 server cannot be parsed,
+/// e.g. by `Connection::wait_for_event()` in `x11rb`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EventParseError {
+    /// The response type of the event, as received (including the bit for sent events).
+    pub response_type: u8,
+    /// The length of the event in bytes.
+    pub len: usize,
+    /// The error that occurred while parsing the event.
+    pub error: ParseError,
+}
+
+#[cfg(feature = "std")]
+impl Error for EventParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl fmt::Display for EventParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "An event with response type {} and length {} could not be parsed: {}",
+            self.response_type, self.len, self.error
+        )
+    }
+}
+
 /// An error that occurred while connecting to an X11 server
 #[derive(Debug)]
 #[non_exhaustive]
@@ -131,7 +162,15 @@ pub enum ConnectError {
 }
 
 #[cfg(feature = "std")]
-impl Error for ConnectError {}
+impl Error for ConnectError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConnectError::ParseError(err) => Some(err),
+            ConnectError::IoError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 impl fmt::Display for ConnectError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
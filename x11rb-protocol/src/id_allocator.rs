@@ -83,6 +83,7 @@ impl Error for IdsExhausted {}
 #[cfg(test)]
 mod test {
     use super::{GetXIDRangeReply, IdAllocator, IdsExhausted};
+    use alloc::vec::Vec;
 
     #[test]
     fn exhaustive() {
@@ -124,6 +125,25 @@ mod test {
         assert_eq!(Some(0x13370), allocator.generate_id());
     }
 
+    #[test]
+    fn exhausted_small_mask_needs_new_range() {
+        let mut allocator = IdAllocator::new(0x0020_0000, 0b11).unwrap();
+        let mut seen = Vec::new();
+        while let Some(id) = allocator.generate_id() {
+            assert_ne!(id, 0);
+            assert!(!seen.contains(&id), "Duplicate id {:#x}", id);
+            seen.push(id);
+        }
+        assert_eq!(seen, [0x0020_0000, 0x0020_0001, 0x0020_0002, 0x0020_0003]);
+        // Exhausted allocators keep failing until a new range is provided
+        assert_eq!(None, allocator.generate_id());
+        allocator
+            .update_xid_range(&generate_get_xid_range_reply(0x0020_0002, 1))
+            .unwrap();
+        assert_eq!(Some(0x0020_0002), allocator.generate_id());
+        assert_eq!(None, allocator.generate_id());
+    }
+
     #[test]
     fn invalid_new_arg() {
         let err = IdAllocator::new(1234, 0).unwrap_err();
@@ -98,4 +98,17 @@ mod tests {
             vec![ConnectAddress::Socket(PathBuf::from("/tmp/.X11-unix/X0")),]
         );
     }
+
+    #[test]
+    fn unix_host_uses_socket() {
+        let pd = parse_display(Some("unix:1")).unwrap();
+        let ci = pd.connect_instruction();
+
+        let ci = ci.collect::<Vec<_>>();
+
+        assert_eq!(
+            ci,
+            vec![ConnectAddress::Socket(PathBuf::from("/tmp/.X11-unix/X1")),]
+        );
+    }
 }
@@ -112,6 +112,24 @@ mod test {
     fn own_good_cases() {
         // The XCB test suite does not test protocol parsing
         for (input, output) in &[
+            (
+                "unix:0",
+                ParsedDisplay {
+                    host: "unix".to_string(),
+                    protocol: None,
+                    display: 0,
+                    screen: 0,
+                },
+            ),
+            (
+                "tcp/host:0",
+                ParsedDisplay {
+                    host: "host".to_string(),
+                    protocol: Some("tcp".to_string()),
+                    display: 0,
+                    screen: 0,
+                },
+            ),
             (
                 "foo/bar:1",
                 ParsedDisplay {
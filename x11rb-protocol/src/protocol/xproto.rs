@@ -696,6 +696,27 @@ impl Screen {
             .try_into().unwrap()
     }
 }
+impl Screen {
+    /// Get the depth of the root window, i.e. the entry of `allowed_depths` for `root_depth`.
+    pub fn default_depth(&self) -> Option<&Depth> {
+        self.allowed_depths
+            .iter()
+            .find(|depth| depth.depth == self.root_depth)
+    }
+
+    /// Find the visual with the given ID and the depth that it belongs to.
+    ///
+    /// The root visual can be found via `screen.visual_by_id(screen.root_visual)`.
+    pub fn visual_by_id(&self, id: Visualid) -> Option<(&Depth, &Visualtype)> {
+        self.allowed_depths.iter().find_map(|depth| {
+            depth
+                .visuals
+                .iter()
+                .find(|visual| visual.visual_id == id)
+                .map(|visual| (depth, visual))
+        })
+    }
+}
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -1089,6 +1110,15 @@ impl Setup {
             .try_into().unwrap()
     }
 }
+impl Setup {
+    /// Get the screen with the given index.
+    ///
+    /// This is the same as `self.roots.get(index)`. The index of the default screen is returned
+    /// when connecting to the X11 server.
+    pub fn default_screen(&self, index: usize) -> Option<&Screen> {
+        self.roots.get(index)
+    }
+}
 
 #[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -1233,6 +1263,39 @@ impl core::fmt::Debug for KeyButMask  {
     }
 }
 bitmask_binop!(KeyButMask, u16);
+impl KeyButMask {
+    /// Get the modifier part of this mask.
+    ///
+    /// The `state` field of e.g. a `KeyPressEvent` contains both the state of the modifiers and the
+    /// state of the pointer buttons. This function removes the pointer buttons so that the result
+    /// can be compared with a `ModMask`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use x11rb_protocol::protocol::xproto::{KeyButMask, ModMask};
+    /// let state = KeyButMask::CONTROL | KeyButMask::BUTTON1;
+    /// assert_eq!(state.modifiers(), ModMask::CONTROL);
+    /// ```
+    pub fn modifiers(self) -> ModMask {
+        ModMask::from(self.0 & 0x00ff)
+    }
+
+    /// Get the pointer button part of this mask.
+    ///
+    /// This function removes the modifiers so that the result can be compared with a `ButtonMask`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use x11rb_protocol::protocol::xproto::{ButtonMask, KeyButMask};
+    /// let state = KeyButMask::CONTROL | KeyButMask::BUTTON1;
+    /// assert_eq!(state.buttons(), ButtonMask::M1);
+    /// ```
+    pub fn buttons(self) -> ButtonMask {
+        ButtonMask::from(self.0 & 0x1f00)
+    }
+}
 
 #[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -67,12 +67,44 @@ impl X11Error {
             })
         }
     }
+
+    /// Get the name of the extension whose request caused this error.
+    ///
+    /// This is `"core"` for requests of the core protocol and `"unknown"` if the major opcode
+    /// belongs to an extension that is not known.
+    pub fn extension(&self) -> &str {
+        match &self.extension_name {
+            Some(name) => name,
+            None if self.major_opcode < 128 => "core",
+            None => "unknown",
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for X11Error {}
+
+impl core::fmt::Display for X11Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?} error from {}::", self.error_kind, self.extension())?;
+        match self.request_name {
+            Some(name) => write!(f, "{}", name)?,
+            None => write!(f, "{}.{}", self.major_opcode, self.minor_opcode)?,
+        }
+        write!(
+            f,
+            " (sequence {}, bad value {:#x})",
+            self.sequence, self.bad_value
+        )
+    }
 }
 
 #[cfg(test)]
 mod tryparse_x11error_test {
     use super::{ErrorKind, ExtInfoProvider, ParseError, X11Error};
     use crate::x11_utils::ExtensionInformation;
+    use alloc::format;
+    use alloc::string::ToString;
 
     struct Provider;
 
@@ -109,11 +141,115 @@ mod tryparse_x11error_test {
         assert_eq!(error, Ok(expected));
     }
 
+    #[test]
+    fn display_core_error() {
+        let input = [
+            0, 3, 5, 0, 0x21, 0, 0, 0, 0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0,
+        ];
+        let error = X11Error::try_parse(&input, &Provider).unwrap();
+        assert_eq!(error.extension(), "core");
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Window error from core::UnmapWindow (sequence {}, bad value {:#x})",
+                u16::from_ne_bytes([5, 0]),
+                u32::from_ne_bytes([0x21, 0, 0, 0]),
+            )
+        );
+    }
+
     #[test]
     fn reject_invalid_response_type() {
         let result = X11Error::try_parse(&[1; 32], &Provider);
         assert_eq!(Err(ParseError::InvalidValue), result);
     }
+
+    /// Provides the information for a single extension with major opcode 143 and first error 150.
+    struct ExtensionProvider(&'static str);
+
+    impl ExtensionProvider {
+        const INFO: ExtensionInformation = ExtensionInformation {
+            major_opcode: 143,
+            first_event: 91,
+            first_error: 150,
+        };
+    }
+
+    impl ExtInfoProvider for ExtensionProvider {
+        fn get_from_major_opcode(&self, major_opcode: u8) -> Option<(&str, ExtensionInformation)> {
+            Some((self.0, Self::INFO)).filter(|_| major_opcode == Self::INFO.major_opcode)
+        }
+        fn get_from_event_code(&self, _event_code: u8) -> Option<(&str, ExtensionInformation)> {
+            unimplemented!()
+        }
+        fn get_from_error_code(&self, error_code: u8) -> Option<(&str, ExtensionInformation)> {
+            Some((self.0, Self::INFO)).filter(|_| error_code >= Self::INFO.first_error)
+        }
+    }
+
+    /// Does not know any extensions.
+    struct NoExtensionProvider;
+
+    impl ExtInfoProvider for NoExtensionProvider {
+        fn get_from_major_opcode(&self, _major_opcode: u8) -> Option<(&str, ExtensionInformation)> {
+            None
+        }
+        fn get_from_event_code(&self, _event_code: u8) -> Option<(&str, ExtensionInformation)> {
+            unimplemented!()
+        }
+        fn get_from_error_code(&self, _error_code: u8) -> Option<(&str, ExtensionInformation)> {
+            None
+        }
+    }
+
+    fn extension_error(error_code: u8) -> [u8; 32] {
+        let mut input = [0; 32];
+        input[1] = error_code;
+        input[8..10].copy_from_slice(&4u16.to_ne_bytes());
+        input[10] = ExtensionProvider::INFO.major_opcode;
+        input
+    }
+
+    #[test]
+    fn unknown_extension_error() {
+        let error =
+            X11Error::try_parse(&extension_error(150), &ExtensionProvider("UNKNOWN")).unwrap();
+        assert_eq!(error.error_kind, ErrorKind::Unknown(150));
+        assert_eq!(error.extension_name.as_deref(), Some("UNKNOWN"));
+        assert_eq!(error.request_name, None);
+        assert!(error
+            .to_string()
+            .starts_with("Unknown(150) error from UNKNOWN::143.4 "));
+
+        // The extension is not even known by name
+        let error = X11Error::try_parse(&extension_error(150), &NoExtensionProvider).unwrap();
+        assert_eq!(error.extension(), "unknown");
+    }
+
+    #[cfg(feature = "damage")]
+    #[test]
+    fn damage_error() {
+        use crate::protocol::damage;
+
+        let provider = ExtensionProvider(damage::X11_EXTENSION_NAME);
+        let error = X11Error::try_parse(&extension_error(150), &provider).unwrap();
+        assert_eq!(error.error_kind, ErrorKind::DamageBadDamage);
+        assert_eq!(error.error_code, 150);
+        assert_eq!(error.minor_opcode, 4);
+        assert_eq!(
+            error.extension_name.as_deref(),
+            Some(damage::X11_EXTENSION_NAME)
+        );
+        assert_eq!(error.request_name, Some("Add"));
+        assert!(error
+            .to_string()
+            .starts_with("DamageBadDamage error from DAMAGE::Add "));
+
+        // Error codes after the extension's errors are unknown
+        let error = X11Error::try_parse(&extension_error(151), &provider).unwrap();
+        assert_eq!(error.error_kind, ErrorKind::Unknown(151));
+    }
 }
 
 impl From<&X11Error> for [u8; 32] {
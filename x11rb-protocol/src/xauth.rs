@@ -375,6 +375,29 @@ mod test {
         );
     }
 
+    #[test]
+    fn first_matching_entry() {
+        let entry = |family, address: &[u8], number: &[u8], data: &[u8]| AuthEntry {
+            family,
+            address: address.to_vec(),
+            number: number.to_vec(),
+            name: MIT_MAGIC_COOKIE_1.to_vec(),
+            data: data.to_vec(),
+        };
+        let entries = vec![
+            Ok(entry(Family::LOCAL, b"otherhost", b"42", b"other host")),
+            Ok(entry(Family::LOCAL, b"whatever", b"1", b"other display")),
+            Ok(entry(Family::LOCAL, b"whatever", b"42", b"first")),
+            Ok(entry(Family::WILD, b"", b"", b"second")),
+        ];
+        assert_eq!(
+            get_auth_impl(entries.into_iter(), Family::LOCAL, b"whatever", 42)
+                .unwrap()
+                .unwrap(),
+            (MIT_MAGIC_COOKIE_1.to_vec(), b"first".to_vec())
+        );
+    }
+
     #[test]
     fn family_mismatch() {
         expect_mismatch(|entry| entry.family = Family::KRB5_PRINCIPAL);
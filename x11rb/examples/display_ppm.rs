@@ -74,16 +74,8 @@ fn create_window(
 /// colors). Otherwise, this exits the process.
 fn check_visual(screen: &Screen, id: Visualid) -> PixelLayout {
     // Find the information about the visual and at the same time check its depth.
-    let visual_info = screen
-        .allowed_depths
-        .iter()
-        .filter_map(|depth| {
-            let info = depth.visuals.iter().find(|depth| depth.visual_id == id);
-            info.map(|info| (depth.depth, info))
-        })
-        .next();
-    let (depth, visual_type) = match visual_info {
-        Some(info) => info,
+    let (depth, visual_type) = match screen.visual_by_id(id) {
+        Some((depth, visual_type)) => (depth.depth, visual_type),
         None => {
             eprintln!("Did not find the root visual's description?!");
             std::process::exit(1);
@@ -0,0 +1,137 @@
+//! Using a connection from asynchronous code.
+//!
+//! [`Connection::wait_for_event`] and [`Cookie::reply`] block the current thread. In an async
+//! application, this stalls the executor. [`AsyncConnection`] wraps an existing connection and
+//! instead awaits until the connection's file descriptor becomes readable, and then uses the
+//! non-blocking [`Connection::poll_for_event`] and [`Cookie::try_reply`]. Waiting for a reply
+//! thus fails with an error if the wrapped connection does not override
+//! [`poll_for_reply_or_raw_error`](crate::connection::RequestConnection::poll_for_reply_or_raw_error).
+//!
+//! This module does not depend on any async runtime. Waiting for the file descriptor is left to
+//! an implementation of [`WaitReadable`], which usually uses the reactor of the runtime. With
+//! tokio, this could e.g. wrap a `tokio::io::unix::AsyncFd` for the result of
+//! `conn.stream().as_raw_fd()` (with [`RustConnection`](crate::rust_connection::RustConnection))
+//! or `conn.as_raw_fd()` (with `XCBConnection`), await `readable()` and clear the readiness.
+//!
+//! The code in this module is only available when the `async` feature of the library is enabled.
+//!
+//! Usage example:
+//! ```no_run
+//! use x11rb::async_connection::{AsyncConnection, WaitReadable};
+//! use x11rb::connection::Connection;
+//! use x11rb::protocol::xproto::ConnectionExt;
+//!
+//! async fn event_loop<C, W>(conn: &AsyncConnection<C, W>) -> Result<(), Box<dyn std::error::Error>>
+//! where
+//!     C: Connection,
+//!     W: WaitReadable,
+//! {
+//!     let cookie = conn.connection().get_input_focus()?;
+//!     let focus = conn.reply(cookie).await?;
+//!     println!("The focus is on window {}", focus.focus);
+//!     loop {
+//!         let event = conn.wait_for_event().await?;
+//!         println!("{:?}", event);
+//!     }
+//! }
+//! ```
+//!
+//! Readiness of the file descriptor is only a hint that data can be read. Data that was already
+//! read by another user of the same connection, e.g. by a call to `poll_for_event` on another
+//! thread, does not make the file descriptor readable again. Thus, while a task waits on the
+//! `AsyncConnection`, events and replies should not be read from the connection elsewhere.
+
+use std::future::Future;
+use std::io::Result as IoResult;
+use std::pin::Pin;
+
+use crate::connection::{Connection, ReplyOrError};
+use crate::cookie::Cookie;
+use crate::errors::{ConnectionError, ReplyError};
+use crate::protocol::Event;
+use crate::x11_utils::TryParse;
+use x11rb_protocol::SequenceNumber;
+
+/// The future returned by [`WaitReadable::wait_readable`].
+pub type ReadableFuture<'a> = Pin<Box<dyn Future<Output = IoResult<()>> + Send + 'a>>;
+
+/// Waiting until the file descriptor of a connection becomes readable.
+///
+/// This is the interface between [`AsyncConnection`] and an async runtime.
+pub trait WaitReadable {
+    /// Wait until the connection's file descriptor is readable.
+    ///
+    /// The returned future should complete when new data can be read. It may also complete
+    /// spuriously, but it should not complete again for data that was already signalled before.
+    fn wait_readable(&self) -> ReadableFuture<'_>;
+}
+
+/// A wrapper around a [`Connection`] with async methods for waiting.
+#[derive(Debug)]
+pub struct AsyncConnection<C, W> {
+    conn: C,
+    waiter: W,
+}
+
+impl<C: Connection, W: WaitReadable> AsyncConnection<C, W> {
+    /// Wrap a connection. `waiter` has to wait for the file descriptor of `conn`.
+    pub fn new(conn: C, waiter: W) -> Self {
+        Self { conn, waiter }
+    }
+
+    /// Get the wrapped connection, e.g. for sending requests.
+    pub fn connection(&self) -> &C {
+        &self.conn
+    }
+
+    /// Get back the wrapped connection and the waiter.
+    pub fn into_inner(self) -> (C, W) {
+        (self.conn, self.waiter)
+    }
+
+    /// Wait for a new event from the X11 server.
+    ///
+    /// This is the async version of [`Connection::wait_for_event`].
+    pub async fn wait_for_event(&self) -> Result<Event, ConnectionError> {
+        loop {
+            if let Some(event) = self.conn.poll_for_event()? {
+                return Ok(event);
+            }
+            self.waiter.wait_readable().await?;
+        }
+    }
+
+    /// Wait for the reply to a request.
+    ///
+    /// This is the async version of [`Cookie::reply`]. The connection is flushed first, so that
+    /// the request actually reaches the X11 server.
+    pub async fn reply<R: TryParse>(&self, mut cookie: Cookie<'_, C, R>) -> Result<R, ReplyError> {
+        self.conn.flush()?;
+        loop {
+            cookie = match cookie.try_reply()? {
+                Ok(reply) => return Ok(reply),
+                Err(cookie) => cookie,
+            };
+            self.waiter.wait_readable().await?;
+        }
+    }
+
+    /// Wait for the reply or error of the request with the given sequence number.
+    ///
+    /// This is the async version of [`RequestConnection::wait_for_reply_or_raw_error`]. The
+    /// connection is flushed first, so that the request actually reaches the X11 server.
+    ///
+    /// [`RequestConnection::wait_for_reply_or_raw_error`]: crate::connection::RequestConnection::wait_for_reply_or_raw_error
+    pub async fn wait_for_reply_or_raw_error(
+        &self,
+        sequence: SequenceNumber,
+    ) -> Result<ReplyOrError<C::Buf>, ConnectionError> {
+        self.conn.flush()?;
+        loop {
+            if let Some(reply) = self.conn.poll_for_reply_or_raw_error(sequence)? {
+                return Ok(reply);
+            }
+            self.waiter.wait_readable().await?;
+        }
+    }
+}
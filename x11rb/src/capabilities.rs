@@ -0,0 +1,250 @@
+//! A report of the X11 extensions that a server supports.
+//!
+//! [`capabilities`] checks for a list of commonly used X11 extensions and queries their version.
+//! All the necessary requests are pipelined so that this needs only two round trips to the X11
+//! server. This is mostly useful for diagnostic purposes, e.g. a command that prints information
+//! about the X11 server.
+//!
+//! Only extensions whose feature flag is enabled are part of the report. For example, the `randr`
+//! field of [`Capabilities`] only exists if the `randr` feature is enabled.
+//!
+//! Usage example:
+//! ```no_run
+//! use x11rb::capabilities::capabilities;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let (conn, _) = x11rb::connect(None)?;
+//!     println!("{:#?}", capabilities(&conn)?);
+//!     Ok(())
+//! }
+//! ```
+
+use crate::connection::RequestConnection;
+use crate::cookie::Cookie;
+use crate::errors::{ConnectionError, ReplyError};
+
+/// The version of an X11 extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    /// The major version.
+    pub major: u32,
+    /// The minor version.
+    pub minor: u32,
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// The versions of the X11 extensions that are supported by an X11 server.
+///
+/// A field is `None` if the X11 server does not support the corresponding extension.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// The version of the `RANDR` extension.
+    #[cfg(feature = "randr")]
+    pub randr: Option<Version>,
+    /// The version of the `XInputExtension` extension, as reported by `XIQueryVersion`.
+    #[cfg(feature = "xinput")]
+    pub xinput: Option<Version>,
+    /// The version of the `XFIXES` extension.
+    #[cfg(feature = "xfixes")]
+    pub xfixes: Option<Version>,
+    /// The version of the `SHAPE` extension.
+    #[cfg(feature = "shape")]
+    pub shape: Option<Version>,
+    /// The version of the `RENDER` extension.
+    #[cfg(feature = "render")]
+    pub render: Option<Version>,
+    /// The version of the `Composite` extension.
+    #[cfg(feature = "composite")]
+    pub composite: Option<Version>,
+    /// The version of the `Present` extension.
+    #[cfg(feature = "present")]
+    pub present: Option<Version>,
+    /// The version of the `MIT-SHM` extension.
+    #[cfg(feature = "shm")]
+    pub shm: Option<Version>,
+    /// The version of the `SYNC` extension.
+    #[cfg(feature = "sync")]
+    pub sync: Option<Version>,
+    /// Whether the `XKEYBOARD` extension is present.
+    ///
+    /// Its version is not queried, since the only request that reports it, `UseExtension`, also
+    /// enables the extension for the client.
+    #[cfg(feature = "xkb")]
+    pub xkb: bool,
+    /// The version of the `DPMS` extension.
+    #[cfg(feature = "dpms")]
+    pub dpms: Option<Version>,
+}
+
+/// Turn the result of sending an extension request into an `Option`.
+///
+/// `ConnectionError::UnsupportedExtension` is turned into `Ok(None)`.
+#[allow(dead_code)]
+fn optional<T>(result: Result<T, ConnectionError>) -> Result<Option<T>, ConnectionError> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(ConnectionError::UnsupportedExtension) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Get the reply for an optional cookie and turn it into a `Version`.
+#[allow(dead_code)]
+fn version<C, R, V>(
+    cookie: Option<Cookie<'_, C, R>>,
+    get_version: impl FnOnce(R) -> (V, V),
+) -> Result<Option<Version>, ReplyError>
+where
+    C: RequestConnection + ?Sized,
+    R: crate::x11_utils::TryParse,
+    V: Into<u32>,
+{
+    Ok(match cookie {
+        Some(cookie) => {
+            let (major, minor) = get_version(cookie.reply()?);
+            Some(Version {
+                major: major.into(),
+                minor: minor.into(),
+            })
+        }
+        None => None,
+    })
+}
+
+/// Query the X11 server for the supported extensions and their versions.
+///
+/// This sends a `QueryExtension` request for every extension and a version request for every
+/// extension that is present. For the `XKEYBOARD` extension, only its presence is reported, see
+/// [`Capabilities::xkb`].
+pub fn capabilities<C: RequestConnection + ?Sized>(conn: &C) -> Result<Capabilities, ReplyError> {
+    #[allow(unused_mut)]
+    let mut result = Capabilities::default();
+
+    // Send all QueryExtension requests before waiting for any reply
+    for &name in EXTENSION_NAMES {
+        conn.prefetch_extension_information(name)?;
+    }
+
+    // Now send the version requests for all present extensions...
+    #[cfg(feature = "randr")]
+    let randr = {
+        use crate::protocol::randr;
+        let (major, minor) = randr::X11_XML_VERSION;
+        optional(randr::query_version(conn, major, minor))?
+    };
+    #[cfg(feature = "xinput")]
+    let xinput = optional(crate::protocol::xinput::xi_query_version(conn, 2, 4))?;
+    #[cfg(feature = "xfixes")]
+    let xfixes = {
+        use crate::protocol::xfixes;
+        let (major, minor) = xfixes::X11_XML_VERSION;
+        optional(xfixes::query_version(conn, major, minor))?
+    };
+    #[cfg(feature = "shape")]
+    let shape = optional(crate::protocol::shape::query_version(conn))?;
+    #[cfg(feature = "render")]
+    let render = {
+        use crate::protocol::render;
+        let (major, minor) = render::X11_XML_VERSION;
+        optional(render::query_version(conn, major, minor))?
+    };
+    #[cfg(feature = "composite")]
+    let composite = {
+        use crate::protocol::composite;
+        let (major, minor) = composite::X11_XML_VERSION;
+        optional(composite::query_version(conn, major, minor))?
+    };
+    #[cfg(feature = "present")]
+    let present = {
+        use crate::protocol::present;
+        let (major, minor) = present::X11_XML_VERSION;
+        optional(present::query_version(conn, major, minor))?
+    };
+    #[cfg(feature = "shm")]
+    let shm = optional(crate::protocol::shm::query_version(conn))?;
+    #[cfg(feature = "sync")]
+    let sync = optional(crate::protocol::sync::initialize(conn, 3, 1))?;
+    #[cfg(feature = "dpms")]
+    let dpms = optional(crate::protocol::dpms::get_version(conn, 1, 1))?;
+
+    // ...and then collect the replies.
+    #[cfg(feature = "randr")]
+    {
+        result.randr = version(randr, |r| (r.major_version, r.minor_version))?;
+    }
+    #[cfg(feature = "xinput")]
+    {
+        result.xinput = version(xinput, |r| (r.major_version, r.minor_version))?;
+    }
+    #[cfg(feature = "xfixes")]
+    {
+        result.xfixes = version(xfixes, |r| (r.major_version, r.minor_version))?;
+    }
+    #[cfg(feature = "shape")]
+    {
+        result.shape = version(shape, |r| (r.major_version, r.minor_version))?;
+    }
+    #[cfg(feature = "render")]
+    {
+        result.render = version(render, |r| (r.major_version, r.minor_version))?;
+    }
+    #[cfg(feature = "composite")]
+    {
+        result.composite = version(composite, |r| (r.major_version, r.minor_version))?;
+    }
+    #[cfg(feature = "present")]
+    {
+        result.present = version(present, |r| (r.major_version, r.minor_version))?;
+    }
+    #[cfg(feature = "shm")]
+    {
+        result.shm = version(shm, |r| (r.major_version, r.minor_version))?;
+    }
+    #[cfg(feature = "sync")]
+    {
+        result.sync = version(sync, |r| (r.major_version, r.minor_version))?;
+    }
+    #[cfg(feature = "xkb")]
+    {
+        let name = crate::protocol::xkb::X11_EXTENSION_NAME;
+        result.xkb = conn.extension_information(name)?.is_some();
+    }
+    #[cfg(feature = "dpms")]
+    {
+        result.dpms = version(dpms, |r| (r.server_major_version, r.server_minor_version))?;
+    }
+
+    Ok(result)
+}
+
+/// The names of all the extensions that are part of the report.
+const EXTENSION_NAMES: &[&str] = &[
+    #[cfg(feature = "randr")]
+    crate::protocol::randr::X11_EXTENSION_NAME,
+    #[cfg(feature = "xinput")]
+    crate::protocol::xinput::X11_EXTENSION_NAME,
+    #[cfg(feature = "xfixes")]
+    crate::protocol::xfixes::X11_EXTENSION_NAME,
+    #[cfg(feature = "shape")]
+    crate::protocol::shape::X11_EXTENSION_NAME,
+    #[cfg(feature = "render")]
+    crate::protocol::render::X11_EXTENSION_NAME,
+    #[cfg(feature = "composite")]
+    crate::protocol::composite::X11_EXTENSION_NAME,
+    #[cfg(feature = "present")]
+    crate::protocol::present::X11_EXTENSION_NAME,
+    #[cfg(feature = "shm")]
+    crate::protocol::shm::X11_EXTENSION_NAME,
+    #[cfg(feature = "sync")]
+    crate::protocol::sync::X11_EXTENSION_NAME,
+    #[cfg(feature = "xkb")]
+    crate::protocol::xkb::X11_EXTENSION_NAME,
+    #[cfg(feature = "dpms")]
+    crate::protocol::dpms::X11_EXTENSION_NAME,
+];
@@ -0,0 +1,232 @@
+//! Utility functions for working with read/write colormap cells.
+//!
+//! On visuals with a writable colormap (e.g. `PseudoColor`), a client can allocate private
+//! colormap cells via `AllocColorCells` and later change the colors of these cells via
+//! `StoreColors`. This is for example used for animations via colormap cycling.
+//!
+//! Usage example:
+//! ```no_run
+//! use x11rb::colormap::ColorCells;
+//! use x11rb::connection::Connection;
+//! use x11rb::errors::ReplyOrIdError;
+//! use x11rb::protocol::xproto::{ColormapAlloc, ColormapWrapper, Visualid, Window};
+//!
+//! fn cycle(conn: &impl Connection, window: Window, visual: Visualid) -> Result<(), ReplyOrIdError> {
+//!     let cmap = ColormapWrapper::create_colormap(conn, ColormapAlloc::NONE, window, visual)?;
+//!     let cells = ColorCells::alloc(conn, cmap.colormap(), false, 16, 0)?.reply()?;
+//!     let mut colors = (0..16u16)
+//!         .map(|i| (i << 12, 0, 0xffff - (i << 12)))
+//!         .collect::<Vec<_>>();
+//!     for _ in 0..100 {
+//!         cells.store(conn, cmap.colormap(), &colors)?;
+//!         colors.rotate_left(1);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::convert::TryFrom;
+
+use crate::connection::RequestConnection;
+use crate::cookie::{Cookie, VoidCookie};
+use crate::errors::{ConnectionError, ParseError, ReplyError};
+use crate::protocol::xproto::{self, AllocColorCellsReply, ColorFlag, Coloritem, Colormap};
+
+/// A cookie for allocating read/write colormap cells.
+///
+/// See `ColorCells`.
+#[derive(Debug)]
+pub struct ColorCellsCookie<'a, Conn: RequestConnection + ?Sized>(
+    Cookie<'a, Conn, AllocColorCellsReply>,
+);
+
+impl<'a, Conn> ColorCellsCookie<'a, Conn>
+where
+    Conn: RequestConnection + ?Sized,
+{
+    /// Send an `AllocColorCells` request for the given colormap.
+    pub fn new(
+        conn: &'a Conn,
+        cmap: Colormap,
+        contiguous: bool,
+        colors: u16,
+        planes: u16,
+    ) -> Result<Self, ConnectionError> {
+        Ok(Self(xproto::alloc_color_cells(
+            conn, contiguous, cmap, colors, planes,
+        )?))
+    }
+
+    /// Get the reply that the server sent.
+    pub fn reply(self) -> Result<ColorCells, ReplyError> {
+        Ok(ColorCells::from_reply(self.0.reply()?))
+    }
+
+    /// Get the reply that the server sent, but have errors handled as events.
+    pub fn reply_unchecked(self) -> Result<Option<ColorCells>, ConnectionError> {
+        Ok(self.0.reply_unchecked()?.map(ColorCells::from_reply))
+    }
+}
+
+/// Read/write colormap cells that were allocated via `AllocColorCells`.
+///
+/// The server allocates `pixels.len() * 2^masks.len()` cells. Each cell is described by one of
+/// the `pixels` combined with any subset of the plane `masks`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColorCells {
+    /// The allocated base pixel values.
+    pub pixels: Vec<u32>,
+    /// The allocated plane masks. Each mask has exactly one bit set.
+    pub masks: Vec<u32>,
+}
+
+impl ColorCells {
+    /// Send an `AllocColorCells` request for `colors` pixels and `planes` plane masks.
+    pub fn alloc<C: RequestConnection + ?Sized>(
+        conn: &C,
+        cmap: Colormap,
+        contiguous: bool,
+        colors: u16,
+        planes: u16,
+    ) -> Result<ColorCellsCookie<'_, C>, ConnectionError> {
+        ColorCellsCookie::new(conn, cmap, contiguous, colors, planes)
+    }
+
+    /// Construct a new `ColorCells` instance from an `AllocColorCellsReply`.
+    pub fn from_reply(reply: AllocColorCellsReply) -> Self {
+        Self {
+            pixels: reply.pixels,
+            masks: reply.masks,
+        }
+    }
+
+    /// Get the bitwise or of all allocated plane masks.
+    pub fn plane_mask(&self) -> u32 {
+        self.masks.iter().fold(0, |acc, mask| acc | mask)
+    }
+
+    /// Get the pixel values of all allocated cells.
+    ///
+    /// This combines every pixel with every subset of the plane masks. The result is ordered by
+    /// pixel first.
+    ///
+    /// `ParseError::ConversionFailed` is returned if the number of cells does not fit into an
+    /// `usize`.
+    pub fn all_pixels(&self) -> Result<Vec<u32>, ParseError> {
+        let subsets = u32::try_from(self.masks.len())
+            .ok()
+            .and_then(|planes| 1usize.checked_shl(planes))
+            .ok_or(ParseError::ConversionFailed)?;
+        let cells = self
+            .pixels
+            .len()
+            .checked_mul(subsets)
+            .ok_or(ParseError::ConversionFailed)?;
+        let mut result = Vec::with_capacity(cells);
+        for &pixel in &self.pixels {
+            for subset in 0..subsets {
+                let planes = self
+                    .masks
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| subset & (1 << index) != 0)
+                    .fold(0, |acc, (_, mask)| acc | mask);
+                result.push(pixel | planes);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Store the given RGB colors in the allocated pixels.
+    ///
+    /// The `n`-th color is stored in the `n`-th entry of `pixels`. Superfluous colors are ignored.
+    pub fn store<'a, C: RequestConnection + ?Sized>(
+        &self,
+        conn: &'a C,
+        cmap: Colormap,
+        colors: &[(u16, u16, u16)],
+    ) -> Result<VoidCookie<'a, C>, ConnectionError> {
+        let items = self
+            .pixels
+            .iter()
+            .zip(colors)
+            .map(|(&pixel, &(red, green, blue))| rgb_item(pixel, red, green, blue))
+            .collect::<Vec<_>>();
+        xproto::store_colors(conn, cmap, &items)
+    }
+
+    /// Free all the allocated cells via `FreeColors`.
+    pub fn free<'a, C: RequestConnection + ?Sized>(
+        &self,
+        conn: &'a C,
+        cmap: Colormap,
+    ) -> Result<VoidCookie<'a, C>, ConnectionError> {
+        xproto::free_colors(conn, cmap, self.plane_mask(), &self.pixels)
+    }
+}
+
+/// Construct a `Coloritem` that sets the red, green and blue components of a pixel.
+///
+/// The result can be passed to [`xproto::store_colors`].
+pub fn rgb_item(pixel: u32, red: u16, green: u16, blue: u16) -> Coloritem {
+    Coloritem {
+        pixel,
+        red,
+        green,
+        blue,
+        flags: ColorFlag::RED | ColorFlag::GREEN | ColorFlag::BLUE,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{rgb_item, ColorCells};
+    use crate::errors::ParseError;
+    use crate::protocol::xproto::{AllocColorCellsReply, ColorFlag};
+
+    #[test]
+    fn test_from_reply() {
+        let reply = AllocColorCellsReply {
+            sequence: 0,
+            length: 3,
+            pixels: vec![1],
+            masks: vec![4, 16],
+        };
+        let cells = ColorCells::from_reply(reply);
+        assert_eq!(cells.pixels, [1]);
+        assert_eq!(cells.masks, [4, 16]);
+        assert_eq!(cells.plane_mask(), 20);
+    }
+
+    #[test]
+    fn test_all_pixels() {
+        let cells = ColorCells {
+            pixels: vec![1, 2],
+            masks: vec![4, 16],
+        };
+        assert_eq!(cells.all_pixels().unwrap(), [1, 5, 17, 21, 2, 6, 18, 22]);
+
+        let cells = ColorCells {
+            pixels: vec![3, 8],
+            masks: vec![],
+        };
+        assert_eq!(cells.all_pixels().unwrap(), [3, 8]);
+
+        // At least as many planes as bits in an usize
+        let cells = ColorCells {
+            pixels: vec![0],
+            masks: vec![1; 64],
+        };
+        assert_eq!(cells.all_pixels(), Err(ParseError::ConversionFailed));
+    }
+
+    #[test]
+    fn test_rgb_item() {
+        let item = rgb_item(7, 1, 2, 3);
+        assert_eq!((item.pixel, item.red, item.green, item.blue), (7, 1, 2, 3));
+        assert_eq!(
+            item.flags,
+            ColorFlag::RED | ColorFlag::GREEN | ColorFlag::BLUE
+        );
+    }
+}
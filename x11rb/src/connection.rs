@@ -6,12 +6,13 @@
 use std::borrow::Cow;
 use std::convert::{TryFrom, TryInto};
 use std::io::IoSlice;
+use std::time::Duration;
 
 use x11rb_protocol::x11_utils::{ReplyFDsRequest, ReplyRequest, VoidRequest};
 
 use crate::cookie::{Cookie, CookieWithFds, VoidCookie};
-use crate::errors::{ConnectionError, ParseError, ReplyError, ReplyOrIdError};
-use crate::protocol::xproto::Setup;
+use crate::errors::{ConnectionError, EventParseError, ParseError, ReplyError, ReplyOrIdError};
+use crate::protocol::xproto::{Format, Setup};
 use crate::protocol::Event;
 use crate::utils::RawFdContainer;
 use crate::x11_utils::{ExtensionInformation, TryParse, TryParseFd, X11Error};
@@ -25,6 +26,8 @@ pub type BufWithFds<B> = (B, Vec<RawFdContainer>);
 pub type EventAndSeqNumber = (Event, SequenceNumber);
 /// A buffer that is logically continuous, but presented in a number of pieces.
 pub type PiecewiseBuf<'a> = Vec<Cow<'a, [u8]>>;
+/// A cookie and the serialized bytes of the request that it belongs to.
+pub type CookieWithBytes<'a, C, R> = (Cookie<'a, C, R>, Vec<u8>);
 
 /// Either a raw reply or a raw error response to an X11 request.
 #[derive(Debug)]
@@ -100,6 +103,43 @@ pub trait RequestConnection {
         self.send_request_with_reply(&[IoSlice::new(&*buf)], fds)
     }
 
+    /// Send a request with a reply to the server and keep a copy of its bytes.
+    ///
+    /// This works like [`RequestConnection::send_trait_request_with_reply`], but additionally
+    /// returns the serialized request. A client that reconnects after losing its connection can
+    /// use these bytes to send the request again, e.g. via
+    /// [`RequestConnection::send_request_with_reply`] on the new connection.
+    ///
+    /// The bytes are only a starting point for replaying the request:
+    ///
+    /// - XIDs (windows, pixmaps, atoms, ...) in the request refer to resources of the old
+    ///   connection. The resources have to be recreated on the new connection and the XIDs in the
+    ///   request have to be replaced with the new ones.
+    /// - The first byte of an extension request is the major opcode of the extension, which can
+    ///   be different on a new connection.
+    /// - The new request gets a new sequence number, so the old cookie cannot be used to get its
+    ///   reply.
+    /// - File descriptors that are part of the request are not retained.
+    fn send_request_retainable<R>(
+        &self,
+        request: R,
+    ) -> Result<CookieWithBytes<'_, Self, R::Reply>, ConnectionError>
+    where
+        R: ReplyRequest,
+    {
+        let opcode = match R::EXTENSION_NAME {
+            None => 0,
+            Some(extension) => {
+                self.extension_information(extension)?
+                    .ok_or(ConnectionError::UnsupportedExtension)?
+                    .major_opcode
+            }
+        };
+        let (buf, fds) = request.serialize(opcode);
+        let cookie = self.send_request_with_reply(&[IoSlice::new(&buf)], fds)?;
+        Ok((cookie, buf))
+    }
+
     /// Send a request with a reply containing file descriptors to the server.
     ///
     /// The `bufs` parameter describes the raw bytes that should be sent. The returned cookie
@@ -206,6 +246,19 @@ pub trait RequestConnection {
     /// Users of this library will most likely not want to use this function directly.
     fn discard_reply(&self, sequence: SequenceNumber, kind: RequestKind, mode: DiscardMode);
 
+    /// The cookie of a request with a reply was consumed without waiting for or discarding its
+    /// reply.
+    ///
+    /// This method is automatically called by e.g. [`crate::cookie::Cookie::forget`]. The reply
+    /// stays with the connection, but the request no longer counts towards
+    /// [`Connection::outstanding_requests`].
+    ///
+    /// Users of this library will most likely not want to use this function directly. The default
+    /// implementation does nothing.
+    fn forget_reply(&self, sequence: SequenceNumber) {
+        let _ = sequence;
+    }
+
     /// Prefetches information about an extension.
     ///
     /// If the information of a extension is not cached yet, this function sends a
@@ -233,6 +286,44 @@ pub trait RequestConnection {
         extension_name: &'static str,
     ) -> Result<Option<ExtensionInformation>, ConnectionError>;
 
+    /// Get information about an extension, matching its name case-insensitively.
+    ///
+    /// The X11 server compares extension names exactly, so e.g. `"randr"` does not find the
+    /// `"RANDR"` extension with [`RequestConnection::extension_information`]. This function
+    /// instead sends a `ListExtensions` request and looks for a name that matches
+    /// `extension_name` while ignoring ASCII case. The canonical name reported by the server is
+    /// returned together with the information about the extension. Extensions that are not
+    /// present are returned as `None`.
+    ///
+    /// Unlike [`RequestConnection::extension_information`], the result is not cached. This
+    /// function is meant for diagnosing names with the wrong case; use the canonical name with
+    /// `extension_information` afterwards.
+    fn extension_information_ci(
+        &self,
+        extension_name: &str,
+    ) -> Result<Option<(String, ExtensionInformation)>, ReplyError> {
+        let names = crate::protocol::xproto::list_extensions(self)?
+            .reply()?
+            .names;
+        let name = match names
+            .into_iter()
+            .find(|name| name.name.eq_ignore_ascii_case(extension_name.as_bytes()))
+        {
+            Some(name) => name.name,
+            None => return Ok(None),
+        };
+        let reply = crate::protocol::xproto::query_extension(self, &name)?.reply()?;
+        if !reply.present {
+            return Ok(None);
+        }
+        let info = ExtensionInformation {
+            major_opcode: reply.major_opcode,
+            first_event: reply.first_event,
+            first_error: reply.first_error,
+        };
+        Ok(Some((String::from_utf8_lossy(&name).into_owned(), info)))
+    }
+
     /// Wait for the reply to a request.
     ///
     /// The given sequence number identifies the request for which replies are expected. If the X11
@@ -259,6 +350,49 @@ pub trait RequestConnection {
         sequence: SequenceNumber,
     ) -> Result<ReplyOrError<Self::Buf>, ConnectionError>;
 
+    /// Check whether the reply to a request was already received, without blocking.
+    ///
+    /// The given sequence number identifies the request for which replies are expected. Returns
+    /// `None` if neither a reply nor an error was received yet. If the X11 server answered the
+    /// request with an error, that error is returned as an `Err`.
+    ///
+    /// Once a reply or error was returned, it is consumed and cannot be returned again.
+    ///
+    /// Users of this library will most likely not want to use this function directly.
+    fn poll_for_reply_or_error(
+        &self,
+        sequence: SequenceNumber,
+    ) -> Result<Option<Self::Buf>, ReplyError> {
+        match self.poll_for_reply_or_raw_error(sequence)? {
+            Some(ReplyOrError::Reply(reply)) => Ok(Some(reply)),
+            Some(ReplyOrError::Error(error)) => {
+                Err(ReplyError::X11Error(self.parse_error(error.as_ref())?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Check whether the reply to a request was already received, without blocking.
+    ///
+    /// The given sequence number identifies the request for which replies are expected. Returns
+    /// `None` if neither a reply nor an error was received yet.
+    ///
+    /// The default implementation returns an error, since checking for a response without
+    /// blocking cannot be implemented on top of the other methods of this trait.
+    ///
+    /// Users of this library will most likely not want to use this function directly.
+    fn poll_for_reply_or_raw_error(
+        &self,
+        sequence: SequenceNumber,
+    ) -> Result<Option<ReplyOrError<Self::Buf>>, ConnectionError> {
+        let _ = sequence;
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "this connection does not support polling for replies",
+        )
+        .into())
+    }
+
     /// Wait for the reply to a request.
     ///
     /// The given sequence number identifies the request for which replies are expected. If the X11
@@ -350,6 +484,32 @@ pub trait RequestConnection {
     /// The maximum number of bytes that the X11 server accepts in a request.
     fn maximum_request_bytes(&self) -> usize;
 
+    /// Negotiates the maximum request length and returns the result.
+    ///
+    /// The first call to `maximum_request_bytes()` has to wait for the reply to a
+    /// `BigRequests::Enable` request. This function allows to perform this round trip at a
+    /// convenient time, for example during startup. Afterwards, `maximum_request_bytes_cached()`
+    /// returns the result without blocking.
+    ///
+    /// This function is a no-op if the maximum request length is already known. If the X11
+    /// server does not support the `BigRequests` extension, the maximum request length from the
+    /// `Setup` is used instead.
+    ///
+    /// The default implementation just calls `maximum_request_bytes()`.
+    fn enable_big_requests(&self) -> Result<usize, ConnectionError> {
+        Ok(self.maximum_request_bytes())
+    }
+
+    /// The maximum number of bytes that the X11 server accepts in a request, if already known.
+    ///
+    /// This function never blocks and never sends a request. It returns `None` if the maximum
+    /// request length was not negotiated yet, see `enable_big_requests()`.
+    ///
+    /// The default implementation always returns `None`.
+    fn maximum_request_bytes_cached(&self) -> Option<usize> {
+        None
+    }
+
     /// Parse a generic error.
     fn parse_error(&self, error: &[u8]) -> Result<X11Error, ParseError>;
 
@@ -372,7 +532,9 @@ pub trait Connection: RequestConnection {
     /// Wait for a new event from the X11 server.
     fn wait_for_event_with_sequence(&self) -> Result<EventAndSeqNumber, ConnectionError> {
         let (event, seq) = self.wait_for_raw_event_with_sequence()?;
-        let event = self.parse_event(event.as_ref())?;
+        let event = self
+            .parse_event(event.as_ref())
+            .map_err(|error| event_parse_error(event.as_ref(), error))?;
         Ok((event, seq))
     }
 
@@ -394,7 +556,12 @@ pub trait Connection: RequestConnection {
     /// Poll for a new event from the X11 server.
     fn poll_for_event_with_sequence(&self) -> Result<Option<EventAndSeqNumber>, ConnectionError> {
         Ok(match self.poll_for_raw_event_with_sequence()? {
-            Some((event, seq)) => Some((self.parse_event(event.as_ref())?, seq)),
+            Some((event, seq)) => {
+                let parsed = self
+                    .parse_event(event.as_ref())
+                    .map_err(|error| event_parse_error(event.as_ref(), error))?;
+                Some((parsed, seq))
+            }
             None => None,
         })
     }
@@ -404,6 +571,56 @@ pub trait Connection: RequestConnection {
         &self,
     ) -> Result<Option<RawEventAndSeqNumber<Self::Buf>>, ConnectionError>;
 
+    /// Get all events that are available without blocking.
+    ///
+    /// This calls [`Connection::poll_for_event`] until it returns `None` and collects the events.
+    /// X11 errors that are handled as events are included at their position in the stream.
+    ///
+    /// If an error occurs, the events that were already received are lost. Use
+    /// [`crate::event_loop_integration::drain_events`] to get them together with the error.
+    fn poll_for_queued_events(&self) -> Result<Vec<Event>, ConnectionError> {
+        let mut events = Vec::new();
+        while let Some(event) = self.poll_for_event()? {
+            events.push(event);
+        }
+        Ok(events)
+    }
+
+    /// Wait for a new event from the X11 server, but at most for the given duration.
+    ///
+    /// Returns `Ok(None)` if no event arrived before the timeout elapsed. This allows to
+    /// integrate the connection into an event loop that also has to do periodic work.
+    fn wait_for_event_with_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<Option<Event>, ConnectionError> {
+        Ok(match self.wait_for_raw_event_with_timeout(timeout)? {
+            Some(event) => Some(
+                self.parse_event(event.as_ref())
+                    .map_err(|error| event_parse_error(event.as_ref(), error))?,
+            ),
+            None => None,
+        })
+    }
+
+    /// Wait for a new raw/unparsed event from the X11 server, but at most for the given duration.
+    ///
+    /// Returns `Ok(None)` if no event arrived before the timeout elapsed.
+    ///
+    /// The default implementation returns an error, since a timeout cannot be implemented on top
+    /// of the other methods of this trait.
+    fn wait_for_raw_event_with_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<Option<Self::Buf>, ConnectionError> {
+        let _ = timeout;
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "this connection does not support waiting with a timeout",
+        )
+        .into())
+    }
+
     /// Send all pending requests to the server.
     ///
     /// Implementations of this trait may buffer requests for batched sending. When this method is
@@ -418,12 +635,92 @@ pub trait Connection: RequestConnection {
     /// The setup information contains X11 server, for example the window id of the root window.
     fn setup(&self) -> &Setup;
 
+    /// Get an owned copy of the vendor string of the X11 server.
+    ///
+    /// Invalid UTF-8 sequences are replaced with `U+FFFD REPLACEMENT CHARACTER`. Unlike
+    /// [`Connection::setup`], the result does not borrow the connection and can be sent to other
+    /// threads.
+    fn vendor_string(&self) -> String {
+        String::from_utf8_lossy(&self.setup().vendor).into_owned()
+    }
+
+    /// Get an owned copy of the pixmap formats supported by the X11 server.
+    ///
+    /// Unlike [`Connection::setup`], the result does not borrow the connection and can be sent to
+    /// other threads.
+    fn pixmap_formats(&self) -> Vec<Format> {
+        self.setup().pixmap_formats.clone()
+    }
+
     /// Generate a new X11 identifier.
     ///
     /// This method can, for example, be used for creating a new window. First, this method is
     /// called to generate an identifier. Next, `xproto::create_window` can be called to
     /// actually create the window.
     fn generate_id(&self) -> Result<u32, ReplyOrIdError>;
+
+    /// Get the number of requests that expect a reply whose reply was not yet handled.
+    ///
+    /// A request counts as outstanding from the moment it is sent until its reply or error is
+    /// waited for, e.g. via [`crate::cookie::Cookie::reply`], until it is discarded, e.g. by
+    /// dropping its cookie, or until its cookie is forgotten via [`crate::cookie::Cookie::forget`].
+    /// Requests without a reply are not counted.
+    ///
+    /// This can be used for flow control or for detecting code that sends requests faster than
+    /// it handles their replies.
+    ///
+    /// The default implementation does not track requests and always returns 0.
+    /// [`RustConnection`](../rust_connection/struct.RustConnection.html) and
+    /// [`XCBConnection`](../xcb_ffi/struct.XCBConnection.html) provide the actual count.
+    fn outstanding_requests(&self) -> usize {
+        0
+    }
+
+    /// Check if the connection is in an error state.
+    ///
+    /// A connection in an error state is broken permanently and all further operations on it
+    /// fail. This can be used to check the connection before sending more requests.
+    ///
+    /// Not all implementations have such a state. [`RustConnection`] reports errors from the
+    /// individual operations and always returns `None` here.
+    ///
+    /// [`RustConnection`]: crate::rust_connection::RustConnection
+    fn has_error(&self) -> Option<ConnectionError>;
+}
+
+/// Describe an event that could not be parsed.
+fn event_parse_error(event: &[u8], error: ParseError) -> EventParseError {
+    EventParseError {
+        response_type: event.first().copied().unwrap_or(0),
+        len: event.len(),
+        error,
+    }
+}
+
+/// A concise, human-readable summary of the setup information of an X11 server.
+///
+/// The `Display` implementation prints the vendor, protocol version, release number, and number
+/// of screens, for example `X11 server "The X.Org Foundation" release 12101004, protocol 11.0, 1
+/// screen`. This is meant for log messages. Use the `Debug` implementation of [`Setup`] for all
+/// details.
+#[derive(Debug, Clone, Copy)]
+pub struct SetupSummary<'a>(pub &'a Setup);
+
+impl std::fmt::Display for SetupSummary<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let setup = self.0;
+        let screens = setup.roots.len();
+        write!(
+            f,
+            "X11 server {:?} release {}, protocol {}.{}, {} screen{}",
+            String::from_utf8_lossy(&setup.vendor),
+            setup.release_number,
+            setup.protocol_major_version,
+            setup.protocol_minor_version,
+            screens,
+            if screens == 1 { "" } else { "s" },
+        )
+    }
 }
 
 /// Does a request have a response?
@@ -611,3 +908,65 @@ pub fn compute_length_field<'b>(
 
     Ok(&storage.0[..])
 }
+
+#[cfg(test)]
+mod test {
+    use super::{event_parse_error, SetupSummary};
+    use crate::errors::{EventParseError, ParseError};
+    use crate::protocol::xproto::{Screen, Setup};
+
+    #[test]
+    fn event_parse_error_context() {
+        use std::error::Error;
+
+        let mut event = [0; 32];
+        event[0] = 0x80 | 33;
+        let error = event_parse_error(&event, ParseError::InvalidValue);
+        assert_eq!(
+            error,
+            EventParseError {
+                response_type: 0xa1,
+                len: 32,
+                error: ParseError::InvalidValue,
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "An event with response type 161 and length 32 could not be parsed: \
+             A value could not be parsed into an enumeration"
+        );
+        assert_eq!(
+            error.source().map(ToString::to_string),
+            Some(ParseError::InvalidValue.to_string())
+        );
+        assert_eq!(
+            event_parse_error(&[], ParseError::InsufficientData),
+            EventParseError {
+                response_type: 0,
+                len: 0,
+                error: ParseError::InsufficientData,
+            }
+        );
+    }
+
+    #[test]
+    fn setup_summary() {
+        let mut setup = Setup {
+            protocol_major_version: 11,
+            protocol_minor_version: 0,
+            release_number: 12101004,
+            vendor: b"The X.Org Foundation".to_vec(),
+            roots: vec![Screen::default()],
+            ..Default::default()
+        };
+        assert_eq!(
+            SetupSummary(&setup).to_string(),
+            "X11 server \"The X.Org Foundation\" release 12101004, protocol 11.0, 1 screen"
+        );
+        setup.roots.push(Screen::default());
+        assert_eq!(
+            SetupSummary(&setup).to_string(),
+            "X11 server \"The X.Org Foundation\" release 12101004, protocol 11.0, 2 screens"
+        );
+    }
+}
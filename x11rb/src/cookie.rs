@@ -43,6 +43,31 @@
 //! | ------ | ---------------------------------- | ------------------------- |
 //! | Get    | `Cookie::reply`                    | `Cookie::reply_unchecked` |
 //! | Ignore | `Cookie::discard_reply_and_errors` | Just drop the cookie      |
+//!
+//! With `Cookie::try_reply`, you can check whether the reply already arrived without blocking.
+//!
+//! ## Dropping cookies
+//!
+//! Dropping a cookie without getting its reply is the same as calling its `discard` method. This
+//! calls [`RequestConnection::discard_reply`] with [`DiscardMode::DiscardReply`]: the reply is
+//! thrown away when it arrives, but an error is still returned from `wait_for_event()`. Errors are
+//! only thrown away with [`DiscardMode::DiscardReplyAndError`], which is what
+//! `VoidCookie::ignore_error` and `Cookie::discard_reply_and_errors` use.
+//!
+//! The `forget` method consumes a cookie without discarding anything. The reply or error is then
+//! kept by the connection until it is explicitly fetched via the returned sequence number, e.g.
+//! with [`RequestConnection::wait_for_reply_or_error`]. If that never happens, the response stays
+//! in memory until the connection is closed. The request no longer counts towards
+//! [`Connection::outstanding_requests`](crate::connection::Connection::outstanding_requests), see
+//! [`RequestConnection::forget_reply`].
+//!
+//! ## Sequence numbers
+//!
+//! Every cookie knows the sequence number of its request via its `sequence_number` method. This
+//! allows to correlate a request with e.g. the sequence number of a later event or to call
+//! [`RequestConnection::discard_reply`] manually. The number is only meaningful while the response
+//! was not yet consumed: once the reply or error was fetched or discarded, the connection no
+//! longer knows about it, and sequence numbers are eventually reused after wrapping around.
 
 use std::marker::PhantomData;
 
@@ -87,6 +112,8 @@ where
     }
 
     /// Get the sequence number of the request that generated this cookie.
+    ///
+    /// See [crate::cookie#sequence-numbers] for when this number is meaningful.
     pub fn sequence_number(&self) -> SequenceNumber {
         self.sequence_number
     }
@@ -115,6 +142,20 @@ where
             DiscardMode::DiscardReplyAndError,
         )
     }
+
+    /// Stop waiting for the response to this request, but have errors handled as events.
+    ///
+    /// This is the same as dropping the cookie. See [crate::cookie#dropping-cookies].
+    pub fn discard(self) {}
+
+    /// Consume this cookie without discarding the response to the request.
+    ///
+    /// A possible error stays with the connection until it is fetched via the returned sequence
+    /// number, e.g. with [`RequestConnection::check_for_error`]. See
+    /// [crate::cookie#dropping-cookies].
+    pub fn forget(self) -> SequenceNumber {
+        self.consume().1
+    }
 }
 
 impl<C> Drop for VoidCookie<'_, C>
@@ -210,11 +251,34 @@ where
     }
 
     /// Get the sequence number of the request that generated this cookie.
+    ///
+    /// See [crate::cookie#sequence-numbers] for when this number is meaningful.
     pub fn sequence_number(&self) -> SequenceNumber {
         self.raw_cookie.sequence_number
     }
 
     /// Get the raw reply that the server sent.
+    ///
+    /// The reply is returned in the connection's buffer type without being copied, e.g. as a
+    /// [`CSlice`](crate::utils::CSlice) that points to the memory allocated by libxcb with
+    /// `XCBConnection`. In contrast, [`Cookie::reply`] copies lists out of the buffer. For large
+    /// replies, it can thus be worthwhile to only parse the parts that are actually needed:
+    ///
+    /// ```no_run
+    /// use x11rb::connection::Connection;
+    /// use x11rb::protocol::xproto::{ConnectionExt, ImageFormat};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let (conn, screen_num) = x11rb::connect(None)?;
+    ///     let root = conn.setup().roots[screen_num].root;
+    ///     let cookie = conn.get_image(ImageFormat::Z_PIXMAP, root, 0, 0, 16, 16, !0)?;
+    ///     let reply = cookie.raw_reply()?;
+    ///     // The image data follows the fixed-size part of the GetImage reply
+    ///     let data = &reply[32..];
+    ///     println!("Got {} bytes of image data", data.len());
+    ///     Ok(())
+    /// }
+    /// ```
     pub fn raw_reply(self) -> Result<C::Buf, ReplyError> {
         let conn = self.raw_cookie.connection;
         conn.wait_for_reply_or_error(self.raw_cookie.into_sequence_number())
@@ -231,6 +295,30 @@ where
         Ok(R::try_parse(self.raw_reply()?.as_ref())?.0)
     }
 
+    /// Get the reply that the server sent if it was already received, without blocking.
+    ///
+    /// Returns `Ok(Err(cookie))` with this cookie if neither a reply nor an error was received
+    /// yet. The cookie can then be used again later, for example with [`Cookie::reply`] once the
+    /// reply is actually needed.
+    ///
+    /// An error is returned if the connection cannot check for the reply without blocking, see
+    /// [`RequestConnection::poll_for_reply_or_raw_error`].
+    pub fn try_reply(self) -> Result<Result<R, Self>, ReplyError> {
+        let conn = self.raw_cookie.connection;
+        // Once the reply or error was handed out, there is nothing left to discard on drop
+        match conn.poll_for_reply_or_error(self.raw_cookie.sequence_number) {
+            Ok(None) => Ok(Err(self)),
+            Ok(Some(buf)) => {
+                let _ = self.raw_cookie.into_sequence_number();
+                Ok(Ok(R::try_parse(buf.as_ref())?.0))
+            }
+            Err(err) => {
+                let _ = self.forget();
+                Err(err)
+            }
+        }
+    }
+
     /// Get the reply that the server sent, but have errors handled as events.
     pub fn reply_unchecked(self) -> Result<Option<R>, ConnectionError> {
         self.raw_reply_unchecked()?
@@ -251,9 +339,26 @@ where
         )
     }
 
+    /// Discard the reply to this request, but have errors handled as events.
+    ///
+    /// This is the same as dropping the cookie. See [crate::cookie#dropping-cookies].
+    pub fn discard(self) {}
+
+    /// Consume this cookie without discarding the reply to the request.
+    ///
+    /// The reply or error stays with the connection until it is fetched via the returned sequence
+    /// number, e.g. with [`RequestConnection::wait_for_reply_or_error`]. See
+    /// [crate::cookie#dropping-cookies].
+    pub fn forget(self) -> SequenceNumber {
+        let conn = self.raw_cookie.connection;
+        let sequence = self.raw_cookie.into_sequence_number();
+        conn.forget_reply(sequence);
+        sequence
+    }
+
     /// Consume this instance and get the contained sequence number out.
     pub(crate) fn into_sequence_number(self) -> SequenceNumber {
-        self.raw_cookie.into_sequence_number()
+        self.forget()
     }
 }
 
@@ -264,6 +369,10 @@ where
 ///
 /// This variant of `Cookie` represents a response that can contain `RawFd`s.
 ///
+/// When this cookie is dropped without getting the reply, the reply is discarded and any file
+/// descriptors that it contains are closed. Errors are still treated as events, unless
+/// [`CookieWithFds::discard_reply_and_errors`] is used.
+///
 /// See [crate::cookie#requests-with-a-reply] for infos on the different ways to handle X11
 /// errors in response to a request.
 #[derive(Debug)]
@@ -292,6 +401,8 @@ where
     }
 
     /// Get the sequence number of the request that generated this cookie.
+    ///
+    /// See [crate::cookie#sequence-numbers] for when this number is meaningful.
     pub fn sequence_number(&self) -> SequenceNumber {
         self.raw_cookie.sequence_number
     }
@@ -307,6 +418,37 @@ where
         let (buffer, mut fds) = self.raw_reply()?;
         Ok(R::try_parse_fd(buffer.as_ref(), &mut fds)?.0)
     }
+
+    /// Discard all responses to the request this cookie represents, even errors.
+    ///
+    /// Any file descriptors in the reply are closed. Without this function, errors are treated
+    /// as events after the cookie is dropped.
+    pub fn discard_reply_and_errors(self) {
+        let conn = self.raw_cookie.connection;
+        conn.discard_reply(
+            self.raw_cookie.into_sequence_number(),
+            RequestKind::HasResponse,
+            DiscardMode::DiscardReplyAndError,
+        )
+    }
+
+    /// Discard the reply to this request, but have errors handled as events.
+    ///
+    /// This is the same as dropping the cookie. Any file descriptors in the reply are closed.
+    /// See [crate::cookie#dropping-cookies].
+    pub fn discard(self) {}
+
+    /// Consume this cookie without discarding the reply to the request.
+    ///
+    /// The reply or error stays with the connection until it is fetched via the returned sequence
+    /// number, e.g. with [`RequestConnection::wait_for_reply_with_fds`]. See
+    /// [crate::cookie#dropping-cookies].
+    pub fn forget(self) -> SequenceNumber {
+        let conn = self.raw_cookie.connection;
+        let sequence = self.raw_cookie.into_sequence_number();
+        conn.forget_reply(sequence);
+        sequence
+    }
 }
 
 macro_rules! multiple_reply_cookie {
@@ -329,6 +471,9 @@ macro_rules! multiple_reply_cookie {
             }
 
             /// Get the sequence number of the request that generated this cookie.
+            ///
+            /// Returns `None` once the iterator ended. See
+            /// [crate::cookie#sequence-numbers] for when this number is meaningful.
             pub fn sequence_number(&self) -> Option<SequenceNumber> {
                 self.0.as_ref().map(|x| x.sequence_number)
             }
@@ -372,6 +517,10 @@ multiple_reply_cookie!(
     ///
     /// `ListFontsWithInfo` generated more than one reply, but `Cookie` only allows getting one reply.
     /// This structure implements `Iterator` and allows to get all the replies.
+    ///
+    /// The iterator ends at the reply with an empty name that terminates the series. This reply is
+    /// not returned. The `replies_hint` field of each reply estimates how many more replies
+    /// follow. Since the server may be wrong, the iterator's `size_hint` does not rely on it.
     pub struct ListFontsWithInfoCookie for ListFontsWithInfoReply
 );
 
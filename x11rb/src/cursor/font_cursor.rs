@@ -0,0 +1,355 @@
+//! Creating cursors from the X11 `cursor` font
+
+use crate::connection::Connection;
+use crate::errors::{ConnectionError, ReplyOrIdError};
+use crate::protocol::xproto::{self, CursorWrapper, Font};
+
+/// The shapes of the cursors in the X11 `cursor` font.
+///
+/// The value of each variant is the index of the corresponding glyph in the font, as also used by
+/// the `XC_*` constants of Xlib. The glyph following it is the cursor's mask.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum CursorShape {
+    /// The `X_cursor` cursor.
+    XCursor = 0,
+    /// The `arrow` cursor.
+    Arrow = 2,
+    /// The `based_arrow_down` cursor.
+    BasedArrowDown = 4,
+    /// The `based_arrow_up` cursor.
+    BasedArrowUp = 6,
+    /// The `boat` cursor.
+    Boat = 8,
+    /// The `bogosity` cursor.
+    Bogosity = 10,
+    /// The `bottom_left_corner` cursor.
+    BottomLeftCorner = 12,
+    /// The `bottom_right_corner` cursor.
+    BottomRightCorner = 14,
+    /// The `bottom_side` cursor.
+    BottomSide = 16,
+    /// The `bottom_tee` cursor.
+    BottomTee = 18,
+    /// The `box_spiral` cursor.
+    BoxSpiral = 20,
+    /// The `center_ptr` cursor.
+    CenterPtr = 22,
+    /// The `circle` cursor.
+    Circle = 24,
+    /// The `clock` cursor.
+    Clock = 26,
+    /// The `coffee_mug` cursor.
+    CoffeeMug = 28,
+    /// The `cross` cursor.
+    Cross = 30,
+    /// The `cross_reverse` cursor.
+    CrossReverse = 32,
+    /// The `crosshair` cursor.
+    Crosshair = 34,
+    /// The `diamond_cross` cursor.
+    DiamondCross = 36,
+    /// The `dot` cursor.
+    Dot = 38,
+    /// The `dotbox` cursor.
+    Dotbox = 40,
+    /// The `double_arrow` cursor.
+    DoubleArrow = 42,
+    /// The `draft_large` cursor.
+    DraftLarge = 44,
+    /// The `draft_small` cursor.
+    DraftSmall = 46,
+    /// The `draped_box` cursor.
+    DrapedBox = 48,
+    /// The `exchange` cursor.
+    Exchange = 50,
+    /// The `fleur` cursor.
+    Fleur = 52,
+    /// The `gobbler` cursor.
+    Gobbler = 54,
+    /// The `gumby` cursor.
+    Gumby = 56,
+    /// The `hand1` cursor.
+    Hand1 = 58,
+    /// The `hand2` cursor.
+    Hand2 = 60,
+    /// The `heart` cursor.
+    Heart = 62,
+    /// The `icon` cursor.
+    Icon = 64,
+    /// The `iron_cross` cursor.
+    IronCross = 66,
+    /// The `left_ptr` cursor.
+    LeftPtr = 68,
+    /// The `left_side` cursor.
+    LeftSide = 70,
+    /// The `left_tee` cursor.
+    LeftTee = 72,
+    /// The `leftbutton` cursor.
+    Leftbutton = 74,
+    /// The `ll_angle` cursor.
+    LlAngle = 76,
+    /// The `lr_angle` cursor.
+    LrAngle = 78,
+    /// The `man` cursor.
+    Man = 80,
+    /// The `middlebutton` cursor.
+    Middlebutton = 82,
+    /// The `mouse` cursor.
+    Mouse = 84,
+    /// The `pencil` cursor.
+    Pencil = 86,
+    /// The `pirate` cursor.
+    Pirate = 88,
+    /// The `plus` cursor.
+    Plus = 90,
+    /// The `question_arrow` cursor.
+    QuestionArrow = 92,
+    /// The `right_ptr` cursor.
+    RightPtr = 94,
+    /// The `right_side` cursor.
+    RightSide = 96,
+    /// The `right_tee` cursor.
+    RightTee = 98,
+    /// The `rightbutton` cursor.
+    Rightbutton = 100,
+    /// The `rtl_logo` cursor.
+    RtlLogo = 102,
+    /// The `sailboat` cursor.
+    Sailboat = 104,
+    /// The `sb_down_arrow` cursor.
+    SbDownArrow = 106,
+    /// The `sb_h_double_arrow` cursor.
+    SbHDoubleArrow = 108,
+    /// The `sb_left_arrow` cursor.
+    SbLeftArrow = 110,
+    /// The `sb_right_arrow` cursor.
+    SbRightArrow = 112,
+    /// The `sb_up_arrow` cursor.
+    SbUpArrow = 114,
+    /// The `sb_v_double_arrow` cursor.
+    SbVDoubleArrow = 116,
+    /// The `shuttle` cursor.
+    Shuttle = 118,
+    /// The `sizing` cursor.
+    Sizing = 120,
+    /// The `spider` cursor.
+    Spider = 122,
+    /// The `spraycan` cursor.
+    Spraycan = 124,
+    /// The `star` cursor.
+    Star = 126,
+    /// The `target` cursor.
+    Target = 128,
+    /// The `tcross` cursor.
+    Tcross = 130,
+    /// The `top_left_arrow` cursor.
+    TopLeftArrow = 132,
+    /// The `top_left_corner` cursor.
+    TopLeftCorner = 134,
+    /// The `top_right_corner` cursor.
+    TopRightCorner = 136,
+    /// The `top_side` cursor.
+    TopSide = 138,
+    /// The `top_tee` cursor.
+    TopTee = 140,
+    /// The `trek` cursor.
+    Trek = 142,
+    /// The `ul_angle` cursor.
+    UlAngle = 144,
+    /// The `umbrella` cursor.
+    Umbrella = 146,
+    /// The `ur_angle` cursor.
+    UrAngle = 148,
+    /// The `watch` cursor.
+    Watch = 150,
+    /// The `xterm` cursor.
+    Xterm = 152,
+}
+
+static SHAPE_NAMES: &[(CursorShape, &str)] = &[
+    (CursorShape::XCursor, "X_cursor"),
+    (CursorShape::Arrow, "arrow"),
+    (CursorShape::BasedArrowDown, "based_arrow_down"),
+    (CursorShape::BasedArrowUp, "based_arrow_up"),
+    (CursorShape::Boat, "boat"),
+    (CursorShape::Bogosity, "bogosity"),
+    (CursorShape::BottomLeftCorner, "bottom_left_corner"),
+    (CursorShape::BottomRightCorner, "bottom_right_corner"),
+    (CursorShape::BottomSide, "bottom_side"),
+    (CursorShape::BottomTee, "bottom_tee"),
+    (CursorShape::BoxSpiral, "box_spiral"),
+    (CursorShape::CenterPtr, "center_ptr"),
+    (CursorShape::Circle, "circle"),
+    (CursorShape::Clock, "clock"),
+    (CursorShape::CoffeeMug, "coffee_mug"),
+    (CursorShape::Cross, "cross"),
+    (CursorShape::CrossReverse, "cross_reverse"),
+    (CursorShape::Crosshair, "crosshair"),
+    (CursorShape::DiamondCross, "diamond_cross"),
+    (CursorShape::Dot, "dot"),
+    (CursorShape::Dotbox, "dotbox"),
+    (CursorShape::DoubleArrow, "double_arrow"),
+    (CursorShape::DraftLarge, "draft_large"),
+    (CursorShape::DraftSmall, "draft_small"),
+    (CursorShape::DrapedBox, "draped_box"),
+    (CursorShape::Exchange, "exchange"),
+    (CursorShape::Fleur, "fleur"),
+    (CursorShape::Gobbler, "gobbler"),
+    (CursorShape::Gumby, "gumby"),
+    (CursorShape::Hand1, "hand1"),
+    (CursorShape::Hand2, "hand2"),
+    (CursorShape::Heart, "heart"),
+    (CursorShape::Icon, "icon"),
+    (CursorShape::IronCross, "iron_cross"),
+    (CursorShape::LeftPtr, "left_ptr"),
+    (CursorShape::LeftSide, "left_side"),
+    (CursorShape::LeftTee, "left_tee"),
+    (CursorShape::Leftbutton, "leftbutton"),
+    (CursorShape::LlAngle, "ll_angle"),
+    (CursorShape::LrAngle, "lr_angle"),
+    (CursorShape::Man, "man"),
+    (CursorShape::Middlebutton, "middlebutton"),
+    (CursorShape::Mouse, "mouse"),
+    (CursorShape::Pencil, "pencil"),
+    (CursorShape::Pirate, "pirate"),
+    (CursorShape::Plus, "plus"),
+    (CursorShape::QuestionArrow, "question_arrow"),
+    (CursorShape::RightPtr, "right_ptr"),
+    (CursorShape::RightSide, "right_side"),
+    (CursorShape::RightTee, "right_tee"),
+    (CursorShape::Rightbutton, "rightbutton"),
+    (CursorShape::RtlLogo, "rtl_logo"),
+    (CursorShape::Sailboat, "sailboat"),
+    (CursorShape::SbDownArrow, "sb_down_arrow"),
+    (CursorShape::SbHDoubleArrow, "sb_h_double_arrow"),
+    (CursorShape::SbLeftArrow, "sb_left_arrow"),
+    (CursorShape::SbRightArrow, "sb_right_arrow"),
+    (CursorShape::SbUpArrow, "sb_up_arrow"),
+    (CursorShape::SbVDoubleArrow, "sb_v_double_arrow"),
+    (CursorShape::Shuttle, "shuttle"),
+    (CursorShape::Sizing, "sizing"),
+    (CursorShape::Spider, "spider"),
+    (CursorShape::Spraycan, "spraycan"),
+    (CursorShape::Star, "star"),
+    (CursorShape::Target, "target"),
+    (CursorShape::Tcross, "tcross"),
+    (CursorShape::TopLeftArrow, "top_left_arrow"),
+    (CursorShape::TopLeftCorner, "top_left_corner"),
+    (CursorShape::TopRightCorner, "top_right_corner"),
+    (CursorShape::TopSide, "top_side"),
+    (CursorShape::TopTee, "top_tee"),
+    (CursorShape::Trek, "trek"),
+    (CursorShape::UlAngle, "ul_angle"),
+    (CursorShape::Umbrella, "umbrella"),
+    (CursorShape::UrAngle, "ur_angle"),
+    (CursorShape::Watch, "watch"),
+    (CursorShape::Xterm, "xterm"),
+];
+
+impl CursorShape {
+    /// Get the index of this cursor's glyph in the `cursor` font.
+    pub fn glyph(self) -> u16 {
+        self as u16
+    }
+
+    /// Get the name of this cursor, e.g. `"watch"` for [`CursorShape::Watch`].
+    pub fn name(self) -> &'static str {
+        SHAPE_NAMES
+            .iter()
+            .find(|&&(shape, _)| shape == self)
+            .map(|&(_, name)| name)
+            .expect("Every shape has a name")
+    }
+
+    /// Find a cursor shape based on its name, e.g. `"watch"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        SHAPE_NAMES
+            .iter()
+            .find(|&&(_, name2)| name == name2)
+            .map(|&(shape, _)| shape)
+    }
+}
+
+/// The X11 `cursor` font, which contains the standard core cursors.
+///
+/// The font is opened once and can then be used to create any number of cursors via
+/// [`CursorFont::font_cursor`]. These cursors do not depend on a cursor theme or the RENDER
+/// extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CursorFont {
+    font: Font,
+}
+
+impl CursorFont {
+    /// Open the `cursor` font.
+    pub fn open<C: Connection>(conn: &C) -> Result<Self, ReplyOrIdError> {
+        let font = conn.generate_id()?;
+        let _ = xproto::open_font(conn, font, b"cursor")?;
+        Ok(Self { font })
+    }
+
+    /// Get the ID of the font.
+    pub fn font(self) -> Font {
+        self.font
+    }
+
+    /// Create a black cursor with a white border in the given shape.
+    ///
+    /// The cursor is freed when the returned wrapper is dropped. Use
+    /// [`CursorWrapper::into_cursor`] to keep it.
+    pub fn font_cursor<'c, C: Connection>(
+        self,
+        conn: &'c C,
+        shape: CursorShape,
+    ) -> Result<CursorWrapper<'c, C>, ReplyOrIdError> {
+        let glyph = shape.glyph();
+        CursorWrapper::create_glyph_cursor(
+            conn,
+            self.font,
+            self.font,
+            glyph,
+            glyph + 1,
+            // foreground color
+            0,
+            0,
+            0,
+            // background color
+            u16::max_value(),
+            u16::max_value(),
+            u16::max_value(),
+        )
+    }
+
+    /// Close the font.
+    ///
+    /// Cursors that were already created from the font stay valid.
+    pub fn close<C: Connection>(self, conn: &C) -> Result<(), ConnectionError> {
+        let _ = xproto::close_font(conn, self.font)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CursorShape;
+
+    #[test]
+    fn shape_glyphs() {
+        assert_eq!(CursorShape::XCursor.glyph(), 0);
+        assert_eq!(CursorShape::LeftPtr.glyph(), 68);
+        assert_eq!(CursorShape::Watch.glyph(), 150);
+        assert_eq!(CursorShape::Xterm.glyph(), 152);
+    }
+
+    #[test]
+    fn shape_names() {
+        assert_eq!(CursorShape::from_name("heart"), Some(CursorShape::Heart));
+        assert_eq!(
+            CursorShape::from_name("X_cursor"),
+            Some(CursorShape::XCursor)
+        );
+        assert_eq!(CursorShape::from_name("no_such_cursor"), None);
+        assert_eq!(CursorShape::SbHDoubleArrow.name(), "sb_h_double_arrow");
+    }
+}
@@ -6,15 +6,19 @@ use crate::connection::Connection;
 use crate::cookie::Cookie as X11Cookie;
 use crate::errors::{ConnectionError, ReplyOrIdError};
 use crate::protocol::render::{self, Pictformat};
-use crate::protocol::xproto::{self, Font, Window};
+use crate::protocol::xproto::{self, Window};
+use crate::render::StandardFormat;
 use crate::resource_manager::Database;
 use crate::NONE;
 
 use std::fs::File;
 
 mod find_cursor;
+mod font_cursor;
 mod parse_cursor;
 
+pub use font_cursor::{CursorFont, CursorShape};
+
 /// The level of cursor support of the X11 server
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum RenderSupport {
@@ -107,8 +111,7 @@ impl<C: Connection> Cookie<'_, '_, C> {
             _ => 0,
         };
         let cursor_size = get_cursor_size(cursor_size, xft_dpi, screen);
-        let cursor_font = conn.generate_id()?;
-        let _ = xproto::open_font(conn, cursor_font, b"cursor")?;
+        let cursor_font = CursorFont::open(conn)?;
         Ok(Handle {
             root: screen.root,
             cursor_font,
@@ -124,7 +127,7 @@ impl<C: Connection> Cookie<'_, '_, C> {
 #[derive(Debug)]
 pub struct Handle {
     root: Window,
-    cursor_font: Font,
+    cursor_font: CursorFont,
     picture_format: Pictformat,
     render_support: RenderSupport,
     theme: Option<String>,
@@ -174,6 +177,21 @@ impl Handle {
     {
         load_cursor(conn, self, name)
     }
+
+    /// Creates a cursor with the given shape from the X11 "cursor" font.
+    ///
+    /// Unlike [`Handle::load_cursor`], this ignores the cursor theme. The font is only opened once
+    /// when the handle is created.
+    pub fn font_cursor<'c, C>(
+        &self,
+        conn: &'c C,
+        shape: CursorShape,
+    ) -> Result<xproto::CursorWrapper<'c, C>, ReplyOrIdError>
+    where
+        C: Connection,
+    {
+        self.cursor_font.font_cursor(conn, shape)
+    }
 }
 
 fn open_cursor(theme: &Option<String>, name: &str) -> Option<find_cursor::Cursor<File>> {
@@ -189,31 +207,6 @@ fn open_cursor(theme: &Option<String>, name: &str) -> Option<find_cursor::Cursor
     }
 }
 
-fn create_core_cursor<C: Connection>(
-    conn: &C,
-    cursor_font: Font,
-    cursor: u16,
-) -> Result<xproto::Cursor, ReplyOrIdError> {
-    let result = conn.generate_id()?;
-    let _ = xproto::create_glyph_cursor(
-        conn,
-        result,
-        cursor_font,
-        cursor_font,
-        cursor,
-        cursor + 1,
-        // foreground color
-        0,
-        0,
-        0,
-        // background color
-        u16::max_value(),
-        u16::max_value(),
-        u16::max_value(),
-    )?;
-    Ok(result)
-}
-
 fn create_render_cursor<C: Connection>(
     conn: &C,
     handle: &Handle,
@@ -280,8 +273,8 @@ fn load_cursor<C: Connection>(
     // Find the right cursor, load it directly if it is a core cursor
     let cursor_file = match open_cursor(&handle.theme, name) {
         None => return Ok(NONE),
-        Some(find_cursor::Cursor::CoreChar(c)) => {
-            return create_core_cursor(conn, handle.cursor_font, c)
+        Some(find_cursor::Cursor::CoreChar(shape)) => {
+            return Ok(handle.font_cursor(conn, shape)?.into_cursor())
         }
         Some(find_cursor::Cursor::File(f)) => f,
     };
@@ -326,23 +319,8 @@ fn load_cursor<C: Connection>(
 }
 
 fn find_format(reply: &render::QueryPictFormatsReply) -> Pictformat {
-    reply
-        .formats
-        .iter()
-        .filter(|format| {
-            format.type_ == render::PictType::DIRECT
-                && format.depth == 32
-                && format.direct.red_shift == 16
-                && format.direct.red_mask == 0xff
-                && format.direct.green_shift == 8
-                && format.direct.green_mask == 0xff
-                && format.direct.blue_shift == 0
-                && format.direct.blue_mask == 0xff
-                && format.direct.alpha_shift == 24
-                && format.direct.alpha_mask == 0xff
-        })
-        .map(|format| format.id)
-        .next()
+    StandardFormat::Argb32
+        .find(reply)
         .expect("The X11 server is missing the RENDER ARGB_32 standard format!")
 }
 
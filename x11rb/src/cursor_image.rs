@@ -0,0 +1,173 @@
+//! Utility functions for fetching the image of the current mouse cursor.
+//!
+//! The `XFIXES` extension allows to query the image of the cursor that is currently displayed via
+//! `GetCursorImage`. This is e.g. needed by remote desktop servers that have to draw the cursor on
+//! the client side. [`get_cursor_image`] fetches the image as a [`CursorImage`].
+//!
+//! To learn when the cursor changes, [`select_cursor_changes`] subscribes to `CursorNotify`
+//! events. Each such event carries the serial number of the new cursor, which can be compared
+//! against [`CursorImage::serial`] to avoid fetching the same image twice.
+//!
+//! Usage example:
+//! ```no_run
+//! use x11rb::connection::Connection;
+//! use x11rb::cursor_image::{get_cursor_image, select_cursor_changes};
+//! use x11rb::protocol::Event;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let (conn, screen_num) = x11rb::connect(None)?;
+//!     let root = conn.setup().roots[screen_num].root;
+//!     select_cursor_changes(&conn, root)?;
+//!     let mut image = get_cursor_image(&conn)?;
+//!     loop {
+//!         if let Event::XfixesCursorNotify(event) = conn.wait_for_event()? {
+//!             if image.is_outdated_by(event.cursor_serial) {
+//!                 image = get_cursor_image(&conn)?;
+//!                 println!("New cursor of size {}x{}", image.width, image.height);
+//!             }
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::connection::RequestConnection;
+use crate::errors::ReplyError;
+use crate::protocol::xfixes::{self, CursorNotifyMask, GetCursorImageReply};
+use crate::protocol::xproto::Window;
+
+/// The image of a mouse cursor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorImage {
+    /// The x coordinate of the cursor's hotspot on the screen.
+    pub x: i16,
+    /// The y coordinate of the cursor's hotspot on the screen.
+    pub y: i16,
+    /// The width of the image.
+    pub width: u16,
+    /// The height of the image.
+    pub height: u16,
+    /// The x coordinate of the hotspot inside of the image.
+    pub xhot: u16,
+    /// The y coordinate of the hotspot inside of the image.
+    pub yhot: u16,
+    /// The serial number of the cursor, as also reported in `CursorNotify` events.
+    pub serial: u32,
+    /// The pixels of the image in row-major order.
+    ///
+    /// Each pixel is a 32 bit ARGB value with the alpha channel in the most significant byte.
+    /// The colour channels are premultiplied with the alpha channel. Use
+    /// [`CursorImage::unpremultiplied`] to get the straight colour values.
+    pub pixels: Vec<u32>,
+}
+
+impl CursorImage {
+    /// Check whether a cursor with the given serial number differs from this image.
+    pub fn is_outdated_by(&self, serial: u32) -> bool {
+        self.serial != serial
+    }
+
+    /// Get the pixel at the given position, or `None` if it is outside of the image.
+    pub fn pixel(&self, x: u16, y: u16) -> Option<u32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let index = usize::from(y) * usize::from(self.width) + usize::from(x);
+        self.pixels.get(index).copied()
+    }
+
+    /// Get the pixels of the image with colour channels that are not premultiplied.
+    ///
+    /// Fully transparent pixels become `0`.
+    pub fn unpremultiplied(&self) -> Vec<u32> {
+        self.pixels.iter().copied().map(unpremultiply).collect()
+    }
+}
+
+impl From<GetCursorImageReply> for CursorImage {
+    fn from(reply: GetCursorImageReply) -> Self {
+        Self {
+            x: reply.x,
+            y: reply.y,
+            width: reply.width,
+            height: reply.height,
+            xhot: reply.xhot,
+            yhot: reply.yhot,
+            serial: reply.cursor_serial,
+            pixels: reply.cursor_image,
+        }
+    }
+}
+
+/// Undo the alpha premultiplication of an ARGB pixel.
+fn unpremultiply(pixel: u32) -> u32 {
+    let alpha = pixel >> 24;
+    if alpha == 0 {
+        return 0;
+    }
+    let channel = |shift: u32| {
+        let value = (pixel >> shift) & 0xff;
+        // Round to the nearest value and clamp invalid inputs where the channel exceeds alpha
+        ((value * 0xff + alpha / 2) / alpha).min(0xff) << shift
+    };
+    (alpha << 24) | channel(16) | channel(8) | channel(0)
+}
+
+/// Fetch the image of the cursor that is currently displayed.
+///
+/// The `XFIXES` extension requires that its version is negotiated before this request can be
+/// used. [`select_cursor_changes`] takes care of this, or `xfixes::query_version` can be sent
+/// directly.
+pub fn get_cursor_image<C>(conn: &C) -> Result<CursorImage, ReplyError>
+where
+    C: RequestConnection + ?Sized,
+{
+    Ok(xfixes::get_cursor_image(conn)?.reply()?.into())
+}
+
+/// Subscribe to `CursorNotify` events that are sent when the displayed cursor changes.
+///
+/// This negotiates the version of the `XFIXES` extension and then selects `CursorNotify` events
+/// on the given window, which should usually be a root window.
+pub fn select_cursor_changes<C>(conn: &C, window: Window) -> Result<(), ReplyError>
+where
+    C: RequestConnection + ?Sized,
+{
+    let (major, minor) = xfixes::X11_XML_VERSION;
+    let _ = xfixes::query_version(conn, major, minor)?.reply()?;
+    xfixes::select_cursor_input(conn, window, CursorNotifyMask::DISPLAY_CURSOR)?.check()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{unpremultiply, CursorImage};
+
+    #[test]
+    fn unpremultiply_pixels() {
+        assert_eq!(unpremultiply(0x0000_0000), 0);
+        assert_eq!(unpremultiply(0x00ff_ffff), 0);
+        assert_eq!(unpremultiply(0xff12_3456), 0xff12_3456);
+        assert_eq!(unpremultiply(0x8080_4000), 0x80ff_8000);
+        assert_eq!(unpremultiply(0x40ff_ffff), 0x40ff_ffff);
+    }
+
+    #[test]
+    fn pixel_access() {
+        let image = CursorImage {
+            x: 10,
+            y: 20,
+            width: 2,
+            height: 2,
+            xhot: 0,
+            yhot: 1,
+            serial: 42,
+            pixels: vec![1, 2, 3, 4],
+        };
+        assert_eq!(image.pixel(1, 0), Some(2));
+        assert_eq!(image.pixel(0, 1), Some(3));
+        assert_eq!(image.pixel(2, 0), None);
+        assert_eq!(image.pixel(0, 2), None);
+        assert!(!image.is_outdated_by(42));
+        assert!(image.is_outdated_by(43));
+        assert_eq!(image.unpremultiplied(), vec![0, 0, 0, 0]);
+    }
+}
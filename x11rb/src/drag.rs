@@ -0,0 +1,179 @@
+//! Utilities for distinguishing clicks from drags.
+//!
+//! A button press only turns into a drag once the pointer moved far enough away from the position
+//! where the button was pressed. [`DragDetector`] implements this logic. It does not talk to the
+//! X11 server itself, but is fed with coordinates, e.g. from `ButtonPress` and `MotionNotify`
+//! events or from `QueryPointer` replies.
+//!
+//! All coordinates should be relative to the same window. The helpers that take events and replies
+//! use root window coordinates for this reason.
+//!
+//! Usage example:
+//! ```no_run
+//! use x11rb::connection::Connection;
+//! use x11rb::drag::DragDetector;
+//! use x11rb::protocol::Event;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let (conn, _) = x11rb::connect(None)?;
+//!     let mut detector = DragDetector::new();
+//!     loop {
+//!         match conn.wait_for_event()? {
+//!             Event::ButtonPress(event) => detector.press_event(&event),
+//!             Event::MotionNotify(event) => {
+//!                 if detector.motion_event(&event) {
+//!                     println!("Dragging to {}, {}", event.root_x, event.root_y);
+//!                 }
+//!             }
+//!             Event::ButtonRelease(_) => {
+//!                 if !detector.release() {
+//!                     println!("Click");
+//!                 }
+//!             }
+//!             _ => {}
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::protocol::xproto::{ButtonPressEvent, MotionNotifyEvent, QueryPointerReply};
+
+/// The default distance in pixels that the pointer has to move before a drag starts.
+pub const DEFAULT_DRAG_THRESHOLD: u16 = 4;
+
+/// A helper for detecting whether the pointer was dragged after a button press.
+///
+/// A drag starts once the pointer moved more than the threshold away from the position of the
+/// button press, either horizontally or vertically. Afterwards, the drag continues until the button
+/// is released, even if the pointer moves back to its initial position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DragDetector {
+    threshold: u16,
+    start: Option<(i16, i16)>,
+    dragging: bool,
+}
+
+impl Default for DragDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DragDetector {
+    /// Create a new `DragDetector` with [`DEFAULT_DRAG_THRESHOLD`].
+    pub fn new() -> Self {
+        Self::with_threshold(DEFAULT_DRAG_THRESHOLD)
+    }
+
+    /// Create a new `DragDetector` with the given threshold in pixels.
+    pub fn with_threshold(threshold: u16) -> Self {
+        Self {
+            threshold,
+            start: None,
+            dragging: false,
+        }
+    }
+
+    /// Get the threshold in pixels.
+    pub fn threshold(&self) -> u16 {
+        self.threshold
+    }
+
+    /// Get the position of the last button press, if a button is currently pressed.
+    pub fn start(&self) -> Option<(i16, i16)> {
+        self.start
+    }
+
+    /// Check whether a drag is in progress.
+    pub fn is_dragging(&self) -> bool {
+        self.dragging
+    }
+
+    /// Record a button press at the given position.
+    ///
+    /// This starts a new potential drag, forgetting any previous one.
+    pub fn press(&mut self, x: i16, y: i16) {
+        self.start = Some((x, y));
+        self.dragging = false;
+    }
+
+    /// Record a button press from a `ButtonPress` event, using root window coordinates.
+    pub fn press_event(&mut self, event: &ButtonPressEvent) {
+        self.press(event.root_x, event.root_y);
+    }
+
+    /// Record that the pointer moved to the given position.
+    ///
+    /// Returns whether a drag is in progress. This is always `false` when no button is pressed.
+    pub fn motion(&mut self, x: i16, y: i16) -> bool {
+        if let Some((start_x, start_y)) = self.start {
+            let threshold = i32::from(self.threshold);
+            let dx = (i32::from(x) - i32::from(start_x)).abs();
+            let dy = (i32::from(y) - i32::from(start_y)).abs();
+            if dx > threshold || dy > threshold {
+                self.dragging = true;
+            }
+        }
+        self.dragging
+    }
+
+    /// Record a pointer motion from a `MotionNotify` event, using root window coordinates.
+    ///
+    /// See [`DragDetector::motion`] for the return value.
+    pub fn motion_event(&mut self, event: &MotionNotifyEvent) -> bool {
+        self.motion(event.root_x, event.root_y)
+    }
+
+    /// Record the pointer position from a `QueryPointer` reply, using root window coordinates.
+    ///
+    /// See [`DragDetector::motion`] for the return value.
+    pub fn motion_pointer(&mut self, reply: &QueryPointerReply) -> bool {
+        self.motion(reply.root_x, reply.root_y)
+    }
+
+    /// Record that the button was released.
+    ///
+    /// Returns whether the button press was a drag. If this returns `false`, the press and release
+    /// form a click.
+    pub fn release(&mut self) -> bool {
+        let dragging = self.dragging;
+        self.start = None;
+        self.dragging = false;
+        dragging
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DragDetector;
+
+    #[test]
+    fn click() {
+        let mut detector = DragDetector::new();
+        detector.press(10, 10);
+        assert!(!detector.motion(14, 6));
+        assert!(!detector.is_dragging());
+        assert!(!detector.release());
+        assert_eq!(detector.start(), None);
+    }
+
+    #[test]
+    fn drag() {
+        let mut detector = DragDetector::new();
+        detector.press(10, 10);
+        assert!(!detector.motion(12, 12));
+        assert!(detector.motion(10, 5));
+        // Moving back does not end the drag
+        assert!(detector.motion(10, 10));
+        assert!(detector.release());
+        assert!(!detector.is_dragging());
+    }
+
+    #[test]
+    fn no_button_pressed() {
+        let mut detector = DragDetector::with_threshold(0);
+        assert!(!detector.motion(100, 100));
+        detector.press(-32768, 0);
+        assert!(detector.motion(32767, 0));
+    }
+}
@@ -0,0 +1,54 @@
+//! Utility functions for opening the DRM device of the X11 server via `DRI3`.
+//!
+//! The `DRI3` extension allows clients to share GPU buffers with the X11 server without copying.
+//! The first step for this is to get a file descriptor for the DRM device that the X11 server
+//! renders with. [`dri3_open`] performs the necessary requests and returns this file descriptor.
+//!
+//! Usage example:
+//! ```no_run
+//! use x11rb::connection::Connection;
+//! use x11rb::dri3_device::dri3_open;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let (conn, screen_num) = x11rb::connect(None)?;
+//!     let root = conn.setup().roots[screen_num].root;
+//!     let device = dri3_open(&conn, root, x11rb::NONE)?;
+//!     println!("Got DRM device {:?}", device);
+//!     Ok(())
+//! }
+//! ```
+
+use crate::connection::RequestConnection;
+use crate::errors::{ConnectionError, ReplyError};
+use crate::protocol::dri3;
+use crate::protocol::xproto::Drawable;
+use crate::utils::RawFdContainer;
+
+/// Open the DRM device that the X11 server uses for the screen of the given drawable.
+///
+/// `provider` selects a RandR provider. Use `x11rb::NONE` for the default device.
+///
+/// This negotiates the version of the `DRI3` extension and then sends an `Open` request. The
+/// returned file descriptor is received via file descriptor passing and is closed when the
+/// `RawFdContainer` is dropped.
+///
+/// If the X11 server does not support the `DRI3` extension, `ConnectionError::UnsupportedExtension`
+/// is returned.
+pub fn dri3_open<C>(
+    conn: &C,
+    drawable: Drawable,
+    provider: u32,
+) -> Result<RawFdContainer, ReplyError>
+where
+    C: RequestConnection + ?Sized,
+{
+    if conn
+        .extension_information(dri3::X11_EXTENSION_NAME)?
+        .is_none()
+    {
+        return Err(ConnectionError::UnsupportedExtension.into());
+    }
+    let (major, minor) = dri3::X11_XML_VERSION;
+    let _ = dri3::query_version(conn, major, minor)?.reply()?;
+    Ok(dri3::open(conn, drawable, provider)?.reply()?.device_fd)
+}
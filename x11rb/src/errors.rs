@@ -2,7 +2,7 @@
 
 use crate::x11_utils::X11Error;
 
-pub use x11rb_protocol::errors::{ConnectError, IdsExhausted, ParseError};
+pub use x11rb_protocol::errors::{ConnectError, EventParseError, IdsExhausted, ParseError};
 
 /// An error occurred  while dynamically loading libxcb.
 #[cfg(feature = "dl-libxcb")]
@@ -69,6 +69,10 @@ pub enum ConnectionError {
     /// Error while parsing some data, see `ParseError`.
     ParseError(ParseError),
 
+    /// An event that was received from the X11 server could not be parsed, see
+    /// `EventParseError`.
+    EventParseError(EventParseError),
+
     /// Out of memory.
     ///
     /// This is `XCB_CONN_CLOSED_MEM_INSUFFICIENT`.
@@ -78,7 +82,16 @@ pub enum ConnectionError {
     IoError(std::io::Error),
 }
 
-impl std::error::Error for ConnectionError {}
+impl std::error::Error for ConnectionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConnectionError::ParseError(err) => Some(err),
+            ConnectionError::EventParseError(err) => Some(err),
+            ConnectionError::IoError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 impl std::fmt::Display for ConnectionError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -91,6 +104,7 @@ impl std::fmt::Display for ConnectionError {
             }
             ConnectionError::FdPassingFailed => write!(f, "FD passing failed"),
             ConnectionError::ParseError(err) => err.fmt(f),
+            ConnectionError::EventParseError(err) => err.fmt(f),
             ConnectionError::IoError(err) => err.fmt(f),
         }
     }
@@ -102,6 +116,12 @@ impl From<ParseError> for ConnectionError {
     }
 }
 
+impl From<EventParseError> for ConnectionError {
+    fn from(err: EventParseError) -> Self {
+        ConnectionError::EventParseError(err)
+    }
+}
+
 impl From<std::io::Error> for ConnectionError {
     fn from(err: std::io::Error) -> Self {
         ConnectionError::IoError(err)
@@ -117,13 +137,20 @@ pub enum ReplyError {
     X11Error(X11Error),
 }
 
-impl std::error::Error for ReplyError {}
+impl std::error::Error for ReplyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReplyError::ConnectionError(err) => Some(err),
+            ReplyError::X11Error(err) => Some(err),
+        }
+    }
+}
 
 impl std::fmt::Display for ReplyError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ReplyError::ConnectionError(e) => write!(f, "{}", e),
-            ReplyError::X11Error(e) => write!(f, "X11 error {:?}", e),
+            ReplyError::X11Error(e) => write!(f, "X11 error {}", e),
         }
     }
 }
@@ -168,12 +195,20 @@ impl std::fmt::Display for ReplyOrIdError {
         match self {
             ReplyOrIdError::IdsExhausted => f.write_str("X11 IDs have been exhausted"),
             ReplyOrIdError::ConnectionError(e) => write!(f, "{}", e),
-            ReplyOrIdError::X11Error(e) => write!(f, "X11 error {:?}", e),
+            ReplyOrIdError::X11Error(e) => write!(f, "X11 error {}", e),
         }
     }
 }
 
-impl std::error::Error for ReplyOrIdError {}
+impl std::error::Error for ReplyOrIdError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReplyOrIdError::IdsExhausted => None,
+            ReplyOrIdError::ConnectionError(err) => Some(err),
+            ReplyOrIdError::X11Error(err) => Some(err),
+        }
+    }
+}
 
 impl From<ParseError> for ReplyOrIdError {
     fn from(err: ParseError) -> Self {
@@ -207,3 +242,33 @@ impl From<IdsExhausted> for ReplyOrIdError {
         ReplyOrIdError::IdsExhausted
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{ConnectionError, ParseError, ReplyError, ReplyOrIdError};
+    use std::error::Error;
+
+    #[test]
+    fn error_sources() {
+        let error = ReplyError::from(ParseError::InvalidValue);
+        let source = error.source().unwrap();
+        assert!(matches!(
+            source.downcast_ref::<ConnectionError>(),
+            Some(ConnectionError::ParseError(_))
+        ));
+        let source = source.source().unwrap();
+        assert_eq!(
+            source.downcast_ref::<ParseError>(),
+            Some(&ParseError::InvalidValue)
+        );
+        assert!(source.source().is_none());
+
+        let io = std::io::Error::new(std::io::ErrorKind::Other, "oops");
+        let error = ReplyOrIdError::from(ConnectionError::from(io));
+        let source = error.source().and_then(Error::source).unwrap();
+        assert_eq!(source.to_string(), "oops");
+
+        assert!(ReplyOrIdError::IdsExhausted.source().is_none());
+        assert!(ConnectionError::UnknownError.source().is_none());
+    }
+}
@@ -41,6 +41,27 @@
 //! x11rb) to become readable. It can also implement timeouts, as seen in the
 //! [`xclock_utc` example](https://github.com/psychon/x11rb/blob/master/x11rb/examples/xclock_utc.rs).
 //!
+//! ## Edge-triggered Polling
+//!
+//! With an edge-triggered event loop, for example `epoll` with `EPOLLET`, a wakeup only happens
+//! when new data arrives on the I/O stream. Thus, the stream has to be drained completely after
+//! each wakeup, otherwise events can get stuck until the X11 server sends something else.
+//! [`drain_events`] does this by calling `conn.poll_for_event()` until no more events are
+//! available:
+//! ```no_run
+//! use x11rb::connection::Connection;
+//! use x11rb::errors::ConnectionError;
+//! use x11rb::event_loop_integration::drain_events;
+//!
+//! fn on_readable(conn: &impl Connection) -> Result<(), ConnectionError> {
+//!     let (events, result) = drain_events(conn);
+//!     for event in events {
+//!         handle_event(event);
+//!     }
+//!     result
+//! }
+//! # fn handle_event<T>(event: T) {}
+//! ```
 //!
 //! ## Threads and Races
 //!
@@ -80,3 +101,255 @@
 //! a dedicated event loop for fetching events from the X11 server. All other threads can now
 //! freely use the X11 connection without events possibly getting stuck and only being processed
 //! later.
+//!
+//! ## Waiting with a Timeout
+//!
+//! If all that is needed is waiting for the next event with a timeout,
+//! [`Connection::wait_for_event_with_timeout`] provides this without access to the underlying I/O
+//! stream. No helper thread is involved, so other threads may use the connection between calls
+//! and no event is lost when the timeout expires.
+//! ```no_run
+//! use std::time::Duration;
+//! use x11rb::connection::Connection;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let (conn, _) = x11rb::connect(None)?;
+//!     loop {
+//!         match conn.wait_for_event_with_timeout(Duration::from_secs(1))? {
+//!             Some(event) => println!("{:?}", event),
+//!             None => println!("Nothing happened for a second"),
+//!         }
+//!     }
+//! }
+//! ```
+
+use std::collections::VecDeque;
+
+use crate::connection::Connection;
+use crate::errors::ConnectionError;
+use crate::protocol::Event;
+
+/// Read all events that are currently available without blocking.
+///
+/// This calls `conn.poll_for_event()` until it returns `None`. Since `poll_for_event()` reads
+/// from the underlying I/O stream until it would block, the stream is completely drained
+/// afterwards. This is what an edge-triggered event loop needs.
+///
+/// The events are returned even if an error occurs. In this case, the events that were received
+/// before the error are returned together with the error.
+pub fn drain_events<C>(conn: &C) -> (Vec<Event>, Result<(), ConnectionError>)
+where
+    C: Connection + ?Sized,
+{
+    let mut events = Vec::new();
+    loop {
+        match conn.poll_for_event() {
+            Ok(Some(event)) => events.push(event),
+            Ok(None) => return (events, Ok(())),
+            Err(error) => return (events, Err(error)),
+        }
+    }
+}
+
+/// Get an iterator that waits for events.
+///
+/// Each call to `next()` calls `conn.wait_for_event()` and thus blocks until an event arrives.
+/// When an error occurs, it is returned once and the iterator ends afterwards. This allows to
+/// write `for event in events(&conn)`.
+pub fn events<C>(conn: &C) -> Events<'_, C>
+where
+    C: Connection + ?Sized,
+{
+    Events { conn, done: false }
+}
+
+/// An iterator over the events of a connection, see [`events`].
+#[derive(Debug)]
+pub struct Events<'c, C: ?Sized> {
+    conn: &'c C,
+    done: bool,
+}
+
+impl<C: Connection + ?Sized> Iterator for Events<'_, C> {
+    type Item = Result<Event, ConnectionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.conn.wait_for_event();
+        self.done = result.is_err();
+        Some(result)
+    }
+}
+
+impl<C: Connection + ?Sized> std::iter::FusedIterator for Events<'_, C> {}
+
+/// Get an iterator over the events that are available without blocking.
+///
+/// Each call to `next()` calls `conn.poll_for_event()`. The iterator ends when no more events
+/// are available or after an error was returned. Unlike [`drain_events`], this does not collect
+/// the events into a `Vec`.
+///
+/// The iterator can be used again later: Once it returned `None` because no events were
+/// available, another call to `next()` polls for new events again. This does not apply to
+/// errors.
+pub fn poll_iter<C>(conn: &C) -> PollIter<'_, C>
+where
+    C: Connection + ?Sized,
+{
+    PollIter {
+        conn,
+        failed: false,
+    }
+}
+
+/// An iterator over the events of a connection that does not block, see [`poll_iter`].
+#[derive(Debug)]
+pub struct PollIter<'c, C: ?Sized> {
+    conn: &'c C,
+    failed: bool,
+}
+
+impl<C: Connection + ?Sized> Iterator for PollIter<'_, C> {
+    type Item = Result<Event, ConnectionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.conn.poll_for_event() {
+            Ok(event) => event.map(Ok),
+            Err(error) => {
+                self.failed = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// A wrapper around a connection that allows to wait for specific events.
+///
+/// [`EventQueue::wait_for_specific_event`] returns the first event that matches a predicate. All
+/// other events that arrive in the meantime are kept and later returned by
+/// [`EventQueue::wait_for_event`] and [`EventQueue::poll_for_event`] in their original order.
+/// This is useful for request/response handshakes like waiting for a `SelectionNotify` event
+/// after a `ConvertSelection` request.
+///
+/// Events that are kept by the queue are not seen by other users of the connection. Thus, all
+/// events should be fetched through the queue while it is in use.
+///
+/// ```no_run
+/// use x11rb::event_loop_integration::EventQueue;
+/// use x11rb::protocol::Event;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let (conn, _) = x11rb::connect(None)?;
+///     let mut queue = EventQueue::new(&conn);
+///     // Send a ConvertSelection request here
+///     let notify = queue.wait_for_specific_event(|event| matches!(event, Event::SelectionNotify(_)))?;
+///     println!("{:?}", notify);
+///     // Handle the other events that arrived in the meantime
+///     while let Some(event) = queue.poll_for_event()? {
+///         println!("{:?}", event);
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct EventQueue<'c, C: ?Sized> {
+    conn: &'c C,
+    queued: VecDeque<Event>,
+}
+
+impl<'c, C: Connection + ?Sized> EventQueue<'c, C> {
+    /// Create a new, empty `EventQueue` for the given connection.
+    pub fn new(conn: &'c C) -> Self {
+        Self {
+            conn,
+            queued: VecDeque::new(),
+        }
+    }
+
+    /// Get the connection that this `EventQueue` reads events from.
+    pub fn connection(&self) -> &'c C {
+        self.conn
+    }
+
+    /// Get the number of events that were kept for later.
+    pub fn len(&self) -> usize {
+        self.queued.len()
+    }
+
+    /// Check if no events were kept for later.
+    pub fn is_empty(&self) -> bool {
+        self.queued.is_empty()
+    }
+
+    /// Wait for the next event.
+    ///
+    /// Events that were kept by an earlier call to
+    /// [`wait_for_specific_event`](Self::wait_for_specific_event) are returned first.
+    pub fn wait_for_event(&mut self) -> Result<Event, ConnectionError> {
+        match self.queued.pop_front() {
+            Some(event) => Ok(event),
+            None => self.conn.wait_for_event(),
+        }
+    }
+
+    /// Get the next event without blocking.
+    ///
+    /// Events that were kept by an earlier call to
+    /// [`wait_for_specific_event`](Self::wait_for_specific_event) are returned first.
+    pub fn poll_for_event(&mut self) -> Result<Option<Event>, ConnectionError> {
+        match self.queued.pop_front() {
+            Some(event) => Ok(Some(event)),
+            None => self.conn.poll_for_event(),
+        }
+    }
+
+    /// Wait for the first event that matches `predicate`.
+    ///
+    /// Events that were kept earlier are checked first. Events that do not match are kept for
+    /// later.
+    pub fn wait_for_specific_event(
+        &mut self,
+        predicate: impl Fn(&Event) -> bool,
+    ) -> Result<Event, ConnectionError> {
+        if let Some(event) = self.take_queued(&predicate) {
+            return Ok(event);
+        }
+        loop {
+            let event = self.conn.wait_for_event()?;
+            if predicate(&event) {
+                return Ok(event);
+            }
+            self.queued.push_back(event);
+        }
+    }
+
+    /// Get the first event that matches `predicate` without blocking.
+    ///
+    /// This works like [`wait_for_specific_event`](Self::wait_for_specific_event), but returns
+    /// `None` once no more events are available.
+    pub fn poll_for_specific_event(
+        &mut self,
+        predicate: impl Fn(&Event) -> bool,
+    ) -> Result<Option<Event>, ConnectionError> {
+        if let Some(event) = self.take_queued(&predicate) {
+            return Ok(Some(event));
+        }
+        while let Some(event) = self.conn.poll_for_event()? {
+            if predicate(&event) {
+                return Ok(Some(event));
+            }
+            self.queued.push_back(event);
+        }
+        Ok(None)
+    }
+
+    fn take_queued(&mut self, predicate: impl Fn(&Event) -> bool) -> Option<Event> {
+        let index = self.queued.iter().position(predicate)?;
+        self.queued.remove(index)
+    }
+}
@@ -118,7 +118,8 @@ impl ExtInfoProvider for ExtensionManager {
             .iter()
             .filter_map(|(name, state)| {
                 if let CheckState::Present(info) = state {
-                    if info.first_event <= event_code {
+                    // Extensions without events have first_event == 0
+                    if info.first_event != 0 && info.first_event <= event_code {
                         Some((*name, *info))
                     } else {
                         None
@@ -135,7 +136,8 @@ impl ExtInfoProvider for ExtensionManager {
             .iter()
             .filter_map(|(name, state)| {
                 if let CheckState::Present(info) = state {
-                    if info.first_error <= error_code {
+                    // Extensions without errors have first_error == 0
+                    if info.first_error != 0 && info.first_error <= error_code {
                         Some((*name, *info))
                     } else {
                         None
@@ -308,4 +310,29 @@ mod test {
         assert_eq!(ext_info.get_from_event_code(5), Some(("present", info)));
         assert_eq!(ext_info.get_from_error_code(6), Some(("present", info)));
     }
+
+    #[test]
+    fn test_info_provider_without_events_and_errors() {
+        let info = ExtensionInformation {
+            major_opcode: 130,
+            first_event: 85,
+            first_error: 140,
+        };
+        let none = ExtensionInformation {
+            major_opcode: 131,
+            first_event: 0,
+            first_error: 0,
+        };
+
+        let mut ext_info = ExtensionManager::default();
+        let _ = ext_info.0.insert("with", CheckState::Present(info));
+        let _ = ext_info.0.insert("without", CheckState::Present(none));
+
+        // Core events and errors do not belong to any extension
+        assert_eq!(ext_info.get_from_event_code(12), None);
+        assert_eq!(ext_info.get_from_error_code(3), None);
+        assert_eq!(ext_info.get_from_event_code(86), Some(("with", info)));
+        assert_eq!(ext_info.get_from_error_code(140), Some(("with", info)));
+        assert_eq!(ext_info.get_from_major_opcode(131), Some(("without", none)));
+    }
 }
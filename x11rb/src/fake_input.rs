@@ -0,0 +1,191 @@
+//! Synthesizing input events with the XTEST extension.
+//!
+//! [`FakeInput`] sends key, button and motion events through `xtest::FakeInput`. By default, the
+//! events are generated by the core devices, which are the virtual core pointer (VCP) and the
+//! virtual core keyboard (VCK) when XInput 2 is used. With multiple master pointers (MPX), another
+//! device can be selected via [`FakeInput::with_device`].
+//!
+//! The code in this module is only available when the `xtest` feature of the library is enabled.
+//!
+//! Usage example:
+//! ```no_run
+//! use x11rb::connection::Connection;
+//! use x11rb::fake_input::{self, FakeInput};
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let (conn, screen_num) = x11rb::connect(None)?;
+//!     let root = conn.setup().roots[screen_num].root;
+//!     if fake_input::query_version(&conn)?.is_none() {
+//!         eprintln!("The XTEST extension is not supported");
+//!         return Ok(());
+//!     }
+//!     let input = FakeInput::new(&conn);
+//!     input.motion(root, 100, 100)?;
+//!     input.button_press(1)?;
+//!     input.button_release(1)?;
+//!     conn.flush()?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::connection::RequestConnection;
+use crate::cookie::VoidCookie;
+use crate::errors::{ConnectionError, ReplyError};
+use crate::protocol::xproto::{self, Button, Keycode, Window};
+use crate::protocol::xtest::{self, FakeInputRequest};
+
+/// The device id that selects the core devices.
+///
+/// With XInput 2, these are the virtual core pointer and the virtual core keyboard.
+pub const CORE_DEVICE: u8 = 0;
+
+/// Query the version of the XTEST extension.
+///
+/// This asks for version 2.2. `None` is returned if the server does not support the extension.
+pub fn query_version<C: RequestConnection + ?Sized>(
+    conn: &C,
+) -> Result<Option<(u8, u16)>, ReplyError> {
+    match xtest::get_version(conn, 2, 2) {
+        Ok(cookie) => {
+            let reply = cookie.reply()?;
+            Ok(Some((reply.major_version, reply.minor_version)))
+        }
+        Err(ConnectionError::UnsupportedExtension) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// A helper for synthesizing input events via the XTEST extension.
+///
+/// All events are sent without any delay, i.e. with a `time` of zero.
+#[derive(Debug, Clone, Copy)]
+pub struct FakeInput<'c, C: ?Sized> {
+    conn: &'c C,
+    device: u8,
+}
+
+impl<'c, C: RequestConnection + ?Sized> FakeInput<'c, C> {
+    /// Create a helper that generates events from the core devices.
+    pub fn new(conn: &'c C) -> Self {
+        Self::with_device(conn, None)
+    }
+
+    /// Create a helper that generates events from the given device.
+    ///
+    /// The device id is an XInput device id. `None` selects the core devices, which is the same
+    /// as [`FakeInput::new`].
+    pub fn with_device(conn: &'c C, device: Option<u8>) -> Self {
+        Self {
+            conn,
+            device: device.unwrap_or(CORE_DEVICE),
+        }
+    }
+
+    /// Get the device id that is used for the events.
+    pub fn device(&self) -> u8 {
+        self.device
+    }
+
+    /// Press the key with the given keycode.
+    pub fn key_press(&self, keycode: Keycode) -> Result<VoidCookie<'c, C>, ConnectionError> {
+        self.send(xproto::KEY_PRESS_EVENT, keycode, crate::NONE, 0, 0)
+    }
+
+    /// Release the key with the given keycode.
+    pub fn key_release(&self, keycode: Keycode) -> Result<VoidCookie<'c, C>, ConnectionError> {
+        self.send(xproto::KEY_RELEASE_EVENT, keycode, crate::NONE, 0, 0)
+    }
+
+    /// Press the given pointer button.
+    pub fn button_press(&self, button: Button) -> Result<VoidCookie<'c, C>, ConnectionError> {
+        self.send(xproto::BUTTON_PRESS_EVENT, button, crate::NONE, 0, 0)
+    }
+
+    /// Release the given pointer button.
+    pub fn button_release(&self, button: Button) -> Result<VoidCookie<'c, C>, ConnectionError> {
+        self.send(xproto::BUTTON_RELEASE_EVENT, button, crate::NONE, 0, 0)
+    }
+
+    /// Move the pointer to the given position on the screen of `root`.
+    ///
+    /// If `root` is `NONE`, the screen that currently contains the pointer is used.
+    pub fn motion(
+        &self,
+        root: Window,
+        x: i16,
+        y: i16,
+    ) -> Result<VoidCookie<'c, C>, ConnectionError> {
+        self.send(xproto::MOTION_NOTIFY_EVENT, 0, root, x, y)
+    }
+
+    /// Move the pointer relative to its current position.
+    pub fn relative_motion(&self, dx: i16, dy: i16) -> Result<VoidCookie<'c, C>, ConnectionError> {
+        self.send(xproto::MOTION_NOTIFY_EVENT, 1, crate::NONE, dx, dy)
+    }
+
+    /// Set whether this client is unaffected by server grabs.
+    ///
+    /// When `impervious` is true, the requests of this client, including the synthesized
+    /// input events, are still processed while another client grabs the server.
+    pub fn set_impervious(&self, impervious: bool) -> Result<VoidCookie<'c, C>, ConnectionError> {
+        xtest::grab_control(self.conn, impervious)
+    }
+
+    fn send(
+        &self,
+        type_: u8,
+        detail: u8,
+        root: Window,
+        root_x: i16,
+        root_y: i16,
+    ) -> Result<VoidCookie<'c, C>, ConnectionError> {
+        let request = fake_input_request(self.device, type_, detail, root, root_x, root_y);
+        xtest::fake_input(
+            self.conn,
+            request.type_,
+            request.detail,
+            request.time,
+            request.root,
+            request.root_x,
+            request.root_y,
+            request.deviceid,
+        )
+    }
+}
+
+/// Build the `FakeInput` request for an event from the given device.
+fn fake_input_request(
+    device: u8,
+    type_: u8,
+    detail: u8,
+    root: Window,
+    root_x: i16,
+    root_y: i16,
+) -> FakeInputRequest {
+    FakeInputRequest {
+        type_,
+        detail,
+        time: 0,
+        root,
+        root_x,
+        root_y,
+        deviceid: device,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::fake_input_request;
+    use crate::protocol::xproto;
+
+    #[test]
+    fn request_for_device() {
+        let request = fake_input_request(3, xproto::MOTION_NOTIFY_EVENT, 0, 42, 10, -5);
+        assert_eq!(request.type_, xproto::MOTION_NOTIFY_EVENT);
+        assert_eq!(request.detail, 0);
+        assert_eq!(request.time, 0);
+        assert_eq!(request.root, 42);
+        assert_eq!((request.root_x, request.root_y), (10, -5));
+        assert_eq!(request.deviceid, 3);
+    }
+}
@@ -0,0 +1,232 @@
+//! Utilities for tracking the input focus.
+//!
+//! The X11 server sends `FocusIn` and `FocusOut` events not only to the window that gains or loses
+//! the focus, but also to the windows between it and the previous focus window. Additionally,
+//! keyboard grabs generate focus events even though the focus did not actually change.
+//! [`is_focus_change`] filters out these events.
+//!
+//! [`FocusHistory`] uses this to remember which windows had the focus recently, so that e.g. a
+//! window manager can give the focus back to the previous window when the focused window closes.
+//!
+//! Usage example:
+//! ```no_run
+//! use x11rb::connection::Connection;
+//! use x11rb::focus::FocusHistory;
+//! use x11rb::protocol::{xproto::*, Event};
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let (conn, _) = x11rb::connect(None)?;
+//!     let mut history = FocusHistory::new();
+//!     loop {
+//!         let event = conn.wait_for_event()?;
+//!         if let Event::DestroyNotify(destroy) = &event {
+//!             if history.current_focus() == Some(destroy.window) {
+//!                 if let Some(previous) = history.previous_focus() {
+//!                     conn.set_input_focus(InputFocus::PARENT, previous, x11rb::CURRENT_TIME)?;
+//!                 }
+//!             }
+//!         }
+//!         history.handle_event(&event);
+//!     }
+//! }
+//! ```
+
+use std::collections::VecDeque;
+
+use crate::protocol::xproto::{FocusInEvent, NotifyDetail, NotifyMode, Window};
+use crate::protocol::Event;
+
+/// The number of windows that [`FocusHistory::new`] remembers.
+pub const DEFAULT_FOCUS_HISTORY_SIZE: usize = 16;
+
+/// Check whether a `FocusIn` or `FocusOut` event describes an actual change of the focus.
+///
+/// This returns `false` for events that are caused by activating or deactivating a keyboard grab
+/// and for events that are sent to windows other than the old or new focus window, e.g. to the
+/// ancestors of the focus window or to the window containing the pointer.
+pub fn is_focus_change(event: &FocusInEvent) -> bool {
+    let grab = event.mode == NotifyMode::GRAB || event.mode == NotifyMode::UNGRAB;
+    let detail = event.detail;
+    let target = detail == NotifyDetail::ANCESTOR
+        || detail == NotifyDetail::INFERIOR
+        || detail == NotifyDetail::NONLINEAR;
+    !grab && target
+}
+
+/// A bounded history of the windows that had the input focus.
+///
+/// The history is fed with `FocusIn` events via [`FocusHistory::handle_event`] or
+/// [`FocusHistory::focus_in`]. Each window appears at most once; focusing a window again moves it
+/// to the front of the history. When the history is full, the window that was focused least
+/// recently is dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FocusHistory {
+    // The most recently focused window is at the back
+    windows: VecDeque<Window>,
+    capacity: usize,
+}
+
+impl Default for FocusHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FocusHistory {
+    /// Create a new history that remembers [`DEFAULT_FOCUS_HISTORY_SIZE`] windows.
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_FOCUS_HISTORY_SIZE)
+    }
+
+    /// Create a new history that remembers at most `capacity` windows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn with_capacity(capacity: usize) -> Self {
+        assert!(
+            capacity > 0,
+            "A focus history must be able to hold a window"
+        );
+        Self {
+            windows: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Get the maximum number of windows that are remembered.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Get the window that currently has the focus, if it is known.
+    pub fn current_focus(&self) -> Option<Window> {
+        self.windows.back().copied()
+    }
+
+    /// Get the window that had the focus before the current focus window.
+    pub fn previous_focus(&self) -> Option<Window> {
+        self.windows.iter().rev().nth(1).copied()
+    }
+
+    /// Iterate over the remembered windows, starting with the current focus window.
+    pub fn iter(&self) -> impl Iterator<Item = Window> + '_ {
+        self.windows.iter().rev().copied()
+    }
+
+    /// Update the history from an event.
+    ///
+    /// `FocusIn` events that pass [`is_focus_change`] record a new focus window. Destroyed windows
+    /// are removed from the history. All other events are ignored.
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::FocusIn(event) if is_focus_change(event) => self.focus_in(event.event),
+            Event::DestroyNotify(event) => self.forget(event.window),
+            _ => {}
+        }
+    }
+
+    /// Record that `window` received the focus.
+    pub fn focus_in(&mut self, window: Window) {
+        self.forget(window);
+        if self.windows.len() == self.capacity {
+            let _ = self.windows.pop_front();
+        }
+        self.windows.push_back(window);
+    }
+
+    /// Remove a window from the history, e.g. because it was unmapped or destroyed.
+    pub fn forget(&mut self, window: Window) {
+        self.windows.retain(|&w| w != window);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_focus_change, FocusHistory};
+    use crate::protocol::xproto::{DestroyNotifyEvent, FocusInEvent, NotifyDetail, NotifyMode};
+    use crate::protocol::Event;
+
+    fn focus_in(window: u32, detail: NotifyDetail, mode: NotifyMode) -> Event {
+        Event::FocusIn(FocusInEvent {
+            response_type: crate::protocol::xproto::FOCUS_IN_EVENT,
+            detail,
+            sequence: 0,
+            event: window,
+            mode,
+        })
+    }
+
+    #[test]
+    fn filter() {
+        let event = |detail, mode| FocusInEvent {
+            response_type: 0,
+            detail,
+            sequence: 0,
+            event: 1,
+            mode,
+        };
+        assert!(is_focus_change(&event(
+            NotifyDetail::NONLINEAR,
+            NotifyMode::NORMAL
+        )));
+        assert!(is_focus_change(&event(
+            NotifyDetail::ANCESTOR,
+            NotifyMode::WHILE_GRABBED
+        )));
+        assert!(!is_focus_change(&event(
+            NotifyDetail::NONLINEAR,
+            NotifyMode::GRAB
+        )));
+        assert!(!is_focus_change(&event(
+            NotifyDetail::NONLINEAR,
+            NotifyMode::UNGRAB
+        )));
+        assert!(!is_focus_change(&event(
+            NotifyDetail::VIRTUAL,
+            NotifyMode::NORMAL
+        )));
+        assert!(!is_focus_change(&event(
+            NotifyDetail::POINTER,
+            NotifyMode::NORMAL
+        )));
+    }
+
+    #[test]
+    fn previous_focus() {
+        let mut history = FocusHistory::new();
+        assert_eq!(history.current_focus(), None);
+        history.handle_event(&focus_in(1, NotifyDetail::NONLINEAR, NotifyMode::NORMAL));
+        assert_eq!(history.previous_focus(), None);
+        history.handle_event(&focus_in(2, NotifyDetail::NONLINEAR, NotifyMode::NORMAL));
+        // Grabs and virtual events are ignored
+        history.handle_event(&focus_in(3, NotifyDetail::NONLINEAR, NotifyMode::GRAB));
+        history.handle_event(&focus_in(
+            4,
+            NotifyDetail::NONLINEAR_VIRTUAL,
+            NotifyMode::NORMAL,
+        ));
+        assert_eq!(history.current_focus(), Some(2));
+        assert_eq!(history.previous_focus(), Some(1));
+
+        // Focusing a window again moves it to the front
+        history.focus_in(1);
+        assert_eq!(history.iter().collect::<Vec<_>>(), [1, 2]);
+
+        history.handle_event(&Event::DestroyNotify(DestroyNotifyEvent {
+            window: 1,
+            ..Default::default()
+        }));
+        assert_eq!(history.current_focus(), Some(2));
+        assert_eq!(history.previous_focus(), None);
+    }
+
+    #[test]
+    fn bounded() {
+        let mut history = FocusHistory::with_capacity(2);
+        history.focus_in(1);
+        history.focus_in(2);
+        history.focus_in(3);
+        assert_eq!(history.iter().collect::<Vec<_>>(), [3, 2]);
+    }
+}
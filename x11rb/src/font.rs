@@ -0,0 +1,277 @@
+//! Utility functions for measuring text that is drawn with core X11 fonts.
+//!
+//! The size of a string in some font can be queried from the X11 server via `QueryTextExtents`.
+//! [`text_extents`] is a small wrapper around this request.
+//!
+//! Since this requires a round trip per string, [`FontMetrics`] allows to cache the metrics of
+//! the ASCII characters of a font. The metrics are fetched once via `QueryFont`. Afterwards, the
+//! extents of strings only consisting of cached characters are computed locally. Other strings
+//! still cause a `QueryTextExtents` request.
+//!
+//! Usage example:
+//! ```no_run
+//! use x11rb::connection::Connection;
+//! use x11rb::errors::ReplyError;
+//! use x11rb::font::FontMetrics;
+//! use x11rb::protocol::xproto::Font;
+//!
+//! fn layout(conn: &impl Connection, font: Font) -> Result<(), ReplyError> {
+//!     let metrics = FontMetrics::query(conn, font)?;
+//!     for line in &["Hello", "World"] {
+//!         let extents = metrics.text_extents(conn, font, line.as_bytes())?;
+//!         println!("{} is {} pixels wide", line, extents.width);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use crate::connection::RequestConnection;
+use crate::errors::ReplyError;
+use crate::protocol::xproto::{
+    self, Char2b, Charinfo, Fontable, QueryFontReply, QueryTextExtentsReply,
+};
+
+/// The number of characters that `FontMetrics` caches.
+const NUM_CACHED_CHARS: u16 = 128;
+
+/// The extents of a string.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct TextExtents {
+    /// The ascent of the font.
+    pub font_ascent: i16,
+    /// The descent of the font.
+    pub font_descent: i16,
+    /// The maximum ascent of the characters in the string.
+    pub ascent: i16,
+    /// The maximum descent of the characters in the string.
+    pub descent: i16,
+    /// The sum of the widths of the characters in the string.
+    pub width: i32,
+    /// The leftmost extent of the string relative to its origin.
+    pub left: i32,
+    /// The rightmost extent of the string relative to its origin.
+    pub right: i32,
+}
+
+impl From<QueryTextExtentsReply> for TextExtents {
+    fn from(reply: QueryTextExtentsReply) -> Self {
+        Self {
+            font_ascent: reply.font_ascent,
+            font_descent: reply.font_descent,
+            ascent: reply.overall_ascent,
+            descent: reply.overall_descent,
+            width: reply.overall_width,
+            left: reply.overall_left,
+            right: reply.overall_right,
+        }
+    }
+}
+
+/// Query the extents of a string from the X11 server.
+///
+/// Each byte of `text` is interpreted as a single character. This sends a `QueryTextExtents`
+/// request and waits for its reply.
+pub fn text_extents<C: RequestConnection + ?Sized>(
+    conn: &C,
+    font: Fontable,
+    text: &[u8],
+) -> Result<TextExtents, ReplyError> {
+    let string = text
+        .iter()
+        .map(|&byte2| Char2b { byte1: 0, byte2 })
+        .collect::<Vec<_>>();
+    Ok(xproto::query_text_extents(conn, font, &string)?
+        .reply()?
+        .into())
+}
+
+/// Cached metrics of the ASCII characters of a font.
+#[derive(Debug, Clone)]
+pub struct FontMetrics {
+    font_ascent: i16,
+    font_descent: i16,
+    chars: Vec<Option<Charinfo>>,
+}
+
+impl FontMetrics {
+    /// Query the metrics of the given font from the X11 server.
+    ///
+    /// This sends a `QueryFont` request and waits for its reply.
+    pub fn query<C: RequestConnection + ?Sized>(
+        conn: &C,
+        font: Fontable,
+    ) -> Result<Self, ReplyError> {
+        Ok(Self::from_reply(&xproto::query_font(conn, font)?.reply()?))
+    }
+
+    /// Construct a new `FontMetrics` instance from a `QueryFontReply`.
+    ///
+    /// Only the metrics of characters `0` to `127` are cached. For two-byte fonts, these are the
+    /// characters in the first row.
+    pub fn from_reply(reply: &QueryFontReply) -> Self {
+        let chars = (0..NUM_CACHED_CHARS).map(|c| char_info(reply, c)).collect();
+        Self {
+            font_ascent: reply.font_ascent,
+            font_descent: reply.font_descent,
+            chars,
+        }
+    }
+
+    /// Get the cached metrics of the given character.
+    ///
+    /// `None` is returned if the character is not cached or does not exist in the font.
+    pub fn char_info(&self, c: u8) -> Option<&Charinfo> {
+        self.chars.get(usize::from(c))?.as_ref()
+    }
+
+    /// Compute the extents of a string locally.
+    ///
+    /// `None` is returned if the string contains characters which metrics are not cached.
+    pub fn local_text_extents(&self, text: &[u8]) -> Option<TextExtents> {
+        let mut extents = TextExtents {
+            font_ascent: self.font_ascent,
+            font_descent: self.font_descent,
+            ..Default::default()
+        };
+        for (index, &c) in text.iter().enumerate() {
+            let info = self.char_info(c)?;
+            let x = extents.width;
+            if index == 0 {
+                extents.ascent = info.ascent;
+                extents.descent = info.descent;
+                extents.left = x + i32::from(info.left_side_bearing);
+                extents.right = x + i32::from(info.right_side_bearing);
+            } else {
+                extents.ascent = extents.ascent.max(info.ascent);
+                extents.descent = extents.descent.max(info.descent);
+                extents.left = extents.left.min(x + i32::from(info.left_side_bearing));
+                extents.right = extents.right.max(x + i32::from(info.right_side_bearing));
+            }
+            extents.width += i32::from(info.character_width);
+        }
+        Some(extents)
+    }
+
+    /// Compute the extents of a string.
+    ///
+    /// If all characters of the string are cached, the extents are computed locally. Otherwise,
+    /// this falls back to [`text_extents`] and thus causes a round trip to the X11 server.
+    pub fn text_extents<C: RequestConnection + ?Sized>(
+        &self,
+        conn: &C,
+        font: Fontable,
+        text: &[u8],
+    ) -> Result<TextExtents, ReplyError> {
+        match self.local_text_extents(text) {
+            Some(extents) => Ok(extents),
+            None => text_extents(conn, font, text),
+        }
+    }
+}
+
+/// Get the metrics of a character from a `QueryFontReply`, if it exists in the font.
+fn char_info(reply: &QueryFontReply, c: u16) -> Option<Charinfo> {
+    if reply.min_byte1 != 0 || c < reply.min_char_or_byte2 || c > reply.max_char_or_byte2 {
+        return None;
+    }
+    if reply.char_infos.is_empty() {
+        // All characters have the same metrics
+        return Some(reply.max_bounds);
+    }
+    let info = *reply
+        .char_infos
+        .get(usize::from(c - reply.min_char_or_byte2))?;
+    // Non-existent characters have all-zero metrics
+    if info == Charinfo::default() {
+        None
+    } else {
+        Some(info)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FontMetrics, TextExtents};
+    use crate::protocol::xproto::{Charinfo, FontDraw, QueryFontReply};
+
+    fn char_info(left: i16, right: i16, width: i16, ascent: i16, descent: i16) -> Charinfo {
+        Charinfo {
+            left_side_bearing: left,
+            right_side_bearing: right,
+            character_width: width,
+            ascent,
+            descent,
+            attributes: 0,
+        }
+    }
+
+    fn font_reply(min_char: u16, char_infos: Vec<Charinfo>) -> QueryFontReply {
+        let max_bounds = char_info(0, 6, 6, 10, 2);
+        QueryFontReply {
+            sequence: 0,
+            length: 0,
+            min_bounds: max_bounds,
+            max_bounds,
+            min_char_or_byte2: min_char,
+            max_char_or_byte2: min_char + 2,
+            default_char: 0,
+            draw_direction: FontDraw::LEFT_TO_RIGHT,
+            min_byte1: 0,
+            max_byte1: 0,
+            all_chars_exist: false,
+            font_ascent: 11,
+            font_descent: 3,
+            properties: Vec::new(),
+            char_infos,
+        }
+    }
+
+    #[test]
+    fn test_proportional_font() {
+        let reply = font_reply(
+            b'a'.into(),
+            vec![
+                char_info(1, 5, 6, 7, 0),
+                Default::default(),
+                char_info(-1, 4, 5, 9, 3),
+            ],
+        );
+        let metrics = FontMetrics::from_reply(&reply);
+        assert_eq!(metrics.char_info(b'a'), Some(&char_info(1, 5, 6, 7, 0)));
+        assert_eq!(metrics.char_info(b'b'), None);
+        assert_eq!(metrics.char_info(b'd'), None);
+        assert_eq!(metrics.char_info(200), None);
+
+        assert_eq!(
+            metrics.local_text_extents(b"ca"),
+            Some(TextExtents {
+                font_ascent: 11,
+                font_descent: 3,
+                ascent: 9,
+                descent: 3,
+                width: 11,
+                left: -1,
+                right: 10,
+            })
+        );
+        assert_eq!(metrics.local_text_extents(b"ab"), None);
+        assert_eq!(
+            metrics.local_text_extents(b""),
+            Some(TextExtents {
+                font_ascent: 11,
+                font_descent: 3,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_monospace_font() {
+        let metrics = FontMetrics::from_reply(&font_reply(b'x'.into(), Vec::new()));
+        assert_eq!(metrics.char_info(b'w'), None);
+        assert_eq!(
+            metrics.local_text_extents(b"xyz").map(|e| e.width),
+            Some(18)
+        );
+    }
+}
@@ -31,9 +31,10 @@ use crate::connection::Connection;
 use crate::cookie::VoidCookie;
 use crate::errors::{ConnectionError, ParseError, ReplyError};
 use crate::protocol::xproto::{
-    get_image, put_image, Drawable, Format, Gcontext, GetImageReply, ImageFormat,
-    ImageOrder as XprotoImageOrder, Setup, VisualClass, Visualtype,
+    get_geometry, get_image, put_image, Drawable, Format, Gcontext, GetImageReply, ImageFormat,
+    ImageOrder as XprotoImageOrder, Rectangle, Setup, VisualClass, Visualid, Visualtype,
 };
+use crate::NONE;
 
 /// The description of a single color component.
 ///
@@ -736,6 +737,9 @@ impl<'a> Image<'a> {
     /// The server's maximum request size is honored. This means that a too large `PutImage`
     /// request is automatically split up into smaller pieces. Thus, if this function returns an
     /// error, the image could already be partially sent.
+    ///
+    /// If a single row of the image does not fit into a request,
+    /// [`ConnectionError::MaximumRequestLengthExceeded`] is returned before anything is sent.
     pub fn put<'c, Conn: Connection>(
         &self,
         conn: &'c Conn,
@@ -745,39 +749,29 @@ impl<'a> Image<'a> {
         dst_y: i16,
     ) -> Result<Vec<VoidCookie<'c, Conn>>, ConnectionError> {
         // Upload the image without exceeding the server's maximum request size
-        let max_bytes = conn.maximum_request_bytes();
-        let put_image_header = 24;
         let stride = self.stride();
-        let lines_per_request = (max_bytes - put_image_header) / stride;
-        let mut result = Vec::with_capacity(
-            (usize::from(self.height()) + lines_per_request - 1) / lines_per_request,
-        );
-        let lines_per_request = lines_per_request.try_into().unwrap_or(u16::max_value());
-        assert!(lines_per_request > 0);
-
-        let (mut y_offset, mut byte_offset) = (0, 0);
-        while y_offset < self.height {
-            let next_lines = lines_per_request.min(self.height - y_offset);
-            let next_byte_offset = byte_offset + usize::from(next_lines) * stride;
-            let data = &self.data[byte_offset..next_byte_offset];
-            result.push(put_image(
-                conn,
-                ImageFormat::Z_PIXMAP,
-                drawable,
-                gc,
-                self.width,
-                next_lines,
-                dst_x,
-                dst_y + i16::try_from(y_offset).unwrap(),
-                0, // left_pad must always be 0 for ZPixmap
-                self.depth,
-                data,
-            )?);
-
-            y_offset += next_lines;
-            byte_offset = next_byte_offset;
-        }
-        Ok(result)
+        let lines_per_request = put_lines_per_band(conn.maximum_request_bytes(), stride)
+            .ok_or(ConnectionError::MaximumRequestLengthExceeded)?;
+        bands(self.height, lines_per_request)
+            .into_iter()
+            .map(|(y_offset, lines)| {
+                let start = usize::from(y_offset) * stride;
+                let data = &self.data[start..start + usize::from(lines) * stride];
+                put_image(
+                    conn,
+                    ImageFormat::Z_PIXMAP,
+                    drawable,
+                    gc,
+                    self.width,
+                    lines,
+                    dst_x,
+                    dst_y + i16::try_from(y_offset).unwrap(),
+                    0, // left_pad must always be 0 for ZPixmap
+                    self.depth,
+                    data,
+                )
+            })
+            .collect()
     }
 
     /// Convert this image into the format specified by the other parameters.
@@ -975,6 +969,504 @@ impl<'a> Image<'a> {
     }
 }
 
+/// An error that occurred while converting RGBA data via [`Image::from_rgba`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RgbaImageError {
+    /// The requested depth is not supported. Only depths 24 and 32 can be targeted.
+    UnsupportedDepth(u8),
+    /// The RGBA data, visual, or server setup could not be interpreted.
+    ParseError(ParseError),
+    /// Some error occurred on the X11 connection.
+    ConnectionError(ConnectionError),
+}
+
+impl std::error::Error for RgbaImageError {}
+
+impl std::fmt::Display for RgbaImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RgbaImageError::UnsupportedDepth(depth) => write!(
+                f,
+                "Cannot convert RGBA data to depth {}, only depths 24 and 32 are supported",
+                depth
+            ),
+            RgbaImageError::ParseError(e) => write!(f, "{}", e),
+            RgbaImageError::ConnectionError(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<ParseError> for RgbaImageError {
+    fn from(err: ParseError) -> Self {
+        Self::ParseError(err)
+    }
+}
+
+impl From<ConnectionError> for RgbaImageError {
+    fn from(err: ConnectionError) -> Self {
+        Self::ConnectionError(err)
+    }
+}
+
+impl Image<'static> {
+    /// Convert RGBA data into an image in the native format of the X11 server.
+    ///
+    /// `rgba` contains `width`x`height` pixels in row-major order with four bytes per pixel in
+    /// the order red, green, blue, alpha. This is e.g. the layout of the `image` crate's
+    /// `RgbaImage`, see also `put_image_from_rgba` with the `image-crate` feature. The colours are
+    /// encoded as described by `visual`.
+    ///
+    /// For depth 24, the alpha channel is ignored. For depth 32, the alpha channel is stored in
+    /// the bits that are not used by the colour channels and the colour channels are
+    /// premultiplied with it, as expected by the `RENDER` extension.
+    ///
+    /// # Errors
+    ///
+    /// `RgbaImageError::UnsupportedDepth` is returned for depths other than 24 and 32.
+    /// `ParseError::InsufficientData` is returned if `rgba` is too short for the given size.
+    pub fn from_rgba(
+        width: u16,
+        height: u16,
+        rgba: &[u8],
+        visual: Visualtype,
+        depth: u8,
+        setup: &Setup,
+    ) -> Result<Self, RgbaImageError> {
+        if depth != 24 && depth != 32 {
+            return Err(RgbaImageError::UnsupportedDepth(depth));
+        }
+        let layout = PixelLayout::from_visual_type(visual)?;
+        let alpha = if depth == 32 {
+            let color_mask = layout.red.mask() | layout.green.mask() | layout.blue.mask();
+            Some(ColorComponent::from_mask(!color_mask)?)
+        } else {
+            None
+        };
+        let num_pixels = usize::from(width) * usize::from(height);
+        if rgba.len() < 4 * num_pixels {
+            return Err(ParseError::InsufficientData.into());
+        }
+
+        let mut image = Self::allocate_native(width, height, depth, setup)?;
+        let pixels = rgba.chunks_exact(4).take(num_pixels);
+        for (index, pixel) in pixels.enumerate() {
+            let (x, y) = (index % usize::from(width), index / usize::from(width));
+            let (x, y) = (x.try_into().unwrap(), y.try_into().unwrap());
+            let value = match alpha {
+                None => layout.encode(rgba_to_rgb16(pixel, 0xff)),
+                Some(alpha) => {
+                    let rgb = rgba_to_rgb16(pixel, pixel[3]);
+                    layout.encode(rgb) | alpha.encode(u16::from(pixel[3]) * 0x101)
+                }
+            };
+            image.put_pixel(x, y, value);
+        }
+        Ok(image)
+    }
+}
+
+/// Expand the colour channels of an RGBA pixel to width 16, premultiplied with `alpha`.
+fn rgba_to_rgb16(pixel: &[u8], alpha: u8) -> (u16, u16, u16) {
+    let expand = |value: u8| {
+        let value = (u32::from(value) * u32::from(alpha) + 0x7f) / 0xff;
+        u16::try_from(value).unwrap() * 0x101
+    };
+    (expand(pixel[0]), expand(pixel[1]), expand(pixel[2]))
+}
+
+/// Upload an `RgbaImage` from the `image` crate to the given `drawable` at position `(x, y)`.
+///
+/// This converts the image via [`Image::from_rgba`] and uploads it via [`Image::put`], so a large
+/// image is split into multiple `PutImage` requests. `depth` must match the depth of `drawable`.
+///
+/// `ParseError::ConversionFailed` is returned if the image is wider or higher than `u16::MAX`
+/// pixels. See [`Image::from_rgba`] for the other errors.
+///
+/// This function is only available when the `image-crate` feature of the library is enabled.
+#[cfg(feature = "image-crate")]
+#[allow(clippy::too_many_arguments)]
+pub fn put_image_from_rgba<'c, Conn: Connection>(
+    conn: &'c Conn,
+    drawable: Drawable,
+    gc: Gcontext,
+    x: i16,
+    y: i16,
+    image: &image_crate::RgbaImage,
+    visual: Visualtype,
+    depth: u8,
+) -> Result<Vec<VoidCookie<'c, Conn>>, RgbaImageError> {
+    let (width, height) = image.dimensions();
+    let width = u16::try_from(width).map_err(|_| ParseError::ConversionFailed)?;
+    let height = u16::try_from(height).map_err(|_| ParseError::ConversionFailed)?;
+    let image = Image::from_rgba(width, height, image.as_raw(), visual, depth, conn.setup())?;
+    Ok(image.put(conn, drawable, gc, x, y)?)
+}
+
+/// Upload pixel data in the native format of the X11 server to `drawable` at `(dst_x, dst_y)`.
+///
+/// `data` contains `height` rows of `width` pixels in `ImageFormat::Z_PIXMAP` format. Each row
+/// takes up as many bytes as the `bits_per_pixel` and `scanline_pad` of the pixmap format for
+/// `depth` in the [`Setup`] require. The byte order is the server's `image_byte_order`.
+///
+/// The image is split into horizontal bands so that each `PutImage` request fits into
+/// [`maximum_request_bytes`]. One request is sent per band. If this function returns an error,
+/// the image could already be partially sent.
+///
+/// An error is returned if the server does not support `depth`, if `data` is too short or if a
+/// single row does not fit into a request.
+///
+/// [`maximum_request_bytes`]: crate::connection::RequestConnection::maximum_request_bytes
+#[allow(clippy::too_many_arguments)]
+pub fn put_image_chunked<'c, Conn: Connection>(
+    conn: &'c Conn,
+    drawable: Drawable,
+    gc: Gcontext,
+    dst_x: i16,
+    dst_y: i16,
+    width: u16,
+    height: u16,
+    depth: u8,
+    data: &[u8],
+) -> Result<Vec<VoidCookie<'c, Conn>>, ConnectionError> {
+    let setup = conn.setup();
+    let format = find_format(setup, depth)?;
+    let image = Image::new(
+        width,
+        height,
+        format.scanline_pad.try_into()?,
+        depth,
+        format.bits_per_pixel.try_into()?,
+        setup.image_byte_order.try_into()?,
+        Cow::Borrowed(data),
+    )?;
+    image.put(conn, drawable, gc, dst_x, dst_y)
+}
+
+/// The result of [`get_image_chunked`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetImageChunkedReply {
+    /// The depth of the drawable.
+    pub depth: u8,
+    /// The visual of the drawable, as reported by the first `GetImage` reply.
+    ///
+    /// This is `NONE` for pixmaps and if no `GetImage` request was sent because `height` is zero.
+    pub visual: Visualid,
+    /// The pixel data of all rows in `ImageFormat::Z_PIXMAP` format.
+    ///
+    /// Each row takes up as many bytes as the pixmap format for `depth` in the [`Setup`] requires.
+    pub data: Vec<u8>,
+}
+
+/// Get a region of a drawable from the X11 server, even if it is larger than a single reply.
+///
+/// A `GetImage` reply for a large region, e.g. a screenshot of a 4K display, can exceed what the
+/// X11 server is willing to send. This function splits the region into horizontal bands so that
+/// each band fits into [`maximum_request_bytes`], sends one `GetImage` request per band and
+/// stitches the rows of all replies together.
+///
+/// This first sends a `GetGeometry` request to learn the depth of `drawable`. An error is returned
+/// if a reply has a different depth or visual than the first one or contains too few rows.
+///
+/// [`maximum_request_bytes`]: crate::connection::RequestConnection::maximum_request_bytes
+#[allow(clippy::too_many_arguments)]
+pub fn get_image_chunked(
+    conn: &impl Connection,
+    drawable: Drawable,
+    x: i16,
+    y: i16,
+    width: u16,
+    height: u16,
+    plane_mask: u32,
+) -> Result<GetImageChunkedReply, ReplyError> {
+    let depth = get_geometry(conn, drawable)?.reply()?.depth;
+    let format = find_format(conn.setup(), depth)?;
+    let stride = compute_stride(
+        width,
+        format.bits_per_pixel.try_into()?,
+        format.scanline_pad.try_into()?,
+    );
+    let lines_per_band = lines_per_band(conn.maximum_request_bytes(), stride);
+
+    // Send all requests before waiting for the first reply
+    let cookies = bands(height, lines_per_band)
+        .into_iter()
+        .map(|(y_offset, lines)| {
+            let y = y + i16::try_from(y_offset).unwrap();
+            get_image(
+                conn,
+                ImageFormat::Z_PIXMAP,
+                drawable,
+                x,
+                y,
+                width,
+                lines,
+                plane_mask,
+            )
+            .map(|cookie| (cookie, lines))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut data = Vec::with_capacity(usize::from(height) * stride);
+    let mut visual = None;
+    for (cookie, lines) in cookies {
+        let reply = cookie.reply()?;
+        let size = usize::from(lines) * stride;
+        if reply.depth != depth
+            || *visual.get_or_insert(reply.visual) != reply.visual
+            || reply.data.len() < size
+        {
+            return Err(ParseError::InvalidValue.into());
+        }
+        data.extend_from_slice(&reply.data[..size]);
+    }
+    Ok(GetImageChunkedReply {
+        depth,
+        visual: visual.unwrap_or(NONE),
+        data,
+    })
+}
+
+/// Get a region of a drawable from the X11 server, even if it is larger than a single reply.
+///
+/// This fetches the region via [`get_image_chunked`] and wraps the result in an [`Image`]. The
+/// byte order of the image is the server's `image_byte_order`.
+pub fn capture_region(
+    conn: &impl Connection,
+    drawable: Drawable,
+    rect: Rectangle,
+) -> Result<Image<'static>, ReplyError> {
+    let reply = get_image_chunked(conn, drawable, rect.x, rect.y, rect.width, rect.height, !0)?;
+    let format = find_format(conn.setup(), reply.depth)?;
+    Ok(Image::new(
+        rect.width,
+        rect.height,
+        format.scanline_pad.try_into()?,
+        reply.depth,
+        format.bits_per_pixel.try_into()?,
+        conn.setup().image_byte_order.try_into()?,
+        Cow::Owned(reply.data),
+    )?)
+}
+
+/// Compute how many rows with the given stride fit into one `GetImage` reply.
+fn lines_per_band(max_bytes: usize, stride: usize) -> u16 {
+    let get_image_reply_header = 32;
+    let lines = max_bytes.saturating_sub(get_image_reply_header) / stride.max(1);
+    lines.try_into().unwrap_or(u16::max_value()).max(1)
+}
+
+/// Compute how many rows with the given stride fit into one `PutImage` request.
+///
+/// Returns `None` if not even a single row fits.
+fn put_lines_per_band(max_bytes: usize, stride: usize) -> Option<u16> {
+    // The request header, including the extra length field of BIG-REQUESTS
+    let put_image_header = 28;
+    let lines = max_bytes.saturating_sub(put_image_header) / stride.max(1);
+    match lines {
+        0 => None,
+        lines => Some(lines.try_into().unwrap_or(u16::max_value())),
+    }
+}
+
+/// Split `height` rows into bands of at most `lines_per_band` rows.
+///
+/// Returns the offset of the first row and the number of rows of each band.
+fn bands(height: u16, lines_per_band: u16) -> Vec<(u16, u16)> {
+    let mut result = Vec::new();
+    let mut y_offset = 0;
+    while y_offset < height {
+        let lines = lines_per_band.min(height - y_offset);
+        result.push((y_offset, lines));
+        y_offset += lines;
+    }
+    result
+}
+
+#[cfg(test)]
+mod test_capture {
+    use super::{bands, lines_per_band, put_lines_per_band};
+
+    #[test]
+    fn test_lines_per_band() {
+        assert_eq!(lines_per_band(32 + 4 * 100, 100), 4);
+        assert_eq!(lines_per_band(32 + 4 * 100 - 1, 100), 3);
+        // At least one line is always requested
+        assert_eq!(lines_per_band(32, 100), 1);
+        assert_eq!(lines_per_band(1 << 30, 0), u16::max_value());
+    }
+
+    #[test]
+    fn test_put_lines_per_band() {
+        assert_eq!(put_lines_per_band(28 + 4 * 100, 100), Some(4));
+        assert_eq!(put_lines_per_band(28 + 4 * 100 - 1, 100), Some(3));
+        assert_eq!(put_lines_per_band(28 + 100, 100), Some(1));
+        // A row that is too large cannot be sent at all
+        assert_eq!(put_lines_per_band(28 + 100 - 1, 100), None);
+        assert_eq!(put_lines_per_band(100, 400), None);
+    }
+
+    #[test]
+    fn test_bands_at_boundary() {
+        assert_eq!(bands(8, 4), [(0, 4), (4, 4)]);
+        assert_eq!(bands(9, 4), [(0, 4), (4, 4), (8, 1)]);
+        assert_eq!(bands(7, 4), [(0, 4), (4, 3)]);
+        assert_eq!(bands(3, 4), [(0, 3)]);
+        assert!(bands(0, 4).is_empty());
+    }
+}
+
+/// Get a single bit plane of a region of a drawable as a bitmap.
+///
+/// This sends a `GetImage` request in `ImageFormat::XY_PIXMAP` with a `plane_mask` that only
+/// selects `plane`, where plane 0 is the least significant bit of each pixel. Such a bitmap can
+/// e.g. be used as a cursor mask or as a stencil.
+///
+/// The server lays out the plane according to the `bitmap_format_*` fields of the [`Setup`]. The
+/// result is converted to a fixed layout instead: Each row is `(rect.width + 7) / 8` bytes long
+/// without further padding, and the leftmost pixel of each byte is its least significant bit.
+/// Thus, the pixel at `(x, y)` is `bitmap[y * stride + x / 8] >> (x % 8) & 1`.
+///
+/// An error is returned if `plane` is not smaller than the depth of `drawable`.
+pub fn get_plane(
+    conn: &impl Connection,
+    drawable: Drawable,
+    rect: Rectangle,
+    plane: u32,
+) -> Result<Vec<u8>, ReplyError> {
+    let plane_mask = 1u32.checked_shl(plane).ok_or(ParseError::InvalidValue)?;
+    let reply = get_image(
+        conn,
+        ImageFormat::XY_PIXMAP,
+        drawable,
+        rect.x,
+        rect.y,
+        rect.width,
+        rect.height,
+        plane_mask,
+    )?
+    .reply()?;
+    if plane >= u32::from(reply.depth) {
+        return Err(ParseError::InvalidValue.into());
+    }
+    let layout = BitmapLayout::from_setup(conn.setup())?;
+    Ok(layout.extract(&reply.data, rect.width, rect.height)?)
+}
+
+/// The layout of a single bit plane as described by the setup.
+#[derive(Debug, Clone, Copy)]
+struct BitmapLayout {
+    scanline_unit: usize,
+    scanline_pad: ScanlinePad,
+    bit_order: ImageOrder,
+    byte_order: ImageOrder,
+}
+
+impl BitmapLayout {
+    fn from_setup(setup: &Setup) -> Result<Self, ParseError> {
+        let scanline_unit = match setup.bitmap_format_scanline_unit {
+            unit @ 8 | unit @ 16 | unit @ 32 => usize::from(unit),
+            _ => return Err(ParseError::InvalidValue),
+        };
+        Ok(Self {
+            scanline_unit,
+            scanline_pad: setup.bitmap_format_scanline_pad.try_into()?,
+            bit_order: setup.bitmap_format_bit_order.try_into()?,
+            byte_order: setup.image_byte_order.try_into()?,
+        })
+    }
+
+    /// Compute the byte and the bit in that byte that contain pixel `x` of a row.
+    fn address(self, x: usize) -> (usize, usize) {
+        let unit_start = x - x % self.scanline_unit;
+        // Index of the bit in the scanline unit, interpreted as a number
+        let bit = match self.bit_order {
+            ImageOrder::LsbFirst => x % self.scanline_unit,
+            ImageOrder::MsbFirst => self.scanline_unit - 1 - x % self.scanline_unit,
+        };
+        let byte = match self.byte_order {
+            ImageOrder::LsbFirst => bit / 8,
+            ImageOrder::MsbFirst => self.scanline_unit / 8 - 1 - bit / 8,
+        };
+        (unit_start / 8 + byte, bit % 8)
+    }
+
+    /// Convert a bitmap in this layout to rows of unpadded bytes in `LsbFirst` bit order.
+    fn extract(self, data: &[u8], width: u16, height: u16) -> Result<Vec<u8>, ParseError> {
+        let in_stride = compute_stride(width, BitsPerPixel::B1, self.scanline_pad);
+        let out_stride = (usize::from(width) + 7) / 8;
+        if data.len() < in_stride * usize::from(height) {
+            return Err(ParseError::InsufficientData);
+        }
+        let mut result = vec![0; out_stride * usize::from(height)];
+        for (row, out) in data
+            .chunks(in_stride.max(1))
+            .zip(result.chunks_mut(out_stride.max(1)))
+        {
+            for x in 0..usize::from(width) {
+                let (byte, bit) = self.address(x);
+                if row[byte] & (1 << bit) != 0 {
+                    out[x / 8] |= 1 << (x % 8);
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod test_plane {
+    use super::{BitmapLayout, ImageOrder, ScanlinePad};
+
+    fn layout(scanline_unit: usize, bit_order: ImageOrder, byte_order: ImageOrder) -> BitmapLayout {
+        BitmapLayout {
+            scanline_unit,
+            scanline_pad: ScanlinePad::Pad32,
+            bit_order,
+            byte_order,
+        }
+    }
+
+    #[test]
+    fn lsb_first() {
+        let layout = layout(32, ImageOrder::LsbFirst, ImageOrder::LsbFirst);
+        let data = [0b0000_0101, 0, 0, 0, 0b0000_0010, 0, 0, 0];
+        assert_eq!(layout.extract(&data, 3, 2), Ok(vec![0b101, 0b010]));
+    }
+
+    #[test]
+    fn msb_first() {
+        let layout = layout(32, ImageOrder::MsbFirst, ImageOrder::MsbFirst);
+        let data = [0b1010_0000, 0, 0, 0x01];
+        // Pixels 0, 2 and 31 are set
+        assert_eq!(
+            layout.extract(&data, 32, 1),
+            Ok(vec![0b0000_0101, 0, 0, 0b1000_0000])
+        );
+    }
+
+    #[test]
+    fn mixed_order() {
+        // The leftmost pixel is the most significant bit of a little endian 16 bit unit
+        let layout = layout(16, ImageOrder::MsbFirst, ImageOrder::LsbFirst);
+        assert_eq!(layout.address(0), (1, 7));
+        assert_eq!(layout.address(8), (0, 7));
+        assert_eq!(layout.address(15), (0, 0));
+        assert_eq!(layout.address(16), (3, 7));
+        let data = [0b0000_0001, 0b1000_0000, 0, 0];
+        assert_eq!(
+            layout.extract(&data, 16, 1),
+            Ok(vec![0b0000_0001, 0b1000_0000])
+        );
+    }
+
+    #[test]
+    fn insufficient_data() {
+        let layout = layout(8, ImageOrder::MsbFirst, ImageOrder::MsbFirst);
+        assert!(layout.extract(&[0; 7], 1, 2).is_err());
+    }
+}
+
 fn compute_depth_1_address(x: usize, order: ImageOrder) -> (usize, usize) {
     let bit = match order {
         ImageOrder::MsbFirst => 7 - x % 8,
@@ -985,7 +1477,10 @@ fn compute_depth_1_address(x: usize, order: ImageOrder) -> (usize, usize) {
 
 #[cfg(test)]
 mod test_image {
-    use super::{BitsPerPixel, Image, ImageOrder, ParseError, ScanlinePad};
+    use super::{
+        BitsPerPixel, Format, Image, ImageOrder, ParseError, RgbaImageError, ScanlinePad, Setup,
+        VisualClass, Visualtype, XprotoImageOrder,
+    };
     use std::borrow::Cow;
 
     #[test]
@@ -1254,6 +1749,61 @@ mod test_image {
         assert_eq!(0xFEDC_BA98, image.get_pixel(1, 1));
     }
 
+    fn rgba_setup() -> Setup {
+        let format = |depth| Format {
+            depth,
+            bits_per_pixel: 32,
+            scanline_pad: 32,
+        };
+        Setup {
+            image_byte_order: XprotoImageOrder::LSB_FIRST,
+            pixmap_formats: vec![format(24), format(32)],
+            ..Default::default()
+        }
+    }
+
+    fn rgba_visual() -> Visualtype {
+        Visualtype {
+            class: VisualClass::TRUE_COLOR,
+            red_mask: 0xff_0000,
+            green_mask: 0x00_ff00,
+            blue_mask: 0x00_00ff,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn from_rgba_depth24() {
+        let rgba = [0x12, 0x34, 0x56, 0x00, 0xff, 0x80, 0x00, 0xff];
+        let image = Image::from_rgba(2, 1, &rgba, rgba_visual(), 24, &rgba_setup()).unwrap();
+        assert_eq!(image.byte_order(), ImageOrder::LsbFirst);
+        assert_eq!(image.get_pixel(0, 0), 0x0012_3456);
+        assert_eq!(image.get_pixel(1, 0), 0x00ff_8000);
+    }
+
+    #[test]
+    fn from_rgba_depth32() {
+        let rgba = [0xff, 0x80, 0x00, 0x80, 0x12, 0x34, 0x56, 0xff];
+        let image = Image::from_rgba(2, 1, &rgba, rgba_visual(), 32, &rgba_setup()).unwrap();
+        assert_eq!(image.get_pixel(0, 0), 0x8080_4000);
+        assert_eq!(image.get_pixel(1, 0), 0xff12_3456);
+    }
+
+    #[test]
+    fn from_rgba_errors() {
+        let (visual, setup) = (rgba_visual(), rgba_setup());
+        match Image::from_rgba(1, 1, &[0; 4], visual, 16, &setup) {
+            Err(RgbaImageError::UnsupportedDepth(16)) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+        match Image::from_rgba(2, 1, &[0; 4], visual, 24, &setup) {
+            Err(RgbaImageError::ParseError(ParseError::InsufficientData)) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+        let message = RgbaImageError::UnsupportedDepth(16).to_string();
+        assert!(message.contains("depth 16"), "{}", message);
+    }
+
     static DATA: [u8; 16] = [
         0xAB, 0x36, 0x18, 0xF8, 0x12, 0x34, 0x56, 0x78, 0x00, 0x00, 0x00, 0x00, 0xFE, 0xDC, 0xBA,
         0x98,
@@ -0,0 +1,395 @@
+//! Utilities for translating keycodes into keysyms.
+//!
+//! `KeyPress` and `KeyRelease` events only contain a keycode and the state of the modifiers. The
+//! symbol that a key produces is determined by the keyboard mapping (see `GetKeyboardMapping`)
+//! and the modifier mapping (see `GetModifierMapping`). [`KeyboardState`] caches both and
+//! implements the lookup rules that are described in the "Keyboards" section of the X11 protocol
+//! specification.
+//!
+//! This only implements the core protocol. The X keyboard extension has its own, much more
+//! complicated, rules.
+//!
+//! Usage example:
+//! ```no_run
+//! use x11rb::connection::Connection;
+//! use x11rb::keyboard::{keysym_to_char, KeyboardState};
+//! use x11rb::protocol::Event;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let (conn, _) = x11rb::connect(None)?;
+//!     let mut keyboard = KeyboardState::new(&conn)?;
+//!     loop {
+//!         let event = conn.wait_for_event()?;
+//!         if let Event::KeyPress(key) = &event {
+//!             let keysym = keyboard.keycode_to_keysym(key.detail, key.state);
+//!             println!("{:?}", keysym.and_then(keysym_to_char));
+//!         }
+//!         let _ = keyboard.handle_event(&conn, &event)?;
+//!     }
+//! }
+//! ```
+
+use crate::connection::Connection;
+use crate::errors::ReplyError;
+use crate::protocol::xproto::{
+    ConnectionExt as _, GetKeyboardMappingReply, GetModifierMappingReply, KeyButMask, Keycode,
+    Keysym, Mapping,
+};
+use crate::protocol::Event;
+use crate::NO_SYMBOL;
+
+const SHIFT_LOCK: Keysym = 0xffe6;
+const CAPS_LOCK: Keysym = 0xffe5;
+const MODE_SWITCH: Keysym = 0xff7e;
+const NUM_LOCK: Keysym = 0xff7f;
+
+/// How the `Lock` modifier is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LockMode {
+    Ignored,
+    CapsLock,
+    ShiftLock,
+}
+
+/// A cache of the keyboard mapping and the modifier mapping of the X11 server.
+#[derive(Debug, Clone)]
+pub struct KeyboardState {
+    min_keycode: Keycode,
+    keysyms_per_keycode: u8,
+    keysyms: Vec<Keysym>,
+    lock: LockMode,
+    mode_switch: u16,
+    num_lock: u16,
+}
+
+impl KeyboardState {
+    /// Query the current keyboard and modifier mapping from the X11 server.
+    pub fn new<C: Connection + ?Sized>(conn: &C) -> Result<Self, ReplyError> {
+        let setup = conn.setup();
+        let min_keycode = setup.min_keycode;
+        let count = setup.max_keycode - min_keycode + 1;
+        let keyboard = conn.get_keyboard_mapping(min_keycode, count)?;
+        let modifiers = conn.get_modifier_mapping()?;
+        Ok(Self::from_replies(
+            min_keycode,
+            keyboard.reply()?,
+            &modifiers.reply()?,
+        ))
+    }
+
+    /// Construct a new `KeyboardState` from the replies of the server.
+    ///
+    /// `keyboard` must describe the keycodes starting at `min_keycode`.
+    pub fn from_replies(
+        min_keycode: Keycode,
+        keyboard: GetKeyboardMappingReply,
+        modifiers: &GetModifierMappingReply,
+    ) -> Self {
+        let mut state = Self {
+            min_keycode,
+            keysyms_per_keycode: keyboard.keysyms_per_keycode,
+            keysyms: keyboard.keysyms,
+            lock: LockMode::Ignored,
+            mode_switch: 0,
+            num_lock: 0,
+        };
+        state.update_modifiers(modifiers);
+        state
+    }
+
+    fn update_modifiers(&mut self, modifiers: &GetModifierMappingReply) {
+        let per_modifier = usize::from(modifiers.keycodes_per_modifier()).max(1);
+        let (mut caps_lock, mut shift_lock) = (false, false);
+        let (mut mode_switch, mut num_lock) = (0, 0);
+        for (index, keycodes) in modifiers.keycodes.chunks(per_modifier).take(8).enumerate() {
+            let mask = 1 << index;
+            let keysyms = keycodes
+                .iter()
+                .filter(|&&keycode| keycode != 0)
+                .flat_map(|&keycode| self.keysyms(keycode).unwrap_or(&[]).iter().copied());
+            for keysym in keysyms {
+                match keysym {
+                    MODE_SWITCH => mode_switch |= mask,
+                    NUM_LOCK => num_lock |= mask,
+                    CAPS_LOCK if mask == u16::from(KeyButMask::LOCK) => caps_lock = true,
+                    SHIFT_LOCK if mask == u16::from(KeyButMask::LOCK) => shift_lock = true,
+                    _ => {}
+                }
+            }
+        }
+        self.mode_switch = mode_switch;
+        self.num_lock = num_lock;
+        self.lock = if caps_lock {
+            LockMode::CapsLock
+        } else if shift_lock {
+            LockMode::ShiftLock
+        } else {
+            LockMode::Ignored
+        };
+    }
+
+    /// Query the mappings again from the X11 server.
+    pub fn refresh<C: Connection + ?Sized>(&mut self, conn: &C) -> Result<(), ReplyError> {
+        *self = Self::new(conn)?;
+        Ok(())
+    }
+
+    /// Update the cached mappings if the given event is a `MappingNotify` for the keyboard or the
+    /// modifiers.
+    ///
+    /// Returns `true` if the mappings were refreshed.
+    pub fn handle_event<C: Connection + ?Sized>(
+        &mut self,
+        conn: &C,
+        event: &Event,
+    ) -> Result<bool, ReplyError> {
+        match event {
+            Event::MappingNotify(event) if event.request != Mapping::POINTER => {
+                self.refresh(conn)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Get all keysyms that are bound to the given keycode.
+    ///
+    /// Returns `None` if the keycode is not part of the keyboard mapping.
+    pub fn keysyms(&self, keycode: Keycode) -> Option<&[Keysym]> {
+        let per_keycode = usize::from(self.keysyms_per_keycode);
+        let start = usize::from(keycode.checked_sub(self.min_keycode)?) * per_keycode;
+        self.keysyms.get(start..start + per_keycode)
+    }
+
+    /// Translate a keycode into a keysym.
+    ///
+    /// `state` is the state of the modifiers, e.g. the `state` field of a `KeyPress` event. The
+    /// group is selected via the modifier that `Mode_switch` is bound to. Within the group, the
+    /// keysym is selected based on `Shift`, `Lock` and the modifier that `Num_Lock` is bound to.
+    ///
+    /// Returns `None` if no symbol is bound to the key.
+    pub fn keycode_to_keysym(&self, keycode: Keycode, state: impl Into<u16>) -> Option<Keysym> {
+        let state = state.into();
+        let keysyms = self.keysyms(keycode)?;
+        let len = keysyms
+            .iter()
+            .rposition(|&keysym| keysym != NO_SYMBOL)
+            .map_or(0, |pos| pos + 1);
+        let keysyms = &keysyms[..len];
+
+        // A list "K1" is treated as "K1 NoSymbol K1 NoSymbol", "K1 K2" as "K1 K2 K1 K2" and
+        // "K1 K2 K3" as "K1 K2 K3 NoSymbol"
+        let get = |index: usize| keysyms.get(index).copied().unwrap_or(NO_SYMBOL);
+        let group1 = (get(0), get(1));
+        let group2 = match len {
+            0 => return None,
+            1 | 2 => group1,
+            _ => (get(2), get(3)),
+        };
+        let group = if state & self.mode_switch != 0 && group2 != (NO_SYMBOL, NO_SYMBOL) {
+            group2
+        } else {
+            group1
+        };
+
+        // A group "K NoSymbol" is treated as "K K", except if K has lower and upper case forms
+        let (first, second) = match group {
+            (first, NO_SYMBOL) => match convert_case(first) {
+                Some((lower, upper)) => (lower, upper),
+                None => (first, first),
+            },
+            group => group,
+        };
+
+        let shift = state & u16::from(KeyButMask::SHIFT) != 0;
+        let lock = state & u16::from(KeyButMask::LOCK) != 0;
+        let caps_lock = lock && self.lock == LockMode::CapsLock;
+        let shift_lock = lock && self.lock == LockMode::ShiftLock;
+        let keysym = if state & self.num_lock != 0 && is_keypad(second) {
+            if shift || shift_lock {
+                first
+            } else {
+                second
+            }
+        } else if !shift && caps_lock {
+            to_upper(first)
+        } else if shift && caps_lock {
+            to_upper(second)
+        } else if shift || shift_lock {
+            second
+        } else {
+            first
+        };
+        Some(keysym).filter(|&keysym| keysym != NO_SYMBOL)
+    }
+}
+
+fn is_keypad(keysym: Keysym) -> bool {
+    (0xff80..=0xffbd).contains(&keysym) || (0x1100_0000..=0x1100_ffff).contains(&keysym)
+}
+
+fn to_upper(keysym: Keysym) -> Keysym {
+    convert_case(keysym).map_or(keysym, |(_, upper)| upper)
+}
+
+/// Get the lower and upper case form of a keysym.
+///
+/// Only Latin-1 and Unicode keysyms are supported. Returns `None` for keysyms without case forms.
+fn convert_case(keysym: Keysym) -> Option<(Keysym, Keysym)> {
+    match keysym {
+        0x41..=0x5a | 0xc0..=0xde if keysym != 0xd7 => Some((keysym + 0x20, keysym)),
+        0x61..=0x7a | 0xe0..=0xfe if keysym != 0xf7 => Some((keysym, keysym - 0x20)),
+        // ÿ and Ÿ; the latter is only available in Latin-9
+        0xff | 0x13be => Some((0xff, 0x13be)),
+        0x0100_0100..=0x0110_ffff => {
+            let c = std::char::from_u32(keysym - 0x0100_0000)?;
+            let lower = 0x0100_0000 + u32::from(single_char(c.to_lowercase())?);
+            let upper = 0x0100_0000 + u32::from(single_char(c.to_uppercase())?);
+            Some((lower, upper)).filter(|&(lower, upper)| lower != upper)
+        }
+        _ => None,
+    }
+}
+
+fn single_char(mut chars: impl Iterator<Item = char>) -> Option<char> {
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+/// Get the character that a keysym produces when it is typed.
+///
+/// This handles Latin-1 and Unicode keysyms, the number pad and the keysyms for control
+/// characters like `Return` and `BackSpace`. Other keysyms, e.g. function keys or legacy
+/// character sets, result in `None`.
+pub fn keysym_to_char(keysym: Keysym) -> Option<char> {
+    let code = match keysym {
+        0x20..=0x7e | 0xa0..=0xff => keysym,
+        0x0100_0100..=0x0110_ffff => keysym - 0x0100_0000,
+        // BackSpace, Tab, Linefeed, Return, Escape
+        0xff08 | 0xff09 | 0xff0a | 0xff0d | 0xff1b => keysym & 0x7f,
+        // Delete
+        0xffff => 0x7f,
+        // KP_Space, KP_Tab, KP_Enter and KP_Multiply to KP_9, KP_Equal
+        0xff80 | 0xff89 | 0xff8d | 0xffaa..=0xffb9 | 0xffbd => keysym - 0xff80,
+        _ => return None,
+    };
+    std::char::from_u32(code)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{keysym_to_char, KeyboardState};
+    use crate::protocol::xproto::{GetKeyboardMappingReply, GetModifierMappingReply, KeyButMask};
+
+    const SHIFT: u16 = 1;
+    const LOCK: u16 = 2;
+    const MOD2: u16 = 1 << 4;
+    const MOD5: u16 = 1 << 7;
+
+    // Keycodes 8 to 14 with four keysyms each
+    fn keyboard(lock_keysym: u32) -> KeyboardState {
+        let keysyms = [
+            // 8: a A ae AE
+            [0x61, 0x41, 0xe6, 0xc6],
+            // 9: 1 exclam
+            [0x31, 0x21, 0, 0],
+            // 10: b
+            [0x62, 0, 0, 0],
+            // 11: KP_End KP_1
+            [0xff9c, 0xffb1, 0, 0],
+            // 12: lock keysym
+            [lock_keysym, 0, 0, 0],
+            // 13: Mode_switch
+            [0xff7e, 0, 0, 0],
+            // 14: Num_Lock
+            [0xff7f, 0, 0, 0],
+        ]
+        .concat();
+        let keyboard = GetKeyboardMappingReply {
+            keysyms_per_keycode: 4,
+            sequence: 0,
+            keysyms,
+        };
+        let mut keycodes = vec![0; 8];
+        keycodes[1] = 12;
+        keycodes[4] = 14;
+        keycodes[7] = 13;
+        let modifiers = GetModifierMappingReply {
+            sequence: 0,
+            length: 2,
+            keycodes,
+        };
+        KeyboardState::from_replies(8, keyboard, &modifiers)
+    }
+
+    #[test]
+    fn test_keysyms() {
+        let keyboard = keyboard(0xffe5);
+        assert_eq!(keyboard.keysyms(7), None);
+        assert_eq!(keyboard.keysyms(9), Some(&[0x31, 0x21, 0, 0][..]));
+        assert_eq!(keyboard.keysyms(15), None);
+        assert_eq!(keyboard.keycode_to_keysym(15, 0u16), None);
+    }
+
+    #[test]
+    fn test_shift_and_groups() {
+        let keyboard = keyboard(0xffe5);
+        assert_eq!(keyboard.keycode_to_keysym(8, 0u16), Some(0x61));
+        assert_eq!(keyboard.keycode_to_keysym(8, KeyButMask::SHIFT), Some(0x41));
+        assert_eq!(keyboard.keycode_to_keysym(8, MOD5), Some(0xe6));
+        assert_eq!(keyboard.keycode_to_keysym(8, MOD5 | SHIFT), Some(0xc6));
+        // A single group is used for both groups
+        assert_eq!(keyboard.keycode_to_keysym(9, MOD5 | SHIFT), Some(0x21));
+        // A single keysym with case forms is treated as its lower and upper case form
+        assert_eq!(keyboard.keycode_to_keysym(10, 0u16), Some(0x62));
+        assert_eq!(keyboard.keycode_to_keysym(10, SHIFT), Some(0x42));
+        assert_eq!(keyboard.keycode_to_keysym(10, MOD5), Some(0x62));
+    }
+
+    #[test]
+    fn test_caps_lock() {
+        let keyboard = keyboard(0xffe5);
+        assert_eq!(keyboard.keycode_to_keysym(8, LOCK), Some(0x41));
+        assert_eq!(keyboard.keycode_to_keysym(8, LOCK | SHIFT), Some(0x41));
+        assert_eq!(keyboard.keycode_to_keysym(8, LOCK | MOD5), Some(0xc6));
+        // Caps Lock only affects letters
+        assert_eq!(keyboard.keycode_to_keysym(9, LOCK), Some(0x31));
+        assert_eq!(keyboard.keycode_to_keysym(9, LOCK | SHIFT), Some(0x21));
+    }
+
+    #[test]
+    fn test_shift_lock() {
+        let keyboard = keyboard(0xffe6);
+        assert_eq!(keyboard.keycode_to_keysym(8, LOCK), Some(0x41));
+        assert_eq!(keyboard.keycode_to_keysym(9, LOCK), Some(0x21));
+        assert_eq!(keyboard.keycode_to_keysym(9, LOCK | SHIFT), Some(0x21));
+    }
+
+    #[test]
+    fn test_ignored_lock() {
+        let keyboard = keyboard(0);
+        assert_eq!(keyboard.keycode_to_keysym(8, LOCK), Some(0x61));
+    }
+
+    #[test]
+    fn test_num_lock() {
+        let keyboard = keyboard(0xffe5);
+        assert_eq!(keyboard.keycode_to_keysym(11, 0u16), Some(0xff9c));
+        assert_eq!(keyboard.keycode_to_keysym(11, SHIFT), Some(0xffb1));
+        assert_eq!(keyboard.keycode_to_keysym(11, MOD2), Some(0xffb1));
+        assert_eq!(keyboard.keycode_to_keysym(11, MOD2 | SHIFT), Some(0xff9c));
+    }
+
+    #[test]
+    fn test_keysym_to_char() {
+        assert_eq!(keysym_to_char(0x61), Some('a'));
+        assert_eq!(keysym_to_char(0xe6), Some('æ'));
+        assert_eq!(keysym_to_char(0x0100_20ac), Some('€'));
+        assert_eq!(keysym_to_char(0xff0d), Some('\r'));
+        assert_eq!(keysym_to_char(0xffb1), Some('1'));
+        assert_eq!(keysym_to_char(0xffbe), None);
+        assert_eq!(keysym_to_char(0), None);
+    }
+}
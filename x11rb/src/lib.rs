@@ -104,6 +104,8 @@
 //! * `resource_manager`: Enable the code in [crate::resource_manager] for loading and querying the
 //!   X11 resource database.
 //! * `image`: Enable the code in [crate::image] for working with pixel image data.
+//! * `image-crate`: Enable [crate::image::put_image_from_rgba] for uploading an `RgbaImage` from
+//!   the [`image`](https://crates.io/crates/image) crate. This implies the `image` feature.
 //! * `dl-libxcb`: Enabling this feature will prevent from libxcb being linked to the
 //!   resulting executable. Instead libxcb will be dynamically loaded at runtime.
 //!   This feature adds the [`crate::xcb_ffi::load_libxcb`] function, that allows to load
@@ -151,17 +153,45 @@ pub mod utils;
 pub mod xcb_ffi;
 #[macro_use]
 pub mod x11_utils;
+#[cfg(feature = "async")]
+pub mod async_connection;
+pub mod capabilities;
+pub mod colormap;
 pub mod connection;
 pub mod cookie;
 #[cfg(feature = "cursor")]
 pub mod cursor;
+#[cfg(feature = "xfixes")]
+pub mod cursor_image;
+pub mod drag;
+#[cfg(feature = "dri3")]
+pub mod dri3_device;
 pub mod errors;
 pub mod extension_manager;
+#[cfg(feature = "xtest")]
+pub mod fake_input;
+pub mod focus;
+pub mod font;
 #[cfg(feature = "image")]
 pub mod image;
+pub mod keyboard;
+#[cfg(feature = "randr")]
+pub mod monitors;
+mod outstanding_requests;
 pub mod properties;
+#[cfg(feature = "render")]
+pub mod render;
 pub mod rust_connection;
+pub mod screen;
+pub mod selection;
+#[cfg(all(feature = "shm", feature = "allow-unsafe-code", unix))]
+pub mod shm_segment;
+pub mod trust;
+pub mod window;
+pub mod wm;
 pub mod wrapper;
+#[cfg(feature = "xkb")]
+pub mod xkb_group;
 #[rustfmt::skip]
 #[allow(missing_docs)]
 pub mod protocol;
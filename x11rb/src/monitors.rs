@@ -0,0 +1,603 @@
+//! Querying the geometry of the monitors that show the screen.
+//!
+//! [`get_monitors`] uses the best mechanism that the X11 server supports:
+//!
+//! 1. The `GetMonitors` request of RandR 1.5.
+//! 2. The active CRTCs of RandR 1.2. Each CRTC that shows a mode becomes a monitor that is named
+//!    after its connected output. With RandR 1.3, the primary output is detected via
+//!    `GetOutputPrimary`.
+//! 3. The screens of the Xinerama extension, if the `xinerama` feature is enabled.
+//! 4. The whole root window as a single monitor.
+//!
+//! [`get_active_outputs`] instead lists the individual RandR outputs that show a part of the
+//! screen, and fails if RandR is not available.
+//!
+//! To react to monitors being plugged in or removed, [`select_monitor_events`] subscribes to the
+//! relevant RandR events and [`MonitorChangeDebouncer`] turns a burst of these events into a
+//! single [`MonitorsChanged`] notification, after which [`get_monitors`] should be called again.
+//!
+//! The code in this module is only available when the `randr` feature of the library is enabled.
+//!
+//! Usage example:
+//! ```no_run
+//! use x11rb::connection::Connection;
+//! use x11rb::monitors::get_monitors;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let (conn, screen_num) = x11rb::connect(None)?;
+//!     let root = conn.setup().roots[screen_num].root;
+//!     for monitor in get_monitors(&conn, root)? {
+//!         println!(
+//!             "{}: {}x{}+{}+{}",
+//!             monitor.name, monitor.width, monitor.height, monitor.x, monitor.y
+//!         );
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::time::{Duration, Instant};
+
+use crate::connection::RequestConnection;
+use crate::cookie::VoidCookie;
+use crate::errors::{ConnectionError, ReplyError};
+use crate::protocol::randr::{
+    self, Crtc, GetCrtcInfoReply, GetOutputInfoReply, NotifyMask, Output,
+};
+use crate::protocol::xproto::{self, Window};
+use crate::protocol::Event;
+
+/// The default delay of [`MonitorChangeDebouncer::new`].
+pub const DEFAULT_MONITOR_CHANGE_DELAY: Duration = Duration::from_millis(100);
+
+/// A rectangular area of the root window that is shown on a monitor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Monitor {
+    /// The name of the monitor, e.g. the name of its output like `HDMI-1`.
+    pub name: String,
+    /// Whether this is the primary monitor.
+    pub primary: bool,
+    /// The x coordinate of the monitor's top left corner in the root window.
+    pub x: i16,
+    /// The y coordinate of the monitor's top left corner in the root window.
+    pub y: i16,
+    /// The width of the monitor in pixels.
+    pub width: u16,
+    /// The height of the monitor in pixels.
+    pub height: u16,
+}
+
+/// Get the monitors that show the screen with the given root window.
+///
+/// See the [module documentation](self) for the mechanisms that are tried. A mechanism that
+/// reports no monitors is skipped, so the result is never empty.
+pub fn get_monitors<C: RequestConnection + ?Sized>(
+    conn: &C,
+    root: Window,
+) -> Result<Vec<Monitor>, ReplyError> {
+    let version = match randr::query_version(conn, 1, 5) {
+        Ok(cookie) => {
+            let reply = cookie.reply()?;
+            Some((reply.major_version, reply.minor_version))
+        }
+        Err(ConnectionError::UnsupportedExtension) => None,
+        Err(err) => return Err(err.into()),
+    };
+    if let Some(version) = version {
+        if version >= (1, 5) {
+            let monitors = randr_monitors(conn, root)?;
+            if !monitors.is_empty() {
+                return Ok(monitors);
+            }
+        }
+        if version >= (1, 2) {
+            let monitors = crtc_monitors(conn, root, version >= (1, 3))?;
+            if !monitors.is_empty() {
+                return Ok(monitors);
+            }
+        }
+    }
+    #[cfg(feature = "xinerama")]
+    {
+        let monitors = xinerama_monitors(conn)?;
+        if !monitors.is_empty() {
+            return Ok(monitors);
+        }
+    }
+    root_monitor(conn, root)
+}
+
+/// Get the monitors via RandR 1.5.
+fn randr_monitors<C: RequestConnection + ?Sized>(
+    conn: &C,
+    root: Window,
+) -> Result<Vec<Monitor>, ReplyError> {
+    let monitors = randr::get_monitors(conn, root, true)?.reply()?.monitors;
+    let names = monitors
+        .iter()
+        .map(|monitor| xproto::get_atom_name(conn, monitor.name))
+        .collect::<Result<Vec<_>, _>>()?;
+    monitors
+        .into_iter()
+        .zip(names)
+        .map(|(monitor, name)| {
+            Ok(Monitor {
+                name: String::from_utf8_lossy(&name.reply()?.name).into_owned(),
+                primary: monitor.primary,
+                x: monitor.x,
+                y: monitor.y,
+                width: monitor.width,
+                height: monitor.height,
+            })
+        })
+        .collect()
+}
+
+/// Get the monitors from the active CRTCs via RandR 1.2.
+///
+/// `GetScreenResourcesCurrent` and `GetOutputPrimary` are only used if `randr_1_3` is true.
+fn crtc_monitors<C: RequestConnection + ?Sized>(
+    conn: &C,
+    root: Window,
+    randr_1_3: bool,
+) -> Result<Vec<Monitor>, ReplyError> {
+    let (crtcs, timestamp, primary) = if randr_1_3 {
+        let resources = randr::get_screen_resources_current(conn, root)?;
+        let primary = randr::get_output_primary(conn, root)?;
+        let resources = resources.reply()?;
+        let primary = primary.reply()?.output;
+        (resources.crtcs, resources.config_timestamp, primary)
+    } else {
+        let resources = randr::get_screen_resources(conn, root)?.reply()?;
+        (resources.crtcs, resources.config_timestamp, crate::NONE)
+    };
+
+    let crtcs = crtcs
+        .into_iter()
+        .map(|crtc| randr::get_crtc_info(conn, crtc, timestamp))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .map(|cookie| cookie.reply())
+        .collect::<Result<Vec<_>, _>>()?;
+    let crtcs = crtcs.into_iter().filter(is_active).collect::<Vec<_>>();
+
+    // Send all GetOutputInfo requests before waiting for the first reply
+    let outputs = crtcs
+        .iter()
+        .map(|crtc| {
+            crtc.outputs
+                .iter()
+                .map(|&output| randr::get_output_info(conn, output, timestamp))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    crtcs
+        .iter()
+        .zip(outputs)
+        .map(|(crtc, outputs)| {
+            let outputs = outputs
+                .into_iter()
+                .map(|cookie| cookie.reply())
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(crtc_monitor(crtc, &outputs, primary))
+        })
+        .collect()
+}
+
+/// Check whether a CRTC currently shows something.
+fn is_active(crtc: &GetCrtcInfoReply) -> bool {
+    crtc.mode != crate::NONE && !crtc.outputs.is_empty() && crtc.width > 0 && crtc.height > 0
+}
+
+/// Turn an active CRTC into a monitor.
+///
+/// `outputs` contains the information about the outputs of the CRTC in the same order as
+/// `crtc.outputs`.
+fn crtc_monitor(
+    crtc: &GetCrtcInfoReply,
+    outputs: &[GetOutputInfoReply],
+    primary: Output,
+) -> Monitor {
+    let name = outputs
+        .iter()
+        .find(|output| output.connection == randr::Connection::CONNECTED)
+        .or_else(|| outputs.first())
+        .map(|output| String::from_utf8_lossy(&output.name).into_owned())
+        .unwrap_or_default();
+    Monitor {
+        name,
+        primary: primary != crate::NONE && crtc.outputs.contains(&primary),
+        x: crtc.x,
+        y: crtc.y,
+        width: crtc.width,
+        height: crtc.height,
+    }
+}
+
+/// An output of RandR that currently shows a part of the screen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveOutput {
+    /// The name of the output, e.g. `HDMI-1`.
+    pub name: String,
+    /// The output itself.
+    pub output: Output,
+    /// The CRTC that drives the output.
+    pub crtc: Crtc,
+    /// The x coordinate of the output's top left corner in the root window.
+    pub x: i16,
+    /// The y coordinate of the output's top left corner in the root window.
+    pub y: i16,
+    /// The width of the output in pixels.
+    pub width: u16,
+    /// The height of the output in pixels.
+    pub height: u16,
+    /// Whether a monitor is known to be connected.
+    ///
+    /// This is `false` if the server cannot tell whether something is connected to the output.
+    pub connected: bool,
+}
+
+/// Get the outputs that show a part of the screen with the given root window.
+///
+/// This uses `GetScreenResourcesCurrent`, `GetOutputInfo` and `GetCrtcInfo` of RandR 1.3.
+/// Outputs that are disconnected or whose CRTC does not show a mode are skipped. Unlike
+/// [`get_monitors`], this does not fall back to other mechanisms:
+/// `ConnectionError::UnsupportedExtension` is returned if the server does not support RandR
+/// 1.3.
+pub fn get_active_outputs<C: RequestConnection + ?Sized>(
+    conn: &C,
+    root: Window,
+) -> Result<Vec<ActiveOutput>, ReplyError> {
+    let version = randr::query_version(conn, 1, 3)?.reply()?;
+    if (version.major_version, version.minor_version) < (1, 3) {
+        return Err(ConnectionError::UnsupportedExtension.into());
+    }
+    let resources = randr::get_screen_resources_current(conn, root)?.reply()?;
+    let timestamp = resources.config_timestamp;
+    let infos = resources
+        .outputs
+        .iter()
+        .map(|&output| randr::get_output_info(conn, output, timestamp))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .map(|cookie| cookie.reply())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Send all GetCrtcInfo requests before waiting for the first reply
+    let candidates = resources
+        .outputs
+        .into_iter()
+        .zip(infos)
+        .filter(|(_, info)| {
+            info.connection != randr::Connection::DISCONNECTED && info.crtc != crate::NONE
+        })
+        .map(|(output, info)| {
+            let crtc = randr::get_crtc_info(conn, info.crtc, timestamp)?;
+            Ok((output, info, crtc))
+        })
+        .collect::<Result<Vec<_>, ConnectionError>>()?;
+    let mut result = Vec::with_capacity(candidates.len());
+    for (output, info, crtc) in candidates {
+        result.extend(active_output(output, &info, &crtc.reply()?));
+    }
+    Ok(result)
+}
+
+/// Describe an output that is driven by the given CRTC, unless it does not show anything.
+fn active_output(
+    output: Output,
+    info: &GetOutputInfoReply,
+    crtc: &GetCrtcInfoReply,
+) -> Option<ActiveOutput> {
+    if info.connection == randr::Connection::DISCONNECTED || crtc.mode == crate::NONE {
+        return None;
+    }
+    Some(ActiveOutput {
+        name: String::from_utf8_lossy(&info.name).into_owned(),
+        output,
+        crtc: info.crtc,
+        x: crtc.x,
+        y: crtc.y,
+        width: crtc.width,
+        height: crtc.height,
+        connected: info.connection == randr::Connection::CONNECTED,
+    })
+}
+
+/// Get the monitors via Xinerama.
+#[cfg(feature = "xinerama")]
+fn xinerama_monitors<C: RequestConnection + ?Sized>(conn: &C) -> Result<Vec<Monitor>, ReplyError> {
+    use crate::protocol::xinerama;
+
+    let active = match xinerama::is_active(conn) {
+        Ok(cookie) => cookie.reply()?.state != 0,
+        Err(ConnectionError::UnsupportedExtension) => false,
+        Err(err) => return Err(err.into()),
+    };
+    if !active {
+        return Ok(Vec::new());
+    }
+    let screens = xinerama::query_screens(conn)?.reply()?.screen_info;
+    Ok(screens
+        .into_iter()
+        .enumerate()
+        .map(|(index, screen)| Monitor {
+            name: format!("XINERAMA-{}", index),
+            // Xinerama lists the primary screen first
+            primary: index == 0,
+            x: screen.x_org,
+            y: screen.y_org,
+            width: screen.width,
+            height: screen.height,
+        })
+        .collect())
+}
+
+/// Use the whole root window as a single monitor.
+fn root_monitor<C: RequestConnection + ?Sized>(
+    conn: &C,
+    root: Window,
+) -> Result<Vec<Monitor>, ReplyError> {
+    let geometry = xproto::get_geometry(conn, root)?.reply()?;
+    Ok(vec![Monitor {
+        name: "default".to_string(),
+        primary: true,
+        x: 0,
+        y: 0,
+        width: geometry.width,
+        height: geometry.height,
+    }])
+}
+
+/// Select the RandR events that indicate a change of the monitors on the given root window.
+///
+/// This selects `ScreenChangeNotify`, `CrtcChangeNotify` and `OutputChangeNotify`. The events
+/// can be passed to a [`MonitorChangeDebouncer`].
+pub fn select_monitor_events<C: RequestConnection + ?Sized>(
+    conn: &C,
+    root: Window,
+) -> Result<VoidCookie<'_, C>, ConnectionError> {
+    let mask = NotifyMask::SCREEN_CHANGE | NotifyMask::CRTC_CHANGE | NotifyMask::OUTPUT_CHANGE;
+    randr::select_input(conn, root, mask)
+}
+
+/// Check whether an event indicates that the monitors might have changed.
+pub fn is_monitor_change(event: &Event) -> bool {
+    match event {
+        Event::RandrScreenChangeNotify(_) => true,
+        Event::RandrNotify(event) => {
+            event.sub_code == randr::Notify::CRTC_CHANGE
+                || event.sub_code == randr::Notify::OUTPUT_CHANGE
+        }
+        _ => false,
+    }
+}
+
+/// A notification that the monitors changed and [`get_monitors`] should be called again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonitorsChanged;
+
+/// Merge a burst of RandR change events into a single [`MonitorsChanged`] notification.
+///
+/// Plugging in a monitor usually causes several events, e.g. one `ScreenChangeNotify` and a
+/// `CrtcChangeNotify` and `OutputChangeNotify` for each affected CRTC and output. Instead of
+/// querying the monitors for each of these events, the notification is only produced once no
+/// further event arrived for the configured delay.
+///
+/// ```no_run
+/// use x11rb::connection::Connection;
+/// use x11rb::monitors::{get_monitors, select_monitor_events, MonitorChangeDebouncer};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let (conn, screen_num) = x11rb::connect(None)?;
+///     let root = conn.setup().roots[screen_num].root;
+///     select_monitor_events(&conn, root)?;
+///     let mut debouncer = MonitorChangeDebouncer::default();
+///     loop {
+///         let event = match debouncer.timeout() {
+///             Some(timeout) => conn.wait_for_event_with_timeout(timeout)?,
+///             None => Some(conn.wait_for_event()?),
+///         };
+///         if let Some(event) = event {
+///             debouncer.handle_event(&event);
+///         }
+///         if debouncer.poll().is_some() {
+///             println!("{:?}", get_monitors(&conn, root)?);
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct MonitorChangeDebouncer {
+    delay: Duration,
+    last_change: Option<Instant>,
+}
+
+impl Default for MonitorChangeDebouncer {
+    fn default() -> Self {
+        Self::new(DEFAULT_MONITOR_CHANGE_DELAY)
+    }
+}
+
+impl MonitorChangeDebouncer {
+    /// Create a new debouncer that waits for the given delay after the last change event.
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            last_change: None,
+        }
+    }
+
+    /// Get the delay after the last change event.
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// Check whether a notification is pending, i.e. [`poll`](Self::poll) will eventually
+    /// return it.
+    pub fn is_pending(&self) -> bool {
+        self.last_change.is_some()
+    }
+
+    /// Handle an event.
+    ///
+    /// Returns whether the event indicates a change of the monitors. Other events are ignored.
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        self.handle_event_at(event, Instant::now())
+    }
+
+    /// Handle an event that was received at the given time.
+    pub fn handle_event_at(&mut self, event: &Event, now: Instant) -> bool {
+        let changed = is_monitor_change(event);
+        if changed {
+            self.last_change = Some(now);
+        }
+        changed
+    }
+
+    /// Get the time until the pending notification is due.
+    ///
+    /// Returns `None` if no notification is pending. This can be used as the timeout for
+    /// [`Connection::wait_for_event_with_timeout`](crate::connection::Connection::wait_for_event_with_timeout).
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout_at(Instant::now())
+    }
+
+    /// Get the time from `now` until the pending notification is due.
+    pub fn timeout_at(&self, now: Instant) -> Option<Duration> {
+        self.last_change
+            .map(|last| (last + self.delay).saturating_duration_since(now))
+    }
+
+    /// Get the notification if it is due.
+    pub fn poll(&mut self) -> Option<MonitorsChanged> {
+        self.poll_at(Instant::now())
+    }
+
+    /// Get the notification if it is due at the given time.
+    pub fn poll_at(&mut self, now: Instant) -> Option<MonitorsChanged> {
+        match self.timeout_at(now) {
+            Some(timeout) if timeout == Duration::from_secs(0) => {
+                self.last_change = None;
+                Some(MonitorsChanged)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{crtc_monitor, is_active, Monitor};
+    use crate::protocol::randr::{Connection, GetCrtcInfoReply, GetOutputInfoReply};
+
+    fn crtc(mode: u32, outputs: Vec<u32>) -> GetCrtcInfoReply {
+        GetCrtcInfoReply {
+            x: 1920,
+            y: 0,
+            width: 1280,
+            height: 1024,
+            mode,
+            outputs,
+            ..Default::default()
+        }
+    }
+
+    fn output(name: &str, connection: Connection) -> GetOutputInfoReply {
+        GetOutputInfoReply {
+            name: name.as_bytes().to_vec(),
+            connection,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn active_crtcs() {
+        assert!(is_active(&crtc(42, vec![1])));
+        assert!(!is_active(&crtc(0, vec![1])));
+        assert!(!is_active(&crtc(42, vec![])));
+    }
+
+    #[test]
+    fn monitor_from_crtc() {
+        let outputs = [
+            output("VGA-1", Connection::DISCONNECTED),
+            output("HDMI-1", Connection::CONNECTED),
+        ];
+        let expected = Monitor {
+            name: "HDMI-1".to_string(),
+            primary: true,
+            x: 1920,
+            y: 0,
+            width: 1280,
+            height: 1024,
+        };
+        assert_eq!(crtc_monitor(&crtc(42, vec![5, 6]), &outputs, 6), expected);
+
+        let monitor = crtc_monitor(&crtc(42, vec![5, 6]), &outputs, 7);
+        assert!(!monitor.primary);
+        let monitor = crtc_monitor(&crtc(42, vec![5, 6]), &outputs, 0);
+        assert!(!monitor.primary);
+    }
+
+    #[test]
+    fn active_outputs() {
+        use super::{active_output, ActiveOutput};
+
+        let mut info = output("DP-2", Connection::CONNECTED);
+        info.crtc = 3;
+        let expected = ActiveOutput {
+            name: "DP-2".to_string(),
+            output: 7,
+            crtc: 3,
+            x: 1920,
+            y: 0,
+            width: 1280,
+            height: 1024,
+            connected: true,
+        };
+        assert_eq!(active_output(7, &info, &crtc(42, vec![7])), Some(expected));
+
+        // A CRTC without a mode does not show anything
+        assert_eq!(active_output(7, &info, &crtc(0, vec![7])), None);
+
+        info.connection = Connection::UNKNOWN;
+        let output = active_output(7, &info, &crtc(42, vec![7])).unwrap();
+        assert!(!output.connected);
+
+        info.connection = Connection::DISCONNECTED;
+        assert_eq!(active_output(7, &info, &crtc(42, vec![7])), None);
+    }
+
+    #[test]
+    fn debounce_changes() {
+        use super::{MonitorChangeDebouncer, MonitorsChanged};
+        use crate::protocol::randr::ScreenChangeNotifyEvent;
+        use crate::protocol::xproto::ExposeEvent;
+        use crate::protocol::Event;
+        use std::time::{Duration, Instant};
+
+        let change = Event::RandrScreenChangeNotify(ScreenChangeNotifyEvent::default());
+        let other = Event::Expose(ExposeEvent::default());
+        let delay = Duration::from_millis(100);
+        let start = Instant::now();
+        let mut debouncer = MonitorChangeDebouncer::new(delay);
+        assert_eq!(debouncer.timeout_at(start), None);
+
+        assert!(!debouncer.handle_event_at(&other, start));
+        assert!(!debouncer.is_pending());
+
+        // A burst of changes only produces one notification after the last one
+        assert!(debouncer.handle_event_at(&change, start));
+        let later = start + Duration::from_millis(60);
+        assert!(debouncer.handle_event_at(&change, later));
+        assert_eq!(debouncer.poll_at(start + delay), None);
+        assert_eq!(
+            debouncer.timeout_at(start + delay),
+            Some(Duration::from_millis(60))
+        );
+        assert_eq!(debouncer.poll_at(later + delay), Some(MonitorsChanged));
+        assert_eq!(debouncer.poll_at(later + delay), None);
+        assert!(!debouncer.is_pending());
+    }
+}
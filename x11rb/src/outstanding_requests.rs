@@ -0,0 +1,55 @@
+//! Tracking of requests whose reply was not yet handled.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use x11rb_protocol::SequenceNumber;
+
+/// The set of requests that expect a reply and whose reply or error was not yet consumed.
+///
+/// A request is added when it is sent and removed when its reply is waited for or discarded or
+/// when its cookie is forgotten.
+#[derive(Debug, Default)]
+pub(crate) struct OutstandingRequests(Mutex<HashSet<SequenceNumber>>);
+
+impl OutstandingRequests {
+    /// Record that a request expecting a reply was sent.
+    pub(crate) fn sent(&self, sequence: SequenceNumber) {
+        let _ = self.0.lock().unwrap().insert(sequence);
+    }
+
+    /// Record that the reply or error to a request was handled.
+    ///
+    /// Unknown sequence numbers are silently ignored.
+    pub(crate) fn resolved(&self, sequence: SequenceNumber) {
+        let _ = self.0.lock().unwrap().remove(&sequence);
+    }
+
+    /// Get the number of outstanding requests.
+    pub(crate) fn count(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::OutstandingRequests;
+
+    #[test]
+    fn counting() {
+        let requests = OutstandingRequests::default();
+        assert_eq!(requests.count(), 0);
+        requests.sent(1);
+        requests.sent(3);
+        assert_eq!(requests.count(), 2);
+        requests.resolved(1);
+        assert_eq!(requests.count(), 1);
+
+        // Resolving twice or resolving unknown requests does not break anything
+        requests.resolved(1);
+        requests.resolved(2);
+        assert_eq!(requests.count(), 1);
+        requests.resolved(3);
+        assert_eq!(requests.count(), 0);
+    }
+}
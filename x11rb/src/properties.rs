@@ -1,11 +1,16 @@
 //! Utility functions for working with X11 properties
 
+use std::collections::HashSet;
 use std::convert::TryInto;
 
 use crate::connection::RequestConnection;
 use crate::cookie::{Cookie, VoidCookie};
 use crate::errors::{ConnectionError, ParseError, ReplyError};
-use crate::protocol::xproto::{self, Atom, AtomEnum, GetPropertyReply, Window};
+use crate::protocol::xproto::{
+    self, Atom, AtomEnum, GetPropertyReply, Property, PropertyNotifyEvent, Timestamp, Window,
+};
+use crate::protocol::Event;
+use crate::wrapper::ConnectionExt as _;
 use crate::x11_utils::{Serialize, TryParse};
 
 macro_rules! property_cookie {
@@ -655,6 +660,340 @@ impl Serialize for WmHints {
     }
 }
 
+// _MOTIF_WM_HINTS
+
+const MOTIF_WM_HINTS: &[u8] = b"_MOTIF_WM_HINTS";
+
+// The `_MOTIF_WM_HINTS` property consists of the fields flags, functions, decorations,
+// input_mode, and status.
+const NUM_MOTIF_WM_HINTS_ELEMENTS: u32 = 5;
+
+// Flag indicating that the decorations field is valid.
+const MOTIF_HINT_DECORATIONS: u32 = 1 << 1;
+// Decorations value requesting all decorations.
+const MOTIF_DECORATIONS_ALL: u32 = 1;
+
+/// Build the value of a `_MOTIF_WM_HINTS` property that only specifies the decorations.
+fn motif_decoration_hints(enabled: bool) -> Vec<u8> {
+    let decorations = if enabled { MOTIF_DECORATIONS_ALL } else { 0 };
+    [MOTIF_HINT_DECORATIONS, 0, decorations, 0, 0]
+        .iter()
+        .flat_map(|value| value.serialize())
+        .collect()
+}
+
+/// Enable or disable the window manager's decorations of a window, e.g. its title bar.
+///
+/// This sets the `_MOTIF_WM_HINTS` property of the given window. This property is not part of any
+/// standard, but is the de-facto way to request a borderless window and is honoured by most
+/// window managers. Other fields of an existing `_MOTIF_WM_HINTS` property are replaced.
+///
+/// The atom for `_MOTIF_WM_HINTS` is interned first, which requires a round trip to the X11
+/// server.
+pub fn set_decorations<C: RequestConnection + ?Sized>(
+    conn: &C,
+    window: Window,
+    enabled: bool,
+) -> Result<VoidCookie<'_, C>, ReplyError> {
+    let atom = xproto::intern_atom(conn, false, MOTIF_WM_HINTS)?
+        .reply()?
+        .atom;
+    Ok(xproto::change_property(
+        conn,
+        xproto::PropMode::REPLACE,
+        window,
+        atom,
+        atom,
+        32,
+        NUM_MOTIF_WM_HINTS_ELEMENTS,
+        &motif_decoration_hints(enabled),
+    )?)
+}
+
+// WM_TRANSIENT_FOR
+
+/// Mark a window as a transient window, e.g. a dialog, of another window.
+///
+/// This sets the `WM_TRANSIENT_FOR` property of `window` to `parent`. Window managers use this
+/// e.g. to place the dialog over its parent and to keep it stacked above the parent.
+pub fn set_transient_for<C: RequestConnection + ?Sized>(
+    conn: &C,
+    window: Window,
+    parent: Window,
+) -> Result<VoidCookie<'_, C>, ConnectionError> {
+    xproto::change_property(
+        conn,
+        xproto::PropMode::REPLACE,
+        window,
+        AtomEnum::WM_TRANSIENT_FOR,
+        AtomEnum::WINDOW,
+        32,
+        1,
+        &parent.serialize(),
+    )
+}
+
+// _NET_WM_STATE_MODAL
+
+const NET_WM_STATE: &[u8] = b"_NET_WM_STATE";
+const NET_WM_STATE_MODAL: &[u8] = b"_NET_WM_STATE_MODAL";
+
+/// Mark a window as a modal dialog for its transient parent.
+///
+/// This adds `_NET_WM_STATE_MODAL` to the `_NET_WM_STATE` property of the given window, so it
+/// has to be done before the window is mapped. Afterwards, the window manager owns this property
+/// and changes have to be requested with a `ClientMessage` instead. The parent window should be
+/// set with [`set_transient_for`].
+///
+/// The atoms are interned and the current value of the property is read first, which requires
+/// two round trips to the X11 server. The property is then replaced with its previous states plus
+/// `_NET_WM_STATE_MODAL`, which is not added a second time if it is already present.
+pub fn set_modal<C: RequestConnection + ?Sized>(
+    conn: &C,
+    window: Window,
+) -> Result<VoidCookie<'_, C>, ReplyError> {
+    let state = xproto::intern_atom(conn, false, NET_WM_STATE)?;
+    let modal = xproto::intern_atom(conn, false, NET_WM_STATE_MODAL)?;
+    let (state, modal) = (state.reply()?.atom, modal.reply()?.atom);
+    let reply = conn.get_property_full(false, window, state, AtomEnum::ATOM)?;
+    let mut states = match reply.value32() {
+        Some(value) => value.collect::<Vec<_>>(),
+        None => Vec::new(),
+    };
+    if !states.contains(&modal) {
+        states.push(modal);
+    }
+    Ok(conn.change_property32(
+        xproto::PropMode::REPLACE,
+        window,
+        state,
+        AtomEnum::ATOM,
+        &states,
+    )?)
+}
+
+// _NET_WM_STATE client messages
+
+const NET_WM_STATE_FULLSCREEN: &[u8] = b"_NET_WM_STATE_FULLSCREEN";
+
+// Source indication of `_NET_WM_STATE` messages for requests from normal applications.
+const SOURCE_APPLICATION: u32 = 1;
+
+/// The action that a `_NET_WM_STATE` client message requests from the window manager.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WmStateAction {
+    /// Remove the state from the window.
+    Remove,
+    /// Add the state to the window.
+    Add,
+    /// Add the state if it is not set, otherwise remove it.
+    Toggle,
+}
+
+impl From<WmStateAction> for u32 {
+    fn from(action: WmStateAction) -> u32 {
+        match action {
+            WmStateAction::Remove => 0,
+            WmStateAction::Add => 1,
+            WmStateAction::Toggle => 2,
+        }
+    }
+}
+
+fn wm_state_message(
+    window: Window,
+    net_wm_state: Atom,
+    action: WmStateAction,
+    first: Atom,
+    second: Atom,
+) -> xproto::ClientMessageEvent {
+    let data = [action.into(), first, second, SOURCE_APPLICATION, 0];
+    xproto::ClientMessageEvent::new(32, window, net_wm_state, data)
+}
+
+/// Ask the window manager to change the `_NET_WM_STATE` of a mapped window.
+///
+/// This sends a `_NET_WM_STATE` client message to the root window of `window`. The message
+/// changes `state` and, unless it is `x11rb::NONE`, also `second` according to `action`. Use this
+/// for example for maximizing a window, which needs both `_NET_WM_STATE_MAXIMIZED_VERT` and
+/// `_NET_WM_STATE_MAXIMIZED_HORZ`. Before the window is mapped, the property has to be set
+/// directly instead, e.g. with [`set_modal`].
+///
+/// The `_NET_WM_STATE` atom and the root window are queried first, which requires a round trip to
+/// the X11 server.
+pub fn set_wm_state<C: RequestConnection + ?Sized>(
+    conn: &C,
+    window: Window,
+    action: WmStateAction,
+    state: Atom,
+    second: Atom,
+) -> Result<VoidCookie<'_, C>, ReplyError> {
+    let net_wm_state = xproto::intern_atom(conn, false, NET_WM_STATE)?;
+    let tree = xproto::query_tree(conn, window)?;
+    let (net_wm_state, root) = (net_wm_state.reply()?.atom, tree.reply()?.root);
+    send_wm_state(conn, root, window, net_wm_state, action, state, second)
+}
+
+/// Ask the window manager to make a mapped window fullscreen or to undo this.
+///
+/// This is [`set_wm_state`] with `_NET_WM_STATE_FULLSCREEN`.
+pub fn fullscreen<C: RequestConnection + ?Sized>(
+    conn: &C,
+    window: Window,
+    enabled: bool,
+) -> Result<VoidCookie<'_, C>, ReplyError> {
+    let net_wm_state = xproto::intern_atom(conn, false, NET_WM_STATE)?;
+    let fullscreen = xproto::intern_atom(conn, false, NET_WM_STATE_FULLSCREEN)?;
+    let tree = xproto::query_tree(conn, window)?;
+    let (net_wm_state, fullscreen) = (net_wm_state.reply()?.atom, fullscreen.reply()?.atom);
+    let root = tree.reply()?.root;
+    let action = if enabled {
+        WmStateAction::Add
+    } else {
+        WmStateAction::Remove
+    };
+    let none = AtomEnum::NONE.into();
+    send_wm_state(conn, root, window, net_wm_state, action, fullscreen, none)
+}
+
+fn send_wm_state<C: RequestConnection + ?Sized>(
+    conn: &C,
+    root: Window,
+    window: Window,
+    net_wm_state: Atom,
+    action: WmStateAction,
+    state: Atom,
+    second: Atom,
+) -> Result<VoidCookie<'_, C>, ReplyError> {
+    let event = wm_state_message(window, net_wm_state, action, state, second);
+    let mask = xproto::EventMask::SUBSTRUCTURE_REDIRECT | xproto::EventMask::SUBSTRUCTURE_NOTIFY;
+    Ok(xproto::send_event(conn, false, root, mask, event)?)
+}
+
+// _NET_WM_PID and WM_CLIENT_MACHINE
+
+const NET_WM_PID: &[u8] = b"_NET_WM_PID";
+
+/// Get the process ID of the client that owns a window.
+///
+/// This reads the `_NET_WM_PID` property. The process ID is only meaningful on the machine from
+/// [`get_client_machine`]. `None` is returned if the property is absent or malformed.
+///
+/// The atom is interned first, which requires a round trip to the X11 server.
+pub fn get_window_pid<C: RequestConnection + ?Sized>(
+    conn: &C,
+    window: Window,
+) -> Result<Option<u32>, ReplyError> {
+    let atom = xproto::intern_atom(conn, true, NET_WM_PID)?.reply()?.atom;
+    if atom == crate::NONE {
+        // Nobody ever used the atom, so no window can have the property
+        return Ok(None);
+    }
+    let reply = xproto::get_property(conn, false, window, atom, AtomEnum::CARDINAL, 0, 1)?;
+    Ok(reply.reply()?.value32().and_then(|mut value| value.next()))
+}
+
+/// Get the name of the machine that the client owning a window runs on.
+///
+/// This reads the `WM_CLIENT_MACHINE` property with
+/// [`get_property_string`](crate::wrapper::ConnectionExt::get_property_string), so that a value of
+/// type `STRING` is decoded as ISO Latin-1. `None` is returned if the property is absent or
+/// malformed.
+pub fn get_client_machine<C: RequestConnection + ?Sized>(
+    conn: &C,
+    window: Window,
+) -> Result<Option<String>, ReplyError> {
+    conn.get_property_string(window, AtomEnum::WM_CLIENT_MACHINE)
+}
+
+// Watching property changes
+
+/// A change of a property that a [`PropertyWatcher`] is interested in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PropertyChange {
+    /// The window whose property changed.
+    pub window: Window,
+    /// The property that changed.
+    pub atom: Atom,
+    /// Whether the property was deleted. Otherwise, it has a new value that can be read with
+    /// `GetProperty`.
+    pub deleted: bool,
+    /// The server time when the property changed.
+    pub time: Timestamp,
+}
+
+/// Filter `PropertyNotify` events down to the properties of interest.
+///
+/// A window sends `PropertyNotify` events for all its properties to clients that select
+/// `PropertyChange` on it. Often, only a few properties are relevant, e.g. a status bar that shows
+/// the `_NET_WM_NAME` of some windows. `PropertyWatcher` remembers the `(window, atom)` pairs of
+/// interest and ignores all other events.
+///
+/// Selecting `EventMask::PROPERTY_CHANGE` on the windows is left to the user.
+#[derive(Debug, Clone, Default)]
+pub struct PropertyWatcher {
+    watched: HashSet<(Window, Atom)>,
+}
+
+impl PropertyWatcher {
+    /// Create a watcher that is not interested in any property yet.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Start watching a property of a window.
+    ///
+    /// Returns `false` if the property was already watched.
+    pub fn watch(&mut self, window: Window, atom: impl Into<Atom>) -> bool {
+        self.watched.insert((window, atom.into()))
+    }
+
+    /// Stop watching a property of a window.
+    ///
+    /// Returns `false` if the property was not watched.
+    pub fn unwatch(&mut self, window: Window, atom: impl Into<Atom>) -> bool {
+        self.watched.remove(&(window, atom.into()))
+    }
+
+    /// Stop watching all properties of a window, e.g. because it was destroyed.
+    pub fn forget(&mut self, window: Window) {
+        self.watched.retain(|&(w, _)| w != window);
+    }
+
+    /// Check whether a property of a window is watched.
+    pub fn is_watched(&self, window: Window, atom: impl Into<Atom>) -> bool {
+        self.watched.contains(&(window, atom.into()))
+    }
+
+    /// Check whether a `PropertyNotify` event is about a watched property.
+    pub fn filter(&self, event: &PropertyNotifyEvent) -> Option<PropertyChange> {
+        if !self.is_watched(event.window, event.atom) {
+            return None;
+        }
+        Some(PropertyChange {
+            window: event.window,
+            atom: event.atom,
+            deleted: event.state == Property::DELETE,
+            time: event.time,
+        })
+    }
+
+    /// Update the watcher from an event.
+    ///
+    /// `PropertyNotify` events about watched properties are returned as a [`PropertyChange`].
+    /// The properties of destroyed windows are no longer watched. All other events are ignored.
+    pub fn handle_event(&mut self, event: &Event) -> Option<PropertyChange> {
+        match event {
+            Event::PropertyNotify(event) => self.filter(event),
+            Event::DestroyNotify(event) => {
+                self.forget(event.window);
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
 /// Parse an element of type `T` and turn it into an `Option` by checking if the given `bit` is set
 /// in `flags`.
 fn parse_with_flag<T: TryParse>(
@@ -674,8 +1013,15 @@ fn parse_with_flag<T: TryParse>(
 mod test {
     use std::convert::TryInto;
 
-    use super::{WmClass, WmHints, WmHintsState, WmSizeHints};
-    use crate::protocol::xproto::{Atom, AtomEnum, GetPropertyReply, Gravity};
+    use super::{
+        motif_decoration_hints, wm_state_message, PropertyChange, PropertyWatcher, WmClass,
+        WmHints, WmHintsState, WmSizeHints, WmStateAction,
+    };
+    use crate::protocol::xproto::{
+        Atom, AtomEnum, DestroyNotifyEvent, GetPropertyReply, Gravity, Property,
+        PropertyNotifyEvent,
+    };
+    use crate::protocol::Event;
     use crate::x11_utils::Serialize;
 
     fn get_property_reply(value: &[u8], format: u8, type_: impl Into<Atom>) -> GetPropertyReply {
@@ -793,4 +1139,74 @@ mod test {
 
         assert_eq!(input, wm_hints.serialize());
     }
+
+    #[test]
+    fn test_motif_decoration_hints() {
+        let to_bytes = |values: [u32; 5]| {
+            values
+                .iter()
+                .flat_map(|v| u32::serialize(v).to_vec())
+                .collect::<Vec<u8>>()
+        };
+        assert_eq!(motif_decoration_hints(true), to_bytes([2, 0, 1, 0, 0]));
+        assert_eq!(motif_decoration_hints(false), to_bytes([2, 0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_wm_state_message() {
+        let event = wm_state_message(0x1234, 10, WmStateAction::Toggle, 11, 12);
+        assert_eq!(event.format, 32);
+        assert_eq!(event.window, 0x1234);
+        assert_eq!(event.type_, 10);
+        assert_eq!(event.data.as_data32(), [2, 11, 12, 1, 0]);
+    }
+
+    #[test]
+    fn property_watcher() {
+        let notify = |window, atom: AtomEnum, state| {
+            Event::PropertyNotify(PropertyNotifyEvent {
+                window,
+                atom: atom.into(),
+                time: 1234,
+                state,
+                ..Default::default()
+            })
+        };
+        let mut watcher = PropertyWatcher::new();
+        assert!(watcher.watch(1, AtomEnum::WM_NAME));
+        assert!(!watcher.watch(1, AtomEnum::WM_NAME));
+        assert!(watcher.watch(2, AtomEnum::WM_NAME));
+
+        assert_eq!(
+            watcher.handle_event(&notify(1, AtomEnum::WM_NAME, Property::NEW_VALUE)),
+            Some(PropertyChange {
+                window: 1,
+                atom: AtomEnum::WM_NAME.into(),
+                deleted: false,
+                time: 1234,
+            })
+        );
+        let change = watcher.handle_event(&notify(2, AtomEnum::WM_NAME, Property::DELETE));
+        assert!(change.unwrap().deleted);
+        // Other properties and windows are ignored
+        assert_eq!(
+            watcher.handle_event(&notify(1, AtomEnum::WM_CLASS, Property::NEW_VALUE)),
+            None
+        );
+        assert_eq!(
+            watcher.handle_event(&notify(3, AtomEnum::WM_NAME, Property::NEW_VALUE)),
+            None
+        );
+
+        // Destroyed windows are forgotten
+        let destroy = Event::DestroyNotify(DestroyNotifyEvent {
+            window: 2,
+            ..Default::default()
+        });
+        assert_eq!(watcher.handle_event(&destroy), None);
+        assert!(!watcher.is_watched(2, AtomEnum::WM_NAME));
+        assert!(watcher.is_watched(1, AtomEnum::WM_NAME));
+        assert!(watcher.unwatch(1, AtomEnum::WM_NAME));
+        assert!(!watcher.unwatch(1, AtomEnum::WM_NAME));
+    }
 }
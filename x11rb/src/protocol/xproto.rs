@@ -5362,28 +5362,76 @@ impl<C: RequestConnection + ?Sized> ConnectionExt for C {}
 /// Any errors during `Drop` are silently ignored. Most likely an error here means that your
 /// X11 connection is broken and later requests will also fail.
 #[derive(Debug)]
-pub struct PixmapWrapper<'c, C: RequestConnection>(&'c C, Pixmap);
+pub struct PixmapWrapper<'c, C: RequestConnection> {
+    conn: &'c C,
+    id: Pixmap,
+    /// The depth that the Pixmap was created with, if known.
+    depth: Option<u8>,
+    /// The width and height at creation time or from the last query, if known.
+    size: Option<(u16, u16)>,
+}
 
 impl<'c, C: RequestConnection> PixmapWrapper<'c, C>
 {
     /// Assume ownership of the given resource and destroy it in `Drop`.
     pub fn for_pixmap(conn: &'c C, id: Pixmap) -> Self {
-        PixmapWrapper(conn, id)
+        PixmapWrapper { conn, id, depth: None, size: None }
     }
 
     /// Get the XID of the wrapped resource
     pub fn pixmap(&self) -> Pixmap {
-        self.1
+        self.id
     }
 
     /// Assume ownership of the XID of the wrapped resource
     ///
     /// This function destroys this wrapper without freeing the underlying resource.
     pub fn into_pixmap(self) -> Pixmap {
-        let id = self.1;
+        let id = self.id;
         std::mem::forget(self);
         id
     }
+
+    /// Get the depth that the Pixmap was created with.
+    ///
+    /// This is `None` if the depth is not known, for example because this wrapper was
+    /// created for an existing Pixmap. Use [Self::query_geometry] to ask the X11 server.
+    pub fn depth(&self) -> Option<u8> {
+        self.depth
+    }
+
+    /// Get the width and height that the Pixmap was created with.
+    ///
+    /// This is `None` if the size is not known, for example because this wrapper was
+    /// created for an existing Pixmap. Use [Self::query_geometry] to ask the X11 server.
+    ///
+    /// The value is not updated when the size changes later, e.g. when a window is resized.
+    /// Use [Self::query_geometry] to get the current size.
+    pub fn size(&self) -> Option<(u16, u16)> {
+        self.size
+    }
+
+    /// Query the depth and size of the Pixmap from the X11 server and remember them.
+    ///
+    /// This sends a [get_geometry] request and waits for its reply. Afterwards,
+    /// [Self::depth] and [Self::size] return the values from the reply.
+    pub fn query_geometry(&mut self) -> Result<(u8, (u16, u16)), crate::errors::ReplyError> {
+        let reply = get_geometry(self.conn, self.id)?.reply()?;
+        self.depth = Some(reply.depth);
+        self.size = Some((reply.width, reply.height));
+        Ok((reply.depth, (reply.width, reply.height)))
+    }
+
+    /// Remember the depth and size that the resource was created with.
+    ///
+    /// A depth of zero is not remembered, because it means "copy from parent" for windows.
+    fn with_geometry(mut self, depth: u8, width: u16, height: u16) -> Self {
+        if depth != 0 {
+            self.depth = Some(depth);
+        }
+        self.size = Some((width, height));
+        self
+    }
 }
 
 impl<'c, C: X11Connection> PixmapWrapper<'c, C>
@@ -5401,7 +5449,7 @@ impl<'c, C: X11Connection> PixmapWrapper<'c, C>
     {
         let pid = conn.generate_id()?;
         let cookie = create_pixmap(conn, depth, pid, drawable, width, height)?;
-        Ok((Self::for_pixmap(conn, pid), cookie))
+        Ok((Self::for_pixmap(conn, pid).with_geometry(depth, width, height), cookie))
     }
 
     /// Create a new Pixmap and return a Pixmap wrapper
@@ -5419,13 +5467,13 @@ impl<'c, C: X11Connection> PixmapWrapper<'c, C>
 
 impl<C: RequestConnection> From<&PixmapWrapper<'_, C>> for Pixmap {
     fn from(from: &PixmapWrapper<'_, C>) -> Self {
-        from.1
+        from.id
     }
 }
 
 impl<C: RequestConnection> Drop for PixmapWrapper<'_, C> {
     fn drop(&mut self) {
-        let _ = free_pixmap(self.0, self.1);
+        let _ = free_pixmap(self.conn, self.id);
     }
 }
 
@@ -5436,28 +5484,76 @@ impl<C: RequestConnection> Drop for PixmapWrapper<'_, C> {
 /// Any errors during `Drop` are silently ignored. Most likely an error here means that your
 /// X11 connection is broken and later requests will also fail.
 #[derive(Debug)]
-pub struct WindowWrapper<'c, C: RequestConnection>(&'c C, Window);
+pub struct WindowWrapper<'c, C: RequestConnection> {
+    conn: &'c C,
+    id: Window,
+    /// The depth that the Window was created with, if known.
+    depth: Option<u8>,
+    /// The width and height at creation time or from the last query, if known.
+    size: Option<(u16, u16)>,
+}
 
 impl<'c, C: RequestConnection> WindowWrapper<'c, C>
 {
     /// Assume ownership of the given resource and destroy it in `Drop`.
     pub fn for_window(conn: &'c C, id: Window) -> Self {
-        WindowWrapper(conn, id)
+        WindowWrapper { conn, id, depth: None, size: None }
     }
 
     /// Get the XID of the wrapped resource
     pub fn window(&self) -> Window {
-        self.1
+        self.id
     }
 
     /// Assume ownership of the XID of the wrapped resource
     ///
     /// This function destroys this wrapper without freeing the underlying resource.
     pub fn into_window(self) -> Window {
-        let id = self.1;
+        let id = self.id;
         std::mem::forget(self);
         id
     }
+
+    /// Get the depth that the Window was created with.
+    ///
+    /// This is `None` if the depth is not known, for example because this wrapper was
+    /// created for an existing Window. Use [Self::query_geometry] to ask the X11 server.
+    pub fn depth(&self) -> Option<u8> {
+        self.depth
+    }
+
+    /// Get the width and height that the Window was created with.
+    ///
+    /// This is `None` if the size is not known, for example because this wrapper was
+    /// created for an existing Window. Use [Self::query_geometry] to ask the X11 server.
+    ///
+    /// The value is not updated when the size changes later, e.g. when a window is resized.
+    /// Use [Self::query_geometry] to get the current size.
+    pub fn size(&self) -> Option<(u16, u16)> {
+        self.size
+    }
+
+    /// Query the depth and size of the Window from the X11 server and remember them.
+    ///
+    /// This sends a [get_geometry] request and waits for its reply. Afterwards,
+    /// [Self::depth] and [Self::size] return the values from the reply.
+    pub fn query_geometry(&mut self) -> Result<(u8, (u16, u16)), crate::errors::ReplyError> {
+        let reply = get_geometry(self.conn, self.id)?.reply()?;
+        self.depth = Some(reply.depth);
+        self.size = Some((reply.width, reply.height));
+        Ok((reply.depth, (reply.width, reply.height)))
+    }
+
+    /// Remember the depth and size that the resource was created with.
+    ///
+    /// A depth of zero is not remembered, because it means "copy from parent" for windows.
+    fn with_geometry(mut self, depth: u8, width: u16, height: u16) -> Self {
+        if depth != 0 {
+            self.depth = Some(depth);
+        }
+        self.size = Some((width, height));
+        self
+    }
 }
 
 impl<'c, C: X11Connection> WindowWrapper<'c, C>
@@ -5475,7 +5571,7 @@ impl<'c, C: X11Connection> WindowWrapper<'c, C>
     {
         let wid = conn.generate_id()?;
         let cookie = create_window(conn, depth, wid, parent, x, y, width, height, border_width, class, visual, value_list)?;
-        Ok((Self::for_window(conn, wid), cookie))
+        Ok((Self::for_window(conn, wid).with_geometry(depth, width, height), cookie))
     }
 
     /// Create a new Window and return a Window wrapper
@@ -5493,13 +5589,13 @@ impl<'c, C: X11Connection> WindowWrapper<'c, C>
 
 impl<C: RequestConnection> From<&WindowWrapper<'_, C>> for Window {
     fn from(from: &WindowWrapper<'_, C>) -> Self {
-        from.1
+        from.id
     }
 }
 
 impl<C: RequestConnection> Drop for WindowWrapper<'_, C> {
     fn drop(&mut self) {
-        let _ = destroy_window(self.0, self.1);
+        let _ = destroy_window(self.conn, self.id);
     }
 }
 
@@ -0,0 +1,201 @@
+use std::collections::HashSet;
+
+use super::{PictFormats, StandardFormat};
+use crate::connection::Connection;
+use crate::errors::{ConnectionError, ParseError, ReplyOrIdError};
+use crate::protocol::render::{
+    self, Color, Glyphinfo, Glyphset, GlyphsetWrapper, PictOp, Pictformat, Picture, PictureWrapper,
+};
+use crate::x11_utils::Serialize;
+
+/// The maximum number of glyphs in one element of a `CompositeGlyphs` request.
+///
+/// A length of 255 is reserved for switching the glyph set.
+const MAX_GLYPHS_PER_ITEM: usize = 254;
+
+/// A helper for drawing anti-aliased text with glyphs that were rasterized by the application.
+///
+/// Glyphs are identified by a `u32` chosen by the application, for example the Unicode code
+/// point of a character or a glyph index of a font. They are uploaded with
+/// [`GlyphRenderer::add_glyph`] and drawn with [`GlyphRenderer::draw_glyphs`] or
+/// [`GlyphRenderer::draw_str`].
+///
+/// The glyph set is freed when the `GlyphRenderer` is dropped.
+#[derive(Debug)]
+pub struct GlyphRenderer<'c, C: Connection> {
+    conn: &'c C,
+    glyphset: GlyphsetWrapper<'c, C>,
+    format: Pictformat,
+    glyphs: HashSet<u32>,
+}
+
+impl<'c, C: Connection> GlyphRenderer<'c, C> {
+    /// Create a new, empty glyph set for 8 bit alpha glyphs.
+    ///
+    /// If the X11 server does not have the standard `A8` format, `ParseError::InvalidValue` is
+    /// returned.
+    pub fn new(conn: &'c C, formats: &PictFormats) -> Result<Self, ReplyOrIdError> {
+        let format = formats
+            .standard(StandardFormat::A8)
+            .ok_or(ParseError::InvalidValue)?;
+        let glyphset = GlyphsetWrapper::create_glyph_set(conn, format)?;
+        Ok(Self {
+            conn,
+            glyphset,
+            format,
+            glyphs: HashSet::new(),
+        })
+    }
+
+    /// Get the glyph set that contains the glyphs.
+    pub fn glyphset(&self) -> Glyphset {
+        self.glyphset.glyphset()
+    }
+
+    /// Check whether a glyph with the given id was already added.
+    pub fn has_glyph(&self, id: u32) -> bool {
+        self.glyphs.contains(&id)
+    }
+
+    /// Upload a glyph to the X11 server.
+    ///
+    /// `info` describes the size of the glyph, the position of its origin inside of the image
+    /// (`x` and `y`), and the offset to the origin of the next glyph (`x_off` and `y_off`).
+    /// `coverage` contains one alpha value per pixel in row-major order without any padding
+    /// between rows. A glyph that was added before with the same id is replaced.
+    ///
+    /// # Errors
+    ///
+    /// `coverage` must contain exactly `info.width * info.height` bytes. Otherwise,
+    /// `ParseError::InsufficientData` is returned if it is too short and `ParseError::InvalidValue`
+    /// if it is too long.
+    pub fn add_glyph(
+        &mut self,
+        id: u32,
+        info: Glyphinfo,
+        coverage: &[u8],
+    ) -> Result<(), ConnectionError> {
+        let width = usize::from(info.width);
+        let expected_size = width * usize::from(info.height);
+        if coverage.len() < expected_size {
+            return Err(ParseError::InsufficientData.into());
+        } else if coverage.len() > expected_size {
+            return Err(ParseError::InvalidValue.into());
+        }
+        let data = pad_rows(coverage, width);
+        let _ = render::add_glyphs(self.conn, self.glyphset(), &[id], &[info], &data)?;
+        let _ = self.glyphs.insert(id);
+        Ok(())
+    }
+
+    /// Draw glyphs in a solid colour.
+    ///
+    /// The origin of the first glyph is placed at `(x, y)` in `dst`. Each following glyph is
+    /// placed according to the offsets of the previous glyph. All glyphs must have been added via
+    /// [`GlyphRenderer::add_glyph`] before, otherwise the X11 server sends a `Glyph` error.
+    pub fn draw_glyphs(
+        &self,
+        dst: Picture,
+        color: Color,
+        x: i16,
+        y: i16,
+        glyphs: &[u32],
+    ) -> Result<(), ReplyOrIdError> {
+        if glyphs.is_empty() {
+            return Ok(());
+        }
+        let source = PictureWrapper::create_solid_fill(self.conn, color)?;
+        let _ = render::composite_glyphs32(
+            self.conn,
+            PictOp::OVER,
+            source.picture(),
+            dst,
+            self.format,
+            self.glyphset(),
+            0,
+            0,
+            &glyph_items(x, y, glyphs),
+        )?;
+        Ok(())
+    }
+
+    /// Draw a string in a solid colour.
+    ///
+    /// This is [`GlyphRenderer::draw_glyphs`] with the Unicode code points of `text` as glyph ids.
+    pub fn draw_str(
+        &self,
+        dst: Picture,
+        color: Color,
+        x: i16,
+        y: i16,
+        text: &str,
+    ) -> Result<(), ReplyOrIdError> {
+        let glyphs = text.chars().map(u32::from).collect::<Vec<_>>();
+        self.draw_glyphs(dst, color, x, y, &glyphs)
+    }
+}
+
+/// Pad each row of 8 bit image data to a multiple of four bytes.
+fn pad_rows(data: &[u8], width: usize) -> Vec<u8> {
+    if width == 0 {
+        return Vec::new();
+    }
+    let stride = (width + 3) & !3;
+    let mut result = Vec::with_capacity(data.len() / width * stride);
+    for row in data.chunks(width) {
+        result.extend_from_slice(row);
+        result.resize(result.len() + stride - width, 0);
+    }
+    result
+}
+
+/// Build the glyph items for a `CompositeGlyphs32` request.
+fn glyph_items(x: i16, y: i16, glyphs: &[u32]) -> Vec<u8> {
+    let mut result = Vec::new();
+    for (index, chunk) in glyphs.chunks(MAX_GLYPHS_PER_ITEM).enumerate() {
+        // Later items continue where the previous item stopped
+        let (dx, dy) = if index == 0 { (x, y) } else { (0, 0) };
+        // The chunk size is at most MAX_GLYPHS_PER_ITEM, so this cannot truncate
+        result.push(chunk.len() as u8);
+        result.extend_from_slice(&[0; 3]);
+        dx.serialize_into(&mut result);
+        dy.serialize_into(&mut result);
+        for glyph in chunk {
+            glyph.serialize_into(&mut result);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::{glyph_items, pad_rows, MAX_GLYPHS_PER_ITEM};
+
+    #[test]
+    fn padding() {
+        assert_eq!(pad_rows(&[1, 2, 3, 4, 5, 6], 3), [1, 2, 3, 0, 4, 5, 6, 0]);
+        assert_eq!(pad_rows(&[1, 2, 3, 4], 4), [1, 2, 3, 4]);
+        assert_eq!(pad_rows(&[1, 2, 3, 4, 5], 5), [1, 2, 3, 4, 5, 0, 0, 0]);
+        assert!(pad_rows(&[], 0).is_empty());
+    }
+
+    #[test]
+    fn items() {
+        let mut expected = vec![2, 0, 0, 0];
+        expected.extend(&10i16.to_ne_bytes());
+        expected.extend(&(-20i16).to_ne_bytes());
+        expected.extend(&65u32.to_ne_bytes());
+        expected.extend(&66u32.to_ne_bytes());
+        assert_eq!(glyph_items(10, -20, &[65, 66]), expected);
+    }
+
+    #[test]
+    fn items_split() {
+        let glyphs = vec![7; MAX_GLYPHS_PER_ITEM + 1];
+        let items = glyph_items(1, 2, &glyphs);
+        let first_len = 8 + 4 * MAX_GLYPHS_PER_ITEM;
+        assert_eq!(items.len(), first_len + 8 + 4);
+        assert_eq!(items[0], 254);
+        assert_eq!(&items[first_len..first_len + 8], &[1, 0, 0, 0, 0, 0, 0, 0]);
+    }
+}
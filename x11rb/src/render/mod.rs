@@ -0,0 +1,346 @@
+//! Utility functions for working with the `RENDER` extension.
+//!
+//! Most uses of `RENDER` start by creating a `Picture` for a window or pixmap. This requires a
+//! `Pictformat` that matches the visual or depth of the drawable. [`PictFormats`] caches the
+//! reply to `QueryPictFormats` and looks up formats. [`create_window_picture`] and
+//! [`create_pixmap_picture`] use it to create pictures that are freed when dropped.
+//!
+//! [`create_cursor_from_argb`] creates a cursor from an ARGB image.
+//!
+//! [`GlyphRenderer`] draws text with glyphs that were rasterized by the application, e.g. with a
+//! font rasterizer. It uploads the glyphs into a glyph set and draws them in a solid colour.
+//!
+//! The code in this module is only available when the `render` feature of the library is enabled.
+//!
+//! Usage example:
+//! ```no_run
+//! use x11rb::connection::Connection;
+//! use x11rb::protocol::render::CreatePictureAux;
+//! use x11rb::render::{create_window_picture, PictFormats};
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let (conn, screen_num) = x11rb::connect(None)?;
+//!     let root = conn.setup().roots[screen_num].root;
+//!     let formats = PictFormats::query(&conn)?;
+//!     let picture = create_window_picture(&conn, &formats, root, &CreatePictureAux::new())?;
+//!     println!("Created picture {}", picture.picture());
+//!     Ok(())
+//! }
+//! ```
+
+use std::borrow::Cow;
+use std::convert::TryInto;
+
+use crate::connection::Connection;
+use crate::errors::{ParseError, ReplyError, ReplyOrIdError};
+use crate::image::{BitsPerPixel, Image, ScanlinePad};
+use crate::protocol::render::{
+    self, CreatePictureAux, PictType, Pictformat, PictureWrapper, QueryPictFormatsReply,
+};
+use crate::protocol::xproto::{
+    self, GcontextWrapper, ImageOrder, Pixmap, PixmapWrapper, Visualid, Window,
+};
+
+mod glyphs;
+
+pub use glyphs::GlyphRenderer;
+
+/// The standard formats that every X11 server with `RENDER` supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StandardFormat {
+    /// 32 bits per pixel with 8 bits for alpha, red, green, and blue, in this order.
+    Argb32,
+    /// 24 bits per pixel with 8 bits for red, green, and blue, in this order.
+    Rgb24,
+    /// 8 bits of alpha.
+    A8,
+    /// 4 bits of alpha.
+    A4,
+    /// 1 bit of alpha.
+    A1,
+}
+
+impl StandardFormat {
+    /// Get the standard format for the given depth, if there is one.
+    pub fn for_depth(depth: u8) -> Option<Self> {
+        match depth {
+            32 => Some(Self::Argb32),
+            24 => Some(Self::Rgb24),
+            8 => Some(Self::A8),
+            4 => Some(Self::A4),
+            1 => Some(Self::A1),
+            _ => None,
+        }
+    }
+
+    /// Get the depth of this format.
+    pub fn depth(self) -> u8 {
+        match self {
+            Self::Argb32 => 32,
+            Self::Rgb24 => 24,
+            Self::A8 => 8,
+            Self::A4 => 4,
+            Self::A1 => 1,
+        }
+    }
+
+    /// Get the expected shifts and masks as `(alpha, red, green, blue)`.
+    fn channels(self) -> [(u16, u16); 4] {
+        match self {
+            Self::Argb32 => [(24, 0xff), (16, 0xff), (8, 0xff), (0, 0xff)],
+            Self::Rgb24 => [(0, 0), (16, 0xff), (8, 0xff), (0, 0xff)],
+            Self::A8 => [(0, 0xff), (0, 0), (0, 0), (0, 0)],
+            Self::A4 => [(0, 0xf), (0, 0), (0, 0), (0, 0)],
+            Self::A1 => [(0, 0x1), (0, 0), (0, 0), (0, 0)],
+        }
+    }
+
+    /// Find this format in a `QueryPictFormats` reply.
+    pub fn find(self, reply: &QueryPictFormatsReply) -> Option<Pictformat> {
+        let channels = self.channels();
+        // Only the shift of channels with a non-zero mask matters
+        let matches = |(shift, mask): (u16, u16), (expected_shift, expected_mask): (u16, u16)| {
+            mask == expected_mask && (mask == 0 || shift == expected_shift)
+        };
+        reply
+            .formats
+            .iter()
+            .find(|format| {
+                let direct = &format.direct;
+                format.type_ == PictType::DIRECT
+                    && format.depth == self.depth()
+                    && matches((direct.alpha_shift, direct.alpha_mask), channels[0])
+                    && matches((direct.red_shift, direct.red_mask), channels[1])
+                    && matches((direct.green_shift, direct.green_mask), channels[2])
+                    && matches((direct.blue_shift, direct.blue_mask), channels[3])
+            })
+            .map(|format| format.id)
+    }
+}
+
+/// A cache of the picture formats that the X11 server supports.
+#[derive(Debug, Clone)]
+pub struct PictFormats(QueryPictFormatsReply);
+
+impl PictFormats {
+    /// Query the picture formats from the X11 server.
+    pub fn query<C: Connection>(conn: &C) -> Result<Self, ReplyError> {
+        Ok(Self::from_reply(render::query_pict_formats(conn)?.reply()?))
+    }
+
+    /// Create a cache from a `QueryPictFormats` reply.
+    pub fn from_reply(reply: QueryPictFormatsReply) -> Self {
+        Self(reply)
+    }
+
+    /// Get the `QueryPictFormats` reply.
+    pub fn reply(&self) -> &QueryPictFormatsReply {
+        &self.0
+    }
+
+    /// Find one of the standard formats.
+    pub fn standard(&self, format: StandardFormat) -> Option<Pictformat> {
+        format.find(&self.0)
+    }
+
+    /// Find the standard format for the given depth.
+    pub fn for_depth(&self, depth: u8) -> Option<Pictformat> {
+        StandardFormat::for_depth(depth).and_then(|format| self.standard(format))
+    }
+
+    /// Find the format that corresponds to the given visual.
+    pub fn for_visual(&self, visual: Visualid) -> Option<Pictformat> {
+        self.0
+            .screens
+            .iter()
+            .flat_map(|screen| &screen.depths)
+            .flat_map(|depth| &depth.visuals)
+            .find(|pict_visual| pict_visual.visual == visual)
+            .map(|pict_visual| pict_visual.format)
+    }
+}
+
+/// Create a picture for a window.
+///
+/// The picture format is determined from the visual of the window, which requires a
+/// `GetWindowAttributes` request. If no format matches, `ParseError::InvalidValue` is returned.
+pub fn create_window_picture<'c, C: Connection>(
+    conn: &'c C,
+    formats: &PictFormats,
+    window: Window,
+    value_list: &CreatePictureAux,
+) -> Result<PictureWrapper<'c, C>, ReplyOrIdError> {
+    let visual = xproto::get_window_attributes(conn, window)?.reply()?.visual;
+    let format = formats.for_visual(visual).ok_or(ParseError::InvalidValue)?;
+    PictureWrapper::create_picture(conn, window, format, value_list)
+}
+
+/// Create a picture for a pixmap with the given depth.
+///
+/// The standard format for `depth` is used. If there is no such format,
+/// `ParseError::InvalidValue` is returned.
+pub fn create_pixmap_picture<'c, C: Connection>(
+    conn: &'c C,
+    formats: &PictFormats,
+    pixmap: Pixmap,
+    depth: u8,
+    value_list: &CreatePictureAux,
+) -> Result<PictureWrapper<'c, C>, ReplyOrIdError> {
+    let format = formats.for_depth(depth).ok_or(ParseError::InvalidValue)?;
+    PictureWrapper::create_picture(conn, pixmap, format, value_list)
+}
+
+/// Create a cursor from an ARGB image via `RenderCreateCursor`.
+///
+/// `data` contains `width`x`height` pixels in row-major order. Each pixel is `0xAARRGGBB` with
+/// premultiplied alpha. The hotspot `(hot_x, hot_y)` must lie inside the image. The image is
+/// uploaded to a temporary pixmap with depth 32 on the screen of `root` via [`Image::put`], which
+/// splits large images into several requests. This pixmap and the temporary picture and graphics
+/// context are freed again before this function returns.
+///
+/// Since `formats` has to be queried first, `ConnectionError::UnsupportedExtension` is already
+/// returned by [`PictFormats::query`] if the server does not support `RENDER`. This function
+/// returns `ParseError::InvalidValue` if the hotspot is outside of the image or if there is no
+/// ARGB32 format, and `ParseError::InsufficientData` if `data` is too short.
+#[allow(clippy::too_many_arguments)]
+pub fn create_cursor_from_argb<C: Connection>(
+    conn: &C,
+    formats: &PictFormats,
+    root: Window,
+    width: u16,
+    height: u16,
+    hot_x: u16,
+    hot_y: u16,
+    data: &[u32],
+) -> Result<xproto::Cursor, ReplyOrIdError> {
+    if hot_x >= width || hot_y >= height {
+        return Err(ParseError::InvalidValue.into());
+    }
+    let data = data
+        .get(..usize::from(width) * usize::from(height))
+        .ok_or(ParseError::InsufficientData)?;
+    let format = formats
+        .standard(StandardFormat::Argb32)
+        .ok_or(ParseError::InvalidValue)?;
+
+    let pixmap = PixmapWrapper::create_pixmap(conn, 32, root, width, height)?;
+    let gc = GcontextWrapper::create_gc(conn, pixmap.pixmap(), &Default::default())?;
+    let byte_order = conn.setup().image_byte_order;
+    // With 32 bits per pixel, every scanline pad results in the same layout
+    let image = Image::new(
+        width,
+        height,
+        ScanlinePad::Pad32,
+        32,
+        BitsPerPixel::B32,
+        byte_order.try_into()?,
+        Cow::Owned(argb_bytes(data, byte_order)),
+    )?;
+    let _ = image.put(conn, pixmap.pixmap(), gc.gcontext(), 0, 0)?;
+    let picture =
+        PictureWrapper::create_picture(conn, pixmap.pixmap(), format, &Default::default())?;
+    let cursor = conn.generate_id()?;
+    let _ = render::create_cursor(conn, cursor, picture.picture(), hot_x, hot_y)?;
+    Ok(cursor)
+}
+
+/// Serialize ARGB pixels for a `PutImage` request with 32 bits per pixel.
+fn argb_bytes(data: &[u32], byte_order: ImageOrder) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len() * 4);
+    for &pixel in data {
+        if byte_order == ImageOrder::MSB_FIRST {
+            result.extend_from_slice(&pixel.to_be_bytes());
+        } else {
+            result.extend_from_slice(&pixel.to_le_bytes());
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PictFormats, StandardFormat};
+    use crate::protocol::render::{
+        Directformat, PictType, Pictdepth, Pictforminfo, Pictscreen, Pictvisual,
+        QueryPictFormatsReply,
+    };
+
+    fn format(id: u32, depth: u8, channels: [(u16, u16); 4]) -> Pictforminfo {
+        let [(alpha_shift, alpha_mask), (red_shift, red_mask), (green_shift, green_mask), (blue_shift, blue_mask)] =
+            channels;
+        Pictforminfo {
+            id,
+            type_: PictType::DIRECT,
+            depth,
+            direct: Directformat {
+                red_shift,
+                red_mask,
+                green_shift,
+                green_mask,
+                blue_shift,
+                blue_mask,
+                alpha_shift,
+                alpha_mask,
+            },
+            colormap: 0,
+        }
+    }
+
+    fn formats() -> PictFormats {
+        PictFormats::from_reply(QueryPictFormatsReply {
+            formats: vec![
+                // BGRA, not a standard format
+                format(1, 32, [(0, 0xff), (8, 0xff), (16, 0xff), (24, 0xff)]),
+                format(2, 32, [(24, 0xff), (16, 0xff), (8, 0xff), (0, 0xff)]),
+                format(3, 24, [(0, 0), (16, 0xff), (8, 0xff), (0, 0xff)]),
+                format(4, 8, [(0, 0xff), (0, 0), (0, 0), (0, 0)]),
+                format(5, 1, [(0, 1), (0, 0), (0, 0), (0, 0)]),
+            ],
+            screens: vec![Pictscreen {
+                fallback: 0,
+                depths: vec![Pictdepth {
+                    depth: 24,
+                    visuals: vec![Pictvisual {
+                        visual: 0x21,
+                        format: 3,
+                    }],
+                }],
+            }],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn standard_formats() {
+        let formats = formats();
+        assert_eq!(formats.standard(StandardFormat::Argb32), Some(2));
+        assert_eq!(formats.standard(StandardFormat::Rgb24), Some(3));
+        assert_eq!(formats.standard(StandardFormat::A8), Some(4));
+        assert_eq!(formats.standard(StandardFormat::A4), None);
+        assert_eq!(formats.for_depth(1), Some(5));
+        assert_eq!(formats.for_depth(16), None);
+    }
+
+    #[test]
+    fn argb_pixel_bytes() {
+        use super::argb_bytes;
+        use crate::protocol::xproto::ImageOrder;
+
+        let data = [0x8011_2233, 0xff00_00ff];
+        assert_eq!(
+            argb_bytes(&data, ImageOrder::LSB_FIRST),
+            [0x33, 0x22, 0x11, 0x80, 0xff, 0, 0, 0xff]
+        );
+        assert_eq!(
+            argb_bytes(&data, ImageOrder::MSB_FIRST),
+            [0x80, 0x11, 0x22, 0x33, 0xff, 0, 0, 0xff]
+        );
+    }
+
+    #[test]
+    fn visual_formats() {
+        let formats = formats();
+        assert_eq!(formats.for_visual(0x21), Some(3));
+        assert_eq!(formats.for_visual(0x22), None);
+    }
+}
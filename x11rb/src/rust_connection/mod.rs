@@ -4,6 +4,7 @@ use std::convert::TryInto;
 use std::io::IoSlice;
 use std::mem::drop;
 use std::sync::{Condvar, Mutex, MutexGuard, TryLockError};
+use std::time::{Duration, Instant};
 
 use crate::connection::{
     compute_length_field, Connection, ReplyOrError, RequestConnection, RequestKind,
@@ -11,8 +12,9 @@ use crate::connection::{
 use crate::cookie::{Cookie, CookieWithFds, VoidCookie};
 pub use crate::errors::{ConnectError, ConnectionError, ParseError, ReplyError, ReplyOrIdError};
 use crate::extension_manager::ExtensionManager;
-use crate::protocol::bigreq::{ConnectionExt as _, EnableReply};
-use crate::protocol::xproto::{Setup, GET_INPUT_FOCUS_REQUEST};
+use crate::outstanding_requests::OutstandingRequests;
+use crate::protocol::bigreq::{self, EnableReply};
+use crate::protocol::xproto::{Setup, GET_INPUT_FOCUS_REQUEST, QUERY_EXTENSION_REQUEST};
 use crate::utils::RawFdContainer;
 use crate::x11_utils::{ExtensionInformation, TryParse, TryParseFd};
 use x11rb_protocol::connect::Connect;
@@ -21,12 +23,16 @@ use x11rb_protocol::id_allocator::IdAllocator;
 use x11rb_protocol::{xauth::get_auth, DiscardMode, RawEventAndSeqNumber, SequenceNumber};
 
 mod packet_reader;
+mod rate_limit;
 mod stream;
 mod write_buffer;
 
 use packet_reader::PacketReader;
+use rate_limit::RateLimiter;
+pub use rate_limit::RequestLimits;
 pub use stream::{DefaultStream, PollMode, Stream};
 use write_buffer::WriteBuffer;
+pub use write_buffer::DEFAULT_WRITE_BUFFER_CAPACITY;
 
 type Buffer = <RustConnection as RequestConnection>::Buf;
 /// A combination of a buffer and a list of file descriptors for use by [`RustConnection`].
@@ -51,6 +57,8 @@ type MutexGuardInner<'a> = MutexGuard<'a, ConnectionInner>;
 pub(crate) enum BlockingMode {
     Blocking,
     NonBlocking,
+    /// Block, but at most for the given duration.
+    Timeout(Duration),
 }
 
 /// A connection to an X11 server implemented in pure rust
@@ -73,6 +81,8 @@ pub struct RustConnection<S: Stream = DefaultStream> {
     extension_manager: Mutex<ExtensionManager>,
     maximum_request_bytes: Mutex<MaxRequestBytes>,
     id_allocator: Mutex<IdAllocator>,
+    outstanding_requests: OutstandingRequests,
+    rate_limiter: Mutex<RateLimiter>,
 }
 
 // Locking rules
@@ -110,6 +120,43 @@ impl RustConnection<DefaultStream> {
     ///
     /// If no `dpy_name` is provided, the value from `$DISPLAY` is used.
     pub fn connect(dpy_name: Option<&str>) -> Result<(Self, usize), ConnectError> {
+        Self::connect_impl(dpy_name, None, None)
+    }
+
+    /// Establish a new connection with the given authorization information.
+    ///
+    /// This works like [`RustConnection::connect`], but instead of looking up the authorization
+    /// information in `~/.Xauthority`, the given information is used. `auth_name` is the name of
+    /// the authorization protocol, for example `MIT-MAGIC-COOKIE-1`, and `auth_data` is the data
+    /// for this protocol.
+    pub fn connect_with_auth(
+        dpy_name: Option<&str>,
+        auth_name: Vec<u8>,
+        auth_data: Vec<u8>,
+    ) -> Result<(Self, usize), ConnectError> {
+        Self::connect_impl(dpy_name, Some((auth_name, auth_data)), None)
+    }
+
+    /// Establish a new connection, but give up if it is not established within `timeout`.
+    ///
+    /// This works like [`RustConnection::connect`], but the timeout limits both connecting the
+    /// socket and receiving the setup from the X11 server. If the timeout elapses, an
+    /// [`ConnectError::IoError`] of kind [`std::io::ErrorKind::TimedOut`] is returned.
+    pub fn connect_with_timeout(
+        dpy_name: Option<&str>,
+        timeout: Duration,
+    ) -> Result<(Self, usize), ConnectError> {
+        Self::connect_impl(dpy_name, None, Some(timeout))
+    }
+
+    fn connect_impl(
+        dpy_name: Option<&str>,
+        mut auth: Option<(Vec<u8>, Vec<u8>)>,
+        timeout: Option<Duration>,
+    ) -> Result<(Self, usize), ConnectError> {
+        // A timeout that is too large to be represented is the same as no timeout
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+
         // Parse display information
         let parsed_display = x11rb_protocol::parse_display::parse_display(dpy_name)
             .ok_or(ConnectError::DisplayParsingError)?;
@@ -119,19 +166,29 @@ impl RustConnection<DefaultStream> {
         // works.
         let mut error = None;
         for addr in parsed_display.connect_instruction() {
-            match DefaultStream::connect(addr) {
+            let stream = match deadline {
+                Some(deadline) => remaining_time(deadline)
+                    .and_then(|timeout| DefaultStream::connect_timeout(addr, timeout)),
+                None => DefaultStream::connect(addr),
+            };
+            match stream {
                 Ok(stream) => {
                     // we found a stream, get auth information
-                    let (family, address) = stream.peer_addr()?;
-                    let (auth_name, auth_data) = get_auth(family, &address, parsed_display.display)
-                        // Ignore all errors while determining auth; instead we just try without auth info.
-                        .unwrap_or(None)
-                        .unwrap_or_else(|| (Vec::new(), Vec::new()));
+                    let (auth_name, auth_data) = match auth.take() {
+                        Some(auth) => auth,
+                        None => {
+                            let (family, address) = stream.peer_addr()?;
+                            get_auth(family, &address, parsed_display.display)
+                                // Ignore all errors while determining auth; instead we just try without auth info.
+                                .unwrap_or(None)
+                                .unwrap_or_else(|| (Vec::new(), Vec::new()))
+                        }
+                    };
 
                     // finish connecting to server
                     return Ok((
-                        Self::connect_to_stream_with_auth_info(
-                            stream, screen, auth_name, auth_data,
+                        Self::connect_to_stream_impl(
+                            stream, screen, auth_name, auth_data, deadline,
                         )?,
                         screen,
                     ));
@@ -175,6 +232,16 @@ impl<S: Stream> RustConnection<S> {
         screen: usize,
         auth_name: Vec<u8>,
         auth_data: Vec<u8>,
+    ) -> Result<Self, ConnectError> {
+        Self::connect_to_stream_impl(stream, screen, auth_name, auth_data, None)
+    }
+
+    fn connect_to_stream_impl(
+        stream: S,
+        screen: usize,
+        auth_name: Vec<u8>,
+        auth_data: Vec<u8>,
+        deadline: Option<Instant>,
     ) -> Result<Self, ConnectError> {
         let (mut connect, setup_request) = Connect::with_authorization(auth_name, auth_data);
 
@@ -183,7 +250,7 @@ impl<S: Stream> RustConnection<S> {
         let mut fds = vec![];
 
         while nwritten != setup_request.len() {
-            stream.poll(PollMode::Writable)?;
+            poll_until(&stream, PollMode::Writable, deadline)?;
             // poll returned successfully, so the stream is writable.
             match stream.write(&setup_request[nwritten..], &mut fds) {
                 Ok(0) => {
@@ -202,7 +269,7 @@ impl<S: Stream> RustConnection<S> {
 
         // read in the setup
         loop {
-            stream.poll(PollMode::Readable)?;
+            poll_until(&stream, PollMode::Readable, deadline)?;
             let adv = match stream.read(connect.buffer(), &mut fds) {
                 Ok(0) => {
                     return Err(std::io::Error::new(
@@ -259,21 +326,28 @@ impl<S: Stream> RustConnection<S> {
             extension_manager: Default::default(),
             maximum_request_bytes: Mutex::new(MaxRequestBytes::Unknown),
             id_allocator: Mutex::new(id_allocator),
+            outstanding_requests: Default::default(),
+            rate_limiter: Default::default(),
         })
     }
 
     /// Internal function for actually sending a request.
     ///
     /// This function "does the actual work" for `send_request_with_reply()` and
-    /// `send_request_without_reply()`.
+    /// `send_request_without_reply()`. The limits from [`RustConnection::set_request_limits`]
+    /// are only enforced if `apply_limits` is true.
     fn send_request(
         &self,
         bufs: &[IoSlice<'_>],
         fds: Vec<RawFdContainer>,
         kind: ReplyFdKind,
+        apply_limits: bool,
     ) -> Result<SequenceNumber, ConnectionError> {
         let mut storage = Default::default();
         let bufs = compute_length_field(self, bufs, &mut storage)?;
+        if apply_limits {
+            self.check_request_limits(kind)?;
+        }
 
         // Note: `inner` must be kept blocked until the request has been completely written
         // or buffered to avoid sending the data of different requests interleaved. For this
@@ -296,6 +370,41 @@ impl<S: Stream> RustConnection<S> {
         }
     }
 
+    /// Enforce the limits from [`RustConnection::set_request_limits`] before sending a request.
+    fn check_request_limits(&self, kind: ReplyFdKind) -> Result<(), std::io::Error> {
+        if kind != ReplyFdKind::NoReply {
+            let outstanding = self.outstanding_requests.count();
+            self.rate_limiter
+                .lock()
+                .unwrap()
+                .check_outstanding(outstanding)?;
+        }
+        loop {
+            // The lock is released before sleeping, so that other threads are not stalled
+            let delay = self.rate_limiter.lock().unwrap().check_rate(Instant::now());
+            match delay {
+                Some(delay) => std::thread::sleep(delay),
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// Send a `BIG-REQUESTS` `Enable` request.
+    ///
+    /// This request is sent internally, so the limits from
+    /// [`RustConnection::set_request_limits`] do not apply to it.
+    fn send_bigreq_enable(&self) -> Result<SequenceNumber, ConnectionError> {
+        let major_opcode = self
+            .extension_information(bigreq::X11_EXTENSION_NAME)?
+            .ok_or(ConnectionError::UnsupportedExtension)?
+            .major_opcode;
+        let (bytes, fds) = bigreq::EnableRequest.serialize(major_opcode);
+        let slices = bytes.iter().map(|b| IoSlice::new(b)).collect::<Vec<_>>();
+        let sequence = self.send_request(&slices, fds, ReplyFdKind::ReplyWithoutFDs, false)?;
+        self.outstanding_requests.sent(sequence);
+        Ok(sequence)
+    }
+
     /// Send a synchronisation packet to the X11 server.
     ///
     /// This function sends a `GetInputFocus` request to the X11 server and arranges for its reply
@@ -419,7 +528,7 @@ impl<S: Stream> RustConnection<S> {
     /// reason, you need to pass in a `MutexGuard` to be dropped. This function locks the mutex
     /// again and returns a new `MutexGuard`.
     ///
-    /// Note: If `mode` is `BlockingMode::Blocking`, the lock on `inner` will be temporarily
+    /// Note: If `mode` is `BlockingMode::Blocking` or `BlockingMode::Timeout`, the lock on `inner` will be temporarily
     /// released. While sending a request, `inner` must be kept locked to avoid sending the data
     /// of different requests interleaved. So, when `read_packet_and_enqueue` is called as part
     /// of a write, it must always be done with `mode` set to `BlockingMode::NonBlocking`.
@@ -434,6 +543,13 @@ impl<S: Stream> RustConnection<S> {
                 // In non-blocking mode, we just return immediately
                 match mode {
                     BlockingMode::NonBlocking => return Ok(inner),
+                    BlockingMode::Timeout(timeout) => {
+                        return Ok(self
+                            .reader_condition
+                            .wait_timeout(inner, timeout)
+                            .unwrap()
+                            .0)
+                    }
                     BlockingMode::Blocking => {}
                 }
 
@@ -454,12 +570,17 @@ impl<S: Stream> RustConnection<S> {
                 let notify_on_drop = NotifyOnDrop(&self.reader_condition);
 
                 // 2.1. Poll for read if mode is blocking.
-                if mode == BlockingMode::Blocking {
+                if mode != BlockingMode::NonBlocking {
                     // 2.1.1. Unlock `inner`, so other threads can use it while
                     // during the poll.
                     drop(inner);
                     // 2.1.2. Do the actual poll
-                    self.stream.poll(PollMode::Readable)?;
+                    match mode {
+                        BlockingMode::Timeout(timeout) => {
+                            self.stream.poll_with_timeout(PollMode::Readable, timeout)?
+                        }
+                        _ => self.stream.poll(PollMode::Readable)?,
+                    }
                     // 2.1.3. Relock inner
                     inner = self.inner.lock().unwrap();
                 }
@@ -467,7 +588,8 @@ impl<S: Stream> RustConnection<S> {
                 // 2.2. Try to read as many packets as possible without blocking.
                 let mut fds = Vec::new();
                 let mut packets = Vec::new();
-                packet_reader.try_read_packets(&self.stream, &mut packets, &mut fds)?;
+                let read_result =
+                    packet_reader.try_read_packets(&self.stream, &mut packets, &mut fds);
 
                 // 2.3. Once `inner` has been relocked, drop the
                 // lock on `packet_reader`. While inner is locked, other
@@ -478,11 +600,17 @@ impl<S: Stream> RustConnection<S> {
                 // for a reply that has been read but not enqueued yet.
                 drop(packet_reader);
 
-                // 2.4. Actually enqueue the read packets.
+                // 2.4. Actually enqueue the read packets. This also happens if reading failed
+                // afterwards, so that no packets are lost. The error is only reported if nothing
+                // was read; otherwise the next read from the stream fails again.
+                let read_nothing = packets.is_empty();
                 inner.inner.enqueue_fds(fds);
                 packets
                     .into_iter()
                     .for_each(|packet| inner.inner.enqueue_packet(packet));
+                if read_nothing {
+                    read_result?;
+                }
 
                 // 2.5. Notify the condvar by dropping the `notify_on_drop` object.
                 // The object would have been dropped when the function returns, so
@@ -498,10 +626,7 @@ impl<S: Stream> RustConnection<S> {
 
     fn prefetch_maximum_request_bytes_impl(&self, max_bytes: &mut MutexGuard<'_, MaxRequestBytes>) {
         if let MaxRequestBytes::Unknown = **max_bytes {
-            let request = self
-                .bigreq_enable()
-                .map(|cookie| cookie.into_sequence_number())
-                .ok();
+            let request = self.send_bigreq_enable().ok();
             **max_bytes = MaxRequestBytes::Requested(request);
         }
     }
@@ -510,6 +635,81 @@ impl<S: Stream> RustConnection<S> {
     pub fn stream(&self) -> &S {
         &self.stream
     }
+
+    /// Change the capacity of the buffer for outgoing requests.
+    ///
+    /// Requests are collected in this buffer and written to the stream together when the buffer
+    /// is full or when [`Connection::flush`] is called. Requests that are at least as large as
+    /// the buffer are written to the stream directly. A capacity of zero disables buffering.
+    ///
+    /// The default capacity is [`DEFAULT_WRITE_BUFFER_CAPACITY`]. Already buffered requests are
+    /// kept when the capacity is reduced.
+    pub fn set_write_buffer_capacity(&self, capacity: usize) {
+        self.inner
+            .lock()
+            .unwrap()
+            .write_buffer
+            .set_capacity(capacity);
+    }
+
+    /// Keep requests in the write buffer until the returned guard is dropped.
+    ///
+    /// Normally, buffered requests are written to the stream as soon as the write buffer is full.
+    /// While the connection is corked, the write buffer grows instead. When the guard is dropped,
+    /// all requests are written together with a single flush. This packs many small requests,
+    /// e.g. for one frame of an animation, into as few system calls as possible.
+    ///
+    /// Corking does not prevent explicit calls to [`Connection::flush`]. Waiting for a reply also
+    /// writes the buffered requests, since the X11 server could otherwise never answer. Corks can
+    /// be nested; requests are only written once all guards are gone.
+    pub fn cork(&self) -> Cork<'_, S> {
+        self.inner.lock().unwrap().write_buffer.cork();
+        Cork(self)
+    }
+
+    fn uncork(&self) -> Result<(), ConnectionError> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.write_buffer.uncork() {
+            let _inner = self.flush_impl(inner)?;
+        }
+        Ok(())
+    }
+
+    /// Get the limits for sending requests.
+    pub fn request_limits(&self) -> RequestLimits {
+        self.rate_limiter.lock().unwrap().limits()
+    }
+
+    /// Limit the rate at which requests are sent.
+    ///
+    /// See [`RequestLimits`] for the available limits and what happens when they are reached.
+    /// By default, no limits are applied.
+    pub fn set_request_limits(&self, limits: RequestLimits) {
+        self.rate_limiter.lock().unwrap().set_limits(limits);
+    }
+}
+
+/// A guard that keeps requests in the write buffer of a [`RustConnection`].
+///
+/// This is returned by [`RustConnection::cork`]. When this struct is dropped, the buffered
+/// requests are flushed. Any errors during `Drop` are silently ignored; use [`Cork::uncork`] to
+/// get them.
+#[derive(Debug)]
+pub struct Cork<'a, S: Stream>(&'a RustConnection<S>);
+
+impl<S: Stream> Cork<'_, S> {
+    /// Stop corking the connection and flush the buffered requests.
+    pub fn uncork(self) -> Result<(), ConnectionError> {
+        let conn = self.0;
+        std::mem::forget(self);
+        conn.uncork()
+    }
+}
+
+impl<S: Stream> Drop for Cork<'_, S> {
+    fn drop(&mut self) {
+        let _ = self.0.uncork();
+    }
 }
 
 impl<S: Stream> RequestConnection for RustConnection<S> {
@@ -523,10 +723,14 @@ impl<S: Stream> RequestConnection for RustConnection<S> {
     where
         Reply: TryParse,
     {
-        Ok(Cookie::new(
-            self,
-            self.send_request(bufs, fds, ReplyFdKind::ReplyWithoutFDs)?,
-        ))
+        // QueryExtension is sent internally when an extension is used for the first time. Setting
+        // up an extension should not fail because of the limits.
+        let is_query_extension =
+            bufs.first().and_then(|buf| buf.first()) == Some(&QUERY_EXTENSION_REQUEST);
+        let sequence =
+            self.send_request(bufs, fds, ReplyFdKind::ReplyWithoutFDs, !is_query_extension)?;
+        self.outstanding_requests.sent(sequence);
+        Ok(Cookie::new(self, sequence))
     }
 
     fn send_request_with_reply_with_fds<Reply>(
@@ -537,10 +741,9 @@ impl<S: Stream> RequestConnection for RustConnection<S> {
     where
         Reply: TryParseFd,
     {
-        Ok(CookieWithFds::new(
-            self,
-            self.send_request(bufs, fds, ReplyFdKind::ReplyWithFDs)?,
-        ))
+        let sequence = self.send_request(bufs, fds, ReplyFdKind::ReplyWithFDs, true)?;
+        self.outstanding_requests.sent(sequence);
+        Ok(CookieWithFds::new(self, sequence))
     }
 
     fn send_request_without_reply(
@@ -550,11 +753,12 @@ impl<S: Stream> RequestConnection for RustConnection<S> {
     ) -> Result<VoidCookie<'_, Self>, ConnectionError> {
         Ok(VoidCookie::new(
             self,
-            self.send_request(bufs, fds, ReplyFdKind::NoReply)?,
+            self.send_request(bufs, fds, ReplyFdKind::NoReply, true)?,
         ))
     }
 
     fn discard_reply(&self, sequence: SequenceNumber, _kind: RequestKind, mode: DiscardMode) {
+        self.outstanding_requests.resolved(sequence);
         self.inner
             .lock()
             .unwrap()
@@ -562,6 +766,10 @@ impl<S: Stream> RequestConnection for RustConnection<S> {
             .discard_reply(sequence, mode);
     }
 
+    fn forget_reply(&self, sequence: SequenceNumber) {
+        self.outstanding_requests.resolved(sequence);
+    }
+
     fn prefetch_extension_information(
         &self,
         extension_name: &'static str,
@@ -592,6 +800,27 @@ impl<S: Stream> RequestConnection for RustConnection<S> {
         }
     }
 
+    fn poll_for_reply_or_raw_error(
+        &self,
+        sequence: SequenceNumber,
+    ) -> Result<Option<ReplyOrError<Vec<u8>>>, ConnectionError> {
+        let mut inner = self.inner.lock().unwrap();
+        // Ensure the request is sent
+        inner = self.flush_impl(inner)?;
+        inner = self.read_packet_and_enqueue(inner, BlockingMode::NonBlocking)?;
+        Ok(inner
+            .inner
+            .poll_for_reply_or_error(sequence)
+            .map(|(reply, _fds)| {
+                self.outstanding_requests.resolved(sequence);
+                if reply[0] == 0 {
+                    ReplyOrError::Error(reply)
+                } else {
+                    ReplyOrError::Reply(reply)
+                }
+            }))
+    }
+
     fn wait_for_reply(&self, sequence: SequenceNumber) -> Result<Option<Vec<u8>>, ConnectionError> {
         let mut inner = self.inner.lock().unwrap();
         inner = self.flush_impl(inner)?;
@@ -599,8 +828,14 @@ impl<S: Stream> RequestConnection for RustConnection<S> {
             let poll_result = inner.inner.poll_for_reply(sequence);
             match poll_result {
                 PollReply::TryAgain => {}
-                PollReply::NoReply => return Ok(None),
-                PollReply::Reply(buffer) => return Ok(Some(buffer)),
+                PollReply::NoReply => {
+                    self.outstanding_requests.resolved(sequence);
+                    return Ok(None);
+                }
+                PollReply::Reply(buffer) => {
+                    self.outstanding_requests.resolved(sequence);
+                    return Ok(Some(buffer));
+                }
             }
             inner = self.read_packet_and_enqueue(inner, BlockingMode::Blocking)?;
         }
@@ -637,6 +872,7 @@ impl<S: Stream> RequestConnection for RustConnection<S> {
         inner = self.flush_impl(inner)?;
         loop {
             if let Some(reply) = inner.inner.poll_for_reply_or_error(sequence) {
+                self.outstanding_requests.resolved(sequence);
                 if reply.0[0] == 0 {
                     return Ok(ReplyOrError::Error(reply.0));
                 } else {
@@ -682,6 +918,29 @@ impl<S: Stream> RequestConnection for RustConnection<S> {
         self.prefetch_maximum_request_bytes_impl(&mut max_bytes);
     }
 
+    fn enable_big_requests(&self) -> Result<usize, ConnectionError> {
+        {
+            let mut max_bytes = self.maximum_request_bytes.lock().unwrap();
+            if let MaxRequestBytes::Unknown = *max_bytes {
+                // Unlike prefetch_maximum_request_bytes_impl(), report errors to the caller
+                let request = match self.send_bigreq_enable() {
+                    Ok(sequence) => Some(sequence),
+                    Err(ConnectionError::UnsupportedExtension) => None,
+                    Err(e) => return Err(e),
+                };
+                *max_bytes = MaxRequestBytes::Requested(request);
+            }
+        }
+        Ok(self.maximum_request_bytes())
+    }
+
+    fn maximum_request_bytes_cached(&self) -> Option<usize> {
+        match *self.maximum_request_bytes.lock().unwrap() {
+            MaxRequestBytes::Known(length) => Some(length),
+            _ => None,
+        }
+    }
+
     fn parse_error(&self, error: &[u8]) -> Result<crate::x11_utils::X11Error, ParseError> {
         let ext_mgr = self.extension_manager.lock().unwrap();
         crate::x11_utils::X11Error::try_parse(error, &*ext_mgr)
@@ -718,6 +977,32 @@ impl<S: Stream> Connection for RustConnection<S> {
         }
     }
 
+    fn wait_for_raw_event_with_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<Option<Vec<u8>>, ConnectionError> {
+        let deadline = match Instant::now().checked_add(timeout) {
+            Some(deadline) => deadline,
+            // The timeout is so large that it cannot elapse
+            None => return self.wait_for_raw_event().map(Some),
+        };
+        let mut inner = self.inner.lock().unwrap();
+        loop {
+            if let Some((event, _)) = inner.inner.poll_for_event_with_sequence() {
+                return Ok(Some(event));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                // Check the stream once more without blocking, e.g. for a zero timeout
+                inner = self.read_packet_and_enqueue(inner, BlockingMode::NonBlocking)?;
+                let event = inner.inner.poll_for_event_with_sequence();
+                return Ok(event.map(|(event, _)| event));
+            }
+            let mode = BlockingMode::Timeout(deadline - now);
+            inner = self.read_packet_and_enqueue(inner, mode)?;
+        }
+    }
+
     fn flush(&self) -> Result<(), ConnectionError> {
         let inner = self.inner.lock().unwrap();
         let _inner = self.flush_impl(inner)?;
@@ -749,6 +1034,22 @@ impl<S: Stream> Connection for RustConnection<S> {
             }
         }
     }
+
+    fn outstanding_requests(&self) -> usize {
+        self.outstanding_requests.count()
+    }
+
+    fn has_error(&self) -> Option<ConnectionError> {
+        // Errors are reported by the individual operations and do not break the connection
+        None
+    }
+}
+
+impl<S: Stream> std::fmt::Display for RustConnection<S> {
+    /// Print a summary of the X11 server, see [`SetupSummary`](crate::connection::SetupSummary).
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        crate::connection::SetupSummary(self.setup()).fmt(f)
+    }
 }
 
 /// Call `notify_all` on a condition variable when dropped.
@@ -760,3 +1061,28 @@ impl Drop for NotifyOnDrop<'_> {
         self.0.notify_all();
     }
 }
+
+/// Get the time until `deadline`, or a `TimedOut` error if it already passed.
+fn remaining_time(deadline: Instant) -> std::io::Result<Duration> {
+    let now = Instant::now();
+    if now >= deadline {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            "timeout while connecting to the X11 server",
+        ))
+    } else {
+        Ok(deadline - now)
+    }
+}
+
+/// Poll the stream, but give up with a `TimedOut` error once the deadline (if any) passed.
+fn poll_until(
+    stream: &impl Stream,
+    mode: PollMode,
+    deadline: Option<Instant>,
+) -> std::io::Result<()> {
+    match deadline {
+        Some(deadline) => stream.poll_with_timeout(mode, remaining_time(deadline)?),
+        None => stream.poll(mode),
+    }
+}
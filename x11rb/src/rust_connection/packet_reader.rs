@@ -109,6 +109,7 @@ mod tests {
     use std::cell::RefCell;
     use std::cmp;
     use std::io::{Error, ErrorKind, Result};
+    use std::time::Duration;
 
     // make a Stream that just reads from a Vec<u8>
     struct TestStream {
@@ -140,6 +141,10 @@ mod tests {
             Ok(())
         }
 
+        fn poll_with_timeout(&self, mode: PollMode, _timeout: Duration) -> Result<()> {
+            self.poll(mode)
+        }
+
         fn write(&self, _: &[u8], _: &mut Vec<RawFdContainer>) -> Result<usize> {
             unreachable!()
         }
@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::io::{Error, ErrorKind};
+use std::time::{Duration, Instant};
+
+const ONE_SECOND: Duration = Duration::from_secs(1);
+
+/// Limits for sending requests via a [`RustConnection`](super::RustConnection).
+///
+/// Limiting requests helps to not flood a slow X11 server, e.g. one that is reached over a
+/// high-latency network link. All limits are disabled by default.
+///
+/// The limits do not apply to the `QueryExtension` and `BIG-REQUESTS` `Enable` requests that the
+/// connection sends internally when setting up extensions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestLimits {
+    /// The maximum number of requests that are sent within one second.
+    ///
+    /// When this limit is reached, sending a request blocks until enough time has passed. A limit
+    /// of zero is treated like a limit of one.
+    pub max_requests_per_second: Option<u32>,
+
+    /// The maximum number of requests with a reply whose reply was not yet handled.
+    ///
+    /// See [`Connection::outstanding_requests`](crate::connection::Connection::outstanding_requests).
+    /// When this limit is reached, sending another request with a reply fails with an
+    /// [`std::io::ErrorKind::WouldBlock`] I/O error. The request can be sent again after some
+    /// replies were handled. Blocking is not an option here, since usually the thread that sends
+    /// requests is also the one that handles their replies.
+    pub max_outstanding_requests: Option<usize>,
+}
+
+/// Enforcement of [`RequestLimits`].
+#[derive(Debug, Default)]
+pub(crate) struct RateLimiter {
+    limits: RequestLimits,
+    // The times at which the requests of the last second were sent, oldest first
+    recent: VecDeque<Instant>,
+}
+
+impl RateLimiter {
+    /// Get the current limits.
+    pub(crate) fn limits(&self) -> RequestLimits {
+        self.limits
+    }
+
+    /// Change the limits.
+    pub(crate) fn set_limits(&mut self, limits: RequestLimits) {
+        self.limits = limits;
+        if limits.max_requests_per_second.is_none() {
+            self.recent.clear();
+        }
+    }
+
+    /// Check whether another request with a reply may be sent.
+    pub(crate) fn check_outstanding(&self, outstanding: usize) -> Result<(), Error> {
+        match self.limits.max_outstanding_requests {
+            Some(max) if outstanding >= max => Err(Error::new(
+                ErrorKind::WouldBlock,
+                "Too many requests are waiting for their reply",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check whether another request may be sent at time `now`.
+    ///
+    /// If the request may be sent, it is recorded and `None` is returned. Otherwise, the time to
+    /// wait before checking again is returned.
+    pub(crate) fn check_rate(&mut self, now: Instant) -> Option<Duration> {
+        let max = match self.limits.max_requests_per_second {
+            Some(max) => usize::try_from(max).unwrap_or(usize::max_value()).max(1),
+            None => return None,
+        };
+        while let Some(&oldest) = self.recent.front() {
+            if now.saturating_duration_since(oldest) < ONE_SECOND {
+                break;
+            }
+            let _ = self.recent.pop_front();
+        }
+        if self.recent.len() >= max {
+            let oldest = self.recent[self.recent.len() - max];
+            return Some(ONE_SECOND - now.saturating_duration_since(oldest));
+        }
+        self.recent.push_back(now);
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::ErrorKind;
+    use std::time::{Duration, Instant};
+
+    use super::{RateLimiter, RequestLimits};
+
+    #[test]
+    fn disabled_by_default() {
+        let mut limiter = RateLimiter::default();
+        let now = Instant::now();
+        for _ in 0..1000 {
+            assert_eq!(limiter.check_rate(now), None);
+        }
+        assert!(limiter.check_outstanding(1000).is_ok());
+    }
+
+    #[test]
+    fn requests_per_second() {
+        let mut limiter = RateLimiter::default();
+        limiter.set_limits(RequestLimits {
+            max_requests_per_second: Some(2),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        assert_eq!(limiter.check_rate(start), None);
+        assert_eq!(limiter.check_rate(start + Duration::from_millis(100)), None);
+        assert_eq!(
+            limiter.check_rate(start + Duration::from_millis(400)),
+            Some(Duration::from_millis(600))
+        );
+        // After a second, the first request no longer counts
+        assert_eq!(
+            limiter.check_rate(start + Duration::from_millis(1000)),
+            None
+        );
+        assert_eq!(
+            limiter.check_rate(start + Duration::from_millis(1000)),
+            Some(Duration::from_millis(100))
+        );
+    }
+
+    #[test]
+    fn outstanding_requests() {
+        let mut limiter = RateLimiter::default();
+        limiter.set_limits(RequestLimits {
+            max_outstanding_requests: Some(3),
+            ..Default::default()
+        });
+        assert!(limiter.check_outstanding(2).is_ok());
+        let error = limiter.check_outstanding(3).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::WouldBlock);
+    }
+}
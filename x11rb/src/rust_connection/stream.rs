@@ -1,5 +1,5 @@
 use std::io::{IoSlice, IoSliceMut, Result};
-use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+use std::net::{Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs};
 #[cfg(any(target_os = "linux", target_os = "android"))]
 use std::os::unix::ffi::OsStrExt as _;
 #[cfg(unix)]
@@ -8,8 +8,9 @@ use std::os::unix::io::{AsRawFd, IntoRawFd, RawFd};
 use std::os::unix::net::UnixStream;
 #[cfg(windows)]
 use std::os::windows::io::{AsRawSocket, IntoRawSocket, RawSocket};
+use std::time::Duration;
 
-use crate::utils::RawFdContainer;
+use crate::utils::{poll_timeout_millis, RawFdContainer};
 use x11rb_protocol::parse_display::ConnectAddress;
 use x11rb_protocol::xauth::Family;
 
@@ -66,6 +67,14 @@ pub trait Stream {
     /// `read` is `true`) or writable (when `write` is `true`).
     fn poll(&self, mode: PollMode) -> Result<()>;
 
+    /// Waits for level-triggered read and/or write events on the stream, but at most for the
+    /// given duration.
+    ///
+    /// This function works like [`Stream::poll`], but also returns when the timeout elapsed.
+    /// Callers cannot distinguish a timeout from a spurious wakeup and have to check the time
+    /// themselves.
+    fn poll_with_timeout(&self, mode: PollMode, timeout: Duration) -> Result<()>;
+
     /// Read some bytes and FDs from this reader without blocking, returning how many bytes
     /// were read.
     ///
@@ -192,10 +201,26 @@ enum DefaultStreamInner {
 impl DefaultStream {
     /// Try to connect to the X11 server described by the given arguments.
     pub fn connect(addr: ConnectAddress<'_>) -> Result<Self> {
+        Self::connect_impl(addr, None)
+    }
+
+    /// Try to connect to the X11 server described by the given arguments, but give up after the
+    /// given timeout.
+    ///
+    /// The timeout only applies to establishing a TCP connection. Connecting to a Unix domain
+    /// socket does not block for a significant time.
+    pub fn connect_timeout(addr: ConnectAddress<'_>, timeout: Duration) -> Result<Self> {
+        Self::connect_impl(addr, Some(timeout))
+    }
+
+    fn connect_impl(addr: ConnectAddress<'_>, timeout: Option<Duration>) -> Result<Self> {
         match addr {
             ConnectAddress::Hostname(host, port) => {
                 // connect over TCP
-                let stream = TcpStream::connect((host, port))?;
+                let stream = match timeout {
+                    Some(timeout) => connect_tcp_timeout(host, port, timeout)?,
+                    None => TcpStream::connect((host, port))?,
+                };
                 Self::from_tcp_stream(stream)
             }
             #[cfg(unix)]
@@ -386,8 +411,9 @@ fn do_write(
     Ok(res)
 }
 
-impl Stream for DefaultStream {
-    fn poll(&self, mode: PollMode) -> Result<()> {
+impl DefaultStream {
+    /// Poll the stream, using `timeout` in milliseconds or `-1` for no timeout.
+    fn poll_impl(&self, mode: PollMode, timeout: i32) -> Result<()> {
         #[cfg(unix)]
         {
             use nix::poll::{poll, PollFd, PollFlags};
@@ -402,7 +428,7 @@ impl Stream for DefaultStream {
             let fd = self.as_raw_fd();
             let mut poll_fds = [PollFd::new(fd, poll_flags)];
             loop {
-                match poll(&mut poll_fds, -1) {
+                match poll(&mut poll_fds, timeout) {
                     Ok(_) => break,
                     Err(nix::Error::EINTR) => {}
                     Err(e) => return Err(e.into()),
@@ -429,11 +455,21 @@ impl Stream for DefaultStream {
                 events,
                 revents: 0,
             }];
-            let _ = wsa_poll(&mut poll_fds, -1)?;
+            let _ = wsa_poll(&mut poll_fds, timeout)?;
             // Let the errors (POLLERR) be handled when trying to read or write.
             Ok(())
         }
     }
+}
+
+impl Stream for DefaultStream {
+    fn poll(&self, mode: PollMode) -> Result<()> {
+        self.poll_impl(mode, -1)
+    }
+
+    fn poll_with_timeout(&self, mode: PollMode, timeout: Duration) -> Result<()> {
+        self.poll_impl(mode, poll_timeout_millis(timeout))
+    }
 
     fn read(&self, buf: &mut [u8], fd_storage: &mut Vec<RawFdContainer>) -> Result<usize> {
         #[cfg(unix)]
@@ -548,6 +584,24 @@ impl Stream for DefaultStream {
     }
 }
 
+/// Connect to the first address that `host` resolves to and that accepts a connection within
+/// `timeout`.
+fn connect_tcp_timeout(host: &str, port: u16, timeout: Duration) -> Result<TcpStream> {
+    let mut error = None;
+    for addr in (host, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => error = Some(e),
+        }
+    }
+    Err(error.unwrap_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "could not resolve to any addresses",
+        )
+    }))
+}
+
 #[cfg(any(target_os = "linux", target_os = "android"))]
 fn connect_abstract_unix_stream(path: &[u8]) -> nix::Result<RawFdContainer> {
     use nix::fcntl::{fcntl, FcntlArg, OFlag};
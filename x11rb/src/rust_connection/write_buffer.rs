@@ -4,25 +4,64 @@ use std::io::IoSlice;
 use super::Stream;
 use crate::utils::RawFdContainer;
 
+/// The default capacity of the write buffer of a `RustConnection`.
+///
+/// This size was chosen by checking what libxcb does.
+pub const DEFAULT_WRITE_BUFFER_CAPACITY: usize = 16384;
+
 #[derive(Debug)]
 pub(super) struct WriteBuffer {
     data_buf: VecDeque<u8>,
     fd_buf: Vec<RawFdContainer>,
+    // The capacity of data_buf that is actually used. VecDeque might allocate more than requested.
+    capacity: usize,
+    // The number of active corks. While this is non-zero, writes never go to the stream.
+    corks: usize,
 }
 
 impl WriteBuffer {
     pub(super) fn new() -> Self {
-        // Buffer size chosen by checking what libxcb does
-        Self::with_capacity(16384)
+        Self::with_capacity(DEFAULT_WRITE_BUFFER_CAPACITY)
     }
 
-    fn with_capacity(capacity: usize) -> Self {
+    pub(super) fn with_capacity(capacity: usize) -> Self {
         Self {
             data_buf: VecDeque::with_capacity(capacity),
             fd_buf: Vec::new(),
+            capacity,
+            corks: 0,
         }
     }
 
+    /// Buffer all writes, no matter how large, until [`WriteBuffer::uncork`] is called.
+    ///
+    /// Explicit flushes still write the buffered data.
+    pub(super) fn cork(&mut self) {
+        self.corks += 1;
+    }
+
+    /// Undo a call to [`WriteBuffer::cork`].
+    ///
+    /// Returns `true` if the buffer is not corked anymore.
+    pub(super) fn uncork(&mut self) -> bool {
+        self.corks -= 1;
+        self.corks == 0
+    }
+
+    /// Change the capacity of the buffer.
+    ///
+    /// Data that is already buffered is kept, even if it exceeds the new capacity. It is written
+    /// with the next write or flush.
+    pub(super) fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        let additional = capacity.saturating_sub(self.data_buf.len());
+        self.data_buf.reserve(additional);
+    }
+
+    fn available(&self) -> usize {
+        self.capacity.saturating_sub(self.data_buf.len())
+    }
+
     fn flush_buffer(&mut self, stream: &impl Stream) -> std::io::Result<()> {
         while self.needs_flush() {
             let (data_buf_1, data_buf_2) = self.data_buf.as_slices();
@@ -66,14 +105,19 @@ impl WriteBuffer {
     {
         self.fd_buf.append(fds);
 
+        if self.corks > 0 {
+            write_buffer(&mut self.data_buf);
+            return Ok(to_write_length);
+        }
+
         // Is there enough buffer space left for this write?
-        if (self.data_buf.capacity() - self.data_buf.len()) < to_write_length {
+        if self.available() < to_write_length {
             // Not enough space, try to flush
             match self.flush_buffer(stream) {
                 Ok(_) => {}
                 Err(e) => {
                     if e.kind() == std::io::ErrorKind::WouldBlock {
-                        let available_buf = self.data_buf.capacity() - self.data_buf.len();
+                        let available_buf = self.available();
                         if available_buf == 0 {
                             // Buffer filled and cannot flush anything without
                             // blocking, so return `WouldBlock`.
@@ -92,7 +136,7 @@ impl WriteBuffer {
             }
         }
 
-        if to_write_length >= self.data_buf.capacity() {
+        if to_write_length > 0 && to_write_length >= self.capacity {
             // Write is larger than the buffer capacity, thus we just flushed the buffer. This
             // means that at this point the buffer is empty. Write directly to self.inner. No data
             // is copied into the buffer, since that would just mean that the large write gets
@@ -161,6 +205,9 @@ impl WriteBuffer {
 mod test {
     use std::io::{Error, ErrorKind, IoSlice, Result};
 
+    use std::cell::RefCell;
+    use std::time::Duration;
+
     use super::super::{PollMode, Stream};
     use super::WriteBuffer;
     use crate::utils::RawFdContainer;
@@ -172,6 +219,10 @@ mod test {
             unimplemented!();
         }
 
+        fn poll_with_timeout(&self, _mode: PollMode, _timeout: Duration) -> Result<()> {
+            unimplemented!();
+        }
+
         fn read(&self, _buf: &mut [u8], _fd_storage: &mut Vec<RawFdContainer>) -> Result<usize> {
             unimplemented!();
         }
@@ -206,4 +257,143 @@ mod test {
             Err(e) => panic!("Unexpected error: {:?}", e),
         }
     }
+
+    /// A stream that records each call to `write`.
+    #[derive(Default)]
+    struct RecordingWriter(RefCell<Vec<Vec<u8>>>);
+
+    impl Stream for RecordingWriter {
+        fn poll(&self, _mode: PollMode) -> Result<()> {
+            unimplemented!();
+        }
+
+        fn poll_with_timeout(&self, _mode: PollMode, _timeout: Duration) -> Result<()> {
+            unimplemented!();
+        }
+
+        fn read(&self, _buf: &mut [u8], _fd_storage: &mut Vec<RawFdContainer>) -> Result<usize> {
+            unimplemented!();
+        }
+
+        fn write(&self, buf: &[u8], _fds: &mut Vec<RawFdContainer>) -> Result<usize> {
+            self.0.borrow_mut().push(buf.to_vec());
+            Ok(buf.len())
+        }
+    }
+
+    impl RecordingWriter {
+        fn take(&self) -> Vec<Vec<u8>> {
+            std::mem::take(&mut *self.0.borrow_mut())
+        }
+    }
+
+    #[test]
+    fn small_writes_are_buffered() {
+        let stream = RecordingWriter::default();
+        let mut write_buffer = WriteBuffer::with_capacity(8);
+        assert_eq!(
+            write_buffer
+                .write(&stream, b"abc", &mut Vec::new())
+                .unwrap(),
+            3
+        );
+        assert_eq!(
+            write_buffer
+                .write(&stream, b"defg", &mut Vec::new())
+                .unwrap(),
+            4
+        );
+        assert!(stream.take().is_empty());
+        assert!(write_buffer.needs_flush());
+
+        write_buffer.flush(&stream).unwrap();
+        assert_eq!(stream.take(), vec![b"abcdefg".to_vec()]);
+        assert!(!write_buffer.needs_flush());
+    }
+
+    #[test]
+    fn full_buffer_is_flushed() {
+        let stream = RecordingWriter::default();
+        let mut write_buffer = WriteBuffer::with_capacity(8);
+        let _ = write_buffer
+            .write(&stream, b"abcde", &mut Vec::new())
+            .unwrap();
+        // Exactly fills the buffer
+        let _ = write_buffer
+            .write(&stream, b"fgh", &mut Vec::new())
+            .unwrap();
+        assert!(stream.take().is_empty());
+        // Does not fit anymore, so the buffered data is written first
+        let _ = write_buffer.write(&stream, b"i", &mut Vec::new()).unwrap();
+        assert_eq!(stream.take(), vec![b"abcdefgh".to_vec()]);
+
+        write_buffer.flush(&stream).unwrap();
+        assert_eq!(stream.take(), vec![b"i".to_vec()]);
+    }
+
+    #[test]
+    fn large_writes_bypass_buffer() {
+        let stream = RecordingWriter::default();
+        let mut write_buffer = WriteBuffer::with_capacity(8);
+        let _ = write_buffer.write(&stream, b"ab", &mut Vec::new()).unwrap();
+        let bufs = &[IoSlice::new(b"cdef"), IoSlice::new(b"ghij")];
+        assert_eq!(
+            write_buffer
+                .write_vectored(&stream, bufs, &mut Vec::new())
+                .unwrap(),
+            4
+        );
+        // The default write_vectored() only writes the first buffer
+        assert_eq!(stream.take(), vec![b"ab".to_vec(), b"cdef".to_vec()]);
+        assert!(!write_buffer.needs_flush());
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let stream = RecordingWriter::default();
+        let mut write_buffer = WriteBuffer::with_capacity(8);
+        let _ = write_buffer
+            .write(&stream, b"abc", &mut Vec::new())
+            .unwrap();
+        write_buffer.set_capacity(0);
+        assert!(write_buffer.needs_flush());
+        let _ = write_buffer.write(&stream, b"d", &mut Vec::new()).unwrap();
+        let _ = write_buffer.write(&stream, b"e", &mut Vec::new()).unwrap();
+        assert_eq!(
+            stream.take(),
+            vec![b"abc".to_vec(), b"d".to_vec(), b"e".to_vec()]
+        );
+        assert!(!write_buffer.needs_flush());
+    }
+
+    #[test]
+    fn corked_writes_are_buffered() {
+        let stream = RecordingWriter::default();
+        let mut write_buffer = WriteBuffer::with_capacity(4);
+        write_buffer.cork();
+        write_buffer.cork();
+        let _ = write_buffer
+            .write(&stream, b"abc", &mut Vec::new())
+            .unwrap();
+        // Neither a full buffer nor a large write cause a write to the stream
+        let bufs = &[IoSlice::new(b"defg"), IoSlice::new(b"hij")];
+        assert_eq!(
+            write_buffer
+                .write_vectored(&stream, bufs, &mut Vec::new())
+                .unwrap(),
+            7
+        );
+        assert!(stream.take().is_empty());
+
+        assert!(!write_buffer.uncork());
+        assert!(write_buffer.uncork());
+        write_buffer.flush(&stream).unwrap();
+        assert_eq!(stream.take(), vec![b"abcdefghij".to_vec()]);
+
+        // Afterwards, the capacity is respected again
+        let _ = write_buffer
+            .write(&stream, b"klmno", &mut Vec::new())
+            .unwrap();
+        assert_eq!(stream.take(), vec![b"klmno".to_vec()]);
+    }
 }
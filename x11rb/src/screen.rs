@@ -0,0 +1,76 @@
+//! Utility functions for working with the screens of an X11 server.
+//!
+//! Usage example:
+//! ```no_run
+//! use x11rb::connection::Connection;
+//! use x11rb::screen::screen_dpi;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let (conn, screen_num) = x11rb::connect(None)?;
+//!     let (dpi_x, dpi_y) = screen_dpi(&conn.setup().roots[screen_num]);
+//!     println!("The screen has {}x{} DPI", dpi_x, dpi_y);
+//!     Ok(())
+//! }
+//! ```
+
+use crate::protocol::xproto::Screen;
+
+/// The DPI that is assumed when the X11 server does not report a physical screen size.
+pub const DEFAULT_DPI: f64 = 96.0;
+
+/// The number of millimeters per inch.
+const MM_PER_INCH: f64 = 25.4;
+
+/// Compute the horizontal and vertical DPI of a screen.
+///
+/// The DPI is derived from the size of the screen in pixels and its size in millimeters. Some
+/// drivers report a size of zero millimeters. In this case, [`DEFAULT_DPI`] is used for the
+/// affected direction.
+pub fn screen_dpi(screen: &Screen) -> (f64, f64) {
+    (
+        dpi(screen.width_in_pixels, screen.width_in_millimeters),
+        dpi(screen.height_in_pixels, screen.height_in_millimeters),
+    )
+}
+
+fn dpi(pixels: u16, millimeters: u16) -> f64 {
+    if millimeters == 0 {
+        DEFAULT_DPI
+    } else {
+        f64::from(pixels) * MM_PER_INCH / f64::from(millimeters)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{screen_dpi, DEFAULT_DPI};
+    use crate::protocol::xproto::Screen;
+
+    fn screen(width: (u16, u16), height: (u16, u16)) -> Screen {
+        Screen {
+            width_in_pixels: width.0,
+            width_in_millimeters: width.1,
+            height_in_pixels: height.0,
+            height_in_millimeters: height.1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn dpi_from_size() {
+        let (x, y) = screen_dpi(&screen((1920, 508), (1080, 254)));
+        assert!((x - 96.0).abs() < 1e-9, "{}", x);
+        assert!((y - 108.0).abs() < 1e-9, "{}", y);
+    }
+
+    #[test]
+    fn dpi_zero_millimeters() {
+        assert_eq!(
+            screen_dpi(&screen((1920, 0), (1080, 0))),
+            (DEFAULT_DPI, DEFAULT_DPI)
+        );
+        let (x, y) = screen_dpi(&screen((1920, 0), (1080, 254)));
+        assert_eq!(x, DEFAULT_DPI);
+        assert!((y - 108.0).abs() < 1e-9, "{}", y);
+    }
+}
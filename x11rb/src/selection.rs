@@ -0,0 +1,645 @@
+//! Utilities for working with selections.
+//!
+//! A client becomes the owner of a selection with `SetSelectionOwner`. It remains the owner until
+//! another client takes over the selection, at which point the X11 server sends a
+//! `SelectionClear` event. [`SelectionOwner`] keeps track of this lifecycle and answers
+//! `SelectionRequest` events from other clients.
+//!
+//! The other side is [`request_selection`], which asks the owner of a selection for its contents.
+//!
+//! Contents that are too large for a single request are transferred in chunks with the `INCR`
+//! protocol from the ICCCM. Both sides support this.
+//!
+//! Usage example:
+//! ```no_run
+//! use x11rb::connection::Connection;
+//! use x11rb::protocol::xproto::AtomEnum;
+//! use x11rb::protocol::Event;
+//! use x11rb::selection::{SelectionData, SelectionOwner};
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let (conn, _) = x11rb::connect(None)?;
+//!     # let (window, timestamp) = (0, 0);
+//!     // `window` is a window of this client, `timestamp` is e.g. from a recent event
+//!     let mut owner = match SelectionOwner::acquire(&conn, window, AtomEnum::PRIMARY, timestamp)? {
+//!         Some(owner) => owner,
+//!         None => return Err("Could not acquire the selection".into()),
+//!     };
+//!     owner.set_lost_callback(|_| println!("Another client took over the selection"));
+//!     while !owner.is_lost() {
+//!         let event = conn.wait_for_event()?;
+//!         owner.handle_event(&event);
+//!         match event {
+//!             Event::SelectionRequest(event) => {
+//!                 let _ = owner.handle_selection_request(&conn, &event, |target| {
+//!                     // Only provide the contents for the STRING target
+//!                     if target == u32::from(AtomEnum::STRING) {
+//!                         Some(SelectionData::new(AtomEnum::STRING, 8, b"Hello".to_vec()))
+//!                     } else {
+//!                         None
+//!                     }
+//!                 })?;
+//!             }
+//!             Event::PropertyNotify(event) => {
+//!                 let _ = owner.handle_property_notify(&conn, &event)?;
+//!             }
+//!             _ => {}
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Getting the contents of a selection:
+//! ```no_run
+//! use x11rb::event_loop_integration::EventQueue;
+//! use x11rb::protocol::xproto::AtomEnum;
+//! use x11rb::selection::request_selection;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let (conn, _) = x11rb::connect(None)?;
+//!     # let (window, timestamp) = (0, 0);
+//!     // `window` is a window of this client that selects PropertyChange events
+//!     let mut queue = EventQueue::new(&conn);
+//!     let data = request_selection(
+//!         &mut queue,
+//!         window,
+//!         AtomEnum::PRIMARY,
+//!         AtomEnum::STRING,
+//!         AtomEnum::STRING,
+//!         timestamp,
+//!     )?;
+//!     match data {
+//!         Some(data) => println!("{}", String::from_utf8_lossy(&data.data)),
+//!         None => println!("The selection could not be converted to a string"),
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::connection::{Connection, RequestConnection};
+use crate::cookie::VoidCookie;
+use crate::errors::{ConnectionError, ReplyError};
+use crate::event_loop_integration::EventQueue;
+use crate::protocol::xproto::{
+    self, Atom, AtomEnum, ChangeWindowAttributesAux, EventMask, GetPropertyReply, PropMode,
+    Property, PropertyNotifyEvent, SelectionClearEvent, SelectionNotifyEvent,
+    SelectionRequestEvent, Timestamp, Window,
+};
+use crate::protocol::Event;
+use crate::wrapper::ConnectionExt as _;
+use crate::{CURRENT_TIME, NONE};
+
+/// The contents of a selection, converted to some target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectionData {
+    /// The type of the data, e.g. `STRING` or `UTF8_STRING`.
+    pub type_: Atom,
+    /// The size of each item in the data in bits. This is 8, 16, or 32.
+    pub format: u8,
+    /// The data. For formats 16 and 32, the items are in native byte order.
+    pub data: Vec<u8>,
+}
+
+impl SelectionData {
+    /// Create new selection data.
+    pub fn new(type_: impl Into<Atom>, format: u8, data: Vec<u8>) -> Self {
+        Self {
+            type_: type_.into(),
+            format,
+            data,
+        }
+    }
+
+    /// Check that the format is valid and the data consists of whole items.
+    fn is_valid(&self) -> bool {
+        match self.format {
+            8 | 16 | 32 => self.data.len() % usize::from(self.format / 8) == 0,
+            _ => false,
+        }
+    }
+}
+
+/// An `INCR` transfer that is in progress.
+#[derive(Debug)]
+struct IncrTransfer {
+    requestor: Window,
+    property: Atom,
+    data: SelectionData,
+    // How much of the data was already sent
+    offset: usize,
+    chunk_size: usize,
+}
+
+type LostCallback = Box<dyn FnMut(&SelectionClearEvent)>;
+
+/// The owner side of a selection.
+///
+/// This tracks whether ownership of a selection was lost to another client based on
+/// `SelectionClear` events. `SelectionClear` events that are older than the acquisition of the
+/// selection are ignored, since they refer to an earlier period of ownership.
+pub struct SelectionOwner {
+    window: Window,
+    selection: Atom,
+    timestamp: Timestamp,
+    lost: bool,
+    lost_callback: Option<LostCallback>,
+    incr_atom: Option<Atom>,
+    transfers: Vec<IncrTransfer>,
+}
+
+impl fmt::Debug for SelectionOwner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SelectionOwner")
+            .field("window", &self.window)
+            .field("selection", &self.selection)
+            .field("timestamp", &self.timestamp)
+            .field("lost", &self.lost)
+            .field("lost_callback", &self.lost_callback.is_some())
+            .field("transfers", &self.transfers)
+            .finish()
+    }
+}
+
+impl SelectionOwner {
+    /// Try to become the owner of a selection.
+    ///
+    /// This sends a `SetSelectionOwner` request and then checks with `GetSelectionOwner` whether
+    /// the request was successful. Returns `None` if the given window did not become the owner.
+    ///
+    /// ICCCM requires `timestamp` to be the timestamp of the event that triggered the acquisition
+    /// and not `CurrentTime`. With `CurrentTime`, stale `SelectionClear` events cannot be
+    /// detected.
+    pub fn acquire<C>(
+        conn: &C,
+        window: Window,
+        selection: impl Into<Atom>,
+        timestamp: Timestamp,
+    ) -> Result<Option<Self>, ReplyError>
+    where
+        C: RequestConnection + ?Sized,
+    {
+        let selection = selection.into();
+        let _ = xproto::set_selection_owner(conn, window, selection, timestamp)?;
+        let owner = xproto::get_selection_owner(conn, selection)?.reply()?.owner;
+        if owner != window {
+            return Ok(None);
+        }
+        Ok(Some(Self::new(window, selection, timestamp)))
+    }
+
+    fn new(window: Window, selection: Atom, timestamp: Timestamp) -> Self {
+        Self {
+            window,
+            selection,
+            timestamp,
+            lost: false,
+            lost_callback: None,
+            incr_atom: None,
+            transfers: Vec::new(),
+        }
+    }
+
+    /// Get the window that owns the selection.
+    pub fn window(&self) -> Window {
+        self.window
+    }
+
+    /// Get the selection that is owned.
+    pub fn selection(&self) -> Atom {
+        self.selection
+    }
+
+    /// Get the timestamp at which the selection was acquired.
+    ///
+    /// This timestamp should be used for the `TIMESTAMP` target.
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    /// Check whether ownership of the selection was lost.
+    pub fn is_lost(&self) -> bool {
+        self.lost
+    }
+
+    /// Set a callback that is called when ownership of the selection is lost.
+    ///
+    /// The callback is called at most once from [`SelectionOwner::handle_event`].
+    pub fn set_lost_callback(&mut self, callback: impl FnMut(&SelectionClearEvent) + 'static) {
+        self.lost_callback = Some(Box::new(callback));
+    }
+
+    /// Handle an event.
+    ///
+    /// Returns `true` if the event is a `SelectionClear` event that causes the loss of the
+    /// selection. In this case, the lost callback is called. All other events are ignored.
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::SelectionClear(event) => self.handle_selection_clear(event),
+            _ => false,
+        }
+    }
+
+    /// Handle a `SelectionClear` event.
+    ///
+    /// See [`SelectionOwner::handle_event`].
+    pub fn handle_selection_clear(&mut self, event: &SelectionClearEvent) -> bool {
+        if self.lost
+            || event.owner != self.window
+            || event.selection != self.selection
+            || is_before(event.time, self.timestamp)
+        {
+            return false;
+        }
+        self.lost = true;
+        if let Some(callback) = self.lost_callback.as_mut() {
+            callback(event);
+        }
+        true
+    }
+
+    /// Answer a `SelectionRequest` event.
+    ///
+    /// `data` is called with the requested target and provides the contents of the selection
+    /// converted to this target, or `None` if the target is not supported. The request is refused
+    /// without calling `data` if it is not for this selection, if ownership was lost, or if it is
+    /// older than the acquisition of the selection. Invalid data, e.g. with a format other than 8,
+    /// 16, or 32, also causes the request to be refused. In all cases, the requestor is notified
+    /// with a `SelectionNotify` event.
+    ///
+    /// Returns `true` if the request was answered with data. If the data does not fit into a
+    /// single request, it is transferred with the `INCR` protocol. This needs
+    /// [`SelectionOwner::handle_property_notify`] to be called for `PropertyNotify` events.
+    pub fn handle_selection_request<C>(
+        &mut self,
+        conn: &C,
+        event: &SelectionRequestEvent,
+        data: impl FnOnce(Atom) -> Option<SelectionData>,
+    ) -> Result<bool, ReplyError>
+    where
+        C: RequestConnection + ?Sized,
+    {
+        // Obsolete clients use None as the property, which means that the target should be used
+        let property = if event.property == NONE {
+            event.target
+        } else {
+            event.property
+        };
+        let refuse = self.lost
+            || event.owner != self.window
+            || event.selection != self.selection
+            || is_before(event.time, self.timestamp);
+        let data = if refuse { None } else { data(event.target) };
+        let answered = match data {
+            Some(data) if data.is_valid() => {
+                self.store(conn, event.requestor, property, data)?;
+                true
+            }
+            _ => false,
+        };
+        let notify = SelectionNotifyEvent {
+            response_type: xproto::SELECTION_NOTIFY_EVENT,
+            sequence: 0,
+            time: event.time,
+            requestor: event.requestor,
+            selection: event.selection,
+            target: event.target,
+            property: if answered { property } else { NONE },
+        };
+        let _ = xproto::send_event(conn, false, event.requestor, EventMask::NO_EVENT, notify)?;
+        Ok(answered)
+    }
+
+    /// Store the data in the property, or start an `INCR` transfer if it is too large.
+    fn store<C>(
+        &mut self,
+        conn: &C,
+        requestor: Window,
+        property: Atom,
+        data: SelectionData,
+    ) -> Result<(), ReplyError>
+    where
+        C: RequestConnection + ?Sized,
+    {
+        let chunk_size = chunk_size(conn.maximum_request_bytes());
+        if data.data.len() <= chunk_size {
+            let _ = store_property(conn, requestor, property, &data, &data.data)?;
+            return Ok(());
+        }
+
+        let incr = match self.incr_atom {
+            Some(atom) => atom,
+            None => {
+                let atom = xproto::intern_atom(conn, false, b"INCR")?.reply()?.atom;
+                self.incr_atom = Some(atom);
+                atom
+            }
+        };
+        // The requestor starts the transfer by deleting the property. This is reported via a
+        // PropertyNotify event.
+        let aux = ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE);
+        let _ = xproto::change_window_attributes(conn, requestor, &aux)?;
+        // The value of the INCR property is a lower bound on the size of the data
+        let size = u32::try_from(data.data.len()).unwrap_or(u32::MAX);
+        let _ = conn.change_property32(PropMode::REPLACE, requestor, property, incr, &[size])?;
+        self.transfers
+            .retain(|transfer| transfer.requestor != requestor || transfer.property != property);
+        self.transfers.push(IncrTransfer {
+            requestor,
+            property,
+            data,
+            offset: 0,
+            chunk_size,
+        });
+        Ok(())
+    }
+
+    /// Continue `INCR` transfers based on a `PropertyNotify` event.
+    ///
+    /// Every time the requestor deleted the property, the next chunk of the data is stored in it.
+    /// Finally, an empty property marks the end of the transfer. Returns `true` if the event
+    /// belonged to a transfer.
+    pub fn handle_property_notify<C>(
+        &mut self,
+        conn: &C,
+        event: &PropertyNotifyEvent,
+    ) -> Result<bool, ConnectionError>
+    where
+        C: RequestConnection + ?Sized,
+    {
+        if event.state != Property::DELETE {
+            return Ok(false);
+        }
+        let index = self.transfers.iter().position(|transfer| {
+            transfer.requestor == event.window && transfer.property == event.atom
+        });
+        let index = match index {
+            Some(index) => index,
+            None => return Ok(false),
+        };
+        let transfer = &mut self.transfers[index];
+        let end = transfer
+            .data
+            .data
+            .len()
+            .min(transfer.offset + transfer.chunk_size);
+        let chunk = &transfer.data.data[transfer.offset..end];
+        let _ = store_property(
+            conn,
+            transfer.requestor,
+            transfer.property,
+            &transfer.data,
+            chunk,
+        )?;
+        if chunk.is_empty() {
+            let _ = self.transfers.remove(index);
+        } else {
+            transfer.offset = end;
+        }
+        Ok(true)
+    }
+
+    /// Get the number of `INCR` transfers that are still in progress.
+    pub fn pending_transfers(&self) -> usize {
+        self.transfers.len()
+    }
+
+    /// Give up ownership of the selection.
+    ///
+    /// If ownership was not already lost, this sends a `SetSelectionOwner` request that sets the
+    /// owner to `None`. The server only performs this change if the selection was not acquired
+    /// by another client in the meantime.
+    pub fn release<C>(self, conn: &C) -> Result<(), ConnectionError>
+    where
+        C: RequestConnection + ?Sized,
+    {
+        if !self.lost {
+            let _ = xproto::set_selection_owner(conn, NONE, self.selection, self.timestamp)?;
+        }
+        Ok(())
+    }
+}
+
+/// Get the contents of a selection, converted to the given target.
+///
+/// This sends a `ConvertSelection` request that asks the owner of the selection to store its
+/// contents in `property` on `window`. After the owner answered with a `SelectionNotify` event,
+/// the property is read and deleted. Returns `None` if the selection has no owner or the owner
+/// refused the conversion. Other events that arrive in the meantime are kept in `queue`, including
+/// `SelectionNotify` events for other conversions, which are recognised by their target and time.
+///
+/// Large contents are transferred with the `INCR` protocol. For this, `window` has to select
+/// `PropertyChange` events. There is no timeout, so a misbehaving owner blocks this function
+/// forever.
+pub fn request_selection<C>(
+    queue: &mut EventQueue<'_, C>,
+    window: Window,
+    selection: impl Into<Atom>,
+    target: impl Into<Atom>,
+    property: impl Into<Atom>,
+    timestamp: Timestamp,
+) -> Result<Option<SelectionData>, ReplyError>
+where
+    C: Connection + ?Sized,
+{
+    let conn = queue.connection();
+    let selection = selection.into();
+    let target = target.into();
+    let incr = xproto::intern_atom(conn, false, b"INCR")?;
+    let _ = xproto::convert_selection(conn, window, selection, target, property, timestamp)?;
+    let incr = incr.reply()?.atom;
+
+    // The owner, or the server if there is none, copies the time of the ConvertSelection request
+    let notify = queue.wait_for_specific_event(|event| match event {
+        Event::SelectionNotify(event) => {
+            event.requestor == window
+                && event.selection == selection
+                && event.target == target
+                && event.time == timestamp
+        }
+        _ => false,
+    })?;
+    let property = match notify {
+        Event::SelectionNotify(event) => event.property,
+        _ => unreachable!("only SelectionNotify events are accepted"),
+    };
+    if property == NONE {
+        return Ok(None);
+    }
+
+    let reply = read_property(conn, window, property)?;
+    if reply.type_ != incr {
+        return Ok(Some(SelectionData::new(
+            reply.type_,
+            reply.format,
+            reply.value,
+        )));
+    }
+
+    // Deleting the INCR property started the transfer. The owner now stores each chunk in the
+    // property and waits for it to be deleted again. An empty chunk marks the end.
+    let mut result: Option<SelectionData> = None;
+    loop {
+        let _ = queue.wait_for_specific_event(|event| match event {
+            Event::PropertyNotify(event) => {
+                event.window == window
+                    && event.atom == property
+                    && event.state == Property::NEW_VALUE
+            }
+            _ => false,
+        })?;
+        let reply = read_property(conn, window, property)?;
+        // Skip events from before the transfer started, e.g. for the INCR property itself
+        if reply.type_ == NONE {
+            continue;
+        }
+        let data =
+            result.get_or_insert_with(|| SelectionData::new(reply.type_, reply.format, Vec::new()));
+        if reply.value.is_empty() {
+            return Ok(result);
+        }
+        data.data.extend_from_slice(&reply.value);
+    }
+}
+
+/// Read the complete value of a property and delete it.
+fn read_property<C>(
+    conn: &C,
+    window: Window,
+    property: Atom,
+) -> Result<GetPropertyReply, ReplyError>
+where
+    C: RequestConnection + ?Sized,
+{
+    xproto::get_property(conn, true, window, property, AtomEnum::ANY, 0, u32::MAX)?.reply()
+}
+
+/// Replace the value of a property with (a part of) the given data.
+fn store_property<'c, C>(
+    conn: &'c C,
+    window: Window,
+    property: Atom,
+    data: &SelectionData,
+    value: &[u8],
+) -> Result<VoidCookie<'c, C>, ConnectionError>
+where
+    C: RequestConnection + ?Sized,
+{
+    let items = value.len() / usize::from(data.format / 8);
+    // The value is at most as large as a request, so the number of items fits into an u32
+    let items = u32::try_from(items).expect("property value too large");
+    xproto::change_property(
+        conn,
+        PropMode::REPLACE,
+        window,
+        property,
+        data.type_,
+        data.format,
+        items,
+        value,
+    )
+}
+
+/// Get the number of bytes of property data that fit into a single `ChangeProperty` request.
+///
+/// The result is a non-zero multiple of four, so that it contains whole items for all formats.
+fn chunk_size(maximum_request_bytes: usize) -> usize {
+    // The request header is 24 bytes, plus four bytes if BIG-REQUESTS is used. An empty chunk
+    // would end the transfer, so at least one item of each format is sent.
+    (maximum_request_bytes.saturating_sub(28) & !3).max(4)
+}
+
+/// Check whether timestamp `a` is before timestamp `b`, taking wrap-around into account.
+///
+/// `CurrentTime` is not before any timestamp.
+fn is_before(a: Timestamp, b: Timestamp) -> bool {
+    if a == CURRENT_TIME || b == CURRENT_TIME {
+        return false;
+    }
+    // Timestamps wrap around after about 49.7 days; treat a as earlier if it is less than half
+    // of the range behind b.
+    a != b && b.wrapping_sub(a) < 1 << 31
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::{chunk_size, is_before, SelectionData, SelectionOwner};
+    use crate::protocol::xproto::{SelectionClearEvent, SelectionNotifyEvent};
+    use crate::protocol::Event;
+
+    fn clear(time: u32, owner: u32, selection: u32) -> Event {
+        Event::SelectionClear(SelectionClearEvent {
+            response_type: 29,
+            sequence: 0,
+            time,
+            owner,
+            selection,
+        })
+    }
+
+    #[test]
+    fn timestamps() {
+        assert!(is_before(1, 2));
+        assert!(!is_before(2, 1));
+        assert!(!is_before(2, 2));
+        assert!(is_before(0xffff_fff0, 5));
+        assert!(!is_before(5, 0xffff_fff0));
+        assert!(!is_before(0, 5));
+        assert!(!is_before(5, 0));
+    }
+
+    #[test]
+    fn selection_data_validity() {
+        assert!(SelectionData::new(1u32, 8, vec![1, 2, 3]).is_valid());
+        assert!(SelectionData::new(1u32, 16, vec![1, 2]).is_valid());
+        assert!(!SelectionData::new(1u32, 16, vec![1, 2, 3]).is_valid());
+        assert!(SelectionData::new(1u32, 32, vec![]).is_valid());
+        assert!(!SelectionData::new(1u32, 32, vec![1, 2]).is_valid());
+        assert!(!SelectionData::new(1u32, 0, vec![]).is_valid());
+        assert!(!SelectionData::new(1u32, 24, vec![1, 2, 3]).is_valid());
+    }
+
+    #[test]
+    fn chunk_sizes() {
+        assert_eq!(chunk_size(4 * 65535), 4 * 65535 - 28);
+        assert_eq!(chunk_size(1 << 20), (1 << 20) - 28);
+        assert_eq!(chunk_size(4097), 4068);
+        assert_eq!(chunk_size(32), 4);
+        assert_eq!(chunk_size(10), 4);
+        assert_eq!(chunk_size(0), 4);
+    }
+
+    #[test]
+    fn selection_lost() {
+        let calls = Rc::new(Cell::new(0));
+        let mut owner = SelectionOwner::new(1, 2, 100);
+        let calls2 = Rc::clone(&calls);
+        owner.set_lost_callback(move |event| {
+            assert_eq!(event.time, 150);
+            calls2.set(calls2.get() + 1);
+        });
+
+        // Unrelated events
+        assert!(!owner.handle_event(&Event::SelectionNotify(SelectionNotifyEvent::default())));
+        assert!(!owner.handle_event(&clear(150, 3, 2)));
+        assert!(!owner.handle_event(&clear(150, 1, 3)));
+        // A stale event
+        assert!(!owner.handle_event(&clear(50, 1, 2)));
+        assert!(!owner.is_lost());
+        assert_eq!(calls.get(), 0);
+
+        assert!(owner.handle_event(&clear(150, 1, 2)));
+        assert!(owner.is_lost());
+        assert_eq!(calls.get(), 1);
+
+        // The callback is only called once
+        assert!(!owner.handle_event(&clear(150, 1, 2)));
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -0,0 +1,354 @@
+//! Shared memory segments for transferring images via the `MIT-SHM` extension.
+//!
+//! A [`ShmSegment`] is a piece of memory that is mapped both into this process and into the X11
+//! server. Images can then be uploaded and downloaded without copying the pixel data through
+//! the X11 connection.
+//!
+//! With version 1.2 of the `MIT-SHM` extension, the segment is a POSIX shared memory object that
+//! is passed to the server via `ShmAttachFd`. Otherwise, a System V shared memory segment is
+//! attached via `ShmAttach`, which only works if the X11 server runs on the same machine.
+//!
+//! The code in this module is only available on Unix when the `shm` and the `allow-unsafe-code`
+//! features of the library are enabled.
+//!
+//! Usage example:
+//! ```no_run
+//! use x11rb::connection::Connection;
+//! use x11rb::protocol::xproto::{ConnectionExt, Gcontext};
+//! use x11rb::shm_segment::ShmSegment;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let (conn, screen_num) = x11rb::connect(None)?;
+//!     let screen = &conn.setup().roots[screen_num];
+//!     let (width, height) = (640, 480);
+//!     let mut segment = ShmSegment::new(&conn, u32::from(width) * u32::from(height) * 4)?;
+//!     // Safety: The segment was not used in any request yet, so the server does not write to it
+//!     for byte in unsafe { segment.data_mut()? } {
+//!         *byte = 0x80;
+//!     }
+//!
+//!     let gc: Gcontext = conn.generate_id()?;
+//!     conn.create_gc(gc, screen.root, &Default::default())?;
+//!     segment.put_image(screen.root, gc, width, height, screen.root_depth, 0, 0, 0)?;
+//!     conn.flush()?;
+//!     Ok(())
+//! }
+//! ```
+
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::ffi::CString;
+use std::io::{Error as IOError, ErrorKind};
+use std::os::unix::io::AsRawFd;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::connection::Connection;
+use crate::cookie::VoidCookie;
+use crate::errors::{ConnectionError, ReplyError, ReplyOrIdError};
+use crate::protocol::shm::{self, GetImageReply, Seg};
+use crate::protocol::xproto::{Drawable, Gcontext, ImageFormat};
+use crate::utils::RawFdContainer;
+use crate::wrapper::ConnectionExt as _;
+
+/// How the memory of a segment was mapped into this process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mapping {
+    /// Via `mmap()` of a POSIX shared memory object.
+    Mmap,
+    /// Via `shmat()` of a System V shared memory segment.
+    SysV,
+}
+
+/// A shared memory segment that is attached to the X11 server.
+///
+/// The segment is detached from the server and unmapped when this is dropped. This is safe even
+/// if the server did not yet process all requests using the segment: The server has its own
+/// mapping of the memory, which stays valid until it processes the `ShmDetach` request.
+///
+/// The server reads the segment asynchronously for [`put_image`](Self::put_image). Thus,
+/// [`data_mut`](Self::data_mut) waits until the server processed all requests before handing out
+/// the memory, so that an image is not modified while it is being uploaded.
+#[derive(Debug)]
+pub struct ShmSegment<'c, C: Connection> {
+    conn: &'c C,
+    seg: Seg,
+    ptr: *mut u8,
+    size: usize,
+    mapping: Mapping,
+    // Whether the server might still read from the segment
+    pending_read: Cell<bool>,
+}
+
+impl<'c, C: Connection> ShmSegment<'c, C> {
+    /// Allocate a new shared memory segment of `size` bytes and attach it to the X11 server.
+    ///
+    /// `ConnectionError::UnsupportedExtension` is returned if the server does not support the
+    /// `MIT-SHM` extension.
+    pub fn new(conn: &'c C, size: u32) -> Result<Self, ReplyOrIdError> {
+        let version = shm::query_version(conn)?.reply()?;
+        let seg = conn.generate_id()?;
+        let size = usize::try_from(size).map_err(|_| ConnectionError::from(invalid_size()))?;
+        if (version.major_version, version.minor_version) >= (1, 2) {
+            Self::attach_fd(conn, seg, size)
+        } else {
+            Self::attach_sysv(conn, seg, size)
+        }
+    }
+
+    fn attach_fd(conn: &'c C, seg: Seg, size: usize) -> Result<Self, ReplyOrIdError> {
+        let (fd, ptr) = create_shm_object(size).map_err(ConnectionError::from)?;
+        let attached = shm::attach_fd(conn, seg, fd, false)
+            .map_err(ReplyError::from)
+            .and_then(|cookie| cookie.check());
+        if let Err(err) = attached {
+            let _ = unsafe { libc::munmap(ptr.cast(), size) };
+            return Err(err.into());
+        }
+        Ok(Self::from_parts(conn, seg, ptr, size, Mapping::Mmap))
+    }
+
+    fn attach_sysv(conn: &'c C, seg: Seg, size: usize) -> Result<Self, ReplyOrIdError> {
+        let shmid = unsafe { libc::shmget(libc::IPC_PRIVATE, size, libc::IPC_CREAT | 0o600) };
+        if shmid < 0 {
+            return Err(ConnectionError::from(IOError::last_os_error()).into());
+        }
+        let ptr = unsafe { libc::shmat(shmid, null_mut(), 0) };
+        if ptr as isize == -1 {
+            let err = IOError::last_os_error();
+            let _ = unsafe { libc::shmctl(shmid, libc::IPC_RMID, null_mut()) };
+            return Err(ConnectionError::from(err).into());
+        }
+        let attached = match u32::try_from(shmid) {
+            Ok(shmid) => shm::attach(conn, seg, shmid, false)
+                .map_err(ReplyError::from)
+                .and_then(|cookie| cookie.check()),
+            Err(_) => Err(ConnectionError::from(invalid_size()).into()),
+        };
+        // Once both sides are attached (or attaching failed), the segment can be marked for
+        // removal. It is destroyed after the last detach.
+        let _ = unsafe { libc::shmctl(shmid, libc::IPC_RMID, null_mut()) };
+        if let Err(err) = attached {
+            let _ = unsafe { libc::shmdt(ptr) };
+            return Err(err.into());
+        }
+        Ok(Self::from_parts(conn, seg, ptr.cast(), size, Mapping::SysV))
+    }
+
+    fn from_parts(conn: &'c C, seg: Seg, ptr: *mut u8, size: usize, mapping: Mapping) -> Self {
+        Self {
+            conn,
+            seg,
+            ptr,
+            size,
+            mapping,
+            pending_read: Cell::new(false),
+        }
+    }
+
+    /// Get the ID of the segment for use in other `MIT-SHM` requests.
+    ///
+    /// If the server writes to the segment in response to such a request, e.g. because it is
+    /// used for a pixmap created via `ShmCreatePixmap`, the caller has to synchronise with the
+    /// server before reading the data.
+    pub fn seg(&self) -> Seg {
+        self.seg
+    }
+
+    /// Get the size of the segment in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Get the contents of the segment.
+    ///
+    /// # Safety
+    ///
+    /// The X11 server must not write to the segment while the returned slice is alive. The server
+    /// writes to the segment when it processes a request that was sent with [`seg`](Self::seg),
+    /// e.g. `ShmGetImage` or drawing to a pixmap created via `ShmCreatePixmap`. All such requests
+    /// must have completed before this is called, for example because their reply was received
+    /// or because of [`sync`](crate::wrapper::ConnectionExt::sync), and none may be sent until the
+    /// slice is dropped. [`get_image`](Self::get_image) waits for its reply and thus fulfils this.
+    pub unsafe fn data(&self) -> &[u8] {
+        std::slice::from_raw_parts(self.ptr, self.size)
+    }
+
+    /// Get the contents of the segment for modification.
+    ///
+    /// If an image was uploaded via [`put_image`](Self::put_image) since the last call, this
+    /// first waits until the X11 server has processed the upload.
+    ///
+    /// # Safety
+    ///
+    /// The same rules as for [`data`](Self::data) apply: The X11 server must not write to the
+    /// segment while the returned slice is alive. Uploads via [`put_image`](Self::put_image) are
+    /// waited for by this function, but requests that make the server write to the segment, e.g.
+    /// `ShmGetImage` or drawing to a pixmap created via `ShmCreatePixmap`, must have completed
+    /// before this is called, and none may be sent until the slice is dropped.
+    pub unsafe fn data_mut(&mut self) -> Result<&mut [u8], ReplyError> {
+        if self.pending_read.get() {
+            self.conn.sync()?;
+            self.pending_read.set(false);
+        }
+        Ok(std::slice::from_raw_parts_mut(self.ptr, self.size))
+    }
+
+    /// Upload an image from the segment to `drawable` at position `(dst_x, dst_y)`.
+    ///
+    /// The image has size `width`x`height` and starts at `offset` bytes into the segment. It must
+    /// be in `ImageFormat::Z_PIXMAP` format in the native format of the X11 server for `depth`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn put_image(
+        &self,
+        drawable: Drawable,
+        gc: Gcontext,
+        width: u16,
+        height: u16,
+        depth: u8,
+        dst_x: i16,
+        dst_y: i16,
+        offset: u32,
+    ) -> Result<VoidCookie<'c, C>, ConnectionError> {
+        let cookie = shm::put_image(
+            self.conn,
+            drawable,
+            gc,
+            width,
+            height,
+            0,
+            0,
+            width,
+            height,
+            dst_x,
+            dst_y,
+            depth,
+            ImageFormat::Z_PIXMAP.into(),
+            false,
+            self.seg,
+            offset,
+        )?;
+        self.pending_read.set(true);
+        Ok(cookie)
+    }
+
+    /// Download the given rectangle of `drawable` into the segment, starting at `offset` bytes
+    /// into it.
+    ///
+    /// The image is stored in `ImageFormat::Z_PIXMAP` format. This waits for the reply of the
+    /// server, so the data is available via [`data`](Self::data) afterwards.
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_image(
+        &mut self,
+        drawable: Drawable,
+        x: i16,
+        y: i16,
+        width: u16,
+        height: u16,
+        plane_mask: u32,
+        offset: u32,
+    ) -> Result<GetImageReply, ReplyError> {
+        shm::get_image(
+            self.conn,
+            drawable,
+            x,
+            y,
+            width,
+            height,
+            plane_mask,
+            ImageFormat::Z_PIXMAP.into(),
+            self.seg,
+            offset,
+        )?
+        .reply()
+    }
+}
+
+impl<C: Connection> Drop for ShmSegment<'_, C> {
+    fn drop(&mut self) {
+        if let Ok(cookie) = shm::detach(self.conn, self.seg) {
+            cookie.ignore_error();
+        }
+        let _ = unsafe {
+            match self.mapping {
+                Mapping::Mmap => libc::munmap(self.ptr.cast(), self.size),
+                Mapping::SysV => libc::shmdt(self.ptr.cast()),
+            }
+        };
+    }
+}
+
+fn invalid_size() -> IOError {
+    IOError::new(ErrorKind::InvalidInput, "invalid shared memory size")
+}
+
+/// Create an anonymous POSIX shared memory object of the given size and map it.
+fn create_shm_object(size: usize) -> Result<(RawFdContainer, *mut u8), IOError> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let name = format!(
+        "/x11rb-shm-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    let name = CString::new(name).unwrap();
+    let flags = libc::O_RDWR | libc::O_CREAT | libc::O_EXCL;
+    let fd = unsafe { libc::shm_open(name.as_ptr(), flags, 0o600) };
+    if fd < 0 {
+        return Err(IOError::last_os_error());
+    }
+    let fd = RawFdContainer::new(fd);
+    // The object stays alive as long as it is open or mapped
+    let _ = unsafe { libc::shm_unlink(name.as_ptr()) };
+
+    let length = libc::off_t::try_from(size).map_err(|_| invalid_size())?;
+    if unsafe { libc::ftruncate(fd.as_raw_fd(), length) } != 0 {
+        return Err(IOError::last_os_error());
+    }
+    let ptr = unsafe {
+        libc::mmap(
+            null_mut(),
+            size,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd.as_raw_fd(),
+            0,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        return Err(IOError::last_os_error());
+    }
+    Ok((fd, ptr.cast()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::create_shm_object;
+
+    #[test]
+    fn shm_object_is_shared() {
+        let size = 4096;
+        let (fd, ptr) = create_shm_object(size).unwrap();
+        unsafe { std::slice::from_raw_parts_mut(ptr, size) }.copy_from_slice(&[0x42; 4096]);
+
+        // A second mapping of the same object sees the data
+        use std::os::unix::io::AsRawFd;
+        let other = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                size,
+                libc::PROT_READ,
+                libc::MAP_SHARED,
+                fd.as_raw_fd(),
+                0,
+            )
+        };
+        assert_ne!(other, libc::MAP_FAILED);
+        let data = unsafe { std::slice::from_raw_parts(other.cast::<u8>(), size) };
+        assert!(data.iter().all(|&b| b == 0x42));
+
+        unsafe {
+            assert_eq!(libc::munmap(other, size), 0);
+            assert_eq!(libc::munmap(ptr.cast(), size), 0);
+        }
+    }
+}
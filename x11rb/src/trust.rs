@@ -0,0 +1,101 @@
+//! Utility functions for diagnosing clients that the X11 server does not trust.
+//!
+//! X11 servers with the `SECURITY` extension distinguish trusted and untrusted clients. For
+//! example, `ssh -X` connects clients as untrusted, while `ssh -Y` connects them as trusted.
+//! Untrusted clients cannot access the resources of trusted clients and cannot use most
+//! extensions. Requests that violate this policy fail with a generic `Access` error, which is
+//! confusing when the same program works fine locally.
+//!
+//! [`is_untrusted`] guesses whether the connection is untrusted and [`access_error_hint`] turns
+//! this guess into an explanation for `Access` errors.
+//!
+//! Both functions are heuristics. The X11 protocol does not offer a way for a client to ask
+//! whether it is trusted. Instead, these functions rely on the fact that an X.Org server hides all
+//! extensions except `XC-MISC` and `BIG-REQUESTS` from untrusted clients, including the `SECURITY`
+//! extension itself.
+//!
+//! Usage example:
+//! ```no_run
+//! use x11rb::errors::ReplyError;
+//! use x11rb::protocol::xproto::ConnectionExt;
+//! use x11rb::trust::access_error_hint;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let (conn, _) = x11rb::connect(None)?;
+//!     if let Err(ReplyError::X11Error(error)) = conn.grab_server()?.check() {
+//!         if let Some(hint) = access_error_hint(&conn, &error)? {
+//!             eprintln!("GrabServer failed: {}", hint);
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use crate::connection::RequestConnection;
+use crate::errors::ReplyError;
+use crate::protocol::xproto;
+use crate::protocol::ErrorKind;
+use crate::x11_utils::X11Error;
+
+/// The extensions that an X.Org server shows to untrusted clients.
+const UNTRUSTED_EXTENSIONS: [&[u8]; 2] = [b"XC-MISC", b"BIG-REQUESTS"];
+
+/// The hint that [`access_error_hint`] returns for untrusted connections.
+pub const UNTRUSTED_ACCESS_HINT: &str = "the X11 server seems to treat this client as untrusted \
+     (e.g. because of `ssh -X`), which forbids many requests; try a trusted connection \
+     (e.g. `ssh -Y`)";
+
+/// Guess whether the X11 server treats this client as untrusted.
+///
+/// This sends a `ListExtensions` request and returns `true` if the server does not report any
+/// extensions other than those that are visible to untrusted clients. This is a heuristic: a
+/// server without any extensions is also reported as untrusted, and servers with a different
+/// security policy than the X.Org server might not be detected.
+pub fn is_untrusted<C>(conn: &C) -> Result<bool, ReplyError>
+where
+    C: RequestConnection + ?Sized,
+{
+    let reply = xproto::list_extensions(conn)?.reply()?;
+    Ok(only_untrusted_extensions(
+        reply.names.iter().map(|name| &name.name[..]),
+    ))
+}
+
+fn only_untrusted_extensions<'a>(mut names: impl Iterator<Item = &'a [u8]>) -> bool {
+    names.all(|name| UNTRUSTED_EXTENSIONS.contains(&name))
+}
+
+/// Get an explanation for an X11 error that was caused by the security policy of the X11 server.
+///
+/// For `Access` errors on connections that [`is_untrusted`] considers to be untrusted, this
+/// returns [`UNTRUSTED_ACCESS_HINT`]. For all other errors, `None` is returned without sending
+/// any requests.
+///
+/// Like [`is_untrusted`], this is a heuristic. `Access` errors also have other causes, for
+/// example trying to grab a key that another client already grabbed.
+pub fn access_error_hint<C>(conn: &C, error: &X11Error) -> Result<Option<&'static str>, ReplyError>
+where
+    C: RequestConnection + ?Sized,
+{
+    if error.error_kind != ErrorKind::Access || !is_untrusted(conn)? {
+        return Ok(None);
+    }
+    Ok(Some(UNTRUSTED_ACCESS_HINT))
+}
+
+#[cfg(test)]
+mod test {
+    use super::only_untrusted_extensions;
+
+    fn check(names: &[&[u8]]) -> bool {
+        only_untrusted_extensions(names.iter().copied())
+    }
+
+    #[test]
+    fn untrusted_extensions() {
+        assert!(check(&[b"XC-MISC", b"BIG-REQUESTS"]));
+        assert!(check(&[b"BIG-REQUESTS"]));
+        assert!(!check(&[b"BIG-REQUESTS", b"SECURITY"]));
+        assert!(!check(&[b"RENDER", b"XC-MISC", b"BIG-REQUESTS"]));
+    }
+}
@@ -8,6 +8,9 @@
 //!
 //! `CSlice` is only available when the `allow-unsafe-code` feature is enabled.
 
+use std::convert::TryFrom;
+use std::time::Duration;
+
 pub use x11rb_protocol::RawFdContainer;
 
 #[cfg(feature = "allow-unsafe-code")]
@@ -97,3 +100,29 @@ mod unsafe_code {
 
 #[cfg(feature = "allow-unsafe-code")]
 pub use unsafe_code::CSlice;
+
+/// Convert a timeout into milliseconds for `poll()`, rounding up.
+///
+/// Timeouts that do not fit into an `i32` are clamped to the largest possible value.
+pub(crate) fn poll_timeout_millis(timeout: Duration) -> i32 {
+    let millis = timeout.as_millis() + u128::from(timeout.subsec_nanos() % 1_000_000 != 0);
+    i32::try_from(millis).unwrap_or(i32::max_value())
+}
+
+#[cfg(test)]
+mod test {
+    use super::poll_timeout_millis;
+    use std::time::Duration;
+
+    #[test]
+    fn timeout_millis() {
+        assert_eq!(poll_timeout_millis(Duration::from_secs(0)), 0);
+        assert_eq!(poll_timeout_millis(Duration::from_micros(1)), 1);
+        assert_eq!(poll_timeout_millis(Duration::from_millis(1500)), 1500);
+        assert_eq!(poll_timeout_millis(Duration::from_micros(1500)), 2);
+        assert_eq!(
+            poll_timeout_millis(Duration::from_secs(u64::max_value())),
+            i32::max_value()
+        );
+    }
+}
@@ -0,0 +1,483 @@
+//! Helpers for creating and configuring windows.
+//!
+//! An `InputOnly` window is invisible. It only receives input, e.g. to capture clicks in an area
+//! of the screen or to implement a hotspot. The X11 server only accepts a few window attributes
+//! for such windows and answers everything else with a `Match` error.
+//! [`create_input_only_window`] creates such a window with valid parameters.
+//!
+//! [`WindowBuilder`] creates a normal application window and sets the properties that window
+//! managers expect from it: `WM_NAME` and `_NET_WM_NAME`, `WM_CLASS`, `WM_NORMAL_HINTS` and
+//! `WM_PROTOCOLS` with `WM_DELETE_WINDOW`.
+//!
+//! [`set_cursor`] shows a cursor, e.g. one loaded via the `cursor` module, while the pointer is in
+//! a window. [`clear_cursor`] goes back to the cursor of the parent window.
+//!
+//! [`wait_for_destroy`] blocks until a window is destroyed, e.g. until a dialog was closed.
+//!
+//! Usage example:
+//! ```no_run
+//! use x11rb::connection::Connection;
+//! use x11rb::protocol::xproto::{ConnectionExt, EventMask, Rectangle};
+//! use x11rb::window::create_input_only_window;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let (conn, screen_num) = x11rb::connect(None)?;
+//!     let root = conn.setup().roots[screen_num].root;
+//!     let area = Rectangle { x: 0, y: 0, width: 10, height: 10 };
+//!     let hotspot = create_input_only_window(&conn, root, area, EventMask::ENTER_WINDOW)?;
+//!     conn.map_window(hotspot.window())?;
+//!     conn.flush()?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::connection::Connection;
+use crate::cookie::VoidCookie;
+use crate::errors::{ConnectionError, ReplyError, ReplyOrIdError};
+use crate::properties::{WmSizeHints, WmSizeHintsSpecification};
+use crate::protocol::xproto::{
+    self, Atom, AtomEnum, ChangeWindowAttributesAux, ConnectionExt as _, CreateWindowAux, Cursor,
+    EventMask, PropMode, Rectangle, Window, WindowClass, WindowWrapper,
+};
+use crate::protocol::{ErrorKind, Event};
+use crate::wrapper::ConnectionExt as _;
+use crate::{COPY_FROM_PARENT, NONE};
+
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+
+/// Create an `InputOnly` window.
+///
+/// The window is created as a child of `parent` at the position and with the size of `rect`. It
+/// selects the events in `event_mask`. Like every window, it has to be mapped before it receives
+/// any input. The returned [`WindowWrapper`] destroys the window when it is dropped.
+///
+/// This enforces the restrictions of the X11 protocol for `InputOnly` windows:
+///
+/// - The depth is zero and the visual is `CopyFromParent`.
+/// - The border width is zero.
+/// - Besides the event mask, no window attributes are set. In particular, there is no background,
+///   no border and no colormap, which would all cause a `Match` error.
+///
+/// The width and height in `rect` must not be zero, otherwise the X11 server sends a `Value`
+/// error.
+pub fn create_input_only_window<C: Connection>(
+    conn: &C,
+    parent: Window,
+    rect: Rectangle,
+    event_mask: EventMask,
+) -> Result<WindowWrapper<'_, C>, ReplyOrIdError> {
+    WindowWrapper::create_window(
+        conn,
+        0,
+        parent,
+        rect.x,
+        rect.y,
+        rect.width,
+        rect.height,
+        0,
+        WindowClass::INPUT_ONLY,
+        COPY_FROM_PARENT,
+        &input_only_aux(event_mask),
+    )
+}
+
+/// The window attributes of an `InputOnly` window that selects the given events.
+fn input_only_aux(event_mask: EventMask) -> CreateWindowAux {
+    CreateWindowAux::new().event_mask(event_mask)
+}
+
+/// The default size of windows created by [`WindowBuilder`].
+pub const DEFAULT_WINDOW_SIZE: (u16, u16) = (640, 480);
+
+/// A builder for top-level application windows.
+///
+/// All properties are optional. By default, the window is 640x480 pixels large, has no title and
+/// no `WM_CLASS`, and participates in the `WM_DELETE_WINDOW` protocol.
+///
+/// ```no_run
+/// use x11rb::connection::Connection;
+/// use x11rb::protocol::xproto::{ConnectionExt, CreateWindowAux, EventMask};
+/// use x11rb::window::WindowBuilder;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let (conn, screen_num) = x11rb::connect(None)?;
+///     let screen = &conn.setup().roots[screen_num];
+///     let aux = CreateWindowAux::new()
+///         .background_pixel(screen.white_pixel)
+///         .event_mask(EventMask::EXPOSURE);
+///     let window = WindowBuilder::new(screen.root)
+///         .size(320, 240)
+///         .title("Hello")
+///         .class("hello", "Hello")
+///         .aux(aux)
+///         .build(&conn)?;
+///     conn.map_window(window.window)?;
+///     conn.flush()?;
+///     // Compare ClientMessage events against window.wm_protocols and window.wm_delete_window
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WindowBuilder {
+    parent: Window,
+    rect: Rectangle,
+    aux: CreateWindowAux,
+    title: Option<String>,
+    class: Option<(String, String)>,
+    size_hints: Option<WmSizeHints>,
+    delete_window: bool,
+}
+
+/// A window that was created by [`WindowBuilder::build`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CreatedWindow {
+    /// The new window.
+    pub window: Window,
+    /// The `WM_PROTOCOLS` atom, which is the type of `ClientMessage` events from the window
+    /// manager.
+    pub wm_protocols: Atom,
+    /// The `WM_DELETE_WINDOW` atom, which the window manager sends when the window should close.
+    pub wm_delete_window: Atom,
+}
+
+impl WindowBuilder {
+    /// Create a builder for a window that is a child of `parent`, usually a root window.
+    pub fn new(parent: Window) -> Self {
+        Self {
+            parent,
+            rect: Rectangle {
+                x: 0,
+                y: 0,
+                width: DEFAULT_WINDOW_SIZE.0,
+                height: DEFAULT_WINDOW_SIZE.1,
+            },
+            aux: CreateWindowAux::new(),
+            title: None,
+            class: None,
+            size_hints: None,
+            delete_window: true,
+        }
+    }
+
+    /// Set the initial position of the window.
+    pub fn position(mut self, x: i16, y: i16) -> Self {
+        self.rect.x = x;
+        self.rect.y = y;
+        self
+    }
+
+    /// Set the initial size of the window.
+    pub fn size(mut self, width: u16, height: u16) -> Self {
+        self.rect.width = width;
+        self.rect.height = height;
+        self
+    }
+
+    /// Set the window attributes that are used for creating the window.
+    pub fn aux(mut self, aux: CreateWindowAux) -> Self {
+        self.aux = aux;
+        self
+    }
+
+    /// Set the title of the window as `_NET_WM_NAME` and `WM_NAME`.
+    ///
+    /// `WM_NAME` has type `STRING`, which is ISO Latin-1. It is only set if the title can be
+    /// represented in this encoding. Window managers that only read `WM_NAME` then show no title.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Set the instance and class name of the window as `WM_CLASS`.
+    pub fn class(mut self, instance: impl Into<String>, class: impl Into<String>) -> Self {
+        self.class = Some((instance.into(), class.into()));
+        self
+    }
+
+    /// Set the `WM_NORMAL_HINTS` of the window.
+    ///
+    /// By default, the hints only contain the program-specified size of the window.
+    pub fn size_hints(mut self, size_hints: WmSizeHints) -> Self {
+        self.size_hints = Some(size_hints);
+        self
+    }
+
+    /// Set whether the window participates in the `WM_DELETE_WINDOW` protocol.
+    ///
+    /// If so, the window manager sends a `ClientMessage` instead of killing the client when the
+    /// user closes the window. This is enabled by default.
+    pub fn delete_window(mut self, enabled: bool) -> Self {
+        self.delete_window = enabled;
+        self
+    }
+
+    /// Create the window and set its properties.
+    ///
+    /// The window is created with the depth and visual of its parent, but not mapped. All
+    /// requests are sent before waiting for any reply. If a request fails after the window was
+    /// created, the window is destroyed again.
+    pub fn build<C: Connection>(&self, conn: &C) -> Result<CreatedWindow, ReplyOrIdError> {
+        let wm_protocols = conn.intern_atom(false, b"WM_PROTOCOLS")?;
+        let wm_delete_window = conn.intern_atom(false, b"WM_DELETE_WINDOW")?;
+        let net_wm_name = conn.intern_atom(false, b"_NET_WM_NAME")?;
+        let utf8_string = conn.intern_atom(false, b"UTF8_STRING")?;
+
+        // Until the window is returned, an error drops the wrapper, which destroys the window
+        let wrapper = WindowWrapper::create_window(
+            conn,
+            COPY_FROM_PARENT as u8,
+            self.parent,
+            self.rect.x,
+            self.rect.y,
+            self.rect.width,
+            self.rect.height,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            COPY_FROM_PARENT,
+            &self.aux,
+        )?;
+        let window = wrapper.window();
+        if let Some((instance, class)) = &self.class {
+            let _ = conn.change_property8(
+                PropMode::REPLACE,
+                window,
+                AtomEnum::WM_CLASS,
+                AtomEnum::STRING,
+                &wm_class_value(instance, class),
+            )?;
+        }
+        let size_hints = self.size_hints.unwrap_or_else(|| WmSizeHints {
+            size: Some((
+                WmSizeHintsSpecification::ProgramSpecified,
+                self.rect.width.into(),
+                self.rect.height.into(),
+            )),
+            ..Default::default()
+        });
+        let _ = size_hints.set_normal_hints(conn, window)?;
+        if let Some(title) = self.title.as_deref().and_then(latin1) {
+            let _ = conn.change_property8(
+                PropMode::REPLACE,
+                window,
+                AtomEnum::WM_NAME,
+                AtomEnum::STRING,
+                &title,
+            )?;
+        }
+
+        let wm_protocols = wm_protocols.reply()?.atom;
+        let wm_delete_window = wm_delete_window.reply()?.atom;
+        let net_wm_name = net_wm_name.reply()?.atom;
+        let utf8_string = utf8_string.reply()?.atom;
+        if let Some(title) = &self.title {
+            let _ = conn.change_property8(
+                PropMode::REPLACE,
+                window,
+                net_wm_name,
+                utf8_string,
+                title.as_bytes(),
+            )?;
+        }
+        if self.delete_window {
+            let _ = conn.change_property32(
+                PropMode::REPLACE,
+                window,
+                wm_protocols,
+                AtomEnum::ATOM,
+                &[wm_delete_window],
+            )?;
+        }
+        Ok(CreatedWindow {
+            window: wrapper.into_window(),
+            wm_protocols,
+            wm_delete_window,
+        })
+    }
+}
+
+/// Encode a string in ISO Latin-1, the encoding of the `STRING` type.
+///
+/// Returns `None` if the string contains characters outside of Latin-1.
+fn latin1(text: &str) -> Option<Vec<u8>> {
+    text.chars()
+        .map(|c| u8::try_from(u32::from(c)).ok())
+        .collect()
+}
+
+/// The value of a `WM_CLASS` property: two null-terminated strings.
+fn wm_class_value(instance: &str, class: &str) -> Vec<u8> {
+    let mut value = Vec::with_capacity(instance.len() + class.len() + 2);
+    value.extend_from_slice(instance.as_bytes());
+    value.push(0);
+    value.extend_from_slice(class.as_bytes());
+    value.push(0);
+    value
+}
+
+/// Set the cursor that is shown while the pointer is in the given window.
+///
+/// This sets the `cursor` attribute of the window. The cursor can be freed afterwards, since the
+/// X11 server keeps it alive as long as it is used.
+pub fn set_cursor<C: Connection>(
+    conn: &C,
+    window: Window,
+    cursor: Cursor,
+) -> Result<VoidCookie<'_, C>, ConnectionError> {
+    xproto::change_window_attributes(conn, window, &cursor_aux(cursor))
+}
+
+/// Remove the cursor of the given window.
+///
+/// Afterwards, the window shows the cursor of its parent window again. For a root window, the
+/// default cursor is used.
+pub fn clear_cursor<C: Connection>(
+    conn: &C,
+    window: Window,
+) -> Result<VoidCookie<'_, C>, ConnectionError> {
+    set_cursor(conn, window, NONE)
+}
+
+/// The window attributes that only change the cursor.
+fn cursor_aux(cursor: Cursor) -> ChangeWindowAttributesAux {
+    ChangeWindowAttributesAux::new().cursor(cursor)
+}
+
+/// The result of [`wait_for_destroy`].
+#[derive(Debug)]
+pub struct DestroyWait {
+    /// Whether the window was destroyed. This is `false` if the timeout expired first.
+    pub destroyed: bool,
+    /// All other events that were received while waiting, in the order in which they arrived.
+    pub other_events: Vec<Event>,
+}
+
+/// Wait until `window` is destroyed, but at most for `timeout`.
+///
+/// This adds `StructureNotify` to the events that this client selects on `window` and waits for
+/// the corresponding `DestroyNotify` event. If the window does not exist (anymore), this returns
+/// immediately.
+///
+/// Events cannot be put back into the event queue of a connection. Thus, all other events that
+/// are received while waiting are collected in [`DestroyWait::other_events`] and should be
+/// handled by the caller.
+pub fn wait_for_destroy<C: Connection>(
+    conn: &C,
+    window: Window,
+    timeout: Duration,
+) -> Result<DestroyWait, ReplyError> {
+    let mut result = DestroyWait {
+        destroyed: true,
+        other_events: Vec::new(),
+    };
+    let event_mask = match conn.get_window_attributes(window)?.reply() {
+        Ok(reply) => reply.your_event_mask | EventMask::STRUCTURE_NOTIFY,
+        Err(err) if is_bad_window(&err) => return Ok(result),
+        Err(err) => return Err(err),
+    };
+    let aux = ChangeWindowAttributesAux::new().event_mask(event_mask);
+    match conn.change_window_attributes(window, &aux)?.check() {
+        Ok(()) => {}
+        // The window was destroyed in the meantime
+        Err(err) if is_bad_window(&err) => return Ok(result),
+        Err(err) => return Err(err),
+    }
+
+    let deadline = Instant::now().checked_add(timeout);
+    loop {
+        let event = match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    result.destroyed = false;
+                    return Ok(result);
+                }
+                match conn.wait_for_event_with_timeout(deadline - now)? {
+                    Some(event) => event,
+                    None => continue,
+                }
+            }
+            None => conn.wait_for_event()?,
+        };
+        if is_destroy_of(&event, window) {
+            return Ok(result);
+        }
+        result.other_events.push(event);
+    }
+}
+
+/// Check whether `err` is a `Window` error, i.e. the window does not exist.
+fn is_bad_window(err: &ReplyError) -> bool {
+    matches!(err, ReplyError::X11Error(err) if err.error_kind == ErrorKind::Window)
+}
+
+/// Check whether `event` reports the destruction of `window`.
+fn is_destroy_of(event: &Event, window: Window) -> bool {
+    matches!(event, Event::DestroyNotify(event) if event.window == window)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{cursor_aux, input_only_aux, is_destroy_of, latin1, wm_class_value};
+    use crate::protocol::xproto::{ChangeWindowAttributesAux, CreateWindowAux, EventMask};
+
+    #[test]
+    fn only_event_mask() {
+        let mask = EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE;
+        let aux = input_only_aux(mask);
+        assert_eq!(
+            aux,
+            CreateWindowAux {
+                event_mask: Some(mask),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn only_cursor() {
+        let expected = ChangeWindowAttributesAux {
+            cursor: Some(42),
+            ..Default::default()
+        };
+        assert_eq!(cursor_aux(42), expected);
+        assert_eq!(cursor_aux(crate::NONE).cursor, Some(0));
+    }
+
+    #[test]
+    fn latin1_encoding() {
+        assert_eq!(latin1("xterm").unwrap(), b"xterm");
+        assert_eq!(latin1("Grüße").unwrap(), b"Gr\xfc\xdfe");
+        assert_eq!(latin1("\u{ff}").unwrap(), [0xff]);
+        assert_eq!(latin1("\u{100}"), None);
+        assert_eq!(latin1("日本語"), None);
+    }
+
+    #[test]
+    fn wm_class() {
+        assert_eq!(wm_class_value("xterm", "XTerm"), b"xterm\0XTerm\0");
+        assert_eq!(wm_class_value("", ""), b"\0\0");
+    }
+
+    #[test]
+    fn destroy_of_window() {
+        use crate::protocol::xproto::{DestroyNotifyEvent, UnmapNotifyEvent, DESTROY_NOTIFY_EVENT};
+        use crate::protocol::Event;
+
+        let destroy = |window| {
+            Event::DestroyNotify(DestroyNotifyEvent {
+                response_type: DESTROY_NOTIFY_EVENT,
+                sequence: 0,
+                event: 1,
+                window,
+            })
+        };
+        assert!(is_destroy_of(&destroy(42), 42));
+        assert!(!is_destroy_of(&destroy(43), 42));
+        let unmap = Event::UnmapNotify(UnmapNotifyEvent {
+            window: 42,
+            ..Default::default()
+        });
+        assert!(!is_destroy_of(&unmap, 42));
+    }
+}
@@ -0,0 +1,510 @@
+//! Utility functions for implementing window managers.
+//!
+//! # Configure requests
+//!
+//! When a window manager selects `SubstructureRedirect` on the root window, attempts by clients to
+//! move, resize, or restack their top-level windows arrive as `ConfigureRequest` events. The
+//! window manager either honours such a request, e.g. via
+//! `ConfigureWindowAux::from_configure_request`, or adjusts it. In both cases, ICCCM §4.1.5
+//! requires a synthetic `ConfigureNotify` event when the window is not actually moved or resized,
+//! and when it is reparented into a frame, since the real `ConfigureNotify` would then contain
+//! coordinates relative to the frame.
+//!
+//! [`WindowGeometry::apply_configure_request`] computes the geometry that a client asks for, and
+//! [`send_synthetic_configure_notify`] informs the client about its final geometry.
+//!
+//! Usage example:
+//! ```no_run
+//! # use x11rb::connection::Connection;
+//! # use x11rb::errors::ReplyError;
+//! # use x11rb::protocol::xproto::ConfigureRequestEvent;
+//! use x11rb::wm::{send_synthetic_configure_notify, WindowGeometry};
+//!
+//! fn handle_configure_request(
+//!     conn: &impl Connection,
+//!     event: &ConfigureRequestEvent,
+//!     current: WindowGeometry,
+//! ) -> Result<(), ReplyError> {
+//!     // A window manager that does not allow clients to move themselves
+//!     let mut geometry = current.apply_configure_request(event);
+//!     geometry.x = current.x;
+//!     geometry.y = current.y;
+//!     // ... resize the frame and the client window ...
+//!     send_synthetic_configure_notify(conn, event.window, geometry)?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! # Absolute geometry of windows
+//!
+//! Real `ConfigureNotify` events report the position of a window relative to its parent. For
+//! override-redirect windows like menus and tooltips, the parent usually is the root window, but
+//! this is not guaranteed, and client windows are reparented into frames by most window managers.
+//! [`RootGeometryTracker`] converts `ConfigureNotify` events into root window coordinates. It
+//! caches the parent of each window, so that only events of windows whose parent is not the root
+//! window need a round trip.
+//!
+//! # Deciding which windows to manage
+//!
+//! Only top-level windows that are not override-redirect should be managed. Override-redirect
+//! windows are e.g. tooltips and menus that position themselves. [`should_manage`] implements this
+//! decision for windows from `MapRequest` events, while [`should_manage_existing`] additionally
+//! requires the window to be mapped, which is needed when taking over the windows that already
+//! exist when the window manager starts.
+//!
+//! # Moving many windows at once
+//!
+//! After a change of the layout, e.g. in a tiling window manager, many windows need to be moved
+//! and resized. [`relayout`] sends all the necessary `ConfigureWindow` requests at once and only
+//! flushes afterwards. Optionally, the server is grabbed meanwhile, so that other clients do not
+//! observe the windows in an intermediate state.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use crate::connection::{Connection, RequestConnection};
+use crate::cookie::VoidCookie;
+use crate::errors::{ConnectionError, ReplyError};
+use crate::protocol::xproto::{
+    self, ConfigWindow, ConfigureNotifyEvent, ConfigureRequestEvent, ConfigureWindowAux, EventMask,
+    GetWindowAttributesReply, MapState, QueryTreeReply, Rectangle, Window, WindowClass,
+    CONFIGURE_NOTIFY_EVENT,
+};
+use crate::protocol::Event;
+use crate::wrapper::GrabServer;
+use crate::NONE;
+
+/// The geometry of a window.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WindowGeometry {
+    /// The x coordinate of the window's outer corner.
+    pub x: i16,
+    /// The y coordinate of the window's outer corner.
+    pub y: i16,
+    /// The width of the window's inside, excluding the border.
+    pub width: u16,
+    /// The height of the window's inside, excluding the border.
+    pub height: u16,
+    /// The width of the window's border.
+    pub border_width: u16,
+}
+
+impl WindowGeometry {
+    /// Compute the geometry that a `ConfigureRequest` event asks for.
+    ///
+    /// Fields that are not included in the event's `value_mask` keep their value from `self`.
+    /// The sibling and stack mode of the request are not part of the geometry and thus ignored.
+    pub fn apply_configure_request(&self, event: &ConfigureRequestEvent) -> Self {
+        let mask = u16::from(event.value_mask);
+        let has = |flag: ConfigWindow| mask & u16::from(flag) != 0;
+        Self {
+            x: if has(ConfigWindow::X) {
+                event.x
+            } else {
+                self.x
+            },
+            y: if has(ConfigWindow::Y) {
+                event.y
+            } else {
+                self.y
+            },
+            width: if has(ConfigWindow::WIDTH) {
+                event.width
+            } else {
+                self.width
+            },
+            height: if has(ConfigWindow::HEIGHT) {
+                event.height
+            } else {
+                self.height
+            },
+            border_width: if has(ConfigWindow::BORDER_WIDTH) {
+                event.border_width
+            } else {
+                self.border_width
+            },
+        }
+    }
+}
+
+/// Build the synthetic `ConfigureNotify` event for a client window.
+///
+/// The coordinates in `geometry` must be relative to the root window, even if the client window
+/// was reparented into a frame. As required by ICCCM, `above_sibling` is `None` and
+/// `override_redirect` is `false`.
+pub fn synthetic_configure_notify(
+    window: Window,
+    geometry: WindowGeometry,
+) -> ConfigureNotifyEvent {
+    ConfigureNotifyEvent {
+        response_type: CONFIGURE_NOTIFY_EVENT,
+        sequence: 0,
+        event: window,
+        window,
+        above_sibling: NONE,
+        x: geometry.x,
+        y: geometry.y,
+        width: geometry.width,
+        height: geometry.height,
+        border_width: geometry.border_width,
+        override_redirect: false,
+    }
+}
+
+/// Send a synthetic `ConfigureNotify` event to a client window.
+///
+/// This tells a client about the geometry of its window after a `ConfigureRequest` was handled.
+/// The coordinates in `geometry` must be relative to the root window. See
+/// [`synthetic_configure_notify`] for details about the event that is sent.
+pub fn send_synthetic_configure_notify<C>(
+    conn: &C,
+    window: Window,
+    geometry: WindowGeometry,
+) -> Result<VoidCookie<'_, C>, ConnectionError>
+where
+    C: RequestConnection + ?Sized,
+{
+    let event = synthetic_configure_notify(window, geometry);
+    xproto::send_event(conn, false, window, EventMask::STRUCTURE_NOTIFY, event)
+}
+
+/// Converts the geometry from `ConfigureNotify` events into root window coordinates.
+///
+/// For each window, its parent is cached. It is determined with a `QueryTree` request for the
+/// first event of a window, unless a `ReparentNotify` event already revealed it. The cache entry
+/// is updated when the window is reparented and removed when it is destroyed, which is detected
+/// in [`RootGeometryTracker::handle_event`].
+///
+/// Moving the parent of a window does not cause events for the window itself, so the position of
+/// a parent is not cached. For windows whose parent is not the root window, it is requested with
+/// a `TranslateCoordinates` request for every event.
+#[derive(Debug, Clone)]
+pub struct RootGeometryTracker {
+    root: Window,
+    parents: HashMap<Window, Window>,
+}
+
+impl RootGeometryTracker {
+    /// Create a new tracker for windows on the screen with the given root window.
+    pub fn new(root: Window) -> Self {
+        Self {
+            root,
+            parents: HashMap::new(),
+        }
+    }
+
+    /// Get the geometry from a `ConfigureNotify` event in root window coordinates.
+    ///
+    /// Synthetic `ConfigureNotify` events sent by a window manager already use root window
+    /// coordinates and are returned unchanged. For real events, this sends a `QueryTree` request
+    /// if the parent of the window is not yet known, and a `TranslateCoordinates` request for the
+    /// current position of the parent unless it is the root window.
+    pub fn geometry<C>(
+        &mut self,
+        conn: &C,
+        event: &ConfigureNotifyEvent,
+    ) -> Result<WindowGeometry, ReplyError>
+    where
+        C: RequestConnection + ?Sized,
+    {
+        if event.response_type & 0x80 != 0 {
+            return Ok(root_geometry(event, (0, 0)));
+        }
+        let parent = match self.parents.get(&event.window) {
+            Some(&parent) => parent,
+            None => {
+                let parent = xproto::query_tree(conn, event.window)?.reply()?.parent;
+                let _ = self.parents.insert(event.window, parent);
+                parent
+            }
+        };
+        if parent == self.root {
+            return Ok(root_geometry(event, (0, 0)));
+        }
+        // The event contains the position relative to the inside corner of the parent
+        let reply = xproto::translate_coordinates(conn, parent, self.root, 0, 0)?.reply()?;
+        let origin = (i32::from(reply.dst_x), i32::from(reply.dst_y));
+        Ok(root_geometry(event, origin))
+    }
+
+    /// Update the cache based on an event.
+    ///
+    /// `ReparentNotify` events record the new parent of the affected window, so that it is known
+    /// without a round trip. `DestroyNotify` events remove the cache entry of the affected window.
+    /// All other events are ignored.
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::ReparentNotify(event) => {
+                let _ = self.parents.insert(event.window, event.parent);
+            }
+            Event::DestroyNotify(event) => self.forget(event.window),
+            _ => {}
+        }
+    }
+
+    /// Remove the cached parent of a window.
+    pub fn forget(&mut self, window: Window) {
+        let _ = self.parents.remove(&window);
+    }
+}
+
+/// Add the root window coordinates of the parent's origin to the position from an event.
+fn root_geometry(event: &ConfigureNotifyEvent, (parent_x, parent_y): (i32, i32)) -> WindowGeometry {
+    WindowGeometry {
+        x: clamp_coordinate(parent_x + i32::from(event.x)),
+        y: clamp_coordinate(parent_y + i32::from(event.y)),
+        width: event.width,
+        height: event.height,
+        border_width: event.border_width,
+    }
+}
+
+fn clamp_coordinate(value: i32) -> i16 {
+    i16::try_from(value).unwrap_or(if value < 0 { i16::MIN } else { i16::MAX })
+}
+
+/// Check whether a window manager should manage the given window.
+///
+/// A window should be managed if it is a direct child of the root window, is not
+/// override-redirect, and is not an `InputOnly` window. The map state is not checked, since this
+/// is meant for windows from `MapRequest` events, which are not yet mapped. See
+/// [`should_manage_existing`] for windows that already exist when the window manager starts.
+pub fn should_manage<C>(conn: &C, window: Window) -> Result<bool, ReplyError>
+where
+    C: RequestConnection + ?Sized,
+{
+    let (attributes, tree) = query_window(conn, window)?;
+    Ok(is_manageable(&attributes, &tree))
+}
+
+/// Check whether a window manager should take over management of an existing window.
+///
+/// This performs the same checks as [`should_manage`], but additionally requires the window to be
+/// viewable. Unmapped windows are managed once they send a `MapRequest`.
+pub fn should_manage_existing<C>(conn: &C, window: Window) -> Result<bool, ReplyError>
+where
+    C: RequestConnection + ?Sized,
+{
+    let (attributes, tree) = query_window(conn, window)?;
+    Ok(is_manageable(&attributes, &tree) && attributes.map_state == MapState::VIEWABLE)
+}
+
+fn query_window<C>(
+    conn: &C,
+    window: Window,
+) -> Result<(GetWindowAttributesReply, QueryTreeReply), ReplyError>
+where
+    C: RequestConnection + ?Sized,
+{
+    // Send both requests before waiting for the replies to save a round trip
+    let attributes = xproto::get_window_attributes(conn, window)?;
+    let tree = xproto::query_tree(conn, window)?;
+    Ok((attributes.reply()?, tree.reply()?))
+}
+
+fn is_manageable(attributes: &GetWindowAttributesReply, tree: &QueryTreeReply) -> bool {
+    !attributes.override_redirect
+        && attributes.class != WindowClass::INPUT_ONLY
+        && tree.parent == tree.root
+}
+
+/// Move and resize many windows at once.
+///
+/// This sends a `ConfigureWindow` request for each window in `layout` without waiting for
+/// anything in between, and flushes the connection at the end. If `grab_server` is true,
+/// the server is grabbed while the requests are processed, so that the relayout appears atomic to
+/// other clients. This also keeps the window manager from reacting to intermediate states.
+///
+/// Errors from the X11 server, e.g. for windows that were destroyed in the meantime, are not
+/// waited for and arrive as events instead.
+pub fn relayout<C: Connection>(
+    conn: &C,
+    layout: &[(Window, Rectangle)],
+    grab_server: bool,
+) -> Result<(), ConnectionError> {
+    let grab = if grab_server {
+        Some(GrabServer::grab(conn)?)
+    } else {
+        None
+    };
+    for &(window, rect) in layout {
+        let _ = xproto::configure_window(conn, window, &geometry_aux(rect))?;
+    }
+    // Ungrab the server. This already flushes, but only the flush below reports errors.
+    drop(grab);
+    conn.flush()
+}
+
+/// The `ConfigureWindowAux` for moving and resizing a window to `rect`.
+fn geometry_aux(rect: Rectangle) -> ConfigureWindowAux {
+    ConfigureWindowAux::new()
+        .x(i32::from(rect.x))
+        .y(i32::from(rect.y))
+        .width(u32::from(rect.width))
+        .height(u32::from(rect.height))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        geometry_aux, is_manageable, root_geometry, synthetic_configure_notify,
+        RootGeometryTracker, WindowGeometry,
+    };
+    use crate::protocol::xproto::{
+        ConfigWindow, ConfigureNotifyEvent, ConfigureRequestEvent, DestroyNotifyEvent,
+        GetWindowAttributesReply, QueryTreeReply, Rectangle, ReparentNotifyEvent, StackMode,
+        WindowClass,
+    };
+    use crate::protocol::Event;
+
+    #[test]
+    fn geometry_of_rectangle() {
+        let aux = geometry_aux(Rectangle {
+            x: -10,
+            y: 20,
+            width: 300,
+            height: 400,
+        });
+        assert_eq!(aux.x, Some(-10));
+        assert_eq!(aux.y, Some(20));
+        assert_eq!(aux.width, Some(300));
+        assert_eq!(aux.height, Some(400));
+        assert_eq!(aux.border_width, None);
+        assert_eq!(aux.stack_mode, None);
+    }
+
+    #[test]
+    fn apply_configure_request() {
+        let current = WindowGeometry {
+            x: 10,
+            y: 20,
+            width: 100,
+            height: 200,
+            border_width: 1,
+        };
+        let event = ConfigureRequestEvent {
+            response_type: 23,
+            stack_mode: StackMode::ABOVE,
+            sequence: 0,
+            parent: 1,
+            window: 2,
+            sibling: 3,
+            x: -5,
+            y: -6,
+            width: 300,
+            height: 400,
+            border_width: 7,
+            value_mask: ConfigWindow::Y | ConfigWindow::WIDTH | ConfigWindow::STACK_MODE,
+        };
+        assert_eq!(
+            current.apply_configure_request(&event),
+            WindowGeometry {
+                x: 10,
+                y: -6,
+                width: 300,
+                height: 200,
+                border_width: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn configure_notify() {
+        let geometry = WindowGeometry {
+            x: 10,
+            y: 20,
+            width: 100,
+            height: 200,
+            border_width: 1,
+        };
+        let event = synthetic_configure_notify(42, geometry);
+        assert_eq!(event.response_type, 22);
+        assert_eq!(
+            (event.event, event.window, event.above_sibling),
+            (42, 42, 0)
+        );
+        assert_eq!(
+            (event.x, event.y, event.width, event.height),
+            (10, 20, 100, 200)
+        );
+        assert_eq!(event.border_width, 1);
+        assert!(!event.override_redirect);
+    }
+
+    #[test]
+    fn manageable() {
+        let attributes = GetWindowAttributesReply {
+            class: WindowClass::INPUT_OUTPUT,
+            ..Default::default()
+        };
+        let top_level = QueryTreeReply {
+            root: 1,
+            parent: 1,
+            ..Default::default()
+        };
+        let child = QueryTreeReply {
+            root: 1,
+            parent: 2,
+            ..Default::default()
+        };
+        assert!(is_manageable(&attributes, &top_level));
+        assert!(!is_manageable(&attributes, &child));
+
+        let override_redirect = GetWindowAttributesReply {
+            override_redirect: true,
+            ..attributes
+        };
+        assert!(!is_manageable(&override_redirect, &top_level));
+
+        let input_only = GetWindowAttributesReply {
+            class: WindowClass::INPUT_ONLY,
+            ..attributes
+        };
+        assert!(!is_manageable(&input_only, &top_level));
+    }
+
+    #[test]
+    fn root_geometry_offset() {
+        let event = ConfigureNotifyEvent {
+            response_type: 22,
+            window: 42,
+            x: 10,
+            y: 20,
+            width: 100,
+            height: 200,
+            border_width: 1,
+            ..Default::default()
+        };
+        let geometry = WindowGeometry {
+            x: 10,
+            y: 20,
+            width: 100,
+            height: 200,
+            border_width: 1,
+        };
+        assert_eq!(root_geometry(&event, (0, 0)), geometry);
+        let expected = WindowGeometry {
+            x: -5,
+            y: i16::MAX,
+            ..geometry
+        };
+        assert_eq!(root_geometry(&event, (-15, i32::from(i16::MAX))), expected);
+    }
+
+    #[test]
+    fn root_geometry_parents() {
+        let mut tracker = RootGeometryTracker::new(1);
+        tracker.handle_event(&Event::ReparentNotify(ReparentNotifyEvent {
+            window: 42,
+            parent: 7,
+            ..Default::default()
+        }));
+        assert_eq!(tracker.parents.get(&42), Some(&7));
+
+        tracker.handle_event(&Event::DestroyNotify(DestroyNotifyEvent {
+            window: 42,
+            ..Default::default()
+        }));
+        assert_eq!(tracker.parents.get(&42), None);
+    }
+}
@@ -2,9 +2,17 @@
 
 use std::convert::TryInto;
 
+use super::connection::Connection;
 use super::cookie::VoidCookie;
-use super::errors::{ConnectionError, ReplyError};
-use super::protocol::xproto::{Atom, ConnectionExt as XProtoConnectionExt, PropMode, Window};
+use super::errors::{ConnectionError, ParseError, ReplyError};
+use super::protocol::xproto::{
+    Allow, Atom, AtomEnum, ConnectionExt as XProtoConnectionExt, Cursor, EventMask,
+    GetPropertyReply, GrabMode, GrabStatus, PropMode, Timestamp, Window,
+};
+use super::protocol::Event;
+
+/// The number of four-byte units that [`ConnectionExt::get_property_full`] requests at once.
+const GET_PROPERTY_CHUNK_LENGTH: u32 = 0x4000;
 
 /// Extension trait that simplifies API use
 pub trait ConnectionExt: XProtoConnectionExt {
@@ -88,6 +96,240 @@ pub trait ConnectionExt: XProtoConnectionExt {
         )
     }
 
+    /// Append data to a property on a window with format 8.
+    ///
+    /// The data is added to the end of the existing value of the property. If the property
+    /// does not exist yet, this behaves like [`ConnectionExt::change_property8`] with
+    /// [`PropMode::REPLACE`].
+    ///
+    /// The property must have format 8 and the given type. Otherwise, the X11 server answers
+    /// with a `Match` error.
+    fn append_property8<A, B>(
+        &self,
+        window: Window,
+        property: A,
+        type_: B,
+        data: &[u8],
+    ) -> Result<VoidCookie<'_, Self>, ConnectionError>
+    where
+        A: Into<Atom>,
+        B: Into<Atom>,
+    {
+        self.change_property8(PropMode::APPEND, window, property, type_, data)
+    }
+
+    /// Prepend data to a property on a window with format 8.
+    ///
+    /// The data is added to the beginning of the existing value of the property. If the property
+    /// does not exist yet, this behaves like [`ConnectionExt::change_property8`] with
+    /// [`PropMode::REPLACE`].
+    ///
+    /// The property must have format 8 and the given type. Otherwise, the X11 server answers
+    /// with a `Match` error.
+    fn prepend_property8<A, B>(
+        &self,
+        window: Window,
+        property: A,
+        type_: B,
+        data: &[u8],
+    ) -> Result<VoidCookie<'_, Self>, ConnectionError>
+    where
+        A: Into<Atom>,
+        B: Into<Atom>,
+    {
+        self.change_property8(PropMode::PREPEND, window, property, type_, data)
+    }
+
+    /// Append data to a property on a window with format 16.
+    ///
+    /// The data is added to the end of the existing value of the property. If the property
+    /// does not exist yet, this behaves like [`ConnectionExt::change_property16`] with
+    /// [`PropMode::REPLACE`].
+    ///
+    /// The property must have format 16 and the given type. Otherwise, the X11 server answers
+    /// with a `Match` error.
+    fn append_property16<A, B>(
+        &self,
+        window: Window,
+        property: A,
+        type_: B,
+        data: &[u16],
+    ) -> Result<VoidCookie<'_, Self>, ConnectionError>
+    where
+        A: Into<Atom>,
+        B: Into<Atom>,
+    {
+        self.change_property16(PropMode::APPEND, window, property, type_, data)
+    }
+
+    /// Prepend data to a property on a window with format 16.
+    ///
+    /// The data is added to the beginning of the existing value of the property. If the property
+    /// does not exist yet, this behaves like [`ConnectionExt::change_property16`] with
+    /// [`PropMode::REPLACE`].
+    ///
+    /// The property must have format 16 and the given type. Otherwise, the X11 server answers
+    /// with a `Match` error.
+    fn prepend_property16<A, B>(
+        &self,
+        window: Window,
+        property: A,
+        type_: B,
+        data: &[u16],
+    ) -> Result<VoidCookie<'_, Self>, ConnectionError>
+    where
+        A: Into<Atom>,
+        B: Into<Atom>,
+    {
+        self.change_property16(PropMode::PREPEND, window, property, type_, data)
+    }
+
+    /// Append data to a property on a window with format 32.
+    ///
+    /// The data is added to the end of the existing value of the property. If the property
+    /// does not exist yet, this behaves like [`ConnectionExt::change_property32`] with
+    /// [`PropMode::REPLACE`].
+    ///
+    /// The property must have format 32 and the given type. Otherwise, the X11 server answers
+    /// with a `Match` error.
+    fn append_property32<A, B>(
+        &self,
+        window: Window,
+        property: A,
+        type_: B,
+        data: &[u32],
+    ) -> Result<VoidCookie<'_, Self>, ConnectionError>
+    where
+        A: Into<Atom>,
+        B: Into<Atom>,
+    {
+        self.change_property32(PropMode::APPEND, window, property, type_, data)
+    }
+
+    /// Prepend data to a property on a window with format 32.
+    ///
+    /// The data is added to the beginning of the existing value of the property. If the property
+    /// does not exist yet, this behaves like [`ConnectionExt::change_property32`] with
+    /// [`PropMode::REPLACE`].
+    ///
+    /// The property must have format 32 and the given type. Otherwise, the X11 server answers
+    /// with a `Match` error.
+    fn prepend_property32<A, B>(
+        &self,
+        window: Window,
+        property: A,
+        type_: B,
+        data: &[u32],
+    ) -> Result<VoidCookie<'_, Self>, ConnectionError>
+    where
+        A: Into<Atom>,
+        B: Into<Atom>,
+    {
+        self.change_property32(PropMode::PREPEND, window, property, type_, data)
+    }
+
+    /// Get the complete value of a property on a window.
+    ///
+    /// A single `GetProperty` request only returns a part of a large property. This function
+    /// sends as many `GetProperty` requests as needed and returns a reply that contains the
+    /// whole value of the property. `type_` can be `AtomEnum::ANY` to accept any type.
+    ///
+    /// If `delete` is true, the property is deleted after it was read completely. This is what
+    /// the requestor of a selection transfer has to do to acknowledge the receipt of the data,
+    /// e.g. for each chunk of an `INCR` transfer. Deleting the property while reading it would
+    /// corrupt the transfer. Thus, `delete` is only applied to the request that reads the end of
+    /// the property. For the first request, this is decided by the X11 server, which only deletes
+    /// a property if nothing of it remains unread.
+    ///
+    /// If the property does not exist or has a different type, the reply to the first request is
+    /// returned as-is and nothing is deleted. If the property is changed by someone else while it
+    /// is read, the returned reply has a non-zero `bytes_after`.
+    ///
+    /// `ParseError::ConversionFailed` is returned if the offset of the next part of the property
+    /// does not fit into a `GetProperty` request.
+    fn get_property_full<A, B>(
+        &self,
+        delete: bool,
+        window: Window,
+        property: A,
+        type_: B,
+    ) -> Result<GetPropertyReply, ReplyError>
+    where
+        A: Into<Atom>,
+        B: Into<Atom>,
+    {
+        let (property, type_) = (property.into(), type_.into());
+        let mut reply = self
+            .get_property(
+                delete,
+                window,
+                property,
+                type_,
+                0,
+                GET_PROPERTY_CHUNK_LENGTH,
+            )?
+            .reply()?;
+        let type_matches = type_ == Atom::from(AtomEnum::ANY) || type_ == reply.type_;
+        if reply.format == 0 || !type_matches {
+            return Ok(reply);
+        }
+
+        while reply.bytes_after > 0 {
+            // Only the request that reads the rest of the property may delete it
+            let is_final = reply.bytes_after <= GET_PROPERTY_CHUNK_LENGTH * 4;
+            let offset = (reply.value.len() / 4)
+                .try_into()
+                .map_err(|_| ParseError::ConversionFailed)?;
+            let next = self
+                .get_property(
+                    delete && is_final,
+                    window,
+                    property,
+                    reply.type_,
+                    offset,
+                    GET_PROPERTY_CHUNK_LENGTH,
+                )?
+                .reply()?;
+            if next.format != reply.format || next.type_ != reply.type_ {
+                // The property was changed while it was read; report what is known
+                reply.bytes_after = next.bytes_after;
+                break;
+            }
+            reply.value.extend(next.value);
+            reply.value_len += next.value_len;
+            reply.bytes_after = next.bytes_after;
+        }
+        Ok(reply)
+    }
+
+    /// Get the value of a text property on a window as a string.
+    ///
+    /// The whole value is read via [`ConnectionExt::get_property_full`]. A value of type
+    /// `STRING` is decoded as ISO Latin-1, as specified by the ICCCM. Values of any other type,
+    /// e.g. `UTF8_STRING`, are decoded as UTF-8, where invalid sequences are replaced with
+    /// `U+FFFD REPLACEMENT CHARACTER`.
+    ///
+    /// `None` is returned if the property does not exist or does not have format 8.
+    fn get_property_string<A>(
+        &self,
+        window: Window,
+        property: A,
+    ) -> Result<Option<String>, ReplyError>
+    where
+        A: Into<Atom>,
+    {
+        let reply = self.get_property_full(false, window, property, AtomEnum::ANY)?;
+        if reply.format != 8 {
+            return Ok(None);
+        }
+        let value = if reply.type_ == Atom::from(AtomEnum::STRING) {
+            reply.value.iter().map(|&byte| char::from(byte)).collect()
+        } else {
+            String::from_utf8_lossy(&reply.value).into_owned()
+        };
+        Ok(Some(value))
+    }
+
     /// Synchronise with the X11 server.
     ///
     /// This function synchronises with the X11 server. This means that all requests that are still
@@ -108,26 +350,304 @@ impl<C: XProtoConnectionExt + ?Sized> ConnectionExt for C {}
 ///
 /// Instances of this struct represent that we sent a [super::protocol::xproto::grab_server]
 /// request. When this struct is dropped, an [super::protocol::xproto::ungrab_server] request is
-/// sent.
+/// sent and the connection is flushed. Thus, the server is also released when a panic unwinds
+/// past the guard. While the server is grabbed, all other clients are stalled, so an
+/// `UngrabServer` request that stays in the output buffer would freeze the whole display.
 ///
 /// Any errors during `Drop` are silently ignored. Most likely an error here means that your
 /// X11 connection is broken and later requests will also fail.
 #[derive(Debug)]
-pub struct GrabServer<'c, C: XProtoConnectionExt>(&'c C);
+pub struct GrabServer<'c, C: XProtoConnectionExt> {
+    conn: &'c C,
+    // Drop cannot require a Connection, so this remembers how to flush the connection
+    flush: fn(&C) -> Result<(), ConnectionError>,
+}
 
-impl<'c, C: XProtoConnectionExt> GrabServer<'c, C> {
+impl<'c, C: Connection> GrabServer<'c, C> {
     /// Grab the server by sending a [super::protocol::xproto::grab_server] request.
     ///
-    /// The returned type will call [super::protocol::xproto::ungrab_server] when it is dropped.
+    /// The connection is flushed, so that the grab takes effect right away. The returned type
+    /// will call [super::protocol::xproto::ungrab_server] when it is dropped.
     pub fn grab(conn: &'c C) -> Result<Self, ConnectionError> {
         // Grab the server, return any errors, ignore the resulting VoidCookie
         drop(conn.grab_server()?);
-        Ok(Self(conn))
+        // Create the guard before flushing, so that a failed flush still sends an ungrab
+        let guard = Self {
+            conn,
+            flush: C::flush,
+        };
+        conn.flush()?;
+        Ok(guard)
     }
 }
 
 impl<C: XProtoConnectionExt> Drop for GrabServer<'_, C> {
     fn drop(&mut self) {
-        let _ = (self.0).ungrab_server();
+        if self.conn.ungrab_server().is_ok() {
+            let _ = (self.flush)(self.conn);
+        }
+    }
+}
+
+/// A RAII-like wrapper around [super::protocol::xproto::grab_pointer] and
+/// [super::protocol::xproto::ungrab_pointer].
+///
+/// Instances of this struct represent an active pointer grab. When this struct is dropped, an
+/// [super::protocol::xproto::ungrab_pointer] request is sent and the connection is flushed. Thus, any error that is propagated with `?` after the grab succeeded still
+/// releases the grab.
+///
+/// Any errors during `Drop` are silently ignored. Most likely an error here means that your
+/// X11 connection is broken and later requests will also fail.
+#[derive(Debug)]
+pub struct GrabPointer<'c, C: XProtoConnectionExt> {
+    conn: &'c C,
+    time: Timestamp,
+    // See GrabServer
+    flush: fn(&C) -> Result<(), ConnectionError>,
+}
+
+impl<'c, C: Connection> GrabPointer<'c, C> {
+    /// Grab the pointer by sending a [super::protocol::xproto::grab_pointer] request.
+    ///
+    /// This waits for the reply. If the X11 server did not grant the grab, `Ok(None)` is
+    /// returned. Otherwise the returned type will call [super::protocol::xproto::ungrab_pointer]
+    /// with the same `time` when it is dropped.
+    #[allow(clippy::too_many_arguments)]
+    pub fn grab<A, B>(
+        conn: &'c C,
+        owner_events: bool,
+        grab_window: Window,
+        event_mask: EventMask,
+        pointer_mode: GrabMode,
+        keyboard_mode: GrabMode,
+        confine_to: A,
+        cursor: B,
+        time: Timestamp,
+    ) -> Result<Option<Self>, ReplyError>
+    where
+        A: Into<Window>,
+        B: Into<Cursor>,
+    {
+        let reply = conn
+            .grab_pointer(
+                owner_events,
+                grab_window,
+                event_mask,
+                pointer_mode,
+                keyboard_mode,
+                confine_to,
+                cursor,
+                time,
+            )?
+            .reply()?;
+        // Create the guard before doing anything else, so that nothing can leak the grab
+        if reply.status == GrabStatus::SUCCESS {
+            Ok(Some(Self {
+                conn,
+                time,
+                flush: C::flush,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<C: XProtoConnectionExt> Drop for GrabPointer<'_, C> {
+    fn drop(&mut self) {
+        if self.conn.ungrab_pointer(self.time).is_ok() {
+            let _ = (self.flush)(self.conn);
+        }
+    }
+}
+
+/// A RAII-like wrapper around [super::protocol::xproto::grab_keyboard] and
+/// [super::protocol::xproto::ungrab_keyboard].
+///
+/// Instances of this struct represent an active keyboard grab. When this struct is dropped, an
+/// [super::protocol::xproto::ungrab_keyboard] request is sent and the connection is flushed. Thus, any error that is propagated with `?` after the grab succeeded still
+/// releases the grab.
+///
+/// Any errors during `Drop` are silently ignored. Most likely an error here means that your
+/// X11 connection is broken and later requests will also fail.
+#[derive(Debug)]
+pub struct GrabKeyboard<'c, C: XProtoConnectionExt> {
+    conn: &'c C,
+    time: Timestamp,
+    // See GrabServer
+    flush: fn(&C) -> Result<(), ConnectionError>,
+}
+
+impl<'c, C: Connection> GrabKeyboard<'c, C> {
+    /// Grab the keyboard by sending a [super::protocol::xproto::grab_keyboard] request.
+    ///
+    /// This waits for the reply. If the X11 server did not grant the grab, `Ok(None)` is
+    /// returned. Otherwise the returned type will call [super::protocol::xproto::ungrab_keyboard]
+    /// with the same `time` when it is dropped.
+    pub fn grab(
+        conn: &'c C,
+        owner_events: bool,
+        grab_window: Window,
+        time: Timestamp,
+        pointer_mode: GrabMode,
+        keyboard_mode: GrabMode,
+    ) -> Result<Option<Self>, ReplyError> {
+        let reply = conn
+            .grab_keyboard(owner_events, grab_window, time, pointer_mode, keyboard_mode)?
+            .reply()?;
+        // Create the guard before doing anything else, so that nothing can leak the grab
+        if reply.status == GrabStatus::SUCCESS {
+            Ok(Some(Self {
+                conn,
+                time,
+                flush: C::flush,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<C: XProtoConnectionExt> Drop for GrabKeyboard<'_, C> {
+    fn drop(&mut self) {
+        if self.conn.ungrab_keyboard(self.time).is_ok() {
+            let _ = (self.flush)(self.conn);
+        }
+    }
+}
+
+/// The state of the keyboard during a [`SyncKeyboardGrab`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyboardGrabState {
+    /// The X11 server does not process keyboard events. Events are queued in the server.
+    Frozen,
+    /// The X11 server sends the next keyboard event and then freezes the keyboard again.
+    WaitingForEvent,
+    /// The X11 server processes keyboard events normally.
+    Thawed,
+}
+
+/// A synchronous keyboard grab for modal interactions.
+///
+/// With a synchronous grab, the X11 server freezes the keyboard: keyboard events are queued in
+/// the server until the client allows them with an `AllowEvents` request. This type sends these
+/// requests and tracks the resulting [`KeyboardGrabState`]:
+///
+/// - [`SyncKeyboardGrab::process_next`] lets exactly one event through. When it arrives and is
+///   given to [`SyncKeyboardGrab::handle_event`], the keyboard is frozen again.
+/// - [`SyncKeyboardGrab::replay`] sends the event that caused the freeze again as if the grab did
+///   not exist, so that it reaches the window that would normally get it. This only has an effect
+///   if the freeze was caused by activating a passive grab, e.g. from
+///   [`GrabKey`](super::protocol::xproto::GrabKeyRequest) with `GrabMode::SYNC`.
+/// - [`SyncKeyboardGrab::discard`] unfreezes the keyboard. The event that caused the freeze is
+///   consumed and later events are delivered without freezing again.
+///
+/// The `AllowEvents` requests use the time of the last key event that was given to
+/// [`SyncKeyboardGrab::handle_event`], so that they cannot accidentally affect a later freeze.
+///
+/// When this struct is dropped, an [super::protocol::xproto::ungrab_keyboard] request is sent,
+/// which also unfreezes the keyboard, and the connection is flushed. Any errors during `Drop` are
+/// silently ignored.
+#[derive(Debug)]
+pub struct SyncKeyboardGrab<'c, C: XProtoConnectionExt> {
+    conn: &'c C,
+    time: Timestamp,
+    state: KeyboardGrabState,
+    // See GrabServer
+    flush: fn(&C) -> Result<(), ConnectionError>,
+}
+
+impl<'c, C: Connection> SyncKeyboardGrab<'c, C> {
+    /// Grab the keyboard with `keyboard_mode = GrabMode::SYNC`.
+    ///
+    /// The pointer is not frozen. This waits for the reply to the
+    /// [super::protocol::xproto::grab_keyboard] request. If the X11 server did not grant the grab,
+    /// `Ok(None)` is returned. Otherwise, the keyboard is frozen.
+    pub fn grab(
+        conn: &'c C,
+        grab_window: Window,
+        time: Timestamp,
+    ) -> Result<Option<Self>, ReplyError> {
+        let reply = conn
+            .grab_keyboard(false, grab_window, time, GrabMode::ASYNC, GrabMode::SYNC)?
+            .reply()?;
+        if reply.status == GrabStatus::SUCCESS {
+            Ok(Some(Self {
+                conn,
+                time,
+                state: KeyboardGrabState::Frozen,
+                flush: C::flush,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<C: XProtoConnectionExt> SyncKeyboardGrab<'_, C> {
+    /// Get the current state of the keyboard.
+    pub fn state(&self) -> KeyboardGrabState {
+        self.state
+    }
+
+    /// Update the state for an event that was received from the X11 server.
+    ///
+    /// All events should be passed to this function. Only `KeyPress` and `KeyRelease` events are
+    /// relevant.
+    pub fn handle_event(&mut self, event: &Event) {
+        let time = match event {
+            Event::KeyPress(event) => event.time,
+            Event::KeyRelease(event) => event.time,
+            _ => return,
+        };
+        self.time = time;
+        if self.state == KeyboardGrabState::WaitingForEvent {
+            self.state = KeyboardGrabState::Frozen;
+        }
+    }
+
+    /// Let the X11 server send the next keyboard event and freeze the keyboard afterwards.
+    pub fn process_next(&mut self) -> Result<(), ConnectionError> {
+        self.allow(Allow::SYNC_KEYBOARD)?;
+        if self.state == KeyboardGrabState::Frozen {
+            self.state = KeyboardGrabState::WaitingForEvent;
+        }
+        Ok(())
+    }
+
+    /// Replay the event that caused the freeze, ignoring the grab, and unfreeze the keyboard.
+    ///
+    /// This does nothing if the keyboard is not frozen.
+    pub fn replay(&mut self) -> Result<(), ConnectionError> {
+        self.unfreeze(Allow::REPLAY_KEYBOARD)
+    }
+
+    /// Consume the event that caused the freeze and unfreeze the keyboard.
+    ///
+    /// This does nothing if the keyboard is not frozen.
+    pub fn discard(&mut self) -> Result<(), ConnectionError> {
+        self.unfreeze(Allow::ASYNC_KEYBOARD)
+    }
+
+    fn unfreeze(&mut self, mode: Allow) -> Result<(), ConnectionError> {
+        if self.state == KeyboardGrabState::Frozen {
+            self.allow(mode)?;
+            self.state = KeyboardGrabState::Thawed;
+        }
+        Ok(())
+    }
+
+    fn allow(&self, mode: Allow) -> Result<(), ConnectionError> {
+        // Errors from AllowEvents are not interesting, ignore the resulting VoidCookie
+        self.conn.allow_events(mode, self.time)?.ignore_error();
+        Ok(())
+    }
+}
+
+impl<C: XProtoConnectionExt> Drop for SyncKeyboardGrab<'_, C> {
+    fn drop(&mut self) {
+        if self.conn.ungrab_keyboard(self.time).is_ok() {
+            let _ = (self.flush)(self.conn);
+        }
     }
 }
@@ -5,6 +5,210 @@ pub use x11rb_protocol::x11_utils::{
     Request, RequestHeader, Serialize, TryParse, TryParseFd, X11Error,
 };
 
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::connection::RequestConnection;
+use crate::cookie::Cookie;
+use crate::errors::{ConnectionError, ReplyError};
+use crate::protocol::xproto::{Atom, AtomEnum, ConnectionExt, InternAtomReply};
+
+/// The names of the atoms that are predefined by the core protocol.
+const PREDEFINED_ATOMS: [(&[u8], AtomEnum); 68] = [
+    (b"PRIMARY", AtomEnum::PRIMARY),
+    (b"SECONDARY", AtomEnum::SECONDARY),
+    (b"ARC", AtomEnum::ARC),
+    (b"ATOM", AtomEnum::ATOM),
+    (b"BITMAP", AtomEnum::BITMAP),
+    (b"CARDINAL", AtomEnum::CARDINAL),
+    (b"COLORMAP", AtomEnum::COLORMAP),
+    (b"CURSOR", AtomEnum::CURSOR),
+    (b"CUT_BUFFER0", AtomEnum::CUT_BUFFE_R0),
+    (b"CUT_BUFFER1", AtomEnum::CUT_BUFFE_R1),
+    (b"CUT_BUFFER2", AtomEnum::CUT_BUFFE_R2),
+    (b"CUT_BUFFER3", AtomEnum::CUT_BUFFE_R3),
+    (b"CUT_BUFFER4", AtomEnum::CUT_BUFFE_R4),
+    (b"CUT_BUFFER5", AtomEnum::CUT_BUFFE_R5),
+    (b"CUT_BUFFER6", AtomEnum::CUT_BUFFE_R6),
+    (b"CUT_BUFFER7", AtomEnum::CUT_BUFFE_R7),
+    (b"DRAWABLE", AtomEnum::DRAWABLE),
+    (b"FONT", AtomEnum::FONT),
+    (b"INTEGER", AtomEnum::INTEGER),
+    (b"PIXMAP", AtomEnum::PIXMAP),
+    (b"POINT", AtomEnum::POINT),
+    (b"RECTANGLE", AtomEnum::RECTANGLE),
+    (b"RESOURCE_MANAGER", AtomEnum::RESOURCE_MANAGER),
+    (b"RGB_COLOR_MAP", AtomEnum::RGB_COLOR_MAP),
+    (b"RGB_BEST_MAP", AtomEnum::RGB_BEST_MAP),
+    (b"RGB_BLUE_MAP", AtomEnum::RGB_BLUE_MAP),
+    (b"RGB_DEFAULT_MAP", AtomEnum::RGB_DEFAULT_MAP),
+    (b"RGB_GRAY_MAP", AtomEnum::RGB_GRAY_MAP),
+    (b"RGB_GREEN_MAP", AtomEnum::RGB_GREEN_MAP),
+    (b"RGB_RED_MAP", AtomEnum::RGB_RED_MAP),
+    (b"STRING", AtomEnum::STRING),
+    (b"VISUALID", AtomEnum::VISUALID),
+    (b"WINDOW", AtomEnum::WINDOW),
+    (b"WM_COMMAND", AtomEnum::WM_COMMAND),
+    (b"WM_HINTS", AtomEnum::WM_HINTS),
+    (b"WM_CLIENT_MACHINE", AtomEnum::WM_CLIENT_MACHINE),
+    (b"WM_ICON_NAME", AtomEnum::WM_ICON_NAME),
+    (b"WM_ICON_SIZE", AtomEnum::WM_ICON_SIZE),
+    (b"WM_NAME", AtomEnum::WM_NAME),
+    (b"WM_NORMAL_HINTS", AtomEnum::WM_NORMAL_HINTS),
+    (b"WM_SIZE_HINTS", AtomEnum::WM_SIZE_HINTS),
+    (b"WM_ZOOM_HINTS", AtomEnum::WM_ZOOM_HINTS),
+    (b"MIN_SPACE", AtomEnum::MIN_SPACE),
+    (b"NORM_SPACE", AtomEnum::NORM_SPACE),
+    (b"MAX_SPACE", AtomEnum::MAX_SPACE),
+    (b"END_SPACE", AtomEnum::END_SPACE),
+    (b"SUPERSCRIPT_X", AtomEnum::SUPERSCRIPT_X),
+    (b"SUPERSCRIPT_Y", AtomEnum::SUPERSCRIPT_Y),
+    (b"SUBSCRIPT_X", AtomEnum::SUBSCRIPT_X),
+    (b"SUBSCRIPT_Y", AtomEnum::SUBSCRIPT_Y),
+    (b"UNDERLINE_POSITION", AtomEnum::UNDERLINE_POSITION),
+    (b"UNDERLINE_THICKNESS", AtomEnum::UNDERLINE_THICKNESS),
+    (b"STRIKEOUT_ASCENT", AtomEnum::STRIKEOUT_ASCENT),
+    (b"STRIKEOUT_DESCENT", AtomEnum::STRIKEOUT_DESCENT),
+    (b"ITALIC_ANGLE", AtomEnum::ITALIC_ANGLE),
+    (b"X_HEIGHT", AtomEnum::X_HEIGHT),
+    (b"QUAD_WIDTH", AtomEnum::QUAD_WIDTH),
+    (b"WEIGHT", AtomEnum::WEIGHT),
+    (b"POINT_SIZE", AtomEnum::POINT_SIZE),
+    (b"RESOLUTION", AtomEnum::RESOLUTION),
+    (b"COPYRIGHT", AtomEnum::COPYRIGHT),
+    (b"NOTICE", AtomEnum::NOTICE),
+    (b"FONT_NAME", AtomEnum::FONT_NAME),
+    (b"FAMILY_NAME", AtomEnum::FAMILY_NAME),
+    (b"FULL_NAME", AtomEnum::FULL_NAME),
+    (b"CAP_HEIGHT", AtomEnum::CAP_HEIGHT),
+    (b"WM_CLASS", AtomEnum::WM_CLASS),
+    (b"WM_TRANSIENT_FOR", AtomEnum::WM_TRANSIENT_FOR),
+];
+
+/// Get the value of an atom that is predefined by the core protocol.
+///
+/// The core protocol defines 68 atoms with fixed values, for example `PRIMARY`, `STRING`, or
+/// `WM_NAME`. These are available as [`AtomEnum`] and do not need to be interned. This function
+/// returns the value of such an atom based on its name, or `None` for any other name.
+///
+/// ```
+/// # use x11rb::protocol::xproto::AtomEnum;
+/// # use x11rb::x11_utils::predefined_atom;
+/// assert_eq!(predefined_atom(b"CARDINAL"), Some(AtomEnum::CARDINAL.into()));
+/// assert_eq!(predefined_atom(b"_NET_WM_NAME"), None);
+/// ```
+pub fn predefined_atom(name: &[u8]) -> Option<Atom> {
+    PREDEFINED_ATOMS
+        .iter()
+        .find(|(atom_name, _)| *atom_name == name)
+        .map(|(_, atom)| (*atom).into())
+}
+
+#[doc(hidden)]
+#[derive(Debug)]
+pub enum __AtomManagerCookie<'a, C: RequestConnection> {
+    Predefined(Atom),
+    Interned(Cookie<'a, C, InternAtomReply>),
+}
+
+impl<'a, C: ConnectionExt> __AtomManagerCookie<'a, C> {
+    #[doc(hidden)]
+    pub fn new(conn: &'a C, name: &[u8]) -> Result<Self, ConnectionError> {
+        match predefined_atom(name) {
+            Some(atom) => Ok(Self::Predefined(atom)),
+            None => Ok(Self::Interned(conn.intern_atom(false, name)?)),
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn reply(self) -> Result<Atom, ReplyError> {
+        match self {
+            Self::Predefined(atom) => Ok(atom),
+            Self::Interned(cookie) => Ok(cookie.reply()?.atom),
+        }
+    }
+}
+
+/// A cache for atoms that are interned at runtime.
+///
+/// Unlike [`atom_manager!`], the names of the atoms do not have to be known at compile time.
+/// Each name is interned at most once via `InternAtom`. Atoms that are predefined by the core
+/// protocol are never interned, see [`predefined_atom`].
+///
+/// Atoms are valid for the lifetime of the X11 server, so a cache must only be used with
+/// connections to the same X11 server.
+///
+/// ```no_run
+/// # use x11rb::x11_utils::AtomCache;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let (conn, _) = x11rb::connect(None)?;
+/// let cache = AtomCache::default();
+/// // Both atoms are interned with a single round trip
+/// let atoms = cache.intern_many(&conn, &["_NET_WM_NAME", "UTF8_STRING"])?;
+/// // This does not contact the X11 server
+/// assert_eq!(cache.get_or_intern(&conn, "UTF8_STRING")?, atoms[1]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct AtomCache {
+    atoms: Mutex<HashMap<String, Atom>>,
+}
+
+impl AtomCache {
+    /// Create a new, empty cache.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Get the atom with the given name from the cache without interning it.
+    pub fn get(&self, name: &str) -> Option<Atom> {
+        predefined_atom(name.as_bytes()).or_else(|| self.atoms.lock().unwrap().get(name).copied())
+    }
+
+    /// Get the atom with the given name, interning it if it is not cached yet.
+    pub fn get_or_intern<C: RequestConnection + ?Sized>(
+        &self,
+        conn: &C,
+        name: &str,
+    ) -> Result<Atom, ReplyError> {
+        Ok(self.intern_many(conn, &[name])?[0])
+    }
+
+    /// Get the atoms with the given names, interning all that are not cached yet.
+    ///
+    /// All necessary `InternAtom` requests are sent before the first reply is waited for, so
+    /// this needs at most one round trip. The returned atoms are in the same order as `names`.
+    pub fn intern_many<C: RequestConnection + ?Sized>(
+        &self,
+        conn: &C,
+        names: &[&str],
+    ) -> Result<Vec<Atom>, ReplyError> {
+        let mut missing = Vec::new();
+        for &name in names {
+            if self.get(name).is_none() && !missing.contains(&name) {
+                missing.push(name);
+            }
+        }
+
+        // The lock is not held while waiting for the X11 server. If another thread interns the
+        // same name in the meantime, both get the same atom from the server.
+        let cookies = missing
+            .iter()
+            .map(|name| crate::protocol::xproto::intern_atom(conn, false, name.as_bytes()))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (name, cookie) in missing.iter().zip(cookies) {
+            let atom = cookie.reply()?.atom;
+            let _ = self.atoms.lock().unwrap().insert(name.to_string(), atom);
+        }
+
+        Ok(names
+            .iter()
+            .map(|name| self.get(name).expect("Atom was just interned"))
+            .collect())
+    }
+}
+
 /// A helper macro for managing atoms
 ///
 /// In X11, one often has to work with many different atoms that are already known at compile time.
@@ -79,6 +283,10 @@ pub use x11rb_protocol::x11_utils::{
 ///     }
 /// }
 /// ```
+///
+/// The expansion above is slightly simplified: For fields that name one of the atoms that are
+/// predefined by the core protocol, e.g. `CARDINAL` or `WM_NAME`, no `InternAtom` request is sent.
+/// Instead, their value is looked up via [`predefined_atom`].
 #[macro_export]
 macro_rules! atom_manager {
     {
@@ -96,7 +304,7 @@ macro_rules! atom_manager {
         $vis struct $cookie_name<'a, C: $crate::protocol::xproto::ConnectionExt> {
             phantom: std::marker::PhantomData<&'a C>,
             $(
-                $field_name: $crate::cookie::Cookie<'a, C, $crate::protocol::xproto::InternAtomReply>,
+                $field_name: $crate::x11_utils::__AtomManagerCookie<'a, C>,
             )*
         }
 
@@ -117,8 +325,8 @@ macro_rules! atom_manager {
                 Ok($cookie_name {
                     phantom: std::marker::PhantomData,
                     $(
-                        $field_name: _conn.intern_atom(
-                            false,
+                        $field_name: $crate::x11_utils::__AtomManagerCookie::new(
+                            _conn,
                             $crate::__atom_manager_atom_value!($field_name$(: $atom_value)?),
                         )?,
                     )*
@@ -130,7 +338,7 @@ macro_rules! atom_manager {
             $vis fn reply(self) -> ::std::result::Result<$struct_name, $crate::errors::ReplyError> {
                 Ok($struct_name {
                     $(
-                        $field_name: self.$field_name.reply()?.atom,
+                        $field_name: self.$field_name.reply()?,
                     )*
                 })
             }
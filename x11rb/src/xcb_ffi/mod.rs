@@ -10,9 +10,10 @@ use std::os::raw::c_int;
 use std::os::unix::io::{AsRawFd, RawFd};
 use std::ptr::{null, null_mut};
 use std::sync::{
-    atomic::{AtomicU64, Ordering},
+    atomic::{AtomicU64, AtomicUsize, Ordering},
     Mutex,
 };
+use std::time::{Duration, Instant};
 
 use libc::c_void;
 
@@ -22,7 +23,10 @@ use crate::connection::{
 use crate::cookie::{Cookie, CookieWithFds, VoidCookie};
 pub use crate::errors::{ConnectError, ConnectionError, ParseError, ReplyError, ReplyOrIdError};
 use crate::extension_manager::ExtensionManager;
+use crate::outstanding_requests::OutstandingRequests;
 use crate::protocol::xproto::Setup;
+#[cfg(unix)]
+use crate::utils::poll_timeout_millis;
 use crate::utils::{CSlice, RawFdContainer};
 use crate::x11_utils::{ExtensionInformation, TryParse, TryParseFd};
 
@@ -44,6 +48,42 @@ pub type BufWithFds = crate::connection::BufWithFds<Buffer>;
 ///
 /// This type wraps `*mut xcb_connection_t` that is provided by libxcb. It provides a rust
 /// interface to this C library.
+///
+/// # Sharing between threads
+///
+/// libxcb is thread-safe and so is this type: `XCBConnection` is `Send` and `Sync` and all its
+/// methods only need `&self`. There is no need for a `Mutex` or a cloneable handle. To use a
+/// connection from several threads, put it into an [`Arc`](std::sync::Arc) and give each thread
+/// a clone. One thread can then block in `wait_for_event()` while other threads send requests
+/// and wait for their replies.
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use x11rb::connection::Connection;
+/// use x11rb::protocol::xproto::ConnectionExt;
+/// use x11rb::xcb_ffi::XCBConnection;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let (conn, screen_num) = XCBConnection::connect(None)?;
+/// let conn = Arc::new(conn);
+///
+/// let event_conn = Arc::clone(&conn);
+/// let event_thread = std::thread::spawn(move || event_conn.wait_for_event());
+///
+/// let root = conn.setup().roots[screen_num].root;
+/// let _tree = conn.query_tree(root)?.reply()?;
+/// let _event = event_thread.join().unwrap()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The connection is closed when the last `Arc` is dropped.
+///
+/// # Waiting with a timeout
+///
+/// On unix, `wait_for_event_with_timeout()` waits until the connection's socket is readable. On
+/// other platforms, it checks for new events every 10 ms, so an event can be noticed up to 10 ms
+/// late.
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Debug)]
 pub struct XCBConnection {
@@ -52,6 +92,11 @@ pub struct XCBConnection {
     ext_mgr: Mutex<ExtensionManager>,
     errors: pending_errors::PendingErrors,
     maximum_sequence_received: AtomicU64,
+    // The result of maximum_request_bytes() or zero if it was not queried yet
+    maximum_request_bytes: AtomicUsize,
+    // The authorization protocol name and data, if known
+    auth_info: Option<(Vec<u8>, Vec<u8>)>,
+    outstanding_requests: OutstandingRequests,
 }
 
 impl XCBConnection {
@@ -95,33 +140,133 @@ impl XCBConnection {
     /// If a `dpy_name` is provided, it describes the display that should be connected to, for
     /// example `127.0.0.1:1`. If no value is provided, the `$DISPLAY` environment variable is
     /// used.
+    ///
+    /// libxcb determines the authorization information itself, e.g. from `~/.Xauthority`. This
+    /// information is not available afterwards, so [`XCBConnection::auth_info`] returns `None`
+    /// for connections established this way.
     pub fn connect(dpy_name: Option<&CStr>) -> Result<(XCBConnection, usize), ConnectError> {
-        use libc::c_int;
         unsafe {
             let mut screen: c_int = 0;
             let dpy_ptr = dpy_name.map_or(null(), |s| s.as_ptr());
-            let connection = raw_ffi::XcbConnectionWrapper::new(
-                raw_ffi::xcb_connect(dpy_ptr, &mut screen),
-                true,
-            );
-            let error = raw_ffi::xcb_connection_has_error(connection.as_ptr());
-            if error != 0 {
-                Err(Self::connect_error_from_c_error(error))
-            } else {
-                let setup = raw_ffi::xcb_get_setup(connection.as_ptr());
-                let conn = XCBConnection {
-                    // `xcb_connect` will never return null.
-                    conn: connection,
-                    setup: Self::parse_setup(setup)?,
-                    ext_mgr: Default::default(),
-                    errors: Default::default(),
-                    maximum_sequence_received: AtomicU64::new(0),
-                };
-                Ok((conn, screen as usize))
+            let connection = raw_ffi::xcb_connect(dpy_ptr, &mut screen);
+            let conn = Self::from_new_connection(connection, None)?;
+            Ok((conn, screen as usize))
+        }
+    }
+
+    /// Establish a new connection to an X11 server with the given authorization information.
+    ///
+    /// `dpy_name` is interpreted like in [`XCBConnection::connect`]. `auth_name` is the name of the
+    /// authorization protocol, for example `MIT-MAGIC-COOKIE-1`, and `auth_data` is the data for
+    /// this protocol.
+    ///
+    /// The authorization information is kept and can be retrieved via
+    /// [`XCBConnection::auth_info`], e.g. for forwarding it to a nested X11 server.
+    pub fn connect_with_auth(
+        dpy_name: Option<&CStr>,
+        auth_name: Vec<u8>,
+        auth_data: Vec<u8>,
+    ) -> Result<(XCBConnection, usize), ConnectError> {
+        let namelen = auth_name
+            .len()
+            .try_into()
+            .map_err(|_| ConnectError::InsufficientMemory)?;
+        let datalen = auth_data
+            .len()
+            .try_into()
+            .map_err(|_| ConnectError::InsufficientMemory)?;
+        unsafe {
+            let mut screen: c_int = 0;
+            let dpy_ptr = dpy_name.map_or(null(), |s| s.as_ptr());
+            // libxcb only reads the authorization information while connecting
+            let mut auth = raw_ffi::xcb_auth_info_t {
+                namelen,
+                name: auth_name.as_ptr() as *mut _,
+                datalen,
+                data: auth_data.as_ptr() as *mut _,
+            };
+            let connection =
+                raw_ffi::xcb_connect_to_display_with_auth_info(dpy_ptr, &mut auth, &mut screen);
+            let conn = Self::from_new_connection(connection, Some((auth_name, auth_data)))?;
+            Ok((conn, screen as usize))
+        }
+    }
+
+    /// Establish a new connection to an X11 server over an already connected socket.
+    ///
+    /// This is useful when e.g. a launcher passes a connected socket to the process. `$DISPLAY`
+    /// is not consulted. Instead, the connection setup is performed over `fd`, optionally with
+    /// the given authorization information as `(name, data)`, see
+    /// [`XCBConnection::connect_with_auth`].
+    ///
+    /// The returned connection owns `fd`: libxcb closes it when the connection is dropped. This
+    /// also happens if connecting fails.
+    ///
+    /// Since there is no display name, the screen to use cannot be determined automatically and
+    /// has to be provided by the caller, e.g. when a launcher passes it along with the socket.
+    /// Like [`XCBConnection::connect`], the connection and the screen number are returned.
+    /// `ConnectError::InvalidScreen` is returned if the server does not have this screen.
+    #[cfg(unix)]
+    pub fn connect_to_fd(
+        fd: impl Into<RawFdContainer>,
+        auth_info: Option<(Vec<u8>, Vec<u8>)>,
+        screen: usize,
+    ) -> Result<(XCBConnection, usize), ConnectError> {
+        let fd = fd.into();
+        let mut auth = match &auth_info {
+            Some((auth_name, auth_data)) => Some(raw_ffi::xcb_auth_info_t {
+                namelen: auth_name
+                    .len()
+                    .try_into()
+                    .map_err(|_| ConnectError::InsufficientMemory)?,
+                name: auth_name.as_ptr() as *mut _,
+                datalen: auth_data
+                    .len()
+                    .try_into()
+                    .map_err(|_| ConnectError::InsufficientMemory)?,
+                data: auth_data.as_ptr() as *mut _,
+            }),
+            None => None,
+        };
+        let auth_ptr: *mut raw_ffi::xcb_auth_info_t = match auth.as_mut() {
+            Some(auth) => auth,
+            None => null_mut(),
+        };
+        unsafe {
+            // libxcb only reads the authorization information while connecting
+            let connection = raw_ffi::xcb_connect_to_fd(fd.into_raw_fd(), auth_ptr);
+            let conn = Self::from_new_connection(connection, auth_info)?;
+            if screen >= conn.setup.roots.len() {
+                return Err(ConnectError::InvalidScreen);
             }
+            Ok((conn, screen))
         }
     }
 
+    /// Wrap a connection that was just returned by one of libxcb's connect functions.
+    unsafe fn from_new_connection(
+        connection: *mut raw_ffi::xcb_connection_t,
+        auth_info: Option<(Vec<u8>, Vec<u8>)>,
+    ) -> Result<XCBConnection, ConnectError> {
+        // libxcb's connect functions never return null.
+        let connection = raw_ffi::XcbConnectionWrapper::new(connection, true);
+        let error = raw_ffi::xcb_connection_has_error(connection.as_ptr());
+        if error != 0 {
+            return Err(Self::connect_error_from_c_error(error));
+        }
+        let setup = raw_ffi::xcb_get_setup(connection.as_ptr());
+        Ok(XCBConnection {
+            conn: connection,
+            setup: Self::parse_setup(setup)?,
+            ext_mgr: Default::default(),
+            errors: Default::default(),
+            maximum_sequence_received: AtomicU64::new(0),
+            maximum_request_bytes: AtomicUsize::new(0),
+            auth_info,
+            outstanding_requests: Default::default(),
+        })
+    }
+
     /// Create a connection wrapper for a raw libxcb `xcb_connection_t`.
     ///
     /// `xcb_disconnect` is called on drop only if `should_drop` is `true`.
@@ -146,9 +291,24 @@ impl XCBConnection {
             ext_mgr: Default::default(),
             errors: Default::default(),
             maximum_sequence_received: AtomicU64::new(0),
+            maximum_request_bytes: AtomicUsize::new(0),
+            auth_info: None,
+            outstanding_requests: Default::default(),
         })
     }
 
+    /// Get the authorization information that was used for establishing this connection.
+    ///
+    /// Returns the name of the authorization protocol and its data. This is only available for
+    /// connections established via [`XCBConnection::connect_with_auth`]. With
+    /// [`XCBConnection::connect`], libxcb determines the authorization information itself and
+    /// does not expose it.
+    pub fn auth_info(&self) -> Option<(&[u8], &[u8])> {
+        self.auth_info
+            .as_ref()
+            .map(|(name, data)| (&name[..], &data[..]))
+    }
+
     unsafe fn parse_setup(setup: *const raw_ffi::xcb_setup_t) -> Result<Setup, ParseError> {
         use std::slice::from_raw_parts;
 
@@ -251,18 +411,6 @@ impl XCBConnection {
         }
     }
 
-    /// Check if the underlying XCB connection is in an error state.
-    pub fn has_error(&self) -> Option<ConnectionError> {
-        unsafe {
-            let error = raw_ffi::xcb_connection_has_error(self.conn.as_ptr());
-            if error == 0 {
-                None
-            } else {
-                Some(Self::connection_error_from_c_error(error))
-            }
-        }
-    }
-
     /// Get access to the raw libxcb `xcb_connection_t`.
     ///
     /// The returned pointer is valid for as long as the original object was not dropped. No
@@ -367,10 +515,9 @@ impl RequestConnection for XCBConnection {
     where
         R: TryParse,
     {
-        Ok(Cookie::new(
-            self,
-            self.send_request(bufs, fds, true, false)?,
-        ))
+        let sequence = self.send_request(bufs, fds, true, false)?;
+        self.outstanding_requests.sent(sequence);
+        Ok(Cookie::new(self, sequence))
     }
 
     fn send_request_with_reply_with_fds<R>(
@@ -381,10 +528,9 @@ impl RequestConnection for XCBConnection {
     where
         R: TryParseFd,
     {
-        Ok(CookieWithFds::new(
-            self,
-            self.send_request(bufs, fds, true, true)?,
-        ))
+        let sequence = self.send_request(bufs, fds, true, true)?;
+        self.outstanding_requests.sent(sequence);
+        Ok(CookieWithFds::new(self, sequence))
     }
 
     fn send_request_without_reply(
@@ -399,6 +545,7 @@ impl RequestConnection for XCBConnection {
     }
 
     fn discard_reply(&self, sequence: SequenceNumber, _kind: RequestKind, mode: DiscardMode) {
+        self.outstanding_requests.resolved(sequence);
         match mode {
             DiscardMode::DiscardReplyAndError => unsafe {
                 // libxcb can throw away everything for us
@@ -409,6 +556,10 @@ impl RequestConnection for XCBConnection {
         }
     }
 
+    fn forget_reply(&self, sequence: SequenceNumber) {
+        self.outstanding_requests.resolved(sequence);
+    }
+
     fn prefetch_extension_information(
         &self,
         extension_name: &'static str,
@@ -436,6 +587,9 @@ impl RequestConnection for XCBConnection {
         unsafe {
             let mut error = null_mut();
             let reply = raw_ffi::xcb_wait_for_reply64(self.conn.as_ptr(), sequence, &mut error);
+            if !reply.is_null() || !error.is_null() {
+                self.outstanding_requests.resolved(sequence);
+            }
             match (reply.is_null(), error.is_null()) {
                 (true, true) => Err(Self::connection_error_from_connection(self.conn.as_ptr())),
                 (false, true) => Ok(ReplyOrError::Reply(self.wrap_reply(reply as _, sequence))),
@@ -446,6 +600,30 @@ impl RequestConnection for XCBConnection {
         }
     }
 
+    fn poll_for_reply_or_raw_error(
+        &self,
+        sequence: SequenceNumber,
+    ) -> Result<Option<ReplyOrError<CSlice>>, ConnectionError> {
+        // Ensure the request is sent
+        self.flush()?;
+        match self.poll_for_reply(sequence) {
+            Err(()) => match unsafe { raw_ffi::xcb_connection_has_error(self.conn.as_ptr()) } {
+                0 => Ok(None),
+                err => Err(Self::connection_error_from_c_error(err)),
+            },
+            // Requests with a reply always get a reply or an error
+            Ok(None) => unsafe { Err(Self::connection_error_from_connection(self.conn.as_ptr())) },
+            Ok(Some(buffer)) => {
+                self.outstanding_requests.resolved(sequence);
+                if buffer[0] == 0 {
+                    Ok(Some(ReplyOrError::Error(buffer)))
+                } else {
+                    Ok(Some(ReplyOrError::Reply(buffer)))
+                }
+            }
+        }
+    }
+
     fn wait_for_reply(&self, sequence: SequenceNumber) -> Result<Option<CSlice>, ConnectionError> {
         match self.wait_for_reply_or_raw_error(sequence)? {
             ReplyOrError::Reply(reply) => Ok(Some(reply)),
@@ -483,9 +661,18 @@ impl RequestConnection for XCBConnection {
     #[cfg(not(unix))]
     fn wait_for_reply_with_fds_raw(
         &self,
-        _sequence: SequenceNumber,
+        sequence: SequenceNumber,
     ) -> Result<ReplyOrError<BufWithFds, Buffer>, ConnectionError> {
-        unimplemented!("FD passing is currently only implemented on Unix-like systems")
+        // libxcb only supports FD passing via Unix domain sockets, so no FDs can be received here.
+        let buffer = match self.wait_for_reply_or_raw_error(sequence)? {
+            ReplyOrError::Reply(reply) => reply,
+            ReplyOrError::Error(error) => return Ok(ReplyOrError::Error(error)),
+        };
+        // The number of FDs is in the second byte (= buffer[1]) in all replies.
+        if buffer[1] != 0 {
+            return Err(ConnectionError::FdPassingFailed);
+        }
+        Ok(ReplyOrError::Reply((buffer, Vec::new())))
     }
 
     fn check_for_raw_error(
@@ -504,7 +691,26 @@ impl RequestConnection for XCBConnection {
     }
 
     fn maximum_request_bytes(&self) -> usize {
-        4 * unsafe { raw_ffi::xcb_get_maximum_request_length(self.conn.as_ptr()) as usize }
+        let length =
+            4 * unsafe { raw_ffi::xcb_get_maximum_request_length(self.conn.as_ptr()) as usize };
+        self.maximum_request_bytes.store(length, Ordering::Relaxed);
+        length
+    }
+
+    fn enable_big_requests(&self) -> Result<usize, ConnectionError> {
+        // libxcb only negotiates the maximum request length once
+        let length = self.maximum_request_bytes();
+        match self.has_error() {
+            Some(error) => Err(error),
+            None => Ok(length),
+        }
+    }
+
+    fn maximum_request_bytes_cached(&self) -> Option<usize> {
+        match self.maximum_request_bytes.load(Ordering::Relaxed) {
+            0 => None,
+            length => Some(length),
+        }
     }
 
     fn prefetch_maximum_request_bytes(&self) {
@@ -523,6 +729,17 @@ impl RequestConnection for XCBConnection {
 }
 
 impl Connection for XCBConnection {
+    fn has_error(&self) -> Option<ConnectionError> {
+        unsafe {
+            let error = raw_ffi::xcb_connection_has_error(self.conn.as_ptr());
+            if error == 0 {
+                None
+            } else {
+                Some(Self::connection_error_from_c_error(error))
+            }
+        }
+    }
+
     fn wait_for_raw_event_with_sequence(&self) -> Result<RawEventAndSeqNumber, ConnectionError> {
         if let Some(error) = self.errors.get(self) {
             return Ok((error.1, error.0));
@@ -556,6 +773,33 @@ impl Connection for XCBConnection {
         }
     }
 
+    fn wait_for_raw_event_with_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<Option<CSlice>, ConnectionError> {
+        let deadline = Instant::now().checked_add(timeout);
+        loop {
+            // This also returns pending errors before reading from the socket
+            if let Some((event, _)) = self.poll_for_raw_event_with_sequence()? {
+                return Ok(Some(event));
+            }
+            let timeout = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Ok(None);
+                    }
+                    deadline - now
+                }
+                // The timeout is so large that it cannot elapse
+                None => MAX_WAIT_SLICE,
+            };
+            // Another thread might read an event from the socket while we are waiting. Waiting
+            // in slices makes sure that such an event is noticed soon.
+            self.wait_readable(timeout.min(MAX_WAIT_SLICE))?;
+        }
+    }
+
     fn flush(&self) -> Result<(), ConnectionError> {
         // xcb_flush() returns 0 if the connection is in (or just entered) an error state, else 1.
         let res = unsafe { raw_ffi::xcb_flush(self.conn.as_ptr()) };
@@ -583,6 +827,51 @@ impl Connection for XCBConnection {
     fn setup(&self) -> &Setup {
         &self.setup
     }
+
+    fn outstanding_requests(&self) -> usize {
+        self.outstanding_requests.count()
+    }
+}
+
+/// The maximum time that `wait_for_raw_event_with_timeout` waits before checking libxcb's event
+/// queue again.
+const MAX_WAIT_SLICE: Duration = Duration::from_millis(50);
+
+impl XCBConnection {
+    /// Wait until the connection's socket is readable, but at most for `timeout`.
+    #[cfg(unix)]
+    fn wait_readable(&self, timeout: Duration) -> Result<(), ConnectionError> {
+        let mut poll_fd = libc::pollfd {
+            fd: self.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        if unsafe { libc::poll(&mut poll_fd, 1, poll_timeout_millis(timeout)) } < 0 {
+            let error = IOError::last_os_error();
+            if error.kind() != ErrorKind::Interrupted {
+                return Err(error.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait for at most 10 ms.
+    ///
+    /// There is no portable way to wait for the socket here, so the caller just polls libxcb's
+    /// event queue again afterwards. Thus, `wait_for_raw_event_with_timeout` notices a new event
+    /// up to 10 ms late and wakes up every 10 ms while it waits.
+    #[cfg(not(unix))]
+    fn wait_readable(&self, timeout: Duration) -> Result<(), ConnectionError> {
+        std::thread::sleep(timeout.min(Duration::from_millis(10)));
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for XCBConnection {
+    /// Print a summary of the X11 server, see [`SetupSummary`](crate::connection::SetupSummary).
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        crate::connection::SetupSummary(self.setup()).fmt(f)
+    }
 }
 
 #[cfg(unix)]
@@ -647,6 +936,164 @@ mod test {
         assert_eq!(screen, 0);
     }
 
+    #[test]
+    fn is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<XCBConnection>();
+
+        let str = CString::new("display name").unwrap();
+        let (conn, _) = XCBConnection::connect(Some(&str)).expect("Failed to 'connect'");
+        let conn = std::sync::Arc::new(conn);
+        let conn2 = std::sync::Arc::clone(&conn);
+        let ptr = std::thread::spawn(move || conn2.get_raw_xcb_connection() as usize)
+            .join()
+            .unwrap();
+        assert_eq!(ptr, conn.get_raw_xcb_connection() as usize);
+    }
+
+    #[test]
+    fn flush_error() {
+        use super::raw_ffi::{self, connection_errors};
+        use crate::connection::Connection;
+        use crate::errors::ConnectionError;
+
+        let str = CString::new("display name").unwrap();
+        let (conn, _) = XCBConnection::connect(Some(&str)).expect("Failed to 'connect'");
+        conn.flush().unwrap();
+
+        unsafe {
+            raw_ffi::set_connection_error(conn.conn.as_ptr(), connection_errors::MEM_INSUFFICIENT)
+        };
+        match conn.flush() {
+            Err(ConnectionError::InsufficientMemory) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn has_error() {
+        use super::raw_ffi::{self, connection_errors};
+        use crate::connection::Connection;
+        use crate::errors::ConnectionError;
+
+        // Check the error state via the trait, as code that is generic over connections does
+        fn check(conn: &impl Connection) -> Option<ConnectionError> {
+            conn.has_error()
+        }
+
+        let str = CString::new("display name").unwrap();
+        let (conn, _) = XCBConnection::connect(Some(&str)).expect("Failed to 'connect'");
+        assert!(check(&conn).is_none());
+
+        unsafe {
+            raw_ffi::set_connection_error(conn.conn.as_ptr(), connection_errors::MEM_INSUFFICIENT)
+        };
+        match check(&conn) {
+            Some(ConnectionError::InsufficientMemory) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn from_raw_xcb_connection() {
+        let str = CString::new("display name").unwrap();
+        let (owner, _) = XCBConnection::connect(Some(&str)).expect("Failed to 'connect'");
+        let ptr = owner.get_raw_xcb_connection();
+
+        // A borrowing wrapper must not disconnect when it is dropped; the mock's xcb_disconnect()
+        // frees the connection, so this would be a double free
+        let borrowed = unsafe { XCBConnection::from_raw_xcb_connection(ptr, false) }
+            .expect("Failed to wrap connection");
+        assert_eq!(borrowed.get_raw_xcb_connection(), ptr);
+        assert_eq!(borrowed.setup.length, owner.setup.length);
+        drop(borrowed);
+        drop(owner);
+    }
+
+    #[test]
+    fn xcb_connect_with_auth() {
+        let str = CString::new("display name").unwrap();
+        let (conn, _) = XCBConnection::connect(Some(&str)).expect("Failed to 'connect'");
+        assert_eq!(conn.auth_info(), None);
+
+        let name = b"MIT-MAGIC-COOKIE-1".to_vec();
+        let data = b"secret".to_vec();
+        let (conn, screen) =
+            XCBConnection::connect_with_auth(Some(&str), name, data).expect("Failed to 'connect'");
+        assert_eq!(screen, 0);
+        assert_eq!(
+            conn.auth_info(),
+            Some((&b"MIT-MAGIC-COOKIE-1"[..], &b"secret"[..]))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn xcb_connect_to_fd() {
+        use crate::errors::ConnectError;
+        use crate::utils::RawFdContainer;
+
+        // The mock does not close the file descriptor, so the value does not need to be valid
+        let (conn, screen) = XCBConnection::connect_to_fd(RawFdContainer::new(42), None, 0)
+            .expect("Failed to 'connect'");
+        assert_eq!(screen, 0);
+        assert_eq!(conn.auth_info(), None);
+
+        let auth = (b"MIT-MAGIC-COOKIE-1".to_vec(), b"secret".to_vec());
+        let (conn, _) = XCBConnection::connect_to_fd(RawFdContainer::new(42), Some(auth), 0)
+            .expect("Failed to 'connect'");
+        assert_eq!(
+            conn.auth_info(),
+            Some((&b"MIT-MAGIC-COOKIE-1"[..], &b"secret"[..]))
+        );
+
+        // The mock server only has a single screen
+        match XCBConnection::connect_to_fd(RawFdContainer::new(42), None, 1) {
+            Err(ConnectError::InvalidScreen) => {}
+            other => panic!("Unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+
+    /// Copy `data` into memory from `malloc()`, as libxcb returns it.
+    fn malloc_event(data: &[u8]) -> *mut u8 {
+        unsafe {
+            let event = libc::malloc(data.len()) as *mut u8;
+            assert!(!event.is_null());
+            std::ptr::copy_nonoverlapping(data.as_ptr(), event, data.len());
+            event
+        }
+    }
+
+    #[test]
+    fn wrap_event() {
+        use crate::protocol::xproto::{GE_GENERIC_EVENT, KEY_PRESS_EVENT};
+
+        let str = CString::new("display name").unwrap();
+        let (conn, _) = XCBConnection::connect(Some(&str)).expect("Failed to 'connect'");
+
+        // libxcb inserts the full sequence number after the first 32 bytes
+        let mut data = vec![0; 36];
+        data[0] = KEY_PRESS_EVENT;
+        data[2..4].copy_from_slice(&7u16.to_ne_bytes());
+        data[32..36].copy_from_slice(&7u32.to_ne_bytes());
+        let (event, seqno) = unsafe { conn.wrap_event(malloc_event(&data)) }.unwrap();
+        assert_eq!(seqno, 7);
+        assert_eq!(&event[..], &data[..32]);
+
+        // A generic event with two additional 4-byte units of data
+        let mut data = vec![0; 44];
+        data[0] = GE_GENERIC_EVENT;
+        data[2..4].copy_from_slice(&8u16.to_ne_bytes());
+        data[4..8].copy_from_slice(&2u32.to_ne_bytes());
+        data[32..36].copy_from_slice(&8u32.to_ne_bytes());
+        data[36..44].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        let (event, seqno) = unsafe { conn.wrap_event(malloc_event(&data)) }.unwrap();
+        assert_eq!(seqno, 8);
+        assert_eq!(event.len(), 40);
+        assert_eq!(&event[..32], &data[..32]);
+        assert_eq!(&event[32..], &data[36..]);
+    }
+
     #[test]
     fn reconstruct_full_sequence() {
         use super::reconstruct_full_sequence_impl;
@@ -68,3 +68,25 @@ impl PendingErrors {
         None
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::ffi::CString;
+
+    use super::PendingErrors;
+    use crate::xcb_ffi::{raw_ffi, XCBConnection};
+
+    #[test]
+    fn poll_sequence_above_u32() {
+        let str = CString::new("display name").unwrap();
+        let (conn, _) = XCBConnection::connect(Some(&str)).expect("Failed to 'connect'");
+        let sequence = u64::from(u32::max_value()) + 5;
+
+        let errors = PendingErrors::default();
+        errors.discard_reply(sequence);
+        assert!(errors.get(&conn).is_none());
+        // The full sequence number must reach libxcb, not a truncated one
+        let polled = unsafe { raw_ffi::polled_replies(conn.conn.as_ptr()) };
+        assert_eq!(polled, [sequence]);
+    }
+}
@@ -8,7 +8,7 @@
 //! `libxcb.so` at runtime. Most of the code is actually responsible for this later feature.
 
 use super::{
-    c_char, c_int, c_uint, c_void, iovec, xcb_connection_t, xcb_generic_error_t,
+    c_char, c_int, c_uint, c_void, iovec, xcb_auth_info_t, xcb_connection_t, xcb_generic_error_t,
     xcb_generic_event_t, xcb_protocol_request_t, xcb_setup_t, xcb_void_cookie_t,
 };
 
@@ -150,6 +150,16 @@ make_ffi_fn_defs! {
         displayname: *const c_char,
         screenp: *mut c_int
     ) -> *mut xcb_connection_t;
+    fn xcb_connect_to_display_with_auth_info(
+        displayname: *const c_char,
+        auth: *mut xcb_auth_info_t,
+        screenp: *mut c_int
+    ) -> *mut xcb_connection_t;
+    #[cfg(unix)]
+    fn xcb_connect_to_fd(
+        fd: c_int,
+        auth_info: *mut xcb_auth_info_t
+    ) -> *mut xcb_connection_t;
     fn xcb_generate_id(c: *mut xcb_connection_t) -> u32;
 
     // From xcbext.h
@@ -114,6 +114,15 @@ pub(crate) struct xcb_setup_t {
     _unused: [u8; 0],
 }
 
+#[allow(non_camel_case_types)]
+#[repr(C)]
+pub(crate) struct xcb_auth_info_t {
+    pub(crate) namelen: c_int,
+    pub(crate) name: *mut c_char,
+    pub(crate) datalen: c_int,
+    pub(crate) data: *mut c_char,
+}
+
 pub(crate) mod connection_errors {
     use std::os::raw::c_int;
 
@@ -5,21 +5,44 @@ use std::ffi::CStr;
 use libc::{c_char, c_int, c_uint, c_void};
 
 use super::{
-    iovec, xcb_connection_t, xcb_generic_error_t, xcb_generic_event_t, xcb_protocol_request_t,
-    xcb_setup_t, xcb_void_cookie_t,
+    iovec, xcb_auth_info_t, xcb_connection_t, xcb_generic_error_t, xcb_generic_event_t,
+    xcb_protocol_request_t, xcb_setup_t, xcb_void_cookie_t,
 };
 use crate::protocol::xproto::{ImageOrder, Setup};
 use crate::x11_utils::Serialize;
 
 #[repr(C)]
 struct ConnectionMock {
-    error: c_int,
+    error: std::cell::Cell<c_int>,
     setup: Vec<u8>,
+    polled_replies: std::cell::RefCell<Vec<u64>>,
+}
+
+/// Get the sequence numbers that were passed to `xcb_poll_for_reply64()`.
+pub(crate) unsafe fn polled_replies(c: *mut xcb_connection_t) -> Vec<u64> {
+    // The pointer is suitable aligned since our xcb_connect() mock above created it
+    #[allow(clippy::cast_ptr_alignment)]
+    (*(c as *const ConnectionMock))
+        .polled_replies
+        .borrow()
+        .clone()
+}
+
+/// Put the connection into an error state, as if an I/O error happened.
+pub(crate) unsafe fn set_connection_error(c: *mut xcb_connection_t, error: c_int) {
+    // The pointer is suitable aligned since our xcb_connect() mock above created it
+    #[allow(clippy::cast_ptr_alignment)]
+    (*(c as *const ConnectionMock)).error.set(error)
 }
 
 // From xcb.h
-pub(crate) unsafe fn xcb_flush(_c: *mut xcb_connection_t) -> c_int {
-    unimplemented!();
+pub(crate) unsafe fn xcb_flush(c: *mut xcb_connection_t) -> c_int {
+    // Nothing is ever buffered, so flushing only fails in an error state
+    if xcb_connection_has_error(c) == 0 {
+        1
+    } else {
+        0
+    }
 }
 
 pub(crate) unsafe fn xcb_get_maximum_request_length(_c: *mut xcb_connection_t) -> u32 {
@@ -63,7 +86,7 @@ pub(crate) unsafe fn xcb_get_file_descriptor(_c: *mut xcb_connection_t) -> c_int
 pub(crate) unsafe fn xcb_connection_has_error(c: *mut xcb_connection_t) -> c_int {
     // The pointer is suitable aligned since our xcb_connect() mock above created it
     #[allow(clippy::cast_ptr_alignment)]
-    (*(c as *const ConnectionMock)).error
+    (*(c as *const ConnectionMock)).error.get()
 }
 
 pub(crate) unsafe fn xcb_disconnect(c: *mut xcb_connection_t) {
@@ -83,8 +106,12 @@ pub(crate) unsafe fn xcb_connect(
         "Did not get the expected displayname",
     );
     std::ptr::write(screenp, 0);
+    new_connection_mock()
+}
 
-    let length_field = 10;
+/// Create a mock connection with a minimal setup.
+fn new_connection_mock() -> *mut xcb_connection_t {
+    let length_field = 20;
     let setup = Setup {
         status: 0,
         protocol_major_version: 0,
@@ -103,15 +130,50 @@ pub(crate) unsafe fn xcb_connect(
         max_keycode: 0,
         vendor: Default::default(),
         pixmap_formats: Default::default(),
-        roots: Default::default(),
+        roots: vec![Default::default()],
     };
     let setup = setup.serialize();
     assert_eq!(setup.len(), 4 * length_field as usize);
 
-    let mock = ConnectionMock { error: 0, setup };
+    let mock = ConnectionMock {
+        error: Default::default(),
+        setup,
+        polled_replies: Default::default(),
+    };
     Box::into_raw(Box::new(mock)) as _
 }
 
+pub(crate) unsafe fn xcb_connect_to_display_with_auth_info(
+    displayname: *const c_char,
+    auth: *mut xcb_auth_info_t,
+    screenp: *mut c_int,
+) -> *mut xcb_connection_t {
+    // Test that the provided auth info is correct
+    let auth = &*auth;
+    let name = std::slice::from_raw_parts(auth.name as *const u8, auth.namelen as usize);
+    let data = std::slice::from_raw_parts(auth.data as *const u8, auth.datalen as usize);
+    assert_eq!(name, b"MIT-MAGIC-COOKIE-1");
+    assert_eq!(data, b"secret");
+    xcb_connect(displayname, screenp)
+}
+
+#[cfg(unix)]
+pub(crate) unsafe fn xcb_connect_to_fd(
+    fd: c_int,
+    auth_info: *mut xcb_auth_info_t,
+) -> *mut xcb_connection_t {
+    // libxcb takes ownership of the file descriptor; the test passes a fake one
+    assert_eq!(fd, 42);
+    if !auth_info.is_null() {
+        let auth = &*auth_info;
+        let name = std::slice::from_raw_parts(auth.name as *const u8, auth.namelen as usize);
+        let data = std::slice::from_raw_parts(auth.data as *const u8, auth.datalen as usize);
+        assert_eq!(name, b"MIT-MAGIC-COOKIE-1");
+        assert_eq!(data, b"secret");
+    }
+    new_connection_mock()
+}
+
 pub(crate) unsafe fn xcb_generate_id(_c: *mut xcb_connection_t) -> u32 {
     unimplemented!();
 }
@@ -147,10 +209,17 @@ pub(crate) unsafe fn xcb_wait_for_reply64(
 }
 
 pub(crate) unsafe fn xcb_poll_for_reply64(
-    _c: *mut xcb_connection_t,
-    _request: u64,
+    c: *mut xcb_connection_t,
+    request: u64,
     _reply: *mut *mut c_void,
     _error: *mut *mut xcb_generic_error_t,
 ) -> c_int {
-    unimplemented!();
+    // The pointer is suitable aligned since our xcb_connect() mock above created it
+    #[allow(clippy::cast_ptr_alignment)]
+    (*(c as *const ConnectionMock))
+        .polled_replies
+        .borrow_mut()
+        .push(request);
+    // The reply was not received yet
+    0
 }
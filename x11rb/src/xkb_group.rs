@@ -0,0 +1,138 @@
+//! Utility functions for tracking the active keyboard group.
+//!
+//! XKB supports up to four keyboard groups, which usually correspond to keyboard layouts.
+//! [`select_group_changes`] enables the `XKEYBOARD` extension and subscribes to `StateNotify`
+//! events that are only sent when the active group changes. [`GroupChanged::from_event`] turns
+//! such an event into a notification, while [`current_group`] queries the active group directly.
+//!
+//! All XKB events share a single event code and are distinguished by their `xkb_type` field.
+//! x11rb already takes care of this when parsing events, so that a `StateNotify` event arrives as
+//! `Event::XkbStateNotify`.
+//!
+//! Usage example:
+//! ```no_run
+//! use x11rb::connection::Connection;
+//! use x11rb::xkb_group::{current_group, select_group_changes, GroupChanged};
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let (conn, _) = x11rb::connect(None)?;
+//!     select_group_changes(&conn)?;
+//!     println!("Active group: {:?}", current_group(&conn)?);
+//!     loop {
+//!         let event = conn.wait_for_event()?;
+//!         if let Some(GroupChanged { group }) = GroupChanged::from_event(&event) {
+//!             println!("Active group: {:?}", group);
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::connection::RequestConnection;
+use crate::errors::{ConnectionError, ReplyError};
+use crate::protocol::xkb::{
+    ConnectionExt as _, EventType, Group, MapPart, SelectEventsAux, SelectEventsAuxBitcase2,
+    StatePart, ID,
+};
+use crate::protocol::Event;
+
+/// A notification that the active keyboard group changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupChanged {
+    /// The new active group.
+    pub group: Group,
+}
+
+impl GroupChanged {
+    /// Check whether the given event indicates a change of the active group.
+    ///
+    /// This returns `None` for all events except for XKB `StateNotify` events that report a
+    /// change of the active group.
+    pub fn from_event(event: &Event) -> Option<Self> {
+        match event {
+            Event::XkbStateNotify(event) => {
+                let group_changed = u16::from(event.changed) & u16::from(StatePart::GROUP_STATE);
+                if group_changed != 0 {
+                    Some(Self { group: event.group })
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Enable the `XKEYBOARD` extension and subscribe to changes of the active group.
+///
+/// Afterwards, changes of the active group of the core keyboard cause `StateNotify` events that
+/// can be recognised via [`GroupChanged::from_event`]. Other state changes, e.g. of the
+/// modifiers, do not cause events.
+///
+/// If the X11 server does not support the required version of the `XKEYBOARD` extension,
+/// `ConnectionError::UnsupportedExtension` is returned.
+pub fn select_group_changes<C>(conn: &C) -> Result<(), ReplyError>
+where
+    C: RequestConnection + ?Sized,
+{
+    let reply = conn.xkb_use_extension(1, 0)?.reply()?;
+    if !reply.supported {
+        return Err(ConnectionError::UnsupportedExtension.into());
+    }
+
+    let details = SelectEventsAux::new().bitcase2(SelectEventsAuxBitcase2 {
+        affect_state: StatePart::GROUP_STATE,
+        state_details: StatePart::GROUP_STATE,
+    });
+    conn.xkb_select_events(
+        ID::USE_CORE_KBD.into(),
+        EventType::from(0u16),
+        EventType::from(0u16),
+        MapPart::from(0u16),
+        MapPart::from(0u16),
+        &details,
+    )?
+    .check()
+}
+
+/// Query the active group of the core keyboard.
+///
+/// The `XKEYBOARD` extension has to be enabled first, e.g. via [`select_group_changes`].
+pub fn current_group<C>(conn: &C) -> Result<Group, ReplyError>
+where
+    C: RequestConnection + ?Sized,
+{
+    Ok(conn.xkb_get_state(ID::USE_CORE_KBD.into())?.reply()?.group)
+}
+
+#[cfg(test)]
+mod test {
+    use super::GroupChanged;
+    use crate::protocol::xkb::{Group, StateNotifyEvent, StatePart};
+    use crate::protocol::xproto::KeyPressEvent;
+    use crate::protocol::Event;
+
+    fn state_notify(changed: StatePart, group: Group) -> Event {
+        Event::XkbStateNotify(StateNotifyEvent {
+            changed,
+            group,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn group_changed() {
+        let event = state_notify(StatePart::GROUP_STATE | StatePart::GROUP_LOCK, Group::M2);
+        assert_eq!(
+            GroupChanged::from_event(&event),
+            Some(GroupChanged { group: Group::M2 })
+        );
+    }
+
+    #[test]
+    fn other_state_changes() {
+        let event = state_notify(StatePart::MODIFIER_STATE, Group::M2);
+        assert_eq!(GroupChanged::from_event(&event), None);
+        let event = Event::KeyPress(KeyPressEvent::default());
+        assert_eq!(GroupChanged::from_event(&event), None);
+    }
+}
@@ -0,0 +1,143 @@
+#[cfg(feature = "async")]
+mod test {
+    use std::future::Future;
+    use std::io::{Error, ErrorKind};
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+    use std::time::Duration;
+
+    use x11rb::async_connection::{AsyncConnection, ReadableFuture, WaitReadable};
+    use x11rb::protocol::xproto::{
+        ClientMessageEvent, ConnectionExt as _, GetInputFocusReply, ImageOrder, InputFocus, Setup,
+    };
+    use x11rb::protocol::Event;
+    use x11rb::rust_connection::{PollMode, RustConnection, Stream};
+    use x11rb::utils::RawFdContainer;
+    use x11rb::x11_utils::Serialize;
+
+    #[derive(Debug, Default)]
+    struct StreamData {
+        to_read: Vec<u8>,
+        written: Vec<u8>,
+    }
+
+    /// A stream that never blocks and returns `WouldBlock` when nothing can be read.
+    #[derive(Debug, Clone, Default)]
+    struct FakeStream(Arc<Mutex<StreamData>>);
+
+    impl Stream for FakeStream {
+        fn poll(&self, _mode: PollMode) -> std::io::Result<()> {
+            Ok(())
+        }
+
+        fn poll_with_timeout(&self, mode: PollMode, _timeout: Duration) -> std::io::Result<()> {
+            self.poll(mode)
+        }
+
+        fn read(
+            &self,
+            buf: &mut [u8],
+            _fd_storage: &mut Vec<RawFdContainer>,
+        ) -> std::io::Result<usize> {
+            let mut data = self.0.lock().unwrap();
+            if data.to_read.is_empty() {
+                return Err(Error::new(ErrorKind::WouldBlock, "Nothing to read"));
+            }
+            let len = data.to_read.len().min(buf.len());
+            buf[..len].copy_from_slice(&data.to_read[..len]);
+            let _ = data.to_read.drain(..len);
+            Ok(len)
+        }
+
+        fn write(&self, buf: &[u8], _fds: &mut Vec<RawFdContainer>) -> std::io::Result<usize> {
+            self.0.lock().unwrap().written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    /// A waiter that makes the given packet readable when it is awaited.
+    #[derive(Debug)]
+    struct Waiter {
+        stream: FakeStream,
+        packet: Vec<u8>,
+        written_when_waiting: Mutex<Vec<usize>>,
+    }
+
+    impl WaitReadable for Waiter {
+        fn wait_readable(&self) -> ReadableFuture<'_> {
+            let mut data = self.stream.0.lock().unwrap();
+            self.written_when_waiting
+                .lock()
+                .unwrap()
+                .push(data.written.len());
+            data.to_read.extend_from_slice(&self.packet);
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn connect(packet: Vec<u8>) -> AsyncConnection<RustConnection<FakeStream>, Waiter> {
+        let setup = Setup {
+            resource_id_mask: 0xff,
+            image_byte_order: ImageOrder::LSB_FIRST,
+            bitmap_format_bit_order: ImageOrder::LSB_FIRST,
+            ..Default::default()
+        };
+        let stream = FakeStream::default();
+        let waiter = Waiter {
+            stream: stream.clone(),
+            packet,
+            written_when_waiting: Default::default(),
+        };
+        let conn = RustConnection::for_connected_stream(stream, setup).unwrap();
+        AsyncConnection::new(conn, waiter)
+    }
+
+    /// Run a future that never returns `Poll::Pending`.
+    fn run<F: Future>(future: F) -> F::Output {
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+        let waker = unsafe { Waker::from_raw(clone(std::ptr::null())) };
+        let mut context = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        match Pin::new(&mut future).poll(&mut context) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("The future is not ready"),
+        }
+    }
+
+    #[test]
+    fn wait_for_event() {
+        let event = ClientMessageEvent::new(32, 0, 1u32, [42, 0, 0, 0, 0]);
+        let conn = connect(<[u8; 32]>::from(&event).to_vec());
+        match run(conn.wait_for_event()).unwrap() {
+            Event::ClientMessage(event) => assert_eq!(event.data.as_data32()[0], 42),
+            other => panic!("Unexpected event {:?}", other),
+        }
+        let (_, waiter) = conn.into_inner();
+        assert_eq!(waiter.written_when_waiting.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn reply_flushes() {
+        let reply = GetInputFocusReply {
+            revert_to: InputFocus::PARENT,
+            sequence: 1,
+            length: 0,
+            focus: 1234,
+        };
+        let mut packet = reply.serialize().to_vec();
+        packet.resize(32, 0);
+        let conn = connect(packet);
+        let cookie = conn.connection().get_input_focus().unwrap();
+        let reply = run(conn.reply(cookie)).unwrap();
+        assert_eq!(reply.focus, 1234);
+        // The GetInputFocus request was written before waiting for the reply
+        let (_, waiter) = conn.into_inner();
+        assert_eq!(*waiter.written_when_waiting.lock().unwrap(), [4]);
+    }
+}
@@ -0,0 +1,77 @@
+use std::io::{ErrorKind, Read};
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+
+use x11rb::errors::ConnectError;
+
+use x11rb::rust_connection::RustConnection;
+
+fn padded_len(len: u16) -> usize {
+    (usize::from(len) + 3) & !3
+}
+
+/// Listen on a free display number on the loopback interface.
+fn fake_server() -> (TcpListener, u16) {
+    (50..100)
+        .find_map(|display| {
+            TcpListener::bind(("127.0.0.1", 6000 + display))
+                .ok()
+                .map(|listener| (listener, display))
+        })
+        .expect("no free port for a fake X11 server")
+}
+
+#[test]
+fn connect_with_auth_sends_auth_info() {
+    let (listener, display) = fake_server();
+
+    // The fake server reads the setup request and then closes the connection
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut header = [0; 12];
+        stream.read_exact(&mut header).unwrap();
+        let read_u16 = |bytes: [u8; 2]| match header[0] {
+            b'l' => u16::from_le_bytes(bytes),
+            _ => u16::from_be_bytes(bytes),
+        };
+        let name_len = read_u16([header[6], header[7]]);
+        let data_len = read_u16([header[8], header[9]]);
+
+        let mut name = vec![0; padded_len(name_len)];
+        stream.read_exact(&mut name).unwrap();
+        name.truncate(name_len.into());
+        let mut data = vec![0; padded_len(data_len)];
+        stream.read_exact(&mut data).unwrap();
+        data.truncate(data_len.into());
+        (name, data)
+    });
+
+    let result = RustConnection::connect_with_auth(
+        Some(&format!("127.0.0.1:{}", display)),
+        b"MIT-MAGIC-COOKIE-1".to_vec(),
+        b"secret".to_vec(),
+    );
+    assert!(result.is_err());
+
+    let (name, data) = server.join().unwrap();
+    assert_eq!(name, b"MIT-MAGIC-COOKIE-1");
+    assert_eq!(data, b"secret");
+}
+
+#[test]
+fn connect_with_timeout_gives_up() {
+    // The server never accepts the connection and thus never sends a setup
+    let (_listener, display) = fake_server();
+
+    let start = Instant::now();
+    let result = RustConnection::connect_with_timeout(
+        Some(&format!("127.0.0.1:{}", display)),
+        Duration::from_millis(100),
+    );
+    match result {
+        Err(ConnectError::IoError(e)) => assert_eq!(e.kind(), ErrorKind::TimedOut),
+        Err(e) => panic!("unexpected error: {:?}", e),
+        Ok(_) => panic!("connecting unexpectedly succeeded"),
+    }
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
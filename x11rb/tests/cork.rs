@@ -0,0 +1,96 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{ConnectionExt, ImageOrder, Setup};
+use x11rb::rust_connection::{PollMode, RustConnection, Stream};
+use x11rb::utils::RawFdContainer;
+
+/// A stream that records each call to `write`.
+#[derive(Debug, Default)]
+struct RecordingStream(Mutex<Vec<Vec<u8>>>);
+
+impl RecordingStream {
+    fn take(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+impl Stream for RecordingStream {
+    fn poll(&self, _mode: PollMode) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn poll_with_timeout(&self, mode: PollMode, _timeout: Duration) -> std::io::Result<()> {
+        self.poll(mode)
+    }
+
+    fn read(
+        &self,
+        _buf: &mut [u8],
+        _fd_storage: &mut Vec<RawFdContainer>,
+    ) -> std::io::Result<usize> {
+        unimplemented!()
+    }
+
+    fn write(&self, buf: &[u8], _fds: &mut Vec<RawFdContainer>) -> std::io::Result<usize> {
+        self.0.lock().unwrap().push(buf.to_vec());
+        Ok(buf.len())
+    }
+}
+
+/// Create a connection whose write buffer only has space for one `MapWindow` request.
+fn connect() -> RustConnection<RecordingStream> {
+    let setup = Setup {
+        resource_id_mask: 0xff,
+        image_byte_order: ImageOrder::LSB_FIRST,
+        bitmap_format_bit_order: ImageOrder::LSB_FIRST,
+        ..Default::default()
+    };
+    let conn = RustConnection::for_connected_stream(RecordingStream::default(), setup).unwrap();
+    conn.set_write_buffer_capacity(8);
+    conn
+}
+
+fn map_windows(conn: &RustConnection<RecordingStream>, count: u32) {
+    for window in 0..count {
+        conn.map_window(window).unwrap().ignore_error();
+    }
+}
+
+#[test]
+fn full_buffer_is_written_without_cork() {
+    let conn = connect();
+    map_windows(&conn, 3);
+    conn.flush().unwrap();
+    assert_eq!(conn.stream().take().len(), 3);
+}
+
+#[test]
+fn cork_writes_once_on_drop() {
+    let conn = connect();
+    let cork = conn.cork();
+    map_windows(&conn, 3);
+    assert!(conn.stream().take().is_empty());
+
+    drop(cork);
+    let writes = conn.stream().take();
+    assert_eq!(writes.len(), 1);
+    assert_eq!(writes[0].len(), 3 * 8);
+}
+
+#[test]
+fn nested_corks() {
+    let conn = connect();
+    let outer = conn.cork();
+    let inner = conn.cork();
+    map_windows(&conn, 2);
+    inner.uncork().unwrap();
+    map_windows(&conn, 2);
+    assert!(conn.stream().take().is_empty());
+
+    outer.uncork().unwrap();
+    let writes = conn.stream().take();
+    assert_eq!(writes.len(), 1);
+    assert_eq!(writes[0].len(), 4 * 8);
+}
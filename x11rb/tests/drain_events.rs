@@ -0,0 +1,204 @@
+use std::io::{Error, ErrorKind};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use x11rb::connection::Connection;
+use x11rb::errors::ConnectionError;
+use x11rb::event_loop_integration::{drain_events, events, poll_iter, EventQueue};
+use x11rb::protocol::xproto::{ClientMessageEvent, ImageOrder, Setup};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::{PollMode, RustConnection, Stream};
+use x11rb::utils::RawFdContainer;
+
+/// A stream that returns some bytes, followed by either an error or blocking.
+#[derive(Debug)]
+struct FakeStream(Mutex<(Vec<u8>, Option<ErrorKind>)>);
+
+impl Stream for FakeStream {
+    fn poll(&self, _mode: PollMode) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn poll_with_timeout(&self, mode: PollMode, _timeout: Duration) -> std::io::Result<()> {
+        self.poll(mode)
+    }
+
+    fn read(
+        &self,
+        buf: &mut [u8],
+        _fd_storage: &mut Vec<RawFdContainer>,
+    ) -> std::io::Result<usize> {
+        let mut inner = self.0.lock().unwrap();
+        if inner.0.is_empty() {
+            let kind = inner.1.unwrap_or(ErrorKind::WouldBlock);
+            return Err(Error::new(kind, "Fake error"));
+        }
+        let len = inner.0.len().min(buf.len());
+        buf[..len].copy_from_slice(&inner.0[..len]);
+        inner.0.drain(..len);
+        Ok(len)
+    }
+
+    fn write(&self, _buf: &[u8], _fds: &mut Vec<RawFdContainer>) -> std::io::Result<usize> {
+        unimplemented!()
+    }
+}
+
+fn connect(num_events: u32, error: Option<ErrorKind>) -> RustConnection<FakeStream> {
+    let setup = Setup {
+        resource_id_mask: 0xff,
+        image_byte_order: ImageOrder::LSB_FIRST,
+        bitmap_format_bit_order: ImageOrder::LSB_FIRST,
+        ..Default::default()
+    };
+    let mut data = Vec::new();
+    for i in 0..num_events {
+        let event = ClientMessageEvent::new(32, 0, 1u32, [i, 0, 0, 0, 0]);
+        data.extend(<[u8; 32]>::from(&event));
+    }
+    let stream = FakeStream(Mutex::new((data, error)));
+    RustConnection::for_connected_stream(stream, setup).unwrap()
+}
+
+fn event_data(event: &Event) -> u32 {
+    match event {
+        Event::ClientMessage(event) => event.data.as_data32()[0],
+        _ => panic!("Unexpected event {:?}", event),
+    }
+}
+
+#[test]
+fn drain_all_events() {
+    let conn = connect(3, None);
+    let (events, result) = drain_events(&conn);
+    result.unwrap();
+    assert_eq!(events.iter().map(event_data).collect::<Vec<_>>(), [0, 1, 2]);
+
+    // Nothing more is available
+    let (events, result) = drain_events(&conn);
+    result.unwrap();
+    assert!(events.is_empty());
+}
+
+#[test]
+fn drain_events_with_error() {
+    let conn = connect(2, Some(ErrorKind::ConnectionReset));
+    let (events, result) = drain_events(&conn);
+    assert_eq!(events.iter().map(event_data).collect::<Vec<_>>(), [0, 1]);
+    match result {
+        Err(ConnectionError::IoError(e)) => assert_eq!(e.kind(), ErrorKind::ConnectionReset),
+        other => panic!("Unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn events_until_error() {
+    let conn = connect(2, Some(ErrorKind::ConnectionReset));
+    let mut iter = events(&conn);
+    assert_eq!(event_data(&iter.next().unwrap().unwrap()), 0);
+    assert_eq!(event_data(&iter.next().unwrap().unwrap()), 1);
+    match iter.next() {
+        Some(Err(ConnectionError::IoError(e))) => assert_eq!(e.kind(), ErrorKind::ConnectionReset),
+        other => panic!("Unexpected result {:?}", other),
+    }
+    // The error is only returned once
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn poll_iter_stops_without_events() {
+    let conn = connect(3, None);
+    let events = poll_iter(&conn).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(events.iter().map(event_data).collect::<Vec<_>>(), [0, 1, 2]);
+    assert!(poll_iter(&conn).next().is_none());
+
+    let conn = connect(1, Some(ErrorKind::ConnectionReset));
+    let mut iter = poll_iter(&conn);
+    assert_eq!(event_data(&iter.next().unwrap().unwrap()), 0);
+    assert!(iter.next().unwrap().is_err());
+    assert!(iter.next().is_none());
+}
+
+/// Get a connection with an event, a `Window` error, and another event in the stream.
+fn connect_with_x11_error() -> RustConnection<FakeStream> {
+    let conn = connect(1, None);
+    {
+        let mut inner = conn.stream().0.lock().unwrap();
+        let mut error = [0; 32];
+        error[1] = 3;
+        inner.0.extend_from_slice(&error);
+        let event = ClientMessageEvent::new(32, 0, 1u32, [1, 0, 0, 0, 0]);
+        inner.0.extend(<[u8; 32]>::from(&event));
+    }
+    conn
+}
+
+fn check_x11_error_in_order(events: &[Event]) {
+    use x11rb::protocol::ErrorKind as X11ErrorKind;
+
+    assert_eq!(events.len(), 3);
+    assert_eq!(event_data(&events[0]), 0);
+    match &events[1] {
+        Event::Error(error) => assert_eq!(error.error_kind, X11ErrorKind::Window),
+        other => panic!("Unexpected event {:?}", other),
+    }
+    assert_eq!(event_data(&events[2]), 1);
+}
+
+#[test]
+fn drain_events_with_x11_error() {
+    let conn = connect_with_x11_error();
+    let (events, result) = drain_events(&conn);
+    result.unwrap();
+    check_x11_error_in_order(&events);
+}
+
+#[test]
+fn poll_for_queued_events() {
+    let conn = connect_with_x11_error();
+    check_x11_error_in_order(&conn.poll_for_queued_events().unwrap());
+    assert!(conn.poll_for_queued_events().unwrap().is_empty());
+
+    let conn = connect(2, Some(ErrorKind::ConnectionReset));
+    match conn.poll_for_queued_events() {
+        Err(ConnectionError::IoError(e)) => assert_eq!(e.kind(), ErrorKind::ConnectionReset),
+        other => panic!("Unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn wait_for_specific_event_keeps_other_events() {
+    let conn = connect(5, None);
+    let mut queue = EventQueue::new(&conn);
+    let event = queue
+        .wait_for_specific_event(|event| event_data(event) == 2)
+        .unwrap();
+    assert_eq!(event_data(&event), 2);
+    assert_eq!(queue.len(), 2);
+
+    // Queued events are checked first
+    let event = queue
+        .wait_for_specific_event(|event| event_data(event) == 1)
+        .unwrap();
+    assert_eq!(event_data(&event), 1);
+
+    // The remaining events are returned in order
+    assert_eq!(event_data(&queue.wait_for_event().unwrap()), 0);
+    assert_eq!(event_data(&queue.wait_for_event().unwrap()), 3);
+    assert_eq!(event_data(&queue.poll_for_event().unwrap().unwrap()), 4);
+    assert!(queue.poll_for_event().unwrap().is_none());
+}
+
+#[test]
+fn poll_for_specific_event_without_match() {
+    let conn = connect(3, None);
+    let mut queue = EventQueue::new(&conn);
+    assert!(queue
+        .poll_for_specific_event(|event| event_data(event) == 42)
+        .unwrap()
+        .is_none());
+    assert_eq!(queue.len(), 3);
+    let events = std::iter::from_fn(|| queue.poll_for_event().unwrap()).collect::<Vec<_>>();
+    assert_eq!(events.iter().map(event_data).collect::<Vec<_>>(), [0, 1, 2]);
+    assert!(queue.is_empty());
+}
@@ -52,6 +52,7 @@ mod fake_stream {
     use std::io::{Error, ErrorKind};
     use std::sync::mpsc::{channel, Receiver, Sender};
     use std::sync::{Condvar, Mutex};
+    use std::time::Duration;
 
     use x11rb::errors::ConnectError;
     use x11rb::protocol::xproto::{
@@ -182,6 +183,10 @@ mod fake_stream {
             }
         }
 
+        fn poll_with_timeout(&self, mode: PollMode, _timeout: Duration) -> std::io::Result<()> {
+            self.poll(mode)
+        }
+
         fn read(
             &self,
             buf: &mut [u8],
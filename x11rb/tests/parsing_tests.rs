@@ -1,6 +1,7 @@
 use x11rb::errors::ParseError;
 use x11rb::protocol::xproto::{Setup, VisualClass};
-use x11rb::x11_utils::TryParse;
+use x11rb::protocol::Event;
+use x11rb::x11_utils::{ExtInfoProvider, ExtensionInformation, TryParse};
 
 fn get_setup_data() -> Vec<u8> {
     let mut s = Vec::new();
@@ -134,6 +135,29 @@ fn parse_setup() -> Result<(), ParseError> {
     Ok(())
 }
 
+#[test]
+fn setup_accessors() -> Result<(), ParseError> {
+    let setup = get_setup_data();
+    let (mut setup, _) = Setup::try_parse(&*setup)?;
+    assert!(setup.default_screen(1).is_none());
+
+    let screen = setup.default_screen(0).unwrap();
+    assert_eq!(1, screen.root);
+    // The root depth of the screen is zero, which is not an allowed depth
+    assert!(screen.default_depth().is_none());
+
+    let (depth, visual) = screen.visual_by_id(80).unwrap();
+    assert_eq!(99, depth.depth);
+    assert_eq!(81, visual.colormap_entries);
+    assert!(screen.visual_by_id(0).is_none());
+
+    setup.roots[0].root_depth = 99;
+    let depth = setup.roots[0].default_depth().unwrap();
+    assert_eq!(99, depth.depth);
+
+    Ok(())
+}
+
 #[cfg(feature = "xinput")]
 #[test]
 fn parse_xi_get_property_reply_format_0() {
@@ -166,3 +190,125 @@ fn parse_xi_get_property_reply_format_0() {
         )),
     );
 }
+
+/// Provides the information for RandR with first event 89.
+struct RandrProvider;
+
+impl RandrProvider {
+    const INFO: ExtensionInformation = ExtensionInformation {
+        major_opcode: 140,
+        first_event: 89,
+        first_error: 147,
+    };
+}
+
+impl ExtInfoProvider for RandrProvider {
+    fn get_from_major_opcode(&self, _major_opcode: u8) -> Option<(&str, ExtensionInformation)> {
+        unimplemented!()
+    }
+    fn get_from_event_code(&self, event_code: u8) -> Option<(&str, ExtensionInformation)> {
+        Some(("RANDR", Self::INFO)).filter(|_| (89..91).contains(&event_code))
+    }
+    fn get_from_error_code(&self, _error_code: u8) -> Option<(&str, ExtensionInformation)> {
+        unimplemented!()
+    }
+}
+
+#[test]
+fn parse_sent_core_event() -> Result<(), ParseError> {
+    use x11rb::protocol::xproto::{ConfigureNotifyEvent, CONFIGURE_NOTIFY_EVENT};
+
+    let event = ConfigureNotifyEvent {
+        response_type: CONFIGURE_NOTIFY_EVENT,
+        window: 42,
+        width: 640,
+        height: 480,
+        ..Default::default()
+    };
+    let mut data = <[u8; 32]>::from(&event);
+
+    let parsed = Event::parse(&data, &RandrProvider)?;
+    assert!(!parsed.sent_event());
+    match parsed {
+        Event::ConfigureNotify(parsed) => assert_eq!(parsed, event),
+        _ => panic!("Unexpected event {:?}", parsed),
+    }
+
+    // Events sent via SendEvent have the most significant bit set
+    data[0] |= 0x80;
+    let parsed = Event::parse(&data, &RandrProvider)?;
+    assert!(parsed.sent_event());
+    assert_eq!(parsed.response_type(), CONFIGURE_NOTIFY_EVENT);
+    match parsed {
+        Event::ConfigureNotify(parsed) => {
+            assert_eq!(parsed.window, 42);
+            assert_eq!((parsed.width, parsed.height), (640, 480));
+        }
+        _ => panic!("Unexpected event {:?}", parsed),
+    }
+    Ok(())
+}
+
+#[cfg(feature = "randr")]
+#[test]
+fn parse_sent_extension_event() -> Result<(), ParseError> {
+    use x11rb::protocol::randr::ScreenChangeNotifyEvent;
+
+    let event = ScreenChangeNotifyEvent {
+        response_type: RandrProvider::INFO.first_event,
+        root: 1,
+        width: 1920,
+        height: 1080,
+        ..Default::default()
+    };
+    let mut data = <[u8; 32]>::from(&event);
+    data[0] |= 0x80;
+
+    let parsed = Event::parse(&data, &RandrProvider)?;
+    assert!(parsed.sent_event());
+    assert_eq!(parsed.response_type(), RandrProvider::INFO.first_event);
+    match parsed {
+        Event::RandrScreenChangeNotify(parsed) => {
+            assert_eq!(parsed.root, 1);
+            assert_eq!((parsed.width, parsed.height), (1920, 1080));
+        }
+        _ => panic!("Unexpected event {:?}", parsed),
+    }
+    Ok(())
+}
+
+#[test]
+fn parse_unknown_generic_event() -> Result<(), ParseError> {
+    use x11rb::protocol::xproto::GE_GENERIC_EVENT;
+
+    struct NoExtensions;
+
+    impl ExtInfoProvider for NoExtensions {
+        fn get_from_major_opcode(&self, _: u8) -> Option<(&str, ExtensionInformation)> {
+            None
+        }
+        fn get_from_event_code(&self, _: u8) -> Option<(&str, ExtensionInformation)> {
+            None
+        }
+        fn get_from_error_code(&self, _: u8) -> Option<(&str, ExtensionInformation)> {
+            None
+        }
+    }
+
+    // A generic event with two additional four-byte units after the first 32 bytes
+    let mut data = vec![GE_GENERIC_EVENT, 200];
+    data.extend(&0x1234u16.to_ne_bytes()); // sequence
+    data.extend(&2u32.to_ne_bytes()); // length
+    data.extend(&7u16.to_ne_bytes()); // event type
+    data.resize(40, 0xab);
+
+    // The unknown event keeps all of its bytes, not just the first 32
+    let parsed = Event::parse(&data, &NoExtensions)?;
+    assert_eq!(parsed.response_type(), GE_GENERIC_EVENT);
+    assert_eq!(parsed.wire_sequence_number(), Some(0x1234));
+    match parsed {
+        Event::Unknown(bytes) => assert_eq!(bytes, data),
+        _ => panic!("Unexpected event {:?}", parsed),
+    }
+    Ok(())
+}
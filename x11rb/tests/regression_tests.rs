@@ -1,14 +1,17 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
 use std::io::IoSlice;
 use std::ops::Deref;
 
 use x11rb::connection::{
-    compute_length_field, BufWithFds, ReplyOrError, RequestConnection, RequestKind,
+    compute_length_field, BufWithFds, Connection, RawEventAndSeqNumber, ReplyOrError,
+    RequestConnection, RequestKind,
 };
 use x11rb::cookie::{Cookie, CookieWithFds, VoidCookie};
-use x11rb::errors::{ConnectionError, ParseError, ReplyError};
+use x11rb::errors::{ConnectionError, ParseError, ReplyError, ReplyOrIdError};
+use x11rb::extension_manager::ExtensionManager;
 use x11rb::protocol::xproto::{
-    ClientMessageData, ConnectionExt, KeymapNotifyEvent, Segment, SetupAuthenticate,
+    ClientMessageData, ConnectionExt, KeymapNotifyEvent, Segment, Setup, SetupAuthenticate,
 };
 use x11rb::utils::RawFdContainer;
 use x11rb::x11_utils::{ExtensionInformation, Serialize, TryParse, TryParseFd};
@@ -33,11 +36,29 @@ impl SavedRequest {
 }
 
 #[derive(Debug, Default)]
-struct FakeConnection(RefCell<Vec<SavedRequest>>);
+struct FakeConnection {
+    requests: RefCell<Vec<SavedRequest>>,
+    replies: RefCell<VecDeque<Vec<u8>>>,
+    discarded: RefCell<Vec<(SequenceNumber, RequestKind, DiscardMode)>>,
+    // The number of sent requests at each call to flush()
+    flushes: RefCell<Vec<usize>>,
+    setup: Setup,
+    last_id: Cell<u32>,
+    // Overrides the default maximum request length
+    max_request_bytes: Option<usize>,
+}
 
 impl FakeConnection {
+    fn add_reply(&self, reply: &impl Serialize) {
+        let mut bytes = Vec::new();
+        reply.serialize_into(&mut bytes);
+        // Replies are at least 32 bytes long and padded to a multiple of four bytes
+        bytes.resize(std::cmp::max(32, (bytes.len() + 3) / 4 * 4), 0);
+        self.replies.borrow_mut().push_back(bytes);
+    }
+
     fn check_requests(&self, expected: &[(bool, Vec<u8>)]) {
-        let vec = self.0.borrow();
+        let vec = self.requests.borrow();
         for (expected, actual) in expected.iter().zip(vec.iter()) {
             assert_eq!(expected.0, actual.has_reply);
             assert_eq!(actual.data, expected.1);
@@ -55,7 +76,9 @@ impl FakeConnection {
         let mut storage = Default::default();
         let bufs = compute_length_field(self, bufs, &mut storage)?;
 
-        self.0.borrow_mut().push(SavedRequest::new(false, bufs));
+        self.requests
+            .borrow_mut()
+            .push(SavedRequest::new(false, bufs));
         Ok(0)
     }
 }
@@ -96,8 +119,8 @@ impl RequestConnection for FakeConnection {
         ))
     }
 
-    fn discard_reply(&self, _sequence: SequenceNumber, _kind: RequestKind, _mode: DiscardMode) {
-        // Just ignore this
+    fn discard_reply(&self, sequence: SequenceNumber, kind: RequestKind, mode: DiscardMode) {
+        self.discarded.borrow_mut().push((sequence, kind, mode));
     }
 
     fn prefetch_extension_information(
@@ -118,7 +141,16 @@ impl RequestConnection for FakeConnection {
         &self,
         _sequence: SequenceNumber,
     ) -> Result<ReplyOrError<Vec<u8>>, ConnectionError> {
-        unimplemented!()
+        let reply = self.replies.borrow_mut().pop_front();
+        Ok(ReplyOrError::Reply(reply.expect("No reply available")))
+    }
+
+    fn poll_for_reply_or_raw_error(
+        &self,
+        _sequence: SequenceNumber,
+    ) -> Result<Option<ReplyOrError<Vec<u8>>>, ConnectionError> {
+        let reply = self.replies.borrow_mut().pop_front();
+        Ok(reply.map(ReplyOrError::Reply))
     }
 
     fn wait_for_reply(
@@ -139,20 +171,23 @@ impl RequestConnection for FakeConnection {
         &self,
         _sequence: SequenceNumber,
     ) -> Result<Option<Vec<u8>>, ConnectionError> {
-        unimplemented!()
+        // Like a real connection, this sends all pending requests
+        self.flush()?;
+        // A queued packet is the error for the request
+        Ok(self.replies.borrow_mut().pop_front())
     }
 
     fn maximum_request_bytes(&self) -> usize {
         // Must be at least 4 * 2^16 so that we can test BIG-REQUESTS
-        2usize.pow(19)
+        self.max_request_bytes.unwrap_or_else(|| 2usize.pow(19))
     }
 
     fn prefetch_maximum_request_bytes(&self) {
         unimplemented!()
     }
 
-    fn parse_error(&self, _error: &[u8]) -> Result<x11rb::x11_utils::X11Error, ParseError> {
-        unimplemented!()
+    fn parse_error(&self, error: &[u8]) -> Result<x11rb::x11_utils::X11Error, ParseError> {
+        x11rb::x11_utils::X11Error::try_parse(error, &ExtensionManager::default())
     }
 
     fn parse_event(&self, _event: &[u8]) -> Result<x11rb::protocol::Event, ParseError> {
@@ -160,6 +195,39 @@ impl RequestConnection for FakeConnection {
     }
 }
 
+impl Connection for FakeConnection {
+    fn wait_for_raw_event_with_sequence(
+        &self,
+    ) -> Result<RawEventAndSeqNumber<Vec<u8>>, ConnectionError> {
+        unimplemented!()
+    }
+
+    fn poll_for_raw_event_with_sequence(
+        &self,
+    ) -> Result<Option<RawEventAndSeqNumber<Vec<u8>>>, ConnectionError> {
+        unimplemented!()
+    }
+
+    fn flush(&self) -> Result<(), ConnectionError> {
+        let sent = self.requests.borrow().len();
+        self.flushes.borrow_mut().push(sent);
+        Ok(())
+    }
+
+    fn setup(&self) -> &Setup {
+        &self.setup
+    }
+
+    fn generate_id(&self) -> Result<u32, ReplyOrIdError> {
+        self.last_id.set(self.last_id.get() + 1);
+        Ok(self.last_id.get())
+    }
+
+    fn has_error(&self) -> Option<ConnectionError> {
+        None
+    }
+}
+
 #[test]
 fn test_poly_segment() -> Result<(), ReplyError> {
     let conn = FakeConnection::default();
@@ -232,6 +300,34 @@ fn test_big_requests() -> Result<(), ConnectionError> {
     Ok(())
 }
 
+#[test]
+fn test_big_requests_threshold() -> Result<(), ConnectionError> {
+    let conn = FakeConnection::default();
+    let header_length = 16;
+    // The largest request that still fits into the 16 bit length field
+    let small_buffer = vec![0; 4 * 0xffff - header_length];
+    // The smallest request that needs BIG-REQUESTS
+    let big_buffer = vec![0; 4 * 0x10000 - header_length];
+    conn.poly_text16(42, 0x1337, 21, 7, &small_buffer)?;
+    conn.poly_text16(42, 0x1337, 21, 7, &big_buffer)?;
+
+    let requests = conn.requests.borrow();
+    assert_eq!(requests.len(), 2);
+
+    let small = &requests[0].data;
+    assert_eq!(small.len(), 4 * 0xffff);
+    assert_eq!(small[2..4], 0xffffu16.to_ne_bytes());
+    assert_eq!(small[4..8], 42u32.to_ne_bytes());
+
+    // The length field is zero and followed by the length, which includes the extra four bytes
+    let big = &requests[1].data;
+    assert_eq!(big.len(), 4 * 0x10001);
+    assert_eq!(big[2..4], [0, 0]);
+    assert_eq!(big[4..8], 0x10001u32.to_ne_bytes());
+    assert_eq!(big[8..12], 42u32.to_ne_bytes());
+    Ok(())
+}
+
 #[test]
 fn test_too_large_request() {
     let conn = FakeConnection::default();
@@ -320,6 +416,434 @@ fn test_set_modifier_mapping() -> Result<(), ConnectionError> {
     Ok(())
 }
 
+#[test]
+fn test_append_property32() -> Result<(), ConnectionError> {
+    use x11rb::wrapper::ConnectionExt as _;
+
+    let conn = FakeConnection::default();
+    let window: u32 = 0x1337;
+    let property: u32 = 42;
+    let type_: u32 = 33;
+    conn.append_property32(window, property, type_, &[7, 8])?;
+
+    let mut expected = vec![
+        x11rb::protocol::xproto::CHANGE_PROPERTY_REQUEST,
+        u8::from(x11rb::protocol::xproto::PropMode::APPEND),
+    ];
+    expected.extend(&8u16.to_ne_bytes()); // length, not in the xml
+    expected.extend(&window.to_ne_bytes());
+    expected.extend(&property.to_ne_bytes());
+    expected.extend(&type_.to_ne_bytes());
+    expected.extend(&[32, 0, 0, 0]); // format and padding
+    expected.extend(&2u32.to_ne_bytes()); // number of elements
+    expected.extend(&7u32.to_ne_bytes());
+    expected.extend(&8u32.to_ne_bytes());
+
+    conn.check_requests(&[(false, expected)]);
+    Ok(())
+}
+
+#[test]
+fn test_set_transient_for() -> Result<(), ConnectionError> {
+    use x11rb::protocol::xproto::{AtomEnum, PropMode, CHANGE_PROPERTY_REQUEST};
+
+    let conn = FakeConnection::default();
+    let (dialog, parent): (u32, u32) = (0x1337, 0x4242);
+    let _ = x11rb::properties::set_transient_for(&conn, dialog, parent)?;
+
+    let mut expected = vec![CHANGE_PROPERTY_REQUEST, u8::from(PropMode::REPLACE)];
+    expected.extend(&7u16.to_ne_bytes()); // length, not in the xml
+    expected.extend(&dialog.to_ne_bytes());
+    expected.extend(&u32::from(AtomEnum::WM_TRANSIENT_FOR).to_ne_bytes());
+    expected.extend(&u32::from(AtomEnum::WINDOW).to_ne_bytes());
+    expected.extend(&[32, 0, 0, 0]); // format and padding
+    expected.extend(&1u32.to_ne_bytes()); // number of elements
+    expected.extend(&parent.to_ne_bytes());
+
+    conn.check_requests(&[(false, expected)]);
+    Ok(())
+}
+
+#[test]
+fn test_extension_information_ci() -> Result<(), ReplyError> {
+    use x11rb::protocol::xproto::{ListExtensionsReply, QueryExtensionReply, Str};
+    use x11rb::x11_utils::ExtensionInformation;
+
+    let conn = FakeConnection::default();
+    let names = ["BIG-REQUESTS", "RANDR"]
+        .iter()
+        .map(|name| Str {
+            name: name.as_bytes().to_vec(),
+        })
+        .collect();
+    conn.add_reply(&ListExtensionsReply {
+        sequence: 0,
+        // 19 bytes of names, padded to five four-byte units
+        length: 5,
+        names,
+    });
+    conn.add_reply(&QueryExtensionReply {
+        sequence: 0,
+        length: 0,
+        present: true,
+        major_opcode: 140,
+        first_event: 89,
+        first_error: 147,
+    });
+    let info = conn.extension_information_ci("randr")?;
+    let expected = ExtensionInformation {
+        major_opcode: 140,
+        first_event: 89,
+        first_error: 147,
+    };
+    assert_eq!(info, Some(("RANDR".to_string(), expected)));
+
+    // The QueryExtension request uses the canonical name
+    let requests = conn.requests.borrow();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(&requests[1].data[8..13], b"RANDR");
+    drop(requests);
+
+    // An extension that the server does not have
+    conn.add_reply(&ListExtensionsReply {
+        sequence: 0,
+        length: 0,
+        names: Vec::new(),
+    });
+    assert_eq!(conn.extension_information_ci("randr")?, None);
+    Ok(())
+}
+
+#[test]
+fn test_get_property_full_delete() -> Result<(), ReplyError> {
+    use x11rb::protocol::xproto::{GetPropertyReply, GET_PROPERTY_REQUEST};
+    use x11rb::wrapper::ConnectionExt as _;
+
+    const CHUNK: u32 = 0x4000;
+    let conn = FakeConnection::default();
+    let (window, property, type_): (u32, u32, u32) = (0x1337, 42, 31);
+
+    // The property has two full chunks and 8 more bytes
+    let chunk = |value: Vec<u8>, bytes_after: u32| GetPropertyReply {
+        format: 8,
+        sequence: 0,
+        length: 0,
+        type_,
+        bytes_after,
+        value_len: value.len() as u32,
+        value,
+    };
+    conn.add_reply(&chunk(vec![1; 4 * CHUNK as usize], 4 * CHUNK + 8));
+    conn.add_reply(&chunk(vec![2; 4 * CHUNK as usize], 8));
+    conn.add_reply(&chunk(vec![3; 8], 0));
+
+    let reply = conn.get_property_full(true, window, property, type_)?;
+    assert_eq!(reply.bytes_after, 0);
+    assert_eq!(reply.value_len, 8 * CHUNK + 8);
+    assert_eq!(reply.value.len(), 8 * CHUNK as usize + 8);
+    assert_eq!(reply.value[4 * CHUNK as usize], 2);
+    assert_eq!(reply.value[8 * CHUNK as usize], 3);
+
+    let request = |delete: u8, offset: u32| {
+        let mut expected = vec![GET_PROPERTY_REQUEST, delete];
+        expected.extend(&6u16.to_ne_bytes()); // length, not in the xml
+        expected.extend(&window.to_ne_bytes());
+        expected.extend(&property.to_ne_bytes());
+        expected.extend(&type_.to_ne_bytes());
+        expected.extend(&offset.to_ne_bytes());
+        expected.extend(&CHUNK.to_ne_bytes());
+        (false, expected)
+    };
+    // Only the final chunk may delete the property (the server ignores the first `delete`
+    // unless the first request reads everything)
+    conn.check_requests(&[request(1, 0), request(0, CHUNK), request(1, 2 * CHUNK)]);
+    Ok(())
+}
+
+#[test]
+fn test_get_property_full_wrong_type() -> Result<(), ReplyError> {
+    use x11rb::protocol::xproto::GetPropertyReply;
+    use x11rb::wrapper::ConnectionExt as _;
+
+    let conn = FakeConnection::default();
+    let reply = GetPropertyReply {
+        format: 8,
+        type_: 31,
+        bytes_after: 0x10_0000,
+        ..Default::default()
+    };
+    conn.add_reply(&reply);
+
+    // Only a single request is sent
+    assert_eq!(conn.get_property_full(true, 1, 2u32, 3u32)?, reply);
+    assert_eq!(conn.requests.borrow().len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_get_property_string() -> Result<(), ReplyError> {
+    use x11rb::protocol::xproto::{AtomEnum, GetPropertyReply};
+    use x11rb::wrapper::ConnectionExt as _;
+
+    const CHUNK: usize = 4 * 0x4000;
+    let conn = FakeConnection::default();
+    let reply = |type_: u32, value: &[u8], bytes_after: u32| GetPropertyReply {
+        format: 8,
+        type_,
+        bytes_after,
+        value_len: value.len() as u32,
+        value: value.to_vec(),
+        ..Default::default()
+    };
+
+    // A truncated value is read completely. 300 stands for UTF8_STRING, which has no fixed atom.
+    let mut value = vec![b'a'; CHUNK - 1];
+    value.push(0xc3);
+    conn.add_reply(&reply(300, &value, 1));
+    conn.add_reply(&reply(300, &[0xa4], 0));
+    let mut expected = "a".repeat(CHUNK - 1);
+    expected.push('ä');
+    assert_eq!(conn.get_property_string(1, 2u32)?, Some(expected));
+    assert_eq!(conn.requests.borrow().len(), 2);
+
+    // STRING is Latin-1
+    conn.add_reply(&reply(AtomEnum::STRING.into(), &[b'x', 0xe4], 0));
+    assert_eq!(conn.get_property_string(1, 2u32)?, Some("xä".to_string()));
+
+    // The property does not exist
+    conn.add_reply(&GetPropertyReply::default());
+    assert_eq!(conn.get_property_string(1, 2u32)?, None);
+    Ok(())
+}
+
+#[test]
+fn test_grab_keyboard_ungrab_on_error() {
+    use x11rb::protocol::xproto::{
+        GrabKeyboardReply, GrabMode, GrabStatus, UNGRAB_KEYBOARD_REQUEST,
+    };
+    use x11rb::wrapper::GrabKeyboard;
+
+    fn setup(conn: &FakeConnection) -> Result<(), ReplyError> {
+        let _grab = GrabKeyboard::grab(conn, false, 1, 2, GrabMode::ASYNC, GrabMode::ASYNC)?;
+        // Some later step of the setup fails
+        Err(ConnectionError::UnknownError.into())
+    }
+
+    let conn = FakeConnection::default();
+    conn.add_reply(&GrabKeyboardReply {
+        status: GrabStatus::SUCCESS,
+        sequence: 0,
+        length: 0,
+    });
+    assert!(setup(&conn).is_err());
+
+    // The GrabKeyboard request is followed by an UngrabKeyboard request with the same time
+    let requests = conn.requests.borrow();
+    assert_eq!(requests.len(), 2);
+    let mut expected = vec![UNGRAB_KEYBOARD_REQUEST, 0];
+    expected.extend(&2u16.to_ne_bytes()); // length
+    expected.extend(&2u32.to_ne_bytes()); // time
+    assert_eq!(requests[1].data, expected);
+    // The UngrabKeyboard request was flushed
+    assert_eq!(*conn.flushes.borrow(), [2]);
+}
+
+#[test]
+fn test_grab_keyboard_refused() -> Result<(), ReplyError> {
+    use x11rb::protocol::xproto::{GrabKeyboardReply, GrabMode, GrabStatus};
+    use x11rb::wrapper::GrabKeyboard;
+
+    let conn = FakeConnection::default();
+    conn.add_reply(&GrabKeyboardReply {
+        status: GrabStatus::ALREADY_GRABBED,
+        sequence: 0,
+        length: 0,
+    });
+    let grab = GrabKeyboard::grab(&conn, false, 1, 2, GrabMode::ASYNC, GrabMode::ASYNC)?;
+    assert!(grab.is_none());
+    drop(grab);
+
+    // No UngrabKeyboard request was sent
+    assert_eq!(conn.requests.borrow().len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_send_request_retainable() -> Result<(), ConnectionError> {
+    use x11rb::protocol::xproto::InternAtomRequest;
+
+    let conn = FakeConnection::default();
+    let request = InternAtomRequest {
+        only_if_exists: true,
+        name: b"FOO".to_vec().into(),
+    };
+    let (_cookie, bytes) = conn.send_request_retainable(request)?;
+
+    // The retained bytes are exactly what was sent
+    assert_eq!(conn.requests.borrow().len(), 1);
+    assert_eq!(conn.requests.borrow()[0].data, bytes);
+    Ok(())
+}
+
+#[test]
+fn test_sync_keyboard_grab() -> Result<(), ReplyError> {
+    use x11rb::protocol::xproto::{
+        Allow, GrabKeyboardReply, GrabStatus, KeyPressEvent, ALLOW_EVENTS_REQUEST,
+        UNGRAB_KEYBOARD_REQUEST,
+    };
+    use x11rb::protocol::Event;
+    use x11rb::wrapper::{KeyboardGrabState, SyncKeyboardGrab};
+
+    let time_request = |opcode: u8, data: u8, time: u32| {
+        let mut request = vec![opcode, data];
+        request.extend(&2u16.to_ne_bytes()); // length
+        request.extend(&time.to_ne_bytes());
+        request
+    };
+
+    let conn = FakeConnection::default();
+    conn.add_reply(&GrabKeyboardReply {
+        status: GrabStatus::SUCCESS,
+        sequence: 0,
+        length: 0,
+    });
+    let mut grab = SyncKeyboardGrab::grab(&conn, 1, 10)?.unwrap();
+    assert_eq!(grab.state(), KeyboardGrabState::Frozen);
+
+    // Let one event through; it freezes the keyboard again
+    grab.process_next()?;
+    assert_eq!(grab.state(), KeyboardGrabState::WaitingForEvent);
+    grab.handle_event(&Event::KeyPress(KeyPressEvent {
+        time: 20,
+        ..Default::default()
+    }));
+    assert_eq!(grab.state(), KeyboardGrabState::Frozen);
+
+    // Replaying unfreezes the keyboard; doing it again has no effect
+    grab.replay()?;
+    assert_eq!(grab.state(), KeyboardGrabState::Thawed);
+    grab.discard()?;
+    drop(grab);
+
+    let requests = conn.requests.borrow();
+    let requests = requests.iter().map(|r| r.data.clone()).collect::<Vec<_>>();
+    assert_eq!(
+        requests[1..],
+        [
+            time_request(ALLOW_EVENTS_REQUEST, Allow::SYNC_KEYBOARD.into(), 10),
+            time_request(ALLOW_EVENTS_REQUEST, Allow::REPLAY_KEYBOARD.into(), 20),
+            time_request(UNGRAB_KEYBOARD_REQUEST, 0, 20),
+        ]
+    );
+    // The UngrabKeyboard request was flushed
+    assert_eq!(conn.flushes.borrow().last(), Some(&requests.len()));
+    Ok(())
+}
+
+#[test]
+fn test_get_window_pid() -> Result<(), ReplyError> {
+    use x11rb::protocol::xproto::{AtomEnum, GetPropertyReply, InternAtomReply};
+
+    let conn = FakeConnection::default();
+    conn.add_reply(&InternAtomReply {
+        sequence: 0,
+        length: 0,
+        atom: 300,
+    });
+    conn.add_reply(&GetPropertyReply {
+        format: 32,
+        type_: AtomEnum::CARDINAL.into(),
+        value_len: 1,
+        value: 4242u32.to_ne_bytes().to_vec(),
+        ..Default::default()
+    });
+    assert_eq!(x11rb::properties::get_window_pid(&conn, 1)?, Some(4242));
+
+    // An absent property is not an error
+    conn.add_reply(&InternAtomReply {
+        sequence: 0,
+        length: 0,
+        atom: 300,
+    });
+    conn.add_reply(&GetPropertyReply::default());
+    assert_eq!(x11rb::properties::get_window_pid(&conn, 1)?, None);
+    Ok(())
+}
+
+#[test]
+fn test_set_modal_does_not_duplicate() -> Result<(), ReplyError> {
+    use x11rb::protocol::xproto::{AtomEnum, GetPropertyReply, PropMode};
+
+    const MODAL: u32 = 101;
+    for &(current, expected) in &[
+        (None, &[MODAL][..]),
+        (Some(&[5][..]), &[5, MODAL][..]),
+        (Some(&[5, MODAL][..]), &[5, MODAL][..]),
+    ] {
+        let conn = FakeConnection::default();
+        add_intern_atom_replies(&conn, 2);
+        conn.add_reply(&match current {
+            Some(states) => GetPropertyReply {
+                format: 32,
+                type_: AtomEnum::ATOM.into(),
+                value_len: states.len() as u32,
+                value: states
+                    .iter()
+                    .flat_map(|s| s.to_ne_bytes().to_vec())
+                    .collect(),
+                ..Default::default()
+            },
+            None => GetPropertyReply::default(),
+        });
+        let _ = x11rb::properties::set_modal(&conn, 1)?;
+
+        let requests = conn.requests.borrow();
+        let change = &requests.last().unwrap().data;
+        assert_eq!(change[1], u8::from(PropMode::REPLACE));
+        let states = change_property_value(change)
+            .chunks(4)
+            .map(|s| u32::from_ne_bytes([s[0], s[1], s[2], s[3]]))
+            .collect::<Vec<_>>();
+        assert_eq!(states, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_get_client_machine() -> Result<(), ReplyError> {
+    use x11rb::protocol::xproto::{AtomEnum, GetPropertyReply};
+
+    let conn = FakeConnection::default();
+    conn.add_reply(&GetPropertyReply {
+        format: 8,
+        type_: AtomEnum::STRING.into(),
+        value_len: 4,
+        value: b"host".to_vec(),
+        ..Default::default()
+    });
+    assert_eq!(
+        x11rb::properties::get_client_machine(&conn, 1)?,
+        Some("host".to_string())
+    );
+
+    // STRING is Latin-1, not UTF-8
+    conn.add_reply(&GetPropertyReply {
+        format: 8,
+        type_: AtomEnum::STRING.into(),
+        value_len: 4,
+        value: b"m\xfcnz".to_vec(),
+        ..Default::default()
+    });
+    assert_eq!(
+        x11rb::properties::get_client_machine(&conn, 1)?,
+        Some("münz".to_string())
+    );
+
+    conn.add_reply(&GetPropertyReply::default());
+    assert_eq!(x11rb::properties::get_client_machine(&conn, 1)?, None);
+    Ok(())
+}
+
 #[test]
 fn test_serialize_setup_authenticate() {
     let setup = SetupAuthenticate {
@@ -340,3 +864,784 @@ fn compile_test(conn: &impl RequestConnection) {
     use x11rb::protocol::xinput::{xi_query_device, Device};
     let _ = xi_query_device(conn, Device::ALL);
 }
+
+#[test]
+fn test_try_reply() -> Result<(), ReplyError> {
+    use x11rb::protocol::xproto::{GetInputFocusReply, InputFocus};
+
+    let conn = FakeConnection::default();
+    let cookie = conn.get_input_focus()?;
+
+    // The reply did not arrive yet, so the cookie is handed back
+    let cookie = match cookie.try_reply()? {
+        Ok(reply) => panic!("Unexpected reply {:?}", reply),
+        Err(cookie) => cookie,
+    };
+
+    conn.add_reply(&GetInputFocusReply {
+        revert_to: InputFocus::PARENT,
+        sequence: 0,
+        length: 0,
+        focus: 42,
+    });
+    let reply = match cookie.try_reply()? {
+        Ok(reply) => reply,
+        Err(_) => panic!("The reply should be available"),
+    };
+    assert_eq!(reply.focus, 42);
+    assert_eq!(reply.revert_to, InputFocus::PARENT);
+
+    // The reply was handed out, so nothing was discarded
+    assert!(conn.discarded.borrow().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_try_reply_then_reply() -> Result<(), ReplyError> {
+    use x11rb::protocol::xproto::GetInputFocusReply;
+
+    let conn = FakeConnection::default();
+    let cookie = conn.get_input_focus()?;
+    let cookie = match cookie.try_reply()? {
+        Ok(reply) => panic!("Unexpected reply {:?}", reply),
+        Err(cookie) => cookie,
+    };
+
+    // A cookie that was handed back can still be used to wait for the reply
+    conn.add_reply(&GetInputFocusReply {
+        focus: 7,
+        ..Default::default()
+    });
+    assert_eq!(cookie.reply()?.focus, 7);
+    assert!(conn.discarded.borrow().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_atom_manager_predefined_atoms() -> Result<(), ReplyError> {
+    use x11rb::protocol::xproto::{AtomEnum, InternAtomReply};
+
+    x11rb::atom_manager! {
+        Atoms: AtomsCookie {
+            CARDINAL,
+            _NET_WM_NAME,
+            WM_NAME,
+        }
+    }
+
+    let conn = FakeConnection::default();
+    conn.add_reply(&InternAtomReply {
+        sequence: 0,
+        length: 0,
+        atom: 300,
+    });
+    let atoms = Atoms::new(&conn)?.reply()?;
+    assert_eq!(atoms.CARDINAL, AtomEnum::CARDINAL.into());
+    assert_eq!(atoms._NET_WM_NAME, 300);
+    assert_eq!(atoms.WM_NAME, AtomEnum::WM_NAME.into());
+    // Only _NET_WM_NAME needs to be interned
+    assert_eq!(conn.requests.borrow().len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_atom_cache() -> Result<(), ReplyError> {
+    use x11rb::protocol::xproto::{AtomEnum, InternAtomReply};
+    use x11rb::x11_utils::AtomCache;
+
+    let conn = FakeConnection::default();
+    for atom in [300, 301].iter() {
+        conn.add_reply(&InternAtomReply {
+            sequence: 0,
+            length: 0,
+            atom: *atom,
+        });
+    }
+    let cache = AtomCache::new();
+    let atoms = cache.intern_many(
+        &conn,
+        &["_NET_WM_NAME", "CARDINAL", "UTF8_STRING", "_NET_WM_NAME"],
+    )?;
+    assert_eq!(atoms, [300, AtomEnum::CARDINAL.into(), 301, 300]);
+    // Each name is only interned once, predefined atoms not at all
+    assert_eq!(conn.requests.borrow().len(), 2);
+
+    assert_eq!(cache.get_or_intern(&conn, "UTF8_STRING")?, 301);
+    assert_eq!(cache.get("WM_NAME"), Some(AtomEnum::WM_NAME.into()));
+    assert_eq!(cache.get("_NET_WM_PID"), None);
+    assert_eq!(conn.requests.borrow().len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_void_cookie_check() -> Result<(), ReplyError> {
+    use x11rb::protocol::ErrorKind;
+
+    let conn = FakeConnection::default();
+    // No error for the request
+    conn.map_window(42)?.check()?;
+
+    let mut error = vec![0; 32];
+    error[1] = 3; // Window error
+    error[4..8].copy_from_slice(&42u32.to_ne_bytes());
+    conn.replies.borrow_mut().push_back(error);
+    match conn.map_window(42)?.check() {
+        Err(ReplyError::X11Error(error)) => {
+            assert_eq!(error.error_kind, ErrorKind::Window);
+            assert_eq!(error.bad_value, 42);
+        }
+        other => panic!("Unexpected result {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_cookie_discard_and_forget() -> Result<(), ConnectionError> {
+    let conn = FakeConnection::default();
+    drop(conn.map_window(1)?);
+    conn.map_window(2)?.discard();
+    conn.map_window(3)?.ignore_error();
+    let _ = conn.map_window(4)?.forget();
+    drop(conn.get_input_focus()?);
+    conn.get_input_focus()?.discard();
+    conn.get_input_focus()?.discard_reply_and_errors();
+    let _ = conn.get_input_focus()?.forget();
+
+    // The FakeConnection always uses sequence number zero
+    use DiscardMode::{DiscardReply, DiscardReplyAndError};
+    use RequestKind::{HasResponse, IsVoid};
+    assert_eq!(
+        conn.discarded
+            .borrow()
+            .iter()
+            .map(|&(_, kind, mode)| (kind, mode))
+            .collect::<Vec<_>>(),
+        [
+            (IsVoid, DiscardReply),
+            (IsVoid, DiscardReply),
+            (IsVoid, DiscardReplyAndError),
+            (HasResponse, DiscardReply),
+            (HasResponse, DiscardReply),
+            (HasResponse, DiscardReplyAndError),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_grab_server_ungrab_on_panic() {
+    use x11rb::protocol::xproto::{GRAB_SERVER_REQUEST, MAP_WINDOW_REQUEST, UNGRAB_SERVER_REQUEST};
+    use x11rb::wrapper::GrabServer;
+
+    let conn = FakeConnection::default();
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let _grab = GrabServer::grab(&conn).unwrap();
+        conn.map_window(1).unwrap().ignore_error();
+        panic!("Something went wrong while the server was grabbed");
+    }));
+    assert!(result.is_err());
+
+    // GrabServer, MapWindow, UngrabServer, with a flush after the grab and after the ungrab
+    let requests = conn.requests.borrow();
+    let opcodes = requests.iter().map(|r| r.data[0]).collect::<Vec<_>>();
+    assert_eq!(
+        opcodes,
+        [
+            GRAB_SERVER_REQUEST,
+            MAP_WINDOW_REQUEST,
+            UNGRAB_SERVER_REQUEST
+        ]
+    );
+    assert_eq!(*conn.flushes.borrow(), [1, 3]);
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_put_image_chunked() -> Result<(), ConnectionError> {
+    use x11rb::image::put_image_chunked;
+    use x11rb::protocol::xproto::{Format, PUT_IMAGE_REQUEST};
+
+    let conn = FakeConnection {
+        setup: Setup {
+            pixmap_formats: vec![Format {
+                depth: 24,
+                bits_per_pixel: 32,
+                scanline_pad: 32,
+            }],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    // Each row takes 4000 bytes, so 131 rows and the 28 byte header fit into 2^19 bytes
+    let (width, height, stride) = (1000, 300, 4000);
+    let data = (0..height * stride).map(|i| i as u8).collect::<Vec<_>>();
+    let _ = put_image_chunked(&conn, 1, 2, 10, 20, width as u16, height as u16, 24, &data)?;
+
+    let mut bands = Vec::new();
+    for request in conn.requests.borrow().iter() {
+        let bytes = &request.data;
+        assert_eq!(bytes[0], PUT_IMAGE_REQUEST);
+        assert!(bytes.len() <= conn.maximum_request_bytes());
+        // Requests with BIG-REQUESTS have a zero length field and an extra length field
+        let header = if bytes[2..4] == [0, 0] { 8 } else { 4 };
+        let field = |offset: usize| {
+            u16::from_ne_bytes([bytes[header + offset], bytes[header + offset + 1]])
+        };
+        let (lines, dst_y) = (field(10), field(14));
+        let body = &bytes[header + 20..];
+        let start = usize::from(dst_y - 20) * stride;
+        assert_eq!(body, &data[start..start + usize::from(lines) * stride]);
+        bands.push((dst_y, lines));
+    }
+    assert_eq!(bands, [(20, 131), (151, 131), (282, 38)]);
+    Ok(())
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_put_image_chunked_row_too_large() {
+    use x11rb::image::put_image_chunked;
+    use x11rb::protocol::xproto::Format;
+
+    let conn = FakeConnection {
+        setup: Setup {
+            pixmap_formats: vec![Format {
+                depth: 24,
+                bits_per_pixel: 32,
+                scanline_pad: 32,
+            }],
+            ..Default::default()
+        },
+        max_request_bytes: Some(28 + 4000 - 1),
+        ..Default::default()
+    };
+    // A single row takes 4000 bytes and does not fit next to the 28 byte request header
+    let data = vec![0; 2 * 4000];
+    let result = put_image_chunked(&conn, 1, 2, 0, 0, 1000, 2, 24, &data);
+    assert!(matches!(
+        result,
+        Err(ConnectionError::MaximumRequestLengthExceeded)
+    ));
+    // Nothing was sent
+    assert!(conn.requests.borrow().is_empty());
+}
+
+#[cfg(feature = "image-crate")]
+#[test]
+fn test_put_image_from_rgba() -> Result<(), Box<dyn std::error::Error>> {
+    use image_crate::{Rgba, RgbaImage};
+    use x11rb::image::put_image_from_rgba;
+    use x11rb::protocol::xproto::{Format, VisualClass, Visualtype, PUT_IMAGE_REQUEST};
+
+    let conn = FakeConnection {
+        setup: Setup {
+            pixmap_formats: vec![Format {
+                depth: 24,
+                bits_per_pixel: 32,
+                scanline_pad: 32,
+            }],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let visual = Visualtype {
+        class: VisualClass::TRUE_COLOR,
+        red_mask: 0xff_0000,
+        green_mask: 0x00_ff00,
+        blue_mask: 0x00_00ff,
+        ..Default::default()
+    };
+    let image = RgbaImage::from_fn(2, 1, |x, _| Rgba([0x12, 0x34, 0x56 + x as u8, 0xff]));
+    let _ = put_image_from_rgba(&conn, 1, 2, 10, 20, &image, visual, 24)?;
+
+    let requests = conn.requests.borrow();
+    assert_eq!(requests.len(), 1);
+    let bytes = &requests[0].data;
+    assert_eq!(bytes[0], PUT_IMAGE_REQUEST);
+    // The image has the requested size and is encoded for the visual
+    assert_eq!(bytes[12..14], 2u16.to_ne_bytes());
+    assert_eq!(bytes[14..16], 1u16.to_ne_bytes());
+    assert_eq!(bytes[24..], [0x56, 0x34, 0x12, 0, 0x57, 0x34, 0x12, 0]);
+    Ok(())
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_get_image_chunked() -> Result<(), ReplyError> {
+    use x11rb::image::get_image_chunked;
+    use x11rb::protocol::xproto::{Format, GetGeometryReply, GetImageReply, GET_IMAGE_REQUEST};
+
+    let conn = FakeConnection {
+        setup: Setup {
+            pixmap_formats: vec![Format {
+                depth: 24,
+                bits_per_pixel: 32,
+                scanline_pad: 32,
+            }],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let geometry = GetGeometryReply {
+        depth: 24,
+        ..Default::default()
+    };
+    let image = |lines: usize, fill: u8| GetImageReply {
+        depth: 24,
+        visual: 42,
+        data: vec![fill; lines * 4000],
+        ..Default::default()
+    };
+
+    // Each row takes 4000 bytes, so 131 rows and the 32 byte header fit into 2^19 bytes
+    conn.add_reply(&geometry);
+    conn.add_reply(&image(131, 1));
+    conn.add_reply(&image(131, 2));
+    conn.add_reply(&image(38, 3));
+    let reply = get_image_chunked(&conn, 1, 10, 20, 1000, 300, !0)?;
+    assert_eq!((reply.depth, reply.visual), (24, 42));
+    let mut expected = vec![1; 131 * 4000];
+    expected.resize(262 * 4000, 2);
+    expected.resize(300 * 4000, 3);
+    assert!(reply.data == expected);
+
+    let bands = conn.requests.borrow()[1..]
+        .iter()
+        .map(|request| {
+            let bytes = &request.data;
+            assert_eq!(bytes[0], GET_IMAGE_REQUEST);
+            let field = |offset: usize| u16::from_ne_bytes([bytes[offset], bytes[offset + 1]]);
+            (field(10), field(14))
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(bands, [(20, 131), (151, 131), (282, 38)]);
+
+    // Without any rows, only the geometry is requested
+    conn.requests.borrow_mut().clear();
+    conn.add_reply(&geometry);
+    let reply = get_image_chunked(&conn, 1, 0, 0, 1000, 0, !0)?;
+    assert_eq!((reply.depth, reply.visual), (24, x11rb::NONE));
+    assert!(reply.data.is_empty());
+    assert_eq!(conn.requests.borrow().len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_list_fonts_with_info_stops_at_sentinel() -> Result<(), ReplyError> {
+    use x11rb::protocol::xproto::ListFontsWithInfoReply;
+
+    let conn = FakeConnection::default();
+    let font = |name: &[u8], replies_hint| ListFontsWithInfoReply {
+        name: name.to_vec(),
+        replies_hint,
+        ..Default::default()
+    };
+    conn.add_reply(&font(b"fixed", 1));
+    conn.add_reply(&font(b"cursor", 0));
+    conn.add_reply(&font(b"", 0));
+    // This reply belongs to a later request and must not be consumed
+    conn.add_reply(&font(b"unrelated", 0));
+
+    let mut cookie = conn.list_fonts_with_info(u16::MAX, b"*")?;
+    let fonts = cookie.by_ref().collect::<Result<Vec<_>, _>>()?;
+    let fonts = fonts
+        .iter()
+        .map(|font| (&font.name[..], font.replies_hint))
+        .collect::<Vec<_>>();
+    assert_eq!(fonts, [(&b"fixed"[..], 1), (&b"cursor"[..], 0)]);
+
+    // The iterator is done and the cookie no longer refers to the request
+    assert!(cookie.next().is_none());
+    assert_eq!(cookie.sequence_number(), None);
+    assert_eq!(conn.replies.borrow().len(), 1);
+    Ok(())
+}
+
+/// Get the value of a `ChangeProperty` request, also if it uses BIG-REQUESTS.
+fn change_property_value(request: &[u8]) -> &[u8] {
+    use x11rb::protocol::xproto::CHANGE_PROPERTY_REQUEST;
+
+    assert_eq!(request[0], CHANGE_PROPERTY_REQUEST);
+    let offset = if request[2..4] == [0, 0] { 4 } else { 0 };
+    let field = |start: usize| {
+        let bytes = &request[offset + start..offset + start + 4];
+        u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    };
+    let format = request[offset + 16];
+    let len = field(20) as usize * usize::from(format / 8);
+    &request[offset + 24..offset + 24 + len]
+}
+
+fn acquire_selection(
+    conn: &FakeConnection,
+) -> Result<x11rb::selection::SelectionOwner, ReplyError> {
+    use x11rb::protocol::xproto::{AtomEnum, GetSelectionOwnerReply};
+
+    conn.add_reply(&GetSelectionOwnerReply {
+        owner: 1,
+        ..Default::default()
+    });
+    let owner = x11rb::selection::SelectionOwner::acquire(conn, 1, AtomEnum::PRIMARY, 100)?;
+    conn.requests.borrow_mut().clear();
+    Ok(owner.unwrap())
+}
+
+fn selection_request(
+    selection: u32,
+    property: u32,
+) -> x11rb::protocol::xproto::SelectionRequestEvent {
+    use x11rb::protocol::xproto::{AtomEnum, SelectionRequestEvent};
+
+    SelectionRequestEvent {
+        time: 200,
+        owner: 1,
+        requestor: 2,
+        selection,
+        target: AtomEnum::STRING.into(),
+        property,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_selection_owner_answers_requests() -> Result<(), ReplyError> {
+    use x11rb::protocol::xproto::{AtomEnum, SEND_EVENT_REQUEST};
+    use x11rb::selection::SelectionData;
+
+    let conn = FakeConnection::default();
+    let mut owner = acquire_selection(&conn)?;
+    let hello = |target| {
+        assert_eq!(target, u32::from(AtomEnum::STRING));
+        Some(SelectionData::new(AtomEnum::STRING, 8, b"Hello".to_vec()))
+    };
+    let primary = AtomEnum::PRIMARY.into();
+    assert!(owner.handle_selection_request(&conn, &selection_request(primary, 3), hello)?);
+    // Requests for another selection, unsupported targets, and invalid data are refused
+    let secondary = AtomEnum::SECONDARY.into();
+    assert!(!owner.handle_selection_request(&conn, &selection_request(secondary, 3), hello)?);
+    assert!(!owner.handle_selection_request(&conn, &selection_request(primary, 3), |_| None)?);
+    let invalid = |_| Some(SelectionData::new(AtomEnum::STRING, 32, b"Hello".to_vec()));
+    assert!(!owner.handle_selection_request(&conn, &selection_request(primary, 3), invalid)?);
+
+    let requests = conn.requests.borrow();
+    assert_eq!(change_property_value(&requests[0].data), b"Hello");
+    // Each request is answered with a SelectionNotify event that names the property or None
+    let notified = requests[1..]
+        .iter()
+        .map(|request| {
+            assert_eq!(request.data[0], SEND_EVENT_REQUEST);
+            let property = &request.data[32..36];
+            u32::from_ne_bytes([property[0], property[1], property[2], property[3]])
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(notified, [3, 0, 0, 0]);
+    Ok(())
+}
+
+#[test]
+fn test_selection_owner_incr_transfer() -> Result<(), ReplyError> {
+    use x11rb::protocol::xproto::{
+        AtomEnum, InternAtomReply, Property, PropertyNotifyEvent, CHANGE_PROPERTY_REQUEST,
+        CHANGE_WINDOW_ATTRIBUTES_REQUEST, INTERN_ATOM_REQUEST, SEND_EVENT_REQUEST,
+    };
+    use x11rb::selection::SelectionData;
+
+    let conn = FakeConnection::default();
+    let mut owner = acquire_selection(&conn)?;
+    conn.add_reply(&InternAtomReply {
+        atom: 42,
+        ..Default::default()
+    });
+
+    // The data does not fit into a single request of 2^19 bytes
+    let data = (0..600_000).map(|i| i as u8).collect::<Vec<_>>();
+    let request = selection_request(AtomEnum::PRIMARY.into(), 3);
+    assert!(owner.handle_selection_request(&conn, &request, |_| {
+        Some(SelectionData::new(AtomEnum::STRING, 8, data.clone()))
+    })?);
+    assert_eq!(owner.pending_transfers(), 1);
+    {
+        let requests = conn.requests.borrow();
+        let opcodes = requests.iter().map(|r| r.data[0]).collect::<Vec<_>>();
+        assert_eq!(
+            opcodes,
+            [
+                INTERN_ATOM_REQUEST,
+                CHANGE_WINDOW_ATTRIBUTES_REQUEST,
+                CHANGE_PROPERTY_REQUEST,
+                SEND_EVENT_REQUEST
+            ]
+        );
+        // The INCR property contains the size of the data
+        assert_eq!(requests[2].data[12..16], 42u32.to_ne_bytes());
+        assert_eq!(
+            change_property_value(&requests[2].data),
+            600_000u32.to_ne_bytes()
+        );
+    }
+
+    // Only deleting the property continues the transfer
+    let mut event = PropertyNotifyEvent {
+        window: 2,
+        atom: 3,
+        state: Property::NEW_VALUE,
+        ..Default::default()
+    };
+    assert!(!owner.handle_property_notify(&conn, &event)?);
+    event.state = Property::DELETE;
+
+    let mut received = Vec::new();
+    loop {
+        conn.requests.borrow_mut().clear();
+        assert!(owner.handle_property_notify(&conn, &event)?);
+        let requests = conn.requests.borrow();
+        assert_eq!(requests.len(), 1);
+        let chunk = change_property_value(&requests[0].data);
+        if chunk.is_empty() {
+            break;
+        }
+        received.extend_from_slice(chunk);
+    }
+    assert!(received == data);
+    assert_eq!(owner.pending_transfers(), 0);
+    assert!(!owner.handle_property_notify(&conn, &event)?);
+    Ok(())
+}
+
+#[test]
+fn test_selection_owner_incr_transfer_format32() -> Result<(), ReplyError> {
+    use x11rb::protocol::xproto::{AtomEnum, InternAtomReply, Property, PropertyNotifyEvent};
+    use x11rb::selection::SelectionData;
+
+    let conn = FakeConnection::default();
+    let mut owner = acquire_selection(&conn)?;
+    conn.add_reply(&InternAtomReply {
+        atom: 42,
+        ..Default::default()
+    });
+
+    // 300000 items of 32 bits need three chunks with requests of at most 2^19 bytes
+    let data = (0..300_000u32)
+        .flat_map(|i| i.to_ne_bytes().to_vec())
+        .collect::<Vec<_>>();
+    let request = selection_request(AtomEnum::PRIMARY.into(), 3);
+    assert!(owner.handle_selection_request(&conn, &request, |_| {
+        Some(SelectionData::new(AtomEnum::INTEGER, 32, data.clone()))
+    })?);
+
+    let event = PropertyNotifyEvent {
+        window: 2,
+        atom: 3,
+        state: Property::DELETE,
+        ..Default::default()
+    };
+    let mut chunks = Vec::new();
+    loop {
+        conn.requests.borrow_mut().clear();
+        assert!(owner.handle_property_notify(&conn, &event)?);
+        let requests = conn.requests.borrow();
+        assert_eq!(requests.len(), 1);
+        // Every chunk has the type and format of the data and contains whole items
+        let request = &requests[0].data;
+        let offset = if request[2..4] == [0, 0] { 4 } else { 0 };
+        assert_eq!(
+            request[offset + 12..offset + 16],
+            u32::from(AtomEnum::INTEGER).to_ne_bytes()
+        );
+        assert_eq!(request[offset + 16], 32);
+        let chunk = change_property_value(request);
+        if chunk.is_empty() {
+            break;
+        }
+        assert_eq!(chunk.len() % 4, 0);
+        chunks.push(chunk.to_vec());
+    }
+    assert_eq!(chunks.len(), 3);
+    assert!(chunks.concat() == data);
+    assert_eq!(owner.pending_transfers(), 0);
+    Ok(())
+}
+
+fn add_intern_atom_replies(conn: &FakeConnection, count: u32) {
+    use x11rb::protocol::xproto::InternAtomReply;
+
+    for atom in 0..count {
+        conn.add_reply(&InternAtomReply {
+            atom: 100 + atom,
+            ..Default::default()
+        });
+    }
+}
+
+#[test]
+fn test_window_builder_title_encoding() -> Result<(), ReplyOrIdError> {
+    use x11rb::protocol::xproto::{AtomEnum, CHANGE_PROPERTY_REQUEST, DESTROY_WINDOW_REQUEST};
+    use x11rb::window::WindowBuilder;
+
+    let wm_name: &[u8] = b"Gr\xfc\xdfe";
+    for &(title, expected) in &[("Grüße", Some(wm_name)), ("日本語", None)] {
+        let conn = FakeConnection::default();
+        add_intern_atom_replies(&conn, 4);
+        let _ = WindowBuilder::new(1).title(title).build(&conn)?;
+
+        let requests = conn.requests.borrow();
+        let property_value = |property: u32| {
+            requests
+                .iter()
+                .find(|request| {
+                    request.data[0] == CHANGE_PROPERTY_REQUEST
+                        && request.data[8..12] == property.to_ne_bytes()
+                })
+                .map(|request| change_property_value(&request.data))
+        };
+        // WM_NAME is only set if the title can be encoded in Latin-1
+        assert_eq!(property_value(AtomEnum::WM_NAME.into()), expected);
+        // _NET_WM_NAME, the third interned atom, always contains the title in UTF-8
+        assert_eq!(property_value(102), Some(title.as_bytes()));
+        // The window is returned and not destroyed
+        assert!(requests
+            .iter()
+            .all(|request| request.data[0] != DESTROY_WINDOW_REQUEST));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_window_builder_destroys_window_on_error() {
+    use x11rb::protocol::xproto::{CREATE_WINDOW_REQUEST, DESTROY_WINDOW_REQUEST};
+    use x11rb::window::WindowBuilder;
+
+    let conn = FakeConnection::default();
+    add_intern_atom_replies(&conn, 1);
+    // The second reply is too short to be parsed
+    conn.replies.borrow_mut().push_back(vec![1, 0, 0, 0]);
+    assert!(WindowBuilder::new(1).build(&conn).is_err());
+
+    let requests = conn.requests.borrow();
+    let created = requests
+        .iter()
+        .find(|request| request.data[0] == CREATE_WINDOW_REQUEST)
+        .expect("The window should have been created");
+    let destroyed = requests
+        .iter()
+        .find(|request| request.data[0] == DESTROY_WINDOW_REQUEST)
+        .expect("The window should have been destroyed");
+    assert_eq!(created.data[4..8], destroyed.data[4..8]);
+}
+
+#[test]
+fn test_root_geometry_tracker_follows_parent() -> Result<(), ReplyError> {
+    use x11rb::protocol::xproto::{
+        ConfigureNotifyEvent, QueryTreeReply, ReparentNotifyEvent, TranslateCoordinatesReply,
+        QUERY_TREE_REQUEST, TRANSLATE_COORDINATES_REQUEST,
+    };
+    use x11rb::protocol::Event;
+    use x11rb::wm::RootGeometryTracker;
+
+    let conn = FakeConnection::default();
+    let mut tracker = RootGeometryTracker::new(1);
+    let event = ConfigureNotifyEvent {
+        response_type: 22,
+        window: 42,
+        x: 10,
+        y: 20,
+        width: 100,
+        height: 200,
+        ..Default::default()
+    };
+    let opcodes = || {
+        let opcodes: Vec<u8> = conn.requests.borrow().iter().map(|r| r.data[0]).collect();
+        conn.requests.borrow_mut().clear();
+        opcodes
+    };
+    let parent_origin = |x, y| TranslateCoordinatesReply {
+        dst_x: x,
+        dst_y: y,
+        ..Default::default()
+    };
+
+    // The parent is looked up once, its position for every event
+    conn.add_reply(&QueryTreeReply {
+        root: 1,
+        parent: 7,
+        ..Default::default()
+    });
+    conn.add_reply(&parent_origin(100, 50));
+    let geometry = tracker.geometry(&conn, &event)?;
+    assert_eq!((geometry.x, geometry.y), (110, 70));
+    assert_eq!(
+        opcodes(),
+        [QUERY_TREE_REQUEST, TRANSLATE_COORDINATES_REQUEST]
+    );
+
+    // The parent moved, which does not cause an event for the window
+    conn.add_reply(&parent_origin(300, 60));
+    let geometry = tracker.geometry(&conn, &event)?;
+    assert_eq!((geometry.x, geometry.y), (310, 80));
+    assert_eq!(opcodes(), [TRANSLATE_COORDINATES_REQUEST]);
+
+    // After reparenting to the root window, no round trip is needed
+    tracker.handle_event(&Event::ReparentNotify(ReparentNotifyEvent {
+        window: 42,
+        parent: 1,
+        ..Default::default()
+    }));
+    let geometry = tracker.geometry(&conn, &event)?;
+    assert_eq!((geometry.x, geometry.y), (10, 20));
+    assert!(opcodes().is_empty());
+    Ok(())
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_capture_region_byte_order() -> Result<(), ReplyError> {
+    use x11rb::image::{capture_region, ImageOrder};
+    use x11rb::protocol::xproto::{
+        Format, GetGeometryReply, GetImageReply, ImageOrder as SetupImageOrder, Rectangle,
+    };
+
+    for &(setup_order, order) in &[
+        (SetupImageOrder::LSB_FIRST, ImageOrder::LsbFirst),
+        (SetupImageOrder::MSB_FIRST, ImageOrder::MsbFirst),
+    ] {
+        let conn = FakeConnection {
+            setup: Setup {
+                image_byte_order: setup_order,
+                pixmap_formats: vec![Format {
+                    depth: 24,
+                    bits_per_pixel: 32,
+                    scanline_pad: 32,
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        conn.add_reply(&GetGeometryReply {
+            depth: 24,
+            ..Default::default()
+        });
+        conn.add_reply(&GetImageReply {
+            depth: 24,
+            visual: 42,
+            data: vec![0; 2 * 2 * 4],
+            ..Default::default()
+        });
+        let rect = Rectangle {
+            x: 0,
+            y: 0,
+            width: 2,
+            height: 2,
+        };
+        let image = capture_region(&conn, 1, rect)?;
+        assert_eq!(image.byte_order(), order);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_wait_for_event_with_timeout_default() {
+    // FakeConnection does not implement waiting with a timeout
+    let conn = FakeConnection::default();
+    let result = conn.wait_for_event_with_timeout(std::time::Duration::from_secs(1));
+    assert!(matches!(result, Err(ConnectionError::IoError(_))));
+}
@@ -0,0 +1,90 @@
+use std::io::ErrorKind;
+use std::time::Duration;
+
+use x11rb::connection::{Connection as _, RequestConnection};
+use x11rb::errors::ConnectionError;
+use x11rb::protocol::xproto::{ConnectionExt, ImageOrder, Setup};
+use x11rb::rust_connection::{PollMode, RequestLimits, RustConnection, Stream};
+use x11rb::utils::RawFdContainer;
+
+/// A stream that accepts all writes and never has anything to read.
+#[derive(Debug, Default)]
+struct SinkStream;
+
+impl Stream for SinkStream {
+    fn poll(&self, _mode: PollMode) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn poll_with_timeout(&self, mode: PollMode, _timeout: Duration) -> std::io::Result<()> {
+        self.poll(mode)
+    }
+
+    fn read(
+        &self,
+        _buf: &mut [u8],
+        _fd_storage: &mut Vec<RawFdContainer>,
+    ) -> std::io::Result<usize> {
+        Err(ErrorKind::WouldBlock.into())
+    }
+
+    fn write(&self, buf: &[u8], _fds: &mut Vec<RawFdContainer>) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+}
+
+fn connect() -> RustConnection<SinkStream> {
+    let setup = Setup {
+        resource_id_mask: 0xff,
+        image_byte_order: ImageOrder::LSB_FIRST,
+        bitmap_format_bit_order: ImageOrder::LSB_FIRST,
+        ..Default::default()
+    };
+    RustConnection::for_connected_stream(SinkStream, setup).unwrap()
+}
+
+#[test]
+fn outstanding_limit_exempts_query_extension() {
+    let conn = connect();
+    conn.set_request_limits(RequestLimits {
+        max_outstanding_requests: Some(1),
+        ..Default::default()
+    });
+    let _cookie = conn.get_input_focus().unwrap();
+
+    // Another request with a reply is refused
+    match conn.get_input_focus() {
+        Err(ConnectionError::IoError(error)) => assert_eq!(error.kind(), ErrorKind::WouldBlock),
+        other => panic!("Unexpected result {:?}", other.map(|_| ())),
+    }
+
+    // Setting up an extension still works
+    conn.prefetch_extension_information("BIG-REQUESTS").unwrap();
+}
+
+#[test]
+fn outstanding_requests_are_removed() {
+    let conn = connect();
+
+    conn.get_input_focus().unwrap().discard();
+    conn.get_input_focus().unwrap().discard_reply_and_errors();
+    let _ = conn.get_input_focus().unwrap().forget();
+    assert_eq!(conn.outstanding_requests(), 0);
+
+    let cookie = conn.get_input_focus().unwrap();
+    assert_eq!(conn.outstanding_requests(), 1);
+    drop(cookie);
+    assert_eq!(conn.outstanding_requests(), 0);
+}
+
+#[test]
+fn forgotten_requests_do_not_trip_limit() {
+    let conn = connect();
+    conn.set_request_limits(RequestLimits {
+        max_outstanding_requests: Some(1),
+        ..Default::default()
+    });
+    for _ in 0..3 {
+        let _ = conn.get_input_focus().unwrap().forget();
+    }
+}
@@ -273,5 +273,9 @@ mod test {
         fn generate_id(&self) -> Result<u32, ReplyOrIdError> {
             unimplemented!()
         }
+
+        fn has_error(&self) -> Option<ConnectionError> {
+            unimplemented!()
+        }
     }
 }
@@ -0,0 +1,313 @@
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use x11rb::event_loop_integration::EventQueue;
+use x11rb::protocol::xproto::{
+    AtomEnum, ImageOrder, Property, PropertyNotifyEvent, SelectionNotifyEvent, Setup,
+    PROPERTY_NOTIFY_EVENT, SELECTION_NOTIFY_EVENT,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::{PollMode, RustConnection, Stream};
+use x11rb::selection::{request_selection, SelectionData};
+use x11rb::utils::RawFdContainer;
+
+const WINDOW: u32 = 1;
+const PROPERTY: u32 = 5;
+const INCR: u32 = 100;
+
+#[derive(Debug, Default)]
+struct ScriptState {
+    // Written bytes that do not yet form a complete request
+    written: Vec<u8>,
+    requests: usize,
+    readable: Vec<u8>,
+    script: VecDeque<(usize, Vec<u8>)>,
+}
+
+/// A stream that answers requests with scripted responses.
+///
+/// Each response becomes readable once the given number of requests was written.
+#[derive(Debug, Default)]
+struct ScriptedStream(Mutex<ScriptState>);
+
+impl Stream for ScriptedStream {
+    fn poll(&self, mode: PollMode) -> std::io::Result<()> {
+        if !mode.writable() && self.0.lock().unwrap().readable.is_empty() {
+            // Waiting would block forever
+            return Err(Error::new(ErrorKind::Other, "no scripted response"));
+        }
+        Ok(())
+    }
+
+    fn poll_with_timeout(&self, mode: PollMode, _timeout: Duration) -> std::io::Result<()> {
+        self.poll(mode)
+    }
+
+    fn read(
+        &self,
+        buf: &mut [u8],
+        _fd_storage: &mut Vec<RawFdContainer>,
+    ) -> std::io::Result<usize> {
+        let mut state = self.0.lock().unwrap();
+        if state.readable.is_empty() {
+            return Err(Error::new(ErrorKind::WouldBlock, "no scripted response"));
+        }
+        let len = state.readable.len().min(buf.len());
+        buf[..len].copy_from_slice(&state.readable[..len]);
+        let _ = state.readable.drain(..len);
+        Ok(len)
+    }
+
+    fn write(&self, buf: &[u8], _fds: &mut Vec<RawFdContainer>) -> std::io::Result<usize> {
+        let mut state = self.0.lock().unwrap();
+        state.written.extend_from_slice(buf);
+        while state.written.len() >= 4 {
+            let len = 4 * usize::from(u16::from_ne_bytes([state.written[2], state.written[3]]));
+            if state.written.len() < len {
+                break;
+            }
+            let _ = state.written.drain(..len);
+            state.requests += 1;
+        }
+        while state
+            .script
+            .front()
+            .map_or(false, |(after, _)| *after <= state.requests)
+        {
+            let (_, response) = state.script.pop_front().unwrap();
+            state.readable.extend_from_slice(&response);
+        }
+        Ok(buf.len())
+    }
+}
+
+fn connect(script: Vec<(usize, Vec<u8>)>) -> RustConnection<ScriptedStream> {
+    let setup = Setup {
+        resource_id_mask: 0xff,
+        image_byte_order: ImageOrder::LSB_FIRST,
+        bitmap_format_bit_order: ImageOrder::LSB_FIRST,
+        ..Default::default()
+    };
+    let stream = ScriptedStream::default();
+    stream.0.lock().unwrap().script = script.into();
+    RustConnection::for_connected_stream(stream, setup).unwrap()
+}
+
+fn intern_atom_reply(sequence: u16, atom: u32) -> Vec<u8> {
+    let mut bytes = vec![1, 0];
+    bytes.extend_from_slice(&sequence.to_ne_bytes());
+    bytes.extend_from_slice(&[0; 4]);
+    bytes.extend_from_slice(&atom.to_ne_bytes());
+    bytes.resize(32, 0);
+    bytes
+}
+
+fn property_reply(sequence: u16, type_: u32, format: u8, value: &[u8]) -> Vec<u8> {
+    let items = match format {
+        0 => 0,
+        _ => value.len() / usize::from(format / 8),
+    };
+    let padded = (value.len() + 3) / 4 * 4;
+    let mut bytes = vec![1, format];
+    bytes.extend_from_slice(&sequence.to_ne_bytes());
+    bytes.extend_from_slice(&(padded as u32 / 4).to_ne_bytes());
+    bytes.extend_from_slice(&type_.to_ne_bytes());
+    bytes.extend_from_slice(&[0; 4]);
+    bytes.extend_from_slice(&(items as u32).to_ne_bytes());
+    bytes.extend_from_slice(&[0; 12]);
+    bytes.extend_from_slice(value);
+    bytes.resize(32 + padded, 0);
+    bytes
+}
+
+fn selection_notify(sequence: u16, property: u32) -> Vec<u8> {
+    selection_notify_for(sequence, AtomEnum::STRING.into(), 0, property)
+}
+
+fn selection_notify_for(sequence: u16, target: u32, time: u32, property: u32) -> Vec<u8> {
+    let event = SelectionNotifyEvent {
+        response_type: SELECTION_NOTIFY_EVENT,
+        sequence,
+        time,
+        requestor: WINDOW,
+        selection: AtomEnum::PRIMARY.into(),
+        target,
+        property,
+    };
+    <[u8; 32]>::from(&event).to_vec()
+}
+
+fn property_notify(sequence: u16, atom: u32) -> Vec<u8> {
+    let event = PropertyNotifyEvent {
+        response_type: PROPERTY_NOTIFY_EVENT,
+        sequence,
+        window: WINDOW,
+        atom,
+        time: 0,
+        state: Property::NEW_VALUE,
+    };
+    <[u8; 32]>::from(&event).to_vec()
+}
+
+fn request(conn: &RustConnection<ScriptedStream>) -> Option<SelectionData> {
+    let mut queue = EventQueue::new(conn);
+    let data = request_selection(
+        &mut queue,
+        WINDOW,
+        AtomEnum::PRIMARY,
+        AtomEnum::STRING,
+        PROPERTY,
+        0,
+    )
+    .unwrap();
+    assert!(queue.is_empty());
+    data
+}
+
+#[test]
+fn request_selection_reads_property() {
+    let conn = connect(vec![
+        (1, intern_atom_reply(1, INCR)),
+        (2, selection_notify(2, PROPERTY)),
+        (3, property_reply(3, AtomEnum::STRING.into(), 8, b"Hello")),
+    ]);
+    let data = request(&conn).unwrap();
+    assert_eq!(
+        data,
+        SelectionData::new(AtomEnum::STRING, 8, b"Hello".to_vec())
+    );
+}
+
+#[test]
+fn request_selection_refused() {
+    let conn = connect(vec![
+        (1, intern_atom_reply(1, INCR)),
+        (2, selection_notify(2, x11rb::NONE)),
+    ]);
+    assert_eq!(request(&conn), None);
+}
+
+#[test]
+fn request_selection_incr() {
+    let string = AtomEnum::STRING.into();
+    let conn = connect(vec![
+        (1, intern_atom_reply(1, INCR)),
+        // The owner stores the INCR property before sending SelectionNotify
+        (2, property_notify(2, PROPERTY)),
+        (2, property_notify(2, 42)),
+        (2, selection_notify(2, PROPERTY)),
+        (3, property_reply(3, INCR, 32, &12u32.to_ne_bytes())),
+        (3, property_notify(3, PROPERTY)),
+        // Reading the property for the stale PropertyNotify finds nothing
+        (4, property_reply(4, x11rb::NONE, 0, &[])),
+        (5, property_reply(5, string, 8, b"Hello, ")),
+        (5, property_notify(5, PROPERTY)),
+        (6, property_reply(6, string, 8, b"world")),
+        (6, property_notify(6, PROPERTY)),
+        (7, property_reply(7, string, 8, b"")),
+    ]);
+    let mut queue = EventQueue::new(&conn);
+    let data = request_selection(
+        &mut queue,
+        WINDOW,
+        AtomEnum::PRIMARY,
+        AtomEnum::STRING,
+        PROPERTY,
+        0,
+    )
+    .unwrap();
+    assert_eq!(
+        data,
+        Some(SelectionData::new(
+            AtomEnum::STRING,
+            8,
+            b"Hello, world".to_vec()
+        ))
+    );
+
+    // The unrelated event is kept
+    match queue.poll_for_event().unwrap() {
+        Some(Event::PropertyNotify(event)) => assert_eq!(event.atom, 42),
+        other => panic!("Unexpected event {:?}", other),
+    }
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn request_selection_ignores_other_conversions() {
+    let conn = connect(vec![
+        (1, intern_atom_reply(1, INCR)),
+        // Answers to other ConvertSelection requests for the same selection and window
+        (
+            2,
+            selection_notify_for(2, AtomEnum::INTEGER.into(), 0, x11rb::NONE),
+        ),
+        (
+            2,
+            selection_notify_for(2, AtomEnum::STRING.into(), 1234, x11rb::NONE),
+        ),
+        (2, selection_notify(2, PROPERTY)),
+        (3, property_reply(3, AtomEnum::STRING.into(), 8, b"Hello")),
+    ]);
+    let mut queue = EventQueue::new(&conn);
+    let data = request_selection(
+        &mut queue,
+        WINDOW,
+        AtomEnum::PRIMARY,
+        AtomEnum::STRING,
+        PROPERTY,
+        0,
+    )
+    .unwrap();
+    assert_eq!(
+        data,
+        Some(SelectionData::new(AtomEnum::STRING, 8, b"Hello".to_vec()))
+    );
+
+    // The other SelectionNotify events are kept
+    let mut kept = Vec::new();
+    while let Some(event) = queue.poll_for_event().unwrap() {
+        match event {
+            Event::SelectionNotify(event) => kept.push((event.target, event.time)),
+            other => panic!("Unexpected event {:?}", other),
+        }
+    }
+    assert_eq!(
+        kept,
+        [
+            (AtomEnum::INTEGER.into(), 0),
+            (AtomEnum::STRING.into(), 1234)
+        ]
+    );
+}
+
+#[test]
+fn request_selection_incr_multiple_chunks() {
+    let integer = AtomEnum::INTEGER.into();
+    let chunks = [[1u32, 2, 3], [4, 5, 6], [7, 8, 9]]
+        .iter()
+        .map(|chunk| {
+            chunk
+                .iter()
+                .flat_map(|i| i.to_ne_bytes().to_vec())
+                .collect()
+        })
+        .collect::<Vec<Vec<u8>>>();
+    let conn = connect(vec![
+        (1, intern_atom_reply(1, INCR)),
+        (2, selection_notify(2, PROPERTY)),
+        (3, property_reply(3, INCR, 32, &36u32.to_ne_bytes())),
+        (3, property_notify(3, PROPERTY)),
+        (4, property_reply(4, integer, 32, &chunks[0])),
+        (4, property_notify(4, PROPERTY)),
+        (5, property_reply(5, integer, 32, &chunks[1])),
+        (5, property_notify(5, PROPERTY)),
+        (6, property_reply(6, integer, 32, &chunks[2])),
+        (6, property_notify(6, PROPERTY)),
+        (7, property_reply(7, integer, 32, &[])),
+    ]);
+    let expected = SelectionData::new(AtomEnum::INTEGER, 32, chunks.concat());
+    assert_eq!(request(&conn), Some(expected));
+}
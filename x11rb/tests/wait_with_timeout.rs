@@ -0,0 +1,137 @@
+use std::io::{Error, ErrorKind};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{ClientMessageEvent, ImageOrder, Setup};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::{PollMode, RustConnection, Stream};
+use x11rb::utils::RawFdContainer;
+
+/// A stream whose `poll` blocks until data was added via `add_data`.
+#[derive(Debug, Default)]
+struct BlockingStream {
+    data: Mutex<Vec<u8>>,
+    condition: Condvar,
+}
+
+impl BlockingStream {
+    fn add_data(&self, data: &[u8]) {
+        self.data.lock().unwrap().extend_from_slice(data);
+        self.condition.notify_all();
+    }
+}
+
+impl Stream for BlockingStream {
+    fn poll(&self, _mode: PollMode) -> std::io::Result<()> {
+        let mut data = self.data.lock().unwrap();
+        while data.is_empty() {
+            data = self.condition.wait(data).unwrap();
+        }
+        Ok(())
+    }
+
+    fn poll_with_timeout(&self, _mode: PollMode, timeout: Duration) -> std::io::Result<()> {
+        let data = self.data.lock().unwrap();
+        if data.is_empty() {
+            let _ = self.condition.wait_timeout(data, timeout).unwrap();
+        }
+        Ok(())
+    }
+
+    fn read(
+        &self,
+        buf: &mut [u8],
+        _fd_storage: &mut Vec<RawFdContainer>,
+    ) -> std::io::Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        if data.is_empty() {
+            return Err(Error::new(ErrorKind::WouldBlock, "No data"));
+        }
+        let len = data.len().min(buf.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        data.drain(..len);
+        Ok(len)
+    }
+
+    fn write(&self, _buf: &[u8], _fds: &mut Vec<RawFdContainer>) -> std::io::Result<usize> {
+        unimplemented!()
+    }
+}
+
+fn client_message(value: u32) -> [u8; 32] {
+    <[u8; 32]>::from(&ClientMessageEvent::new(32, 0, 1u32, [value, 0, 0, 0, 0]))
+}
+
+fn event_data(event: Option<Event>) -> u32 {
+    match event {
+        Some(Event::ClientMessage(event)) => event.data.as_data32()[0],
+        _ => panic!("Unexpected event {:?}", event),
+    }
+}
+
+fn connect() -> RustConnection<BlockingStream> {
+    let setup = Setup {
+        resource_id_mask: 0xff,
+        image_byte_order: ImageOrder::LSB_FIRST,
+        bitmap_format_bit_order: ImageOrder::LSB_FIRST,
+        ..Default::default()
+    };
+    RustConnection::for_connected_stream(BlockingStream::default(), setup).unwrap()
+}
+
+#[test]
+fn connection_wait_for_event_with_timeout() {
+    let conn = Arc::new(connect());
+
+    // Nothing arrives, so the wait times out
+    let timeout = Duration::from_millis(10);
+    assert!(conn.wait_for_event_with_timeout(timeout).unwrap().is_none());
+    assert!(conn
+        .wait_for_event_with_timeout(Duration::from_secs(0))
+        .unwrap()
+        .is_none());
+
+    // Events that are already available are returned even with a zero timeout
+    conn.stream().add_data(&client_message(1));
+    assert_eq!(
+        event_data(
+            conn.wait_for_event_with_timeout(Duration::from_secs(0))
+                .unwrap()
+        ),
+        1
+    );
+
+    // An event arriving during the wait ends it early
+    let conn2 = Arc::clone(&conn);
+    let thread = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(10));
+        conn2.stream().add_data(&client_message(2));
+    });
+    let timeout = Duration::from_secs(10);
+    assert_eq!(
+        event_data(conn.wait_for_event_with_timeout(timeout).unwrap()),
+        2
+    );
+    thread.join().unwrap();
+
+    // An event arriving after a timeout is picked up by the next wait
+    assert!(conn
+        .wait_for_event_with_timeout(Duration::from_millis(10))
+        .unwrap()
+        .is_none());
+    conn.stream().add_data(&client_message(3));
+    assert_eq!(
+        event_data(conn.wait_for_event_with_timeout(timeout).unwrap()),
+        3
+    );
+
+    // Events that are already available are returned in order
+    conn.stream().add_data(&client_message(4));
+    conn.stream().add_data(&client_message(5));
+    assert_eq!(
+        event_data(conn.wait_for_event_with_timeout(timeout).unwrap()),
+        4
+    );
+    assert_eq!(event_data(conn.poll_for_event().unwrap()), 5);
+}
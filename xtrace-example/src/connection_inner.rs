@@ -264,7 +264,8 @@ impl ExtInfoProvider for ExtInfo {
     fn get_from_event_code(&self, event_code: u8) -> Option<(&str, ExtensionInformation)> {
         self.exts
             .iter()
-            .filter(|(_, ext)| ext.first_event <= event_code)
+            // Extensions without events have first_event == 0
+            .filter(|(_, ext)| ext.first_event != 0 && ext.first_event <= event_code)
             .max_by_key(|(_, ext)| ext.first_event)
             .map(|(s, ext)| (s.as_ref(), *ext))
     }
@@ -272,8 +273,9 @@ impl ExtInfoProvider for ExtInfo {
     fn get_from_error_code(&self, error_code: u8) -> Option<(&str, ExtensionInformation)> {
         self.exts
             .iter()
-            .filter(|(_, ext)| ext.first_error <= error_code)
-            .max_by_key(|(_, ext)| ext.first_event)
+            // Extensions without errors have first_error == 0
+            .filter(|(_, ext)| ext.first_error != 0 && ext.first_error <= error_code)
+            .max_by_key(|(_, ext)| ext.first_error)
             .map(|(s, ext)| (s.as_ref(), *ext))
     }
 }